@@ -0,0 +1,79 @@
+use crate::{MuyZipido, ZipEntry, ZipError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncRead;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_util::io::SyncIoBridge;
+
+/// Async counterpart of [`MuyZipido`] for any `tokio::io::AsyncRead` source.
+///
+/// The parser runs on tokio's blocking thread pool and hands entries over a
+/// bounded channel, so reading pauses while the caller is busy with an
+/// entry. Dropping the `AsyncMuyZipido` stops the parser before its next
+/// read from `reader`, which is then dropped.
+pub struct AsyncMuyZipido {
+    receiver: mpsc::Receiver<Result<ZipEntry, ZipError>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl AsyncMuyZipido {
+    /// Streams the archive from `reader`.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn from_reader<R>(reader: R, chunk_size: usize) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let bridge = SyncIoBridge::new(reader);
+        let (sender, receiver) = mpsc::channel(1);
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let flag = Arc::clone(&cancel);
+        tokio::task::spawn_blocking(move || {
+            for entry in MuyZipido::from_reader(bridge, chunk_size).with_cancel_flag(flag) {
+                if sender.blocking_send(entry).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { receiver, cancel }
+    }
+
+    /// Returns the next entry, or `None` once the archive is exhausted.
+    pub async fn next_entry(&mut self) -> Option<Result<ZipEntry, ZipError>> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for AsyncMuyZipido {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl MuyZipido {
+    /// Streams an archive delivered over a raw TCP connection, e.g. an FTP
+    /// data channel or a custom binary protocol.
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// use muy_zipido::MuyZipido;
+    ///
+    /// let stream = tokio::net::TcpStream::connect("127.0.0.1:9000").await?;
+    /// let mut extractor = MuyZipido::from_tcp_stream(stream, 10240);
+    ///
+    /// while let Some(entry) = extractor.next_entry().await {
+    ///     let entry = entry?;
+    ///     println!("{}: {} bytes", entry.filename, entry.data.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn from_tcp_stream(stream: TcpStream, chunk_size: usize) -> AsyncMuyZipido {
+        AsyncMuyZipido::from_reader(stream, chunk_size)
+    }
+}
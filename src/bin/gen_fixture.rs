@@ -0,0 +1,115 @@
+//! Writes synthetic ZIP archives with chosen properties, so regression
+//! fixtures can be made without external ZIP tools.
+//!
+//! ```sh
+//! cargo run --features test-util --bin gen_fixture -- --entries 5 --data-descriptor out.zip
+//! ```
+
+use clap::{Parser, ValueEnum};
+use muy_zipido::fixture::ArchiveBuilder;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Generate a ZIP archive for testing.
+#[derive(Parser, Debug)]
+#[command(name = "gen_fixture")]
+struct Args {
+    /// File to write the archive to
+    output: PathBuf,
+
+    /// Number of entries
+    #[arg(long, default_value_t = 3)]
+    entries: usize,
+
+    /// Uncompressed size of each entry
+    #[arg(long, value_name = "BYTES", default_value_t = 1024)]
+    size: usize,
+
+    /// Compression method of the entries
+    #[arg(long, value_enum, default_value_t = Method::Deflate)]
+    method: Method,
+
+    /// Write each entry's sizes and CRC-32 in a data descriptor after its
+    /// data
+    #[arg(long)]
+    data_descriptor: bool,
+
+    /// Leave the optional signature off the data descriptors
+    #[arg(long, requires = "data_descriptor")]
+    unsigned_descriptors: bool,
+
+    /// Move local header sizes into Zip64 extra fields (for entries without
+    /// a data descriptor) and end with the Zip64 end of central directory
+    /// records
+    #[arg(long)]
+    zip64: bool,
+
+    /// Set the encrypted flag on every entry; the data is not encrypted
+    #[arg(long)]
+    encrypted: bool,
+
+    /// Archive comment
+    #[arg(long)]
+    comment: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Method {
+    Stored,
+    Deflate,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let archive = build(&args);
+    if let Err(e) = std::fs::write(&args.output, &archive) {
+        eprintln!("Error: {}: {}", args.output.display(), e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn build(args: &Args) -> Vec<u8> {
+    let mut builder = ArchiveBuilder::new();
+    if args.unsigned_descriptors {
+        builder = builder.with_unsigned_descriptors();
+    }
+
+    for index in 1..=args.entries {
+        let name = format!("entry-{:03}.txt", index);
+        let data = entry_data(index, args.size);
+        if args.encrypted {
+            builder = builder.with_encrypted_flag();
+        }
+        if args.zip64 {
+            builder = builder.with_zip64_sizes();
+        }
+        builder = match (args.method, args.data_descriptor) {
+            (Method::Stored, false) => builder.stored(&name, &data),
+            (Method::Stored, true) => builder.stored_with_descriptor(&name, &data),
+            (Method::Deflate, false) => builder.deflated(&name, &data),
+            (Method::Deflate, true) => builder.with_data_descriptor(&name, &data),
+        };
+    }
+
+    if args.zip64 {
+        builder = builder.with_zip64_end_records();
+    }
+    if let Some(comment) = &args.comment {
+        builder = builder.with_comment(comment.as_bytes());
+    }
+    builder.build()
+}
+
+/// `size` bytes of numbered lines, different for each entry so every
+/// entry has its own CRC-32.
+fn entry_data(index: usize, size: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(size);
+    let mut line = 1;
+    while data.len() < size {
+        data.extend_from_slice(format!("entry {} line {}\n", index, line).as_bytes());
+        line += 1;
+    }
+    data.truncate(size);
+    data
+}
@@ -0,0 +1,65 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A cache file being written alongside a download. Data goes to a
+/// `.partial` file that only replaces the cache once the whole archive has
+/// been read, so an interrupted download never leaves a truncated cache.
+pub(crate) struct CacheFile {
+    writer: BufWriter<File>,
+    temp: PathBuf,
+    path: PathBuf,
+}
+
+impl CacheFile {
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = with_suffix(path, ".partial");
+        Ok(Self {
+            writer: BufWriter::new(File::create(&temp)?),
+            temp,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub(crate) fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(data)
+    }
+
+    /// Moves the completed download into place, recording `etag` next to
+    /// it for revalidation.
+    pub(crate) fn commit(mut self, etag: Option<&str>) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        fs::rename(&self.temp, &self.path)?;
+
+        let etag_path = etag_path(&self.path);
+        match etag {
+            Some(etag) => fs::write(etag_path, etag),
+            None => match fs::remove_file(etag_path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl Drop for CacheFile {
+    fn drop(&mut self) {
+        // Only still present if the download was not committed.
+        let _ = fs::remove_file(&self.temp);
+    }
+}
+
+/// Where the ETag of the cache at `path` is recorded.
+pub(crate) fn etag_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".etag")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
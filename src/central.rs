@@ -0,0 +1,183 @@
+//! Reading the central directory at the end of a complete archive.
+//!
+//! The streaming extractor never needs the central directory, but an
+//! archive that is already in memory, or fetched with range requests, can
+//! be indexed from it without reading every entry.
+
+use crate::entry::CompressionMethod;
+use crate::error::ZipError;
+use crate::header::{
+    CentralDirectoryHeader, EndOfCentralDirectory, Zip64EndOfCentralDirectory,
+    Zip64EndOfCentralDirectoryLocator,
+};
+
+/// One file header from the central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CentralDirEntry {
+    pub filename: String,
+    pub version_needed: u16,
+    pub flags: u16,
+    pub compression_method: CompressionMethod,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    /// Offset of the entry's local file header from the start of the archive.
+    pub local_header_offset: u64,
+    /// Host-dependent attributes; the upper 16 bits hold the Unix mode for
+    /// archives written on Unix.
+    pub external_attrs: u32,
+}
+
+/// The entries listed in an archive's central directory, in the order they
+/// appear there.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CentralDirectory {
+    entries: Vec<CentralDirEntry>,
+}
+
+impl CentralDirectory {
+    /// Parses the central directory of `archive`, a complete ZIP file, by
+    /// locating the end of central directory record at its end, and the
+    /// Zip64 one before it if its fields are saturated.
+    pub fn parse(archive: &[u8]) -> Result<Self, ZipError> {
+        let end = EndOfCentralDirectory::find(archive).ok_or(ZipError::UnexpectedEof)?;
+        let (record, _) = EndOfCentralDirectory::parse(&archive[end..])?;
+        let (count, start, size) = if record.is_zip64() {
+            let record = zip64_record(archive, end)?;
+            (
+                record.total_entries,
+                record.central_dir_offset,
+                record.central_dir_size,
+            )
+        } else {
+            (
+                record.total_entries as u64,
+                record.central_dir_offset as u64,
+                record.central_dir_size as u64,
+            )
+        };
+        let start = usize::try_from(start).unwrap_or(usize::MAX);
+        let size = usize::try_from(size).unwrap_or(usize::MAX);
+        let directory = archive
+            .get(start..start.saturating_add(size))
+            .ok_or(ZipError::UnexpectedEof)?;
+
+        let capacity = directory.len() / CentralDirectoryHeader::FIXED_LEN;
+        let mut entries = Vec::with_capacity(capacity.min(count as usize));
+        let mut pos = 0;
+        for _ in 0..count {
+            let (header, len) = CentralDirectoryHeader::parse(&directory[pos..])
+                .map_err(|e| at_offset(e, start + pos))?;
+            entries.push(CentralDirEntry {
+                filename: String::from_utf8_lossy(&header.filename).to_string(),
+                version_needed: header.version_needed,
+                flags: header.flags.0,
+                compression_method: header.compression_method,
+                crc32: header.crc32,
+                compressed_size: header.compressed_size,
+                uncompressed_size: header.uncompressed_size,
+                external_attrs: header.external_attrs,
+                local_header_offset: header.local_header_offset,
+            });
+            pos += len;
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// The entries in central directory order.
+    pub fn entries(&self) -> &[CentralDirEntry] {
+        &self.entries
+    }
+
+    /// The entries sorted by the offset of their local header, the order a
+    /// sequential reader meets them in. Entries sharing an offset, which a
+    /// valid archive never has, keep their central directory order.
+    pub fn entries_by_offset(&self) -> impl Iterator<Item = &CentralDirEntry> {
+        let mut sorted: Vec<&CentralDirEntry> = self.entries.iter().collect();
+        sorted.sort_by_key(|entry| entry.local_header_offset);
+        sorted.into_iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The Zip64 end of central directory record of `archive`, found through
+/// the locator just before the classic record at `end`.
+fn zip64_record(archive: &[u8], end: usize) -> Result<Zip64EndOfCentralDirectory, ZipError> {
+    let locator_start = end
+        .checked_sub(Zip64EndOfCentralDirectoryLocator::LEN)
+        .ok_or(ZipError::UnexpectedEof)?;
+    let (locator, _) = Zip64EndOfCentralDirectoryLocator::parse(&archive[locator_start..end])
+        .map_err(|e| at_offset(e, locator_start))?;
+    let start = usize::try_from(locator.eocd_offset)
+        .ok()
+        .filter(|&start| start <= locator_start)
+        .ok_or(ZipError::UnexpectedEof)?;
+    let (record, _) = Zip64EndOfCentralDirectory::parse(&archive[start..locator_start])
+        .map_err(|e| at_offset(e, start))?;
+    Ok(record)
+}
+
+/// Places a signature error from parsing a slice at `offset` in the archive.
+fn at_offset(error: ZipError, offset: usize) -> ZipError {
+    match error {
+        ZipError::InvalidSignature { found, .. } => ZipError::InvalidSignature {
+            found,
+            offset: offset as u64,
+        },
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(filename: &str, local_header_offset: u64) -> CentralDirEntry {
+        CentralDirEntry {
+            filename: filename.to_string(),
+            version_needed: 20,
+            flags: 0,
+            compression_method: CompressionMethod::Stored,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            local_header_offset,
+            external_attrs: 0,
+        }
+    }
+
+    #[test]
+    fn test_entries_by_offset_is_stable() {
+        let directory = CentralDirectory {
+            entries: vec![
+                entry("c", 200),
+                entry("a", 0),
+                entry("b1", 100),
+                entry("b2", 100),
+            ],
+        };
+
+        let names: Vec<&str> = directory
+            .entries_by_offset()
+            .map(|e| e.filename.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b1", "b2", "c"]);
+        assert_eq!(directory.entries()[0].filename, "c");
+    }
+
+    #[test]
+    fn test_parse_missing_end_record() {
+        assert!(matches!(
+            CentralDirectory::parse(b"PK\x03\x04 not a whole archive"),
+            Err(ZipError::UnexpectedEof)
+        ));
+    }
+}
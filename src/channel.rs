@@ -0,0 +1,79 @@
+//! Handing entries to other threads over a bounded channel, for
+//! [`MuyZipido::into_channel`].
+
+use crate::{MuyZipido, ZipEntry, ZipError, ZipWarning};
+use std::sync::mpsc::{Receiver, sync_channel};
+use std::thread::{self, JoinHandle};
+
+/// The receiving end of [`MuyZipido::into_channel`]. Share it between
+/// workers behind a `Mutex`, or receive on one thread and dispatch.
+pub type EntryReceiver = Receiver<Result<ZipEntry, ZipError>>;
+
+/// What the thread started by [`MuyZipido::into_channel`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Entries sent, including recoverable errors.
+    pub sent: usize,
+    /// Total size of the data of the entries sent.
+    pub bytes: u64,
+    /// `false` if the receiver was dropped before the archive ended.
+    pub completed: bool,
+    pub warnings: Vec<ZipWarning>,
+}
+
+impl MuyZipido {
+    /// Reads the archive on a new thread, sending entries over a channel
+    /// that holds at most `capacity` of them.
+    ///
+    /// When the receiver falls behind, the thread waits for room before
+    /// reading further, so no more than `capacity` decoded entries (plus
+    /// the one being sent) are held at a time. A `capacity` of 0 hands each
+    /// entry over directly.
+    ///
+    /// Recoverable errors, such as a CRC mismatch, are sent like entries.
+    /// An error that ends the archive is not sent; the channel closes and
+    /// the thread's handle returns it. Dropping the receiver stops the
+    /// thread at its next send, which then drops the stream.
+    ///
+    /// ```no_run
+    /// # use muy_zipido::MuyZipido;
+    /// let file = std::fs::File::open("data.zip")?;
+    /// let (entries, handle) = MuyZipido::from_reader(file, 8192).into_channel(4);
+    /// for entry in entries {
+    ///     let entry = entry?;
+    ///     println!("{}: {} bytes", entry.filename, entry.data.len());
+    /// }
+    /// let stats = handle.join().expect("reader thread panicked")?;
+    /// println!("{} entries", stats.sent);
+    /// # Ok::<(), muy_zipido::ZipError>(())
+    /// ```
+    pub fn into_channel(
+        mut self,
+        capacity: usize,
+    ) -> (EntryReceiver, JoinHandle<Result<ChannelStats, ZipError>>) {
+        let (sender, receiver) = sync_channel(capacity);
+
+        let handle = thread::spawn(move || {
+            let mut stats = ChannelStats::default();
+            for result in self.by_ref() {
+                let result = match result {
+                    Err(e) if !e.is_recoverable() => return Err(e),
+                    result => result,
+                };
+                let len = result.as_ref().map_or(0, |entry| entry.data.len() as u64);
+                if sender.send(result).is_err() {
+                    stats.warnings = self.warnings().to_vec();
+                    return Ok(stats);
+                }
+                stats.sent += 1;
+                stats.bytes += len;
+            }
+
+            stats.completed = true;
+            stats.warnings = self.warnings().to_vec();
+            Ok(stats)
+        });
+
+        (receiver, handle)
+    }
+}
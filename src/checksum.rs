@@ -0,0 +1,154 @@
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Returns the SHA-256 digest of `data` as lowercase hex.
+pub fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256Hasher::default();
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// Computes a SHA-256 digest a piece at a time, for data that is never held
+/// in memory whole.
+#[derive(Clone, Default)]
+pub(crate) struct Sha256Hasher(Sha256);
+
+impl Sha256Hasher {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// The digest as lowercase hex.
+    pub(crate) fn finish(self) -> String {
+        let digest = self.0.finalize();
+        let mut hex = String::with_capacity(digest.len() * 2);
+        for b in digest.iter() {
+            hex.push_str(&format!("{:02x}", b));
+        }
+        hex
+    }
+}
+
+/// A list of digests in the format written by `sha256sum`, one
+/// `<hex>  ./<path>` line per file, so `sha256sum -c` accepts it.
+///
+/// Paths are stored without the leading `./` and keep insertion order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    entries: Vec<(String, String)>,
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: &str, digest: String) {
+        let path = normalize(path);
+        match self.entries.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, existing)) => *existing = digest,
+            None => self.entries.push((path.to_string(), digest)),
+        }
+    }
+
+    /// Returns the recorded digest for `path`, with or without a leading `./`.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        let path = normalize(path);
+        self.entries
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, digest)| digest.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(path, digest)| (path.as_str(), digest.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn normalize(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, digest) in &self.entries {
+            writeln!(f, "{}  ./{}", digest, path)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = String;
+
+    /// Parses `sha256sum` output, accepting both the text (`<hex>  path`) and
+    /// binary (`<hex> *path`) markers. Blank lines are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut manifest = Manifest::new();
+
+        for (number, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let parsed = line.split_once(' ').and_then(|(digest, rest)| {
+                let path = rest.strip_prefix([' ', '*'])?;
+                let valid = digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit());
+                (valid && !path.is_empty()).then_some((digest, path))
+            });
+
+            let Some((digest, path)) = parsed else {
+                return Err(format!("invalid manifest line {}: {}", number + 1, line));
+            };
+            manifest.insert(path, digest.to_ascii_lowercase());
+        }
+
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let mut manifest = Manifest::new();
+        manifest.insert("data/points.csv", sha256_hex(b"x,y\n"));
+        manifest.insert("./readme.txt", sha256_hex(b"hello"));
+
+        let text = manifest.to_string();
+        assert!(text.starts_with(&format!("{}  ./data/points.csv\n", sha256_hex(b"x,y\n"))));
+
+        let parsed: Manifest = text.parse().unwrap();
+        assert_eq!(parsed, manifest);
+        assert_eq!(
+            parsed.get("readme.txt"),
+            Some(sha256_hex(b"hello").as_str())
+        );
+        assert_eq!(parsed.get("./readme.txt"), parsed.get("readme.txt"));
+
+        let binary = format!("{} *./a.bin\n\n", sha256_hex(b"")).parse::<Manifest>();
+        assert_eq!(binary.unwrap().len(), 1);
+        assert!("not a digest  ./a.bin".parse::<Manifest>().is_err());
+    }
+}
@@ -147,7 +147,7 @@ mod tests {
 
         assert_eq!(cb.get_last_n(2), vec![30, 40]);
         assert_eq!(cb.get_last_n(10), vec![10, 20, 30, 40]);
-        assert_eq!(cb.get_last_n(0), vec![]);
+        assert_eq!(cb.get_last_n(0), Vec::<i32>::new());
 
         cb.write(50);
         assert_eq!(cb.get_last_n(3), vec![30, 40, 50]);
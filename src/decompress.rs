@@ -0,0 +1,120 @@
+//! Pluggable decompression backends for [`MuyZipido::with_decompressor`](crate::MuyZipido::with_decompressor).
+
+use crate::entry::CompressionMethod;
+use crate::error::ZipError;
+use flate2::read::DeflateDecoder;
+use std::io::Read;
+
+/// Decodes the compressed data of an entry.
+///
+/// Implementations do not know which entry they are decoding, so errors
+/// such as [`ZipError::CorruptData`] may leave `filename` empty; the
+/// extractor fills it in.
+pub trait Decompressor: Send + Sync {
+    /// Decodes `input`, the whole of an entry's data as stored, compressed
+    /// with `method`.
+    fn decompress(&self, method: CompressionMethod, input: &[u8]) -> Result<Vec<u8>, ZipError>;
+
+    /// Whether `method` can be decoded. Entries using other methods are
+    /// skipped with [`ZipError::UnsupportedCompression`] without calling
+    /// [`Decompressor::decompress`]. Defaults to stored and deflate.
+    fn supports(&self, method: CompressionMethod) -> bool {
+        matches!(
+            method,
+            CompressionMethod::Stored | CompressionMethod::Deflate
+        )
+    }
+}
+
+/// Stored and deflate entries, decoded with `flate2`, as the extractor does
+/// when no other decompressor is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlateDecompressor;
+
+impl Decompressor for FlateDecompressor {
+    fn decompress(&self, method: CompressionMethod, input: &[u8]) -> Result<Vec<u8>, ZipError> {
+        match method {
+            CompressionMethod::Stored => Ok(input.to_vec()),
+            CompressionMethod::Deflate => {
+                let mut data = Vec::new();
+                DeflateDecoder::new(input)
+                    .read_to_end(&mut data)
+                    .map_err(|source| ZipError::CorruptData {
+                        filename: String::new(),
+                        source,
+                    })?;
+                Ok(data)
+            }
+            _ => Err(ZipError::UnsupportedCompression {
+                method: method.code(),
+                filename: String::new(),
+            }),
+        }
+    }
+}
+
+/// Names `filename` in an error from a [`Decompressor`] that left it out.
+pub(crate) fn attribute(error: ZipError, filename: &str) -> ZipError {
+    match error {
+        ZipError::CorruptData {
+            filename: name,
+            source,
+        } if name.is_empty() => ZipError::CorruptData {
+            filename: filename.to_string(),
+            source,
+        },
+        ZipError::UnsupportedCompression {
+            method,
+            filename: name,
+        } if name.is_empty() => ZipError::UnsupportedCompression {
+            method,
+            filename: filename.to_string(),
+        },
+        ZipError::InvalidContent {
+            filename: name,
+            source,
+        } if name.is_empty() => ZipError::InvalidContent {
+            filename: filename.to_string(),
+            source,
+        },
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn test_flate_decompressor() {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"hello hello hello").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let flate = FlateDecompressor;
+        assert_eq!(
+            flate
+                .decompress(CompressionMethod::Deflate, &compressed)
+                .unwrap(),
+            b"hello hello hello"
+        );
+        assert_eq!(
+            flate
+                .decompress(CompressionMethod::Stored, b"as is")
+                .unwrap(),
+            b"as is"
+        );
+        assert!(!flate.supports(CompressionMethod::Bzip2));
+
+        let error = flate
+            .decompress(CompressionMethod::Deflate, b"\xff\xff")
+            .unwrap_err();
+        assert!(matches!(
+            attribute(error, "a.bin"),
+            ZipError::CorruptData { filename, .. } if filename == "a.bin"
+        ));
+    }
+}
@@ -0,0 +1,621 @@
+use crate::ZipError;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Metadata for a single archive member, as read from its local file header
+/// (and data descriptor, for streamed entries).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EntryInfo {
+    #[cfg_attr(feature = "serde", serde(rename = "name"))]
+    pub filename: String,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    /// Serialized as the numeric method code.
+    #[cfg_attr(feature = "serde", serde(rename = "method"))]
+    pub compression_method: CompressionMethod,
+    #[cfg_attr(feature = "serde", serde(rename = "crc"))]
+    pub crc32: u32,
+    #[cfg_attr(feature = "serde", serde(rename = "timestamp"))]
+    pub modified: Option<ZipDateTime>,
+    pub is_dir: bool,
+    /// "Version needed to extract" from the local header: the ZIP
+    /// specification version times ten, e.g. 20 for 2.0 (deflate) or 45 for
+    /// 4.5 (Zip64).
+    pub version_needed: u16,
+    /// The entry name as stored, when
+    /// [`MuyZipido::with_raw_filenames`](crate::MuyZipido::with_raw_filenames)
+    /// is enabled.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub filename_raw: Option<Vec<u8>>,
+    /// Unix file mode, e.g. `0o100644`, when the local header carries an
+    /// ASi Unix extra field. The mode in the external attributes is only in
+    /// the central directory, after the entries have been streamed.
+    pub unix_mode: Option<u32>,
+    /// Modification time in seconds since the Unix epoch, when the local
+    /// header carries an extended timestamp extra field. Unlike `modified`
+    /// it is in UTC.
+    pub unix_mtime: Option<u32>,
+    /// Offset of the entry's local header from the start of the archive.
+    pub header_offset: u64,
+    /// Offset of the entry's data, just after its local header, from the
+    /// start of the archive.
+    pub data_offset: u64,
+    /// Length of the entry's data as it appeared in the stream, so that
+    /// `data_offset..data_offset + data_len_on_wire` is the data's byte
+    /// range. For entries with a data descriptor it is 0 until the
+    /// descriptor has been found.
+    pub data_len_on_wire: u64,
+}
+
+/// How an entry's data is compressed. Only [`CompressionMethod::Stored`] and
+/// [`CompressionMethod::Deflate`] can be decompressed, unless a
+/// [`Decompressor`](crate::decompress::Decompressor) supports more; other
+/// entries are skipped with [`ZipError::UnsupportedCompression`] unless raw
+/// entries are requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressionMethod {
+    Stored,
+    Deflate,
+    Deflate64,
+    Bzip2,
+    Lzma,
+    Zstd,
+    Xz,
+    Ppmd,
+    /// Any other method code.
+    Unknown(u16),
+}
+
+impl CompressionMethod {
+    /// The method code stored in ZIP headers.
+    pub fn code(self) -> u16 {
+        match self {
+            CompressionMethod::Stored => 0,
+            CompressionMethod::Deflate => 8,
+            CompressionMethod::Deflate64 => 9,
+            CompressionMethod::Bzip2 => 12,
+            CompressionMethod::Lzma => 14,
+            CompressionMethod::Zstd => 93,
+            CompressionMethod::Xz => 95,
+            CompressionMethod::Ppmd => 98,
+            CompressionMethod::Unknown(code) => code,
+        }
+    }
+}
+
+impl From<u16> for CompressionMethod {
+    fn from(code: u16) -> Self {
+        match code {
+            0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflate,
+            9 => CompressionMethod::Deflate64,
+            12 => CompressionMethod::Bzip2,
+            14 => CompressionMethod::Lzma,
+            93 => CompressionMethod::Zstd,
+            95 => CompressionMethod::Xz,
+            98 => CompressionMethod::Ppmd,
+            _ => CompressionMethod::Unknown(code),
+        }
+    }
+}
+
+impl From<CompressionMethod> for u16 {
+    fn from(method: CompressionMethod) -> Self {
+        method.code()
+    }
+}
+
+impl fmt::Display for CompressionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CompressionMethod::Stored => "stored",
+            CompressionMethod::Deflate => "deflate",
+            CompressionMethod::Deflate64 => "deflate64",
+            CompressionMethod::Bzip2 => "bzip2",
+            CompressionMethod::Lzma => "lzma",
+            CompressionMethod::Zstd => "zstd",
+            CompressionMethod::Xz => "xz",
+            CompressionMethod::Ppmd => "ppmd",
+            CompressionMethod::Unknown(code) => return write!(f, "method {}", code),
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CompressionMethod {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.code())
+    }
+}
+
+/// File type bits of a Unix mode, and the value for a symbolic link.
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+impl EntryInfo {
+    /// Whether the entry is a symbolic link, whose data is the link target.
+    pub fn is_symlink(&self) -> bool {
+        self.unix_mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+    }
+
+    /// The modification time, from the extended timestamp if present and
+    /// otherwise from the DOS timestamp.
+    pub fn modified_time(&self) -> Option<SystemTime> {
+        match self.unix_mtime {
+            Some(seconds) => Some(UNIX_EPOCH + Duration::from_secs(seconds as u64)),
+            None => self.modified.and_then(|m| m.to_system_time()),
+        }
+    }
+
+    /// How many times smaller the entry is compressed, e.g. `4.0` for data
+    /// compressed to a quarter of its size.
+    ///
+    /// `None` for stored entries and empty ones. After a
+    /// [transform](crate::MuyZipido::with_entry_transform) the sizes no
+    /// longer belong to the same data, so use those of
+    /// [`ZipEntry::original`] instead.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.compression_method == CompressionMethod::Stored || self.compressed_size == 0 {
+            return None;
+        }
+        Some(self.uncompressed_size as f64 / self.compressed_size as f64)
+    }
+}
+
+/// A decompressed archive member.
+///
+/// Derefs to [`EntryInfo`], so metadata is available directly as
+/// `entry.filename`, `entry.uncompressed_size` and so on.
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    pub info: EntryInfo,
+    /// The decompressed data, or the data as stored when
+    /// [`MuyZipido::with_raw_entries`](crate::MuyZipido::with_raw_entries) is enabled.
+    pub data: Vec<u8>,
+    /// The metadata as stored in the archive, when a
+    /// [`Transform`](crate::Transform) changed the entry. `info` then has
+    /// the transformed name and size.
+    pub original: Option<EntryInfo>,
+    /// Time spent reading the entry's compressed bytes from the stream, set
+    /// when [`MuyZipido::with_timing`](crate::MuyZipido::with_timing) is enabled.
+    pub download_duration: Option<Duration>,
+    /// Time spent decompressing the entry, set when
+    /// [`MuyZipido::with_timing`](crate::MuyZipido::with_timing) is enabled.
+    pub decompression_duration: Option<Duration>,
+}
+
+impl Deref for ZipEntry {
+    type Target = EntryInfo;
+
+    fn deref(&self) -> &EntryInfo {
+        &self.info
+    }
+}
+
+impl ZipEntry {
+    /// The path a symbolic link entry points to, or `None` if the entry is not
+    /// a symbolic link or the target is not valid UTF-8.
+    pub fn symlink_target(&self) -> Option<&str> {
+        self.is_symlink()
+            .then(|| std::str::from_utf8(&self.data).ok())
+            .flatten()
+    }
+
+    /// Parses the entry's data as JSON.
+    #[cfg(feature = "serde")]
+    pub fn as_json<T: serde::de::DeserializeOwned>(&self) -> Result<T, ZipError> {
+        serde_json::from_slice(&self.data).map_err(|e| ZipError::InvalidContent {
+            filename: self.filename.clone(),
+            source: Box::new(e),
+        })
+    }
+
+    /// A CSV reader over the entry's data, treating the first row as headers.
+    #[cfg(feature = "csv")]
+    pub fn as_csv_reader(&self) -> csv::Reader<&[u8]> {
+        csv::Reader::from_reader(&self.data)
+    }
+
+    /// Writes the entry's data to `path`, creating parent directories as
+    /// needed. Directory entries create `path` as a directory instead.
+    ///
+    /// The data goes to a temporary file next to `path` that is renamed
+    /// into place, so `path` never holds a partial file. The file's
+    /// modification time is set from [`EntryInfo::modified_time`] when present.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<(), ZipError> {
+        let path = path.as_ref();
+        if self.is_dir {
+            fs::create_dir_all(path)?;
+            return Ok(());
+        }
+
+        if path.file_name().is_none() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} does not name a file", path.display()),
+            )
+            .into());
+        }
+        let parent = path.parent().unwrap_or(Path::new(""));
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp = temp_sibling(path);
+        let result = (|| {
+            let mut file = File::create(&temp)?;
+            file.write_all(&self.data)?;
+            if let Some(modified) = self.modified_time() {
+                file.set_modified(modified)?;
+            }
+            file.sync_all()?;
+            fs::rename(&temp, path)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        Ok(result?)
+    }
+
+    /// Writes the entry's data to a new temporary file, deleted when the
+    /// returned handle is dropped unless it is persisted first.
+    ///
+    /// The file is rewound to the start, and its modification time is set
+    /// from [`EntryInfo::modified_time`] when present.
+    #[cfg(feature = "tempfile")]
+    pub fn to_tempfile(&self) -> Result<tempfile::NamedTempFile, ZipError> {
+        use std::io::{Seek, SeekFrom};
+
+        let mut file = tempfile::NamedTempFile::new()?;
+        file.write_all(&self.data)?;
+        if let Some(modified) = self.modified_time() {
+            file.as_file().set_modified(modified)?;
+        }
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    }
+
+    /// The entry's data in standard base64, with padding.
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(&self.data)
+    }
+
+    /// Reads the entry's data as [`ZipEntry::to_base64`] would return it,
+    /// encoding as it goes rather than building the whole string, e.g. to
+    /// copy into a response body.
+    #[cfg(feature = "base64")]
+    pub fn base64_reader(&self) -> impl std::io::Read + '_ {
+        Base64Reader {
+            data: &self.data,
+            pending: [0; 4],
+            pending_range: 0..0,
+        }
+    }
+}
+
+/// A name for a temporary file next to `path`, unique within the process.
+pub(crate) fn temp_sibling(path: &Path) -> PathBuf {
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path.file_name().unwrap_or_default();
+    path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Encodes whole groups of three bytes straight into the caller's buffer,
+/// going through `pending` only when the buffer has room for less than one.
+#[cfg(feature = "base64")]
+struct Base64Reader<'a> {
+    data: &'a [u8],
+    pending: [u8; 4],
+    pending_range: std::ops::Range<usize>,
+}
+
+#[cfg(feature = "base64")]
+impl std::io::Read for Base64Reader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use base64::Engine;
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        if self.pending_range.is_empty() && buf.len() < 4 && !self.data.is_empty() {
+            let take = self.data.len().min(3);
+            let len = engine
+                .encode_slice(&self.data[..take], &mut self.pending)
+                .expect("four bytes hold one encoded group");
+            self.data = &self.data[take..];
+            self.pending_range = 0..len;
+        }
+        if !self.pending_range.is_empty() {
+            let len = self.pending_range.len().min(buf.len());
+            let start = self.pending_range.start;
+            buf[..len].copy_from_slice(&self.pending[start..start + len]);
+            self.pending_range.start += len;
+            return Ok(len);
+        }
+
+        let take = self.data.len().min(buf.len() / 4 * 3);
+        let len = engine
+            .encode_slice(&self.data[..take], buf)
+            .expect("the buffer holds the encoded groups");
+        self.data = &self.data[take..];
+        Ok(len)
+    }
+}
+
+/// Totals over the entries read by an extractor with
+/// [`MuyZipido::with_timing`](crate::MuyZipido::with_timing) enabled.
+///
+/// Comparing `download_duration` with `decompression_duration` shows whether
+/// an archive is limited by the network or by the CPU.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DownloadStats {
+    pub entries: usize,
+    pub compressed_bytes: u64,
+    pub download_duration: Duration,
+    pub decompression_duration: Duration,
+    /// Times the request for the archive was rate limited and retried; see
+    /// [`ZipSession::with_rate_limit_retries`](crate::ZipSession::with_rate_limit_retries).
+    pub rate_limited: usize,
+    /// Time spent waiting before those retries.
+    pub rate_limit_wait: Duration,
+}
+
+/// An MS-DOS timestamp as stored in ZIP headers (local time, 2 second resolution).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ZipDateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl ZipDateTime {
+    /// Decodes DOS date and time fields, returning `None` for the zero date
+    /// some writers use when no timestamp is available.
+    pub fn from_dos(date: u16, time: u16) -> Option<Self> {
+        let month = ((date >> 5) & 0x0f) as u8;
+        let day = (date & 0x1f) as u8;
+
+        if date == 0 || month == 0 || month > 12 || day == 0 {
+            return None;
+        }
+
+        Some(Self {
+            year: 1980 + (date >> 9),
+            month,
+            day,
+            hour: (time >> 11) as u8,
+            minute: ((time >> 5) & 0x3f) as u8,
+            second: ((time & 0x1f) * 2) as u8,
+        })
+    }
+}
+
+impl ZipDateTime {
+    /// Converts the timestamp to a `SystemTime`. ZIP headers do not record a
+    /// time zone, so the fields are taken as UTC. Returns `None` for
+    /// out-of-range fields.
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        if self.hour > 23 || self.minute > 59 || self.second > 59 {
+            return None;
+        }
+
+        // Days since 1970-01-01 in the proleptic Gregorian calendar.
+        let (month, day) = (self.month as i64, self.day as i64);
+        let year = self.year as i64 - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        let seconds =
+            days * 86_400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+    }
+}
+
+impl fmt::Display for ZipDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ZipDateTime {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(filename: &str, data: &[u8]) -> ZipEntry {
+        ZipEntry {
+            info: EntryInfo {
+                filename: filename.to_string(),
+                compressed_size: data.len() as u64,
+                uncompressed_size: data.len() as u64,
+                compression_method: CompressionMethod::Stored,
+                crc32: 0,
+                modified: None,
+                is_dir: false,
+                version_needed: 20,
+                filename_raw: None,
+                unix_mode: None,
+                unix_mtime: None,
+                header_offset: 0,
+                data_offset: 0,
+                data_len_on_wire: data.len() as u64,
+            },
+            data: data.to_vec(),
+            original: None,
+            download_duration: None,
+            decompression_duration: None,
+        }
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        let mut entry = entry("a.txt", b"hello");
+        assert_eq!(entry.compression_ratio(), None);
+
+        entry.info.compression_method = CompressionMethod::Deflate;
+        entry.info.compressed_size = 20;
+        entry.info.uncompressed_size = 80;
+        assert_eq!(entry.compression_ratio(), Some(4.0));
+
+        entry.info.compressed_size = 0;
+        assert_eq!(entry.compression_ratio(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_as_json() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Feature {
+            id: u32,
+            name: String,
+        }
+
+        let feature: Feature = entry("f.json", br#"{"id": 7, "name": "Thames"}"#)
+            .as_json()
+            .unwrap();
+        assert_eq!((feature.id, feature.name.as_str()), (7, "Thames"));
+
+        let error = entry("bad.json", b"{").as_json::<Feature>().unwrap_err();
+        assert!(
+            matches!(error, ZipError::InvalidContent { ref filename, .. } if filename == "bad.json")
+        );
+    }
+
+    #[cfg(feature = "tempfile")]
+    #[test]
+    fn test_to_tempfile() {
+        use std::io::Read;
+
+        let mut entry = entry("points.csv", b"x,y\n1,2\n");
+        entry.info.unix_mtime = Some(1_700_000_000);
+        let mut file = entry.to_tempfile().unwrap();
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"x,y\n1,2\n");
+        assert_eq!(
+            fs::metadata(file.path()).unwrap().modified().unwrap(),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        use std::io::Read;
+
+        assert_eq!(entry("empty", b"").to_base64(), "");
+        let entry = entry("hello.txt", b"hello, world");
+        assert_eq!(entry.to_base64(), "aGVsbG8sIHdvcmxk");
+
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 256) as u8).collect();
+        let entry = ZipEntry { data, ..entry };
+        let expected = entry.to_base64();
+        for buffer in [1, 2, 3, 4, 5, 7, 64, 4096] {
+            let mut reader = entry.base64_reader();
+            let mut encoded = Vec::new();
+            let mut buf = vec![0; buffer];
+            loop {
+                match reader.read(&mut buf).unwrap() {
+                    0 => break,
+                    len => encoded.extend_from_slice(&buf[..len]),
+                }
+            }
+            assert_eq!(String::from_utf8(encoded).unwrap(), expected, "{}", buffer);
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_as_csv_reader() {
+        let entry = entry("points.csv", b"x,y\n1,2\n3,4\n");
+        let mut reader = entry.as_csv_reader();
+        assert_eq!(reader.headers().unwrap(), vec!["x", "y"]);
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[1][0], "3");
+    }
+
+    #[test]
+    fn test_compression_method_codes() {
+        for code in [0, 8, 9, 12, 14, 93, 95, 98, 99] {
+            assert_eq!(CompressionMethod::from(code).code(), code);
+        }
+        assert_eq!(CompressionMethod::from(12), CompressionMethod::Bzip2);
+        assert_eq!(CompressionMethod::Deflate.to_string(), "deflate");
+        assert_eq!(CompressionMethod::from(99).to_string(), "method 99");
+    }
+
+    #[test]
+    fn test_dos_datetime() {
+        // 2024-03-15 13:45:30
+        let date = ((2024 - 1980) << 9) | (3 << 5) | 15;
+        let time = (13 << 11) | (45 << 5) | 15;
+
+        let parsed = ZipDateTime::from_dos(date, time).unwrap();
+        assert_eq!(parsed.to_string(), "2024-03-15T13:45:30");
+        assert_eq!(ZipDateTime::from_dos(0, 0), None);
+    }
+
+    #[test]
+    fn test_to_system_time() {
+        let epoch = |dt: ZipDateTime| {
+            dt.to_system_time()
+                .unwrap()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        };
+        let date = |year, month, day| ZipDateTime {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        };
+
+        assert_eq!(epoch(date(1980, 1, 1)), 315_532_800);
+        assert_eq!(epoch(date(2000, 2, 29)), 951_782_400);
+        let parsed = ZipDateTime::from_dos(
+            ((2024 - 1980) << 9) | (3 << 5) | 15,
+            (13 << 11) | (45 << 5) | 15,
+        );
+        assert_eq!(epoch(parsed.unwrap()), 1_710_510_330);
+    }
+}
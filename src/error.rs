@@ -0,0 +1,501 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ZipError {
+    #[cfg(feature = "http")]
+    Http {
+        url: String,
+        error: reqwest::Error,
+    },
+    /// The server answered `url` with a non-success `status`. `body_snippet`
+    /// holds the first few KB of the response body, lossily decoded.
+    #[cfg(feature = "http")]
+    HttpStatus {
+        status: u16,
+        url: String,
+        body_snippet: String,
+    },
+    /// A request was redirected more times than
+    /// [`ZipSession::with_redirect_policy`](crate::ZipSession::with_redirect_policy)
+    /// allows, or back to a URL it had already visited. `chain` lists the
+    /// URLs in the order they were requested.
+    #[cfg(feature = "http")]
+    RedirectLoop {
+        chain: Vec<String>,
+    },
+    /// The server kept answering `url` with a rate limit (429, or 503 with
+    /// `Retry-After`) after the retries
+    /// [`ZipSession::with_rate_limit_retries`](crate::ZipSession::with_rate_limit_retries)
+    /// allows, or asked to wait longer than
+    /// [`ZipSession::with_max_retry_after`](crate::ZipSession::with_max_retry_after).
+    /// `retry_after` is the wait it last asked for, if it gave one.
+    #[cfg(feature = "http")]
+    RateLimited {
+        url: String,
+        retry_after: Option<std::time::Duration>,
+    },
+    /// The server's TLS certificate failed verification, e.g. against one
+    /// pinned with
+    /// [`ZipSession::with_pinned_certificate`](crate::ZipSession::with_pinned_certificate).
+    /// Holds the URL and the TLS library's reason.
+    #[cfg(feature = "http")]
+    TlsCertificateRejected(String),
+    /// The bytes given to
+    /// [`ZipSession::with_pinned_certificate`](crate::ZipSession::with_pinned_certificate)
+    /// are not a DER-encoded certificate.
+    #[cfg(feature = "http")]
+    InvalidCertificate(String),
+    /// The response from `url` was turned down by a check of its own, such
+    /// as one given to
+    /// [`ZipSession::with_response_validator`](crate::ZipSession::with_response_validator).
+    #[cfg(feature = "http")]
+    ResponseRejected {
+        url: String,
+        reason: String,
+    },
+    /// `url` cannot be requested: it is not an `http` or `https` URL, or it
+    /// is malformed in a way [`normalize_url`](crate::session::normalize_url)
+    /// cannot fix.
+    #[cfg(feature = "http")]
+    InvalidUrl {
+        url: String,
+        reason: String,
+    },
+    /// The archive ended before a complete record could be read, e.g. while
+    /// parsing a central directory. Streamed archives fail with
+    /// [`ZipError::UnexpectedEofInHeader`] or
+    /// [`ZipError::UnexpectedEofInEntry`] instead.
+    UnexpectedEof,
+    /// The stream ended at or inside the local header starting at `offset`,
+    /// so every entry before it is complete. Ending exactly there can mean
+    /// the archive was written without a central directory; see
+    /// [`MuyZipido::with_allow_missing_central_directory`](crate::MuyZipido::with_allow_missing_central_directory).
+    UnexpectedEofInHeader {
+        offset: u64,
+    },
+    /// The stream ended `bytes_missing` bytes before the end of the data of
+    /// `filename`, so that entry is incomplete.
+    UnexpectedEofInEntry {
+        filename: String,
+        bytes_missing: u64,
+    },
+    /// The HTTP response ended early or failed mid-stream, with `offset`
+    /// bytes of the archive received. Unlike [`ZipError::Io`], which is
+    /// reserved for local IO, retrying or resuming may succeed.
+    NetworkInterrupted {
+        offset: u64,
+        reason: String,
+    },
+    InvalidSignature {
+        found: String,
+        offset: u64,
+    },
+    /// The local header at `offset` is malformed or exceeds one of the
+    /// configured [`Limits`](crate::Limits).
+    InvalidHeader {
+        offset: u64,
+        reason: String,
+    },
+    Io(std::io::Error),
+    /// An entry's compressed data could not be decoded.
+    CorruptData {
+        filename: String,
+        source: std::io::Error,
+    },
+    /// An entry's data could not be parsed by a helper such as
+    /// [`ZipEntry::as_json`](crate::ZipEntry::as_json).
+    InvalidContent {
+        filename: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// An entry uses a compression method that cannot be decoded: anything
+    /// but stored (0) or deflate (8), unless a
+    /// [`Decompressor`](crate::decompress::Decompressor) supports it.
+    UnsupportedCompression {
+        method: u16,
+        filename: String,
+    },
+    /// An entry's "version needed to extract" asks for a feature this crate
+    /// does not support, described by `hint`. The entry cannot be skipped
+    /// safely, so reading stops. See
+    /// [`MuyZipido::with_ignore_version_needed`](crate::MuyZipido::with_ignore_version_needed).
+    ///
+    /// `needed_version` is 0 and `filename` empty when the archive as a whole
+    /// lacks something required of it, such as the signature entry asked for
+    /// with `MuyZipido::with_verify_signatures`.
+    UnsupportedFeature {
+        needed_version: u16,
+        filename: String,
+        hint: String,
+    },
+    /// An entry is larger than the `limit` bytes allowed by
+    /// [`Limits::max_entry_size`](crate::Limits::max_entry_size). The entry
+    /// is skipped.
+    LimitExceeded {
+        limit: u64,
+        filename: String,
+    },
+    /// The archive as a whole went over `limit`, the name of a
+    /// [`Limits`](crate::Limits) field such as `max_download_bytes`, set to
+    /// `max`. Unlike [`ZipError::LimitExceeded`], reading stops.
+    ArchiveLimitExceeded {
+        limit: &'static str,
+        max: u64,
+    },
+    /// The stream ended, or more data than
+    /// [`Limits::max_descriptor_scan`](crate::Limits::max_descriptor_scan)
+    /// went by, while looking for an entry's data descriptor.
+    DescriptorNotFound {
+        filename: String,
+    },
+    /// An entry's decompressed data does not match the CRC-32 recorded in the
+    /// archive.
+    CrcMismatch {
+        filename: String,
+        expected: u32,
+        actual: u32,
+    },
+    /// `source` occurred while reading the data of `filename`, the entry at
+    /// 0-based position `index` among those read by the extractor, with the
+    /// stream at byte `offset`. Use [`ZipError::root`] to match on the
+    /// underlying error.
+    InEntry {
+        filename: String,
+        index: usize,
+        offset: u64,
+        source: Box<ZipError>,
+    },
+    /// A second entry named `filename` was found under
+    /// [`DuplicatePolicy::Error`](crate::extract::DuplicatePolicy::Error).
+    DuplicateEntry {
+        filename: String,
+    },
+    /// The archive ended without a single entry, under
+    /// [`MuyZipido::with_require_non_empty`](crate::MuyZipido::with_require_non_empty).
+    EmptyArchive,
+    /// An entry's headers are inconsistent in the way described, under
+    /// [`MuyZipido::with_strict`](crate::MuyZipido::with_strict). The entry has been
+    /// consumed, so reading can continue.
+    Inconsistent(ZipWarning),
+    /// The archive failed the check set up with
+    /// [`MuyZipido::with_verify_signatures`](crate::MuyZipido::with_verify_signatures),
+    /// or the public key given to it could not be read.
+    #[cfg(feature = "signatures")]
+    SignatureRejected {
+        reason: String,
+    },
+    /// The flag passed to [`MuyZipido::with_cancel_flag`](crate::MuyZipido::with_cancel_flag)
+    /// was set.
+    Cancelled,
+}
+
+impl ZipError {
+    /// The underlying error with any [`ZipError::InEntry`] context removed.
+    pub fn root(&self) -> &ZipError {
+        match self {
+            ZipError::InEntry { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// Whether the error was confined to a single entry that has been fully
+    /// read, leaving the stream positioned at the next entry.
+    ///
+    /// Iteration continues past recoverable errors; any other error ends it.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self.root(),
+            ZipError::CrcMismatch { .. }
+                | ZipError::CorruptData { .. }
+                | ZipError::UnsupportedCompression { .. }
+                | ZipError::LimitExceeded { .. }
+                | ZipError::Inconsistent(_)
+        )
+    }
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "http")]
+            ZipError::Http { url, error } => write!(f, "HTTP error for {}: {}", url, error),
+            #[cfg(feature = "http")]
+            ZipError::RateLimited { url, retry_after } => {
+                write!(f, "Rate limited by {}", url)?;
+                if let Some(wait) = retry_after {
+                    write!(f, ", asked to retry after {}s", wait.as_secs())?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "http")]
+            ZipError::TlsCertificateRejected(reason) => {
+                write!(f, "TLS certificate rejected for {}", reason)
+            }
+            #[cfg(feature = "http")]
+            ZipError::InvalidCertificate(reason) => write!(f, "Invalid certificate: {}", reason),
+            #[cfg(feature = "http")]
+            ZipError::ResponseRejected { url, reason } => {
+                write!(f, "Response from {} rejected: {}", url, reason)
+            }
+            #[cfg(feature = "http")]
+            ZipError::HttpStatus {
+                status,
+                url,
+                body_snippet,
+            } => {
+                write!(f, "HTTP status {} for {}", status, url)?;
+                if !body_snippet.is_empty() {
+                    write!(f, ": {}", body_snippet)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "http")]
+            ZipError::RedirectLoop { chain } => {
+                write!(f, "Too many redirects: {}", chain.join(" -> "))
+            }
+            #[cfg(feature = "http")]
+            ZipError::InvalidUrl { url, reason } => {
+                write!(f, "Invalid URL {:?}: {}", url, reason)
+            }
+            ZipError::UnexpectedEof => write!(f, "Unexpected end of stream"),
+            ZipError::UnexpectedEofInHeader { offset } => write!(
+                f,
+                "Unexpected end of stream in the local header at offset {}",
+                offset
+            ),
+            ZipError::UnexpectedEofInEntry {
+                filename,
+                bytes_missing,
+            } => write!(
+                f,
+                "Unexpected end of stream in {}, {} bytes short",
+                filename, bytes_missing
+            ),
+            ZipError::NetworkInterrupted { offset, reason } => {
+                write!(f, "Network interrupted at offset {}: {}", offset, reason)
+            }
+            ZipError::InvalidSignature { found, offset } => {
+                write!(f, "Invalid signature: {} at offset {}", found, offset)
+            }
+            ZipError::InvalidHeader { offset, reason } => {
+                write!(f, "Invalid local header at offset {}: {}", offset, reason)
+            }
+            ZipError::Io(e) => write!(f, "IO error: {}", e),
+            ZipError::CorruptData { filename, source } => {
+                write!(f, "Decompression error in {}: {}", filename, source)
+            }
+            ZipError::InvalidContent { filename, source } => {
+                write!(f, "Could not parse {}: {}", filename, source)
+            }
+            ZipError::UnsupportedCompression { method, filename } => write!(
+                f,
+                "Unsupported compression ({}) for {}",
+                crate::CompressionMethod::from(*method),
+                filename
+            ),
+            ZipError::UnsupportedFeature {
+                needed_version: 0,
+                hint,
+                ..
+            } => write!(f, "Unsupported archive: {}", hint),
+            ZipError::UnsupportedFeature {
+                needed_version,
+                filename,
+                hint,
+            } => write!(
+                f,
+                "{} needs ZIP version {}.{}: {}",
+                filename,
+                (needed_version & 0xff) / 10,
+                (needed_version & 0xff) % 10,
+                hint
+            ),
+            ZipError::LimitExceeded { limit, filename } => {
+                write!(f, "{} exceeds the limit of {} bytes", filename, limit)
+            }
+            ZipError::ArchiveLimitExceeded { limit, max } => {
+                write!(f, "Archive exceeds {} of {}", limit, max)
+            }
+            ZipError::DescriptorNotFound { filename } => {
+                write!(f, "Data descriptor not found for {}", filename)
+            }
+            ZipError::CrcMismatch {
+                filename,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "CRC-32 mismatch in {}: expected {:08x}, got {:08x}",
+                filename, expected, actual
+            ),
+            ZipError::InEntry {
+                filename,
+                index,
+                offset,
+                source,
+            } => write!(
+                f,
+                "while processing '{}' (entry {}, offset {:#x}): {}",
+                filename, index, offset, source
+            ),
+            ZipError::DuplicateEntry { filename } => {
+                write!(f, "Duplicate entry name {}", filename)
+            }
+            ZipError::EmptyArchive => write!(f, "The archive has no entries"),
+            ZipError::Inconsistent(warning) => write!(f, "Inconsistent headers: {}", warning),
+            #[cfg(feature = "signatures")]
+            ZipError::SignatureRejected { reason } => {
+                write!(f, "Signature check failed: {}", reason)
+            }
+            ZipError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl Error for ZipError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "http")]
+            ZipError::Http { error, .. } => Some(error),
+            ZipError::Io(e) => Some(e),
+            ZipError::CorruptData { source, .. } => Some(source),
+            ZipError::InvalidContent { source, .. } => Some(source.as_ref()),
+            ZipError::InEntry { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Something unusual about an archive that did not stop it being read.
+///
+/// Collected by [`MuyZipido::warnings`](crate::MuyZipido::warnings) and
+/// passed to the handler set with
+/// [`MuyZipido::with_warning_handler`](crate::MuyZipido::with_warning_handler).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum ZipWarning {
+    /// The local header of `filename` recorded an uncompressed size of
+    /// `header` bytes, but its data descriptor, or its data once decoded and
+    /// checked against the CRC-32, gave `actual`.
+    SizeMismatch {
+        filename: String,
+        header: u64,
+        actual: u64,
+    },
+    /// The name of the entry at `offset` was not valid UTF-8 and was decoded
+    /// as code page 437 to give `filename`.
+    Cp437Filename { filename: String, offset: u64 },
+    /// An extra field of `filename` claims more bytes than the extra data
+    /// holds. Fields from the damaged one on are ignored. Under
+    /// [`MuyZipido::with_strict`](crate::MuyZipido::with_strict), trailing bytes too
+    /// short for a field count too.
+    TruncatedExtraField { filename: String },
+    /// The whole response was read and came to `received` bytes, not the
+    /// `expected` announced by its Content-Length.
+    ContentLengthMismatch { expected: u64, received: u64 },
+    /// `filename` needs ZIP version `needed_version`, which was not checked
+    /// because of [`MuyZipido::with_ignore_version_needed`](crate::MuyZipido::with_ignore_version_needed).
+    VersionNeededIgnored {
+        filename: String,
+        needed_version: u16,
+    },
+    /// `filename` is flagged as having a data descriptor, but its local
+    /// header also records sizes, which should then be zero.
+    DescriptorWithSizes { filename: String },
+    /// `filename` has no data and no data descriptor, but its local header
+    /// records an uncompressed size of `uncompressed_size` bytes. The entry
+    /// is read as empty.
+    EmptyDataWithSize {
+        filename: String,
+        uncompressed_size: u64,
+    },
+    /// `filename` records an uncompressed size of 0 but decoded to `actual`
+    /// bytes, which are kept.
+    DataWithZeroSize { filename: String, actual: u64 },
+    /// A second entry named `filename` was found under
+    /// [`DuplicatePolicy::Warn`](crate::extract::DuplicatePolicy::Warn).
+    DuplicateEntry { filename: String },
+    /// The stream ended at `offset`, where the next local header would
+    /// start, without a central directory, and
+    /// [`MuyZipido::with_allow_missing_central_directory`](crate::MuyZipido::with_allow_missing_central_directory)
+    /// let the archive end there.
+    MissingCentralDirectory { offset: u64 },
+}
+
+impl fmt::Display for ZipWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZipWarning::SizeMismatch {
+                filename,
+                header,
+                actual,
+            } => write!(
+                f,
+                "{} is {} bytes, but its local header says {}",
+                filename, actual, header
+            ),
+            ZipWarning::Cp437Filename { filename, offset } => write!(
+                f,
+                "Name of entry at offset {} is not UTF-8, decoded as CP437: {}",
+                offset, filename
+            ),
+            ZipWarning::TruncatedExtraField { filename } => {
+                write!(f, "Truncated extra field in {}", filename)
+            }
+            ZipWarning::ContentLengthMismatch { expected, received } => write!(
+                f,
+                "Received {} bytes, but Content-Length was {}",
+                received, expected
+            ),
+            ZipWarning::VersionNeededIgnored {
+                filename,
+                needed_version,
+            } => write!(
+                f,
+                "Ignored ZIP version {}.{} needed by {}",
+                (needed_version & 0xff) / 10,
+                (needed_version & 0xff) % 10,
+                filename
+            ),
+            ZipWarning::DescriptorWithSizes { filename } => write!(
+                f,
+                "{} has a data descriptor but sizes in its local header",
+                filename
+            ),
+            ZipWarning::EmptyDataWithSize {
+                filename,
+                uncompressed_size,
+            } => write!(
+                f,
+                "{} has no data, but its local header says {} bytes",
+                filename, uncompressed_size
+            ),
+            ZipWarning::DataWithZeroSize { filename, actual } => write!(
+                f,
+                "{} is {} bytes, but its local header says it is empty",
+                filename, actual
+            ),
+            ZipWarning::DuplicateEntry { filename } => {
+                write!(f, "Duplicate entry name {}", filename)
+            }
+            ZipWarning::MissingCentralDirectory { offset } => write!(
+                f,
+                "Stream ended at offset {} without a central directory",
+                offset
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for ZipError {
+    fn from(e: std::io::Error) -> Self {
+        ZipError::Io(e)
+    }
+}
@@ -0,0 +1,1046 @@
+use crate::{EntryInfo, EntrySink, MuyZipido, ZipError, ZipWarning};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// What to do when an entry's target path already exists on disk, e.g.
+/// when several archives are extracted into one directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace the existing file.
+    Overwrite,
+    /// Leave the existing file alone and record the entry as skipped.
+    #[default]
+    Skip,
+    /// Leave the existing file alone and record the entry as failed.
+    Error,
+    /// Leave the existing file alone and write the entry next to it as
+    /// `name (1).ext`, or the first of `name (2).ext`, `name (3).ext`, ...
+    /// that is free. [`WrittenFile::path`] records the name used.
+    ///
+    /// Files are created only if they do not exist yet, so two extractions
+    /// into the same directory never claim the same name.
+    Rename,
+}
+
+/// What to do with an entry whose name was already used by an earlier entry,
+/// as in archives where an updated member was appended without removing the
+/// old one. Set with [`MuyZipido::with_duplicates`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Treat every entry on its own.
+    #[default]
+    KeepAll,
+    /// Skip entries whose name has already been seen.
+    KeepFirst,
+    /// Let later entries replace earlier ones with the same name.
+    ///
+    /// This needs to know what comes later, so it only applies to
+    /// [`MuyZipido::extract_to_dir`], which replaces the file it wrote for
+    /// the earlier entry, and [`MuyZipido::list_entries`], which holds back
+    /// the listing until the end of the archive. Iterating the extractor
+    /// yields every copy, as with [`DuplicatePolicy::KeepAll`].
+    KeepLast,
+    /// Treat every entry on its own, as with [`DuplicatePolicy::KeepAll`],
+    /// but raise a [`ZipWarning::DuplicateEntry`](crate::ZipWarning::DuplicateEntry)
+    /// for each repeated name.
+    Warn,
+    /// Fail with [`ZipError::DuplicateEntry`] at the first repeated name.
+    Error,
+}
+
+/// Settings for [`MuyZipido::extract_to_dir_with`].
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    pub overwrite: OverwritePolicy,
+    /// Report what would be written without touching the filesystem.
+    pub dry_run: bool,
+    /// Record entries that cannot be decoded or fail CRC-32 verification in
+    /// `ExtractReport::failed` and carry on, instead of aborting extraction.
+    /// See [`ZipError::is_recoverable`].
+    pub keep_going: bool,
+    /// Compute a SHA-256 digest of every written file.
+    #[cfg(feature = "sha256")]
+    pub checksums: bool,
+    /// Create symbolic link entries as links on Unix instead of as regular
+    /// files holding the link target. Links whose target would lead outside
+    /// the output directory, including by way of links already written, are
+    /// skipped as unsafe.
+    pub allow_symlinks: bool,
+    /// Set the modification time of written files and directories from
+    /// [`EntryInfo::modified_time`].
+    pub preserve_mtime: bool,
+    /// Set the permissions of written files and directories from
+    /// [`EntryInfo::unix_mode`], with the bits in `umask` cleared. Has no
+    /// effect on Windows.
+    pub preserve_permissions: bool,
+    /// Permission bits never set by `preserve_permissions`. Defaults to
+    /// `0o022`.
+    pub umask: u32,
+    /// Called after each file has been written to disk.
+    pub on_written: Option<EntryCallback>,
+    /// Directory holding the index that
+    /// [`with_cache_dir`](ExtractOptions::with_cache_dir) keeps.
+    #[cfg(feature = "serde")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// A callback run for an entry during extraction.
+#[derive(Clone)]
+pub struct EntryCallback(Arc<dyn Fn(&EntryInfo) + Send + Sync>);
+
+impl fmt::Debug for EntryCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EntryCallback")
+    }
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            overwrite: OverwritePolicy::default(),
+            dry_run: false,
+            keep_going: false,
+            #[cfg(feature = "sha256")]
+            checksums: false,
+            allow_symlinks: false,
+            preserve_mtime: false,
+            preserve_permissions: false,
+            umask: 0o022,
+            on_written: None,
+            #[cfg(feature = "serde")]
+            cache_dir: None,
+        }
+    }
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_overwrite(mut self, policy: OverwritePolicy) -> Self {
+        self.overwrite = policy;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    pub fn with_allow_symlinks(mut self, allow_symlinks: bool) -> Self {
+        self.allow_symlinks = allow_symlinks;
+        self
+    }
+
+    pub fn with_preserve_mtime(mut self, preserve: bool) -> Self {
+        self.preserve_mtime = preserve;
+        self
+    }
+
+    pub fn with_preserve_permissions(mut self, preserve: bool) -> Self {
+        self.preserve_permissions = preserve;
+        self
+    }
+
+    pub fn with_umask(mut self, umask: u32) -> Self {
+        self.umask = umask;
+        self
+    }
+
+    /// Runs `callback` after each file is written, e.g. to record progress
+    /// that should survive an interrupted extraction. It is not called in
+    /// dry runs.
+    pub fn with_on_written<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&EntryInfo) + Send + Sync + 'static,
+    {
+        self.on_written = Some(EntryCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Keeps an index in `dir` of the CRC-32, size and modification time of
+    /// each file written, and skips entries that match it, whose files are
+    /// still in place, as [`SkipReason::Unchanged`]. Repeated extraction
+    /// of a mostly unchanged archive then leaves the unchanged files alone.
+    ///
+    /// The index belongs to one output directory; one that is damaged,
+    /// from another version of this crate or for another directory is
+    /// ignored and replaced. Entries with a data descriptor only have their
+    /// CRC-32 once read, so they are decompressed before being skipped, and
+    /// only when the overwrite policy would replace them. Duplicate entry
+    /// names are always written.
+    #[cfg(feature = "serde")]
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    #[cfg(feature = "sha256")]
+    pub fn with_checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtractReport {
+    pub written: Vec<WrittenFile>,
+    pub skipped: Vec<SkippedEntry>,
+    pub failed: Vec<ExtractFailure>,
+    pub bytes_written: u64,
+    /// Warnings raised while extracting, also in [`MuyZipido::warnings`].
+    pub warnings: Vec<ZipWarning>,
+    /// Set when nothing was actually written (see [`ExtractOptions::dry_run`]).
+    pub dry_run: bool,
+}
+
+/// A file written to disk, or that would have been in a dry run.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WrittenFile {
+    /// The name of the entry in the archive.
+    pub filename: String,
+    pub path: PathBuf,
+    pub size: u64,
+    /// Hex SHA-256 digest, when [`ExtractOptions::checksums`] is set.
+    #[cfg(feature = "sha256")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub sha256: Option<String>,
+}
+
+/// An entry that was deliberately not written.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SkippedEntry {
+    pub filename: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SkipReason {
+    /// The entry name would escape the output directory.
+    UnsafePath,
+    /// The target exists and the policy is [`OverwritePolicy::Skip`].
+    AlreadyExists,
+    /// Another entry with the same name takes precedence under the
+    /// [`DuplicatePolicy`].
+    Duplicate,
+    /// The file was written from identical data by an earlier extraction;
+    /// see [`ExtractOptions::with_cache_dir`].
+    Unchanged,
+}
+
+/// Summary of [`MuyZipido::process_with`].
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcessReport {
+    /// Entries given a writer, in archive order.
+    pub processed: Vec<ProcessedEntry>,
+    /// Names of the entries the sink factory declined.
+    pub skipped: Vec<String>,
+    pub bytes_written: u64,
+}
+
+/// An entry handed to a writer by [`MuyZipido::process_with`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcessedEntry {
+    pub filename: String,
+    /// Bytes the writer accepted.
+    pub bytes_written: u64,
+    /// Why the entry was not written in full: the sink factory or writer
+    /// failed, or the entry could not be decoded.
+    pub error: Option<String>,
+}
+
+/// Summary of [`MuyZipido::validate`].
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ValidationReport {
+    /// Every entry checked, in archive order.
+    pub entries: Vec<ValidatedEntry>,
+    /// Warnings raised while reading, also in [`MuyZipido::warnings`].
+    pub warnings: Vec<ZipWarning>,
+}
+
+impl ValidationReport {
+    /// Whether every entry passed. Warnings do not count against it.
+    pub fn is_valid(&self) -> bool {
+        self.entries.iter().all(|entry| entry.problems.is_empty())
+    }
+
+    /// The entries with problems.
+    pub fn findings(&self) -> impl Iterator<Item = &ValidatedEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.problems.is_empty())
+    }
+}
+
+/// An entry checked by [`MuyZipido::validate`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ValidatedEntry {
+    pub filename: String,
+    /// Bytes of data decoded, which is all of it unless decoding failed.
+    pub size: u64,
+    /// Why the entry would not extract cleanly; empty if it would.
+    pub problems: Vec<String>,
+}
+
+/// An entry that was read successfully but could not be written to disk.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtractFailure {
+    pub filename: String,
+    pub error: String,
+}
+
+impl MuyZipido {
+    /// Extracts every remaining entry below `dir` with the default
+    /// [`ExtractOptions`], which skip files that already exist.
+    pub fn extract_to_dir(&mut self, dir: impl AsRef<Path>) -> Result<ExtractReport, ZipError> {
+        self.extract_to_dir_with(dir, &ExtractOptions::default())
+    }
+
+    /// Extracts every remaining entry below `dir`, creating directories as needed.
+    ///
+    /// Leading `/` separators are stripped; entries whose names would still
+    /// escape `dir` (`..` components or drive prefixes), or that would be
+    /// written through a symbolic link leading outside it, are not written and
+    /// are listed in `ExtractReport::skipped`. Existing files are handled according
+    /// to `options.overwrite`. Entries that are not going to be written are
+    /// skipped in the stream without being decompressed.
+    ///
+    /// Files are decompressed straight to disk a chunk at a time, as in
+    /// [`MuyZipido::process_with`]. They go to a temporary file moved into
+    /// place once the entry checks out, so a corrupt entry never replaces an
+    /// existing file. Entries with a data descriptor and symbolic links are
+    /// read into memory first.
+    ///
+    /// Entries with repeated names are handled according to the extractor's
+    /// [`DuplicatePolicy`].
+    ///
+    /// Failures writing an individual file are recorded in
+    /// `ExtractReport::failed` and extraction continues; errors reading the
+    /// archive itself abort extraction, as do corrupt entries unless
+    /// `options.keep_going` is set.
+    pub fn extract_to_dir_with(
+        &mut self,
+        dir: impl AsRef<Path>,
+        options: &ExtractOptions,
+    ) -> Result<ExtractReport, ZipError> {
+        let result = self.extract_entries(dir.as_ref(), options);
+        if result.as_ref().is_err_and(|e| !e.is_recoverable()) {
+            self.finished = true;
+        }
+        result
+    }
+
+    /// Streams each remaining entry into a writer chosen by `sink_factory`,
+    /// without collecting the entries in memory.
+    ///
+    /// The factory sees each entry's metadata and returns a writer for its
+    /// data, or `None` to skip the entry without decompressing it. Data is
+    /// decompressed and written a chunk at a time, except for entries with a
+    /// data descriptor, which are buffered first since their size is only
+    /// known at the end. [`Transform`](crate::Transform)s are not applied.
+    ///
+    /// Failures of the factory or a writer, and entries that cannot be
+    /// decoded, are recorded in the report and processing continues; errors
+    /// reading the archive itself are returned.
+    pub fn process_with<F>(mut self, mut sink_factory: F) -> Result<ProcessReport, ZipError>
+    where
+        F: FnMut(&EntryInfo) -> io::Result<Option<Box<dyn Write>>>,
+    {
+        let mut report = ProcessReport::default();
+
+        while let Some((mut info, has_data_descriptor)) = self.next_accepted()? {
+            let mut writer = match sink_factory(&info) {
+                Ok(Some(writer)) => writer,
+                Ok(None) => {
+                    self.pass_over_entry(&mut info, has_data_descriptor)?;
+                    report.skipped.push(info.filename);
+                    continue;
+                }
+                Err(e) => {
+                    self.pass_over_entry(&mut info, has_data_descriptor)?;
+                    report.processed.push(ProcessedEntry {
+                        filename: info.filename,
+                        bytes_written: 0,
+                        error: Some(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+
+            let mut sink = EntrySink::new(&mut *writer);
+            let result = self.stream_entry_data(&mut info, has_data_descriptor, &mut sink);
+            let bytes_written = sink.written;
+            let sink_error = sink.finish();
+            let error = match result {
+                Ok(()) => sink_error.map(|e| e.to_string()),
+                Err(e) if e.is_recoverable() => Some(e.to_string()),
+                Err(e) => {
+                    self.finished = true;
+                    return Err(e);
+                }
+            };
+
+            report.bytes_written += bytes_written;
+            report.processed.push(ProcessedEntry {
+                filename: info.filename,
+                bytes_written,
+                error,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Reads the whole archive and checks that it would extract cleanly,
+    /// without keeping any entry data.
+    ///
+    /// Each entry is decompressed a chunk at a time, checked against its
+    /// CRC-32 and the [`Limits`](crate::Limits), and its name checked with
+    /// [`sanitize_path`]. Entries that fail, including those with an
+    /// unsupported compression method, are listed with their problems and
+    /// the rest are still checked; errors reading the archive itself are
+    /// returned. Entries with a data descriptor, and all entries when a
+    /// [custom decompressor](MuyZipido::with_decompressor) is set, are
+    /// buffered one at a time as in [`MuyZipido::process_with`].
+    ///
+    /// When a signature check is set, a missing signature fails with
+    /// [`ZipError::UnsupportedFeature`] and a mismatched one with
+    /// `ZipError::SignatureRejected` once every entry has been read.
+    pub fn validate(mut self) -> Result<ValidationReport, ZipError> {
+        let mut report = ValidationReport::default();
+
+        while let Some((mut info, has_data_descriptor)) = self.next_accepted()? {
+            let mut discard = io::sink();
+            let mut sink = EntrySink::new(&mut discard);
+            let mut problems = Vec::new();
+            match self.stream_entry_data(&mut info, has_data_descriptor, &mut sink) {
+                Ok(()) => {}
+                Err(e) if e.is_recoverable() => problems.push(e.root().to_string()),
+                Err(e) => {
+                    self.finished = true;
+                    return Err(e);
+                }
+            }
+            if sanitize_path(&info.filename).is_none() {
+                problems.push("Unsafe path that would escape the output directory".to_string());
+            }
+
+            report.entries.push(ValidatedEntry {
+                filename: info.filename,
+                size: sink.decoded,
+                problems,
+            });
+        }
+        report.warnings = self.warnings.clone();
+
+        Ok(report)
+    }
+
+    fn extract_entries(
+        &mut self,
+        dir: &Path,
+        options: &ExtractOptions,
+    ) -> Result<ExtractReport, ZipError> {
+        let mut report = ExtractReport {
+            dry_run: options.dry_run,
+            ..ExtractReport::default()
+        };
+        let warnings_before = self.warnings.len();
+
+        // Links already written, by this archive or an earlier one, are
+        // followed when checking where an entry would end up.
+        let root = match options.dry_run {
+            true => None,
+            false => {
+                fs::create_dir_all(dir)?;
+                Some(dir.canonicalize()?)
+            }
+        };
+        #[cfg(feature = "serde")]
+        let mut index = options
+            .cache_dir
+            .as_deref()
+            .map(|cache_dir| crate::extract_cache::ExtractIndex::load(cache_dir, dir));
+        // Directory metadata is applied once everything is written, since
+        // creating files inside a directory changes its modification time.
+        let mut directories = Vec::new();
+
+        while !self.finished {
+            let Some((mut info, has_data_descriptor)) = self.read_local_header()? else {
+                self.finish_signature_check()?;
+                break;
+            };
+
+            if !self.accepts(&info) {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                continue;
+            }
+
+            let duplicate = self.is_duplicate(&info)?;
+            if duplicate && self.duplicates == DuplicatePolicy::KeepFirst {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                report.skip(info, SkipReason::Duplicate);
+                continue;
+            }
+
+            let Some(relative) = sanitize_path(&info.filename) else {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                report.skip(info, SkipReason::UnsafePath);
+                continue;
+            };
+            let target = dir.join(&relative);
+            if root
+                .as_deref()
+                .is_some_and(|root| !resolves_inside(root, &target))
+            {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                report.skip(info, SkipReason::UnsafePath);
+                continue;
+            }
+
+            if info.is_dir {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                if options.dry_run {
+                    continue;
+                }
+                match fs::create_dir_all(&target) {
+                    Ok(()) => directories.push((target, info)),
+                    Err(e) => report.fail(info, e.to_string()),
+                }
+                continue;
+            }
+
+            #[cfg(feature = "serde")]
+            if !has_data_descriptor
+                && !duplicate
+                && index
+                    .as_ref()
+                    .is_some_and(|index| index.is_unchanged(&info, &target))
+            {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                report.skip(info, SkipReason::Unchanged);
+                continue;
+            }
+
+            // Under KeepLast a file written for an earlier copy is replaced
+            // whatever the overwrite policy says.
+            let superseded = (duplicate && self.duplicates == DuplicatePolicy::KeepLast)
+                .then(|| report.written.iter().position(|file| file.path == target))
+                .flatten();
+            let rename = options.overwrite == OverwritePolicy::Rename && superseded.is_none();
+            if let Some(index) = superseded {
+                let earlier = report.written.remove(index);
+                report.bytes_written -= earlier.size;
+                report.skipped.push(SkippedEntry {
+                    filename: info.filename.clone(),
+                    reason: SkipReason::Duplicate,
+                });
+            } else if target.exists() {
+                match options.overwrite {
+                    OverwritePolicy::Overwrite | OverwritePolicy::Rename => {}
+                    OverwritePolicy::Skip => {
+                        self.pass_over_entry(&mut info, has_data_descriptor)?;
+                        report.skip(info, SkipReason::AlreadyExists);
+                        continue;
+                    }
+                    OverwritePolicy::Error => {
+                        self.pass_over_entry(&mut info, has_data_descriptor)?;
+                        let error = format!("{} already exists", target.display());
+                        report.fail(info, error);
+                        continue;
+                    }
+                }
+            }
+
+            if options.dry_run {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                let path = match rename {
+                    true => candidates(&target)
+                        .find(|path| {
+                            !path.exists() && !report.written.iter().any(|file| &file.path == path)
+                        })
+                        .expect("candidate names never run out"),
+                    false => target,
+                };
+                report.bytes_written += info.uncompressed_size;
+                report.written.push(WrittenFile {
+                    filename: info.filename,
+                    path,
+                    size: info.uncompressed_size,
+                    #[cfg(feature = "sha256")]
+                    sha256: None,
+                });
+                continue;
+            }
+
+            // Links are checked before they are created, and entries with a
+            // data descriptor are only known to be unchanged once read, so
+            // those are read into memory first. Everything else is streamed
+            // straight into its file.
+            let symlink = options.allow_symlinks && info.is_symlink();
+            #[cfg(feature = "sha256")]
+            let sha256;
+            let (written, size) = if symlink || has_data_descriptor {
+                let data = match self.read_entry_data(&mut info, has_data_descriptor) {
+                    Ok(data) => data,
+                    Err(e) if options.keep_going && e.is_recoverable() => {
+                        report.fail(info, e.to_string());
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                #[cfg(feature = "serde")]
+                if has_data_descriptor
+                    && !duplicate
+                    && !rename
+                    && index
+                        .as_ref()
+                        .is_some_and(|index| index.is_unchanged(&info, &target))
+                {
+                    report.skip(info, SkipReason::Unchanged);
+                    continue;
+                }
+
+                if symlink
+                    && !(link_stays_inside(&relative, &data)
+                        && root
+                            .as_deref()
+                            .is_none_or(|root| link_resolves_inside(root, &target, &data)))
+                {
+                    report.skip(info, SkipReason::UnsafePath);
+                    continue;
+                }
+                #[cfg(feature = "sha256")]
+                {
+                    sha256 = options
+                        .checksums
+                        .then(|| crate::checksum::sha256_hex(&data));
+                }
+                let written = match (symlink, rename) {
+                    (true, false) => write_symlink(&target, &data, true).map(|()| target),
+                    (true, true) => {
+                        create_renamed(&target, |path| write_symlink(path, &data, false))
+                            .map(|(path, ())| path)
+                    }
+                    (false, false) => write_file(&target, &data).map(|()| target),
+                    (false, true) => create_renamed(&target, |path| write_new_file(path, &data))
+                        .map(|(path, ())| path),
+                };
+                (written, data.len() as u64)
+            } else {
+                // Without renaming, the data goes to a temporary file moved
+                // over `target` once the entry checks out, so a corrupt entry
+                // leaves an existing file alone.
+                let opened = match rename {
+                    true => create_renamed(&target, create_new_file)
+                        .map(|(path, file)| (path.clone(), path, file)),
+                    false => {
+                        let temp = crate::entry::temp_sibling(&target);
+                        create_new_file(&temp).map(|file| (temp, target.clone(), file))
+                    }
+                };
+                let (temp, path, file) = match opened {
+                    Ok(opened) => opened,
+                    Err(e) => {
+                        self.pass_over_entry(&mut info, has_data_descriptor)?;
+                        report.fail(info, e.to_string());
+                        continue;
+                    }
+                };
+
+                let mut writer = io::BufWriter::new(file);
+                let mut sink = EntrySink::new(&mut writer);
+                #[cfg(feature = "sha256")]
+                if options.checksums {
+                    sink = sink.with_sha256();
+                }
+                let result = self.stream_entry_data(&mut info, has_data_descriptor, &mut sink);
+                #[cfg(feature = "sha256")]
+                {
+                    sha256 = sink
+                        .sha256
+                        .take()
+                        .map(crate::checksum::Sha256Hasher::finish);
+                }
+                let size = sink.decoded;
+                let sink_error = sink.finish();
+                drop(writer);
+
+                let moved = match sink_error {
+                    Some(e) => Err(e),
+                    None if result.is_ok() && temp != path => fs::rename(&temp, &path),
+                    None => Ok(()),
+                };
+                // A file cut short by a bad entry or a failed write is not
+                // left behind.
+                if result.is_err() || moved.is_err() {
+                    let _ = fs::remove_file(&temp);
+                }
+                match result {
+                    Ok(()) => {}
+                    Err(e) if options.keep_going && e.is_recoverable() => {
+                        report.fail(info, e.to_string());
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+                (moved.map(|()| path), size)
+            };
+            let written = written.and_then(|path| match symlink {
+                true => Ok(path),
+                false => set_metadata(&path, &info, options).map(|()| path),
+            });
+
+            #[cfg(feature = "serde")]
+            if let Some(index) = &mut index {
+                match &written {
+                    Ok(_) if !symlink && !rename => index.record(&info),
+                    _ => index.forget(&info.filename),
+                }
+            }
+
+            match written {
+                Ok(path) => {
+                    if let Some(EntryCallback(callback)) = &options.on_written {
+                        callback(&info);
+                    }
+                    report.bytes_written += size;
+                    report.written.push(WrittenFile {
+                        filename: info.filename,
+                        path,
+                        size,
+                        #[cfg(feature = "sha256")]
+                        sha256,
+                    });
+                }
+                Err(e) => report.fail(info, e.to_string()),
+            }
+        }
+
+        for (path, info) in directories.into_iter().rev() {
+            if let Err(e) = set_metadata(&path, &info, options) {
+                report.fail(info, e.to_string());
+            }
+        }
+        #[cfg(feature = "serde")]
+        if let Some(index) = index.filter(|_| !options.dry_run)
+            && let Err(e) = index.save()
+        {
+            // Without the index the next extraction writes everything.
+            self.log(format_args!("Could not save the extraction cache: {}", e));
+        }
+        report.warnings = self.warnings[warnings_before..].to_vec();
+
+        Ok(report)
+    }
+}
+
+impl ExtractReport {
+    /// Number of entries skipped as [`SkipReason::Unchanged`].
+    pub fn unchanged(&self) -> usize {
+        self.skipped
+            .iter()
+            .filter(|entry| entry.reason == SkipReason::Unchanged)
+            .count()
+    }
+
+    fn skip(&mut self, info: EntryInfo, reason: SkipReason) {
+        self.skipped.push(SkippedEntry {
+            filename: info.filename,
+            reason,
+        });
+    }
+
+    fn fail(&mut self, info: EntryInfo, error: String) {
+        self.failed.push(ExtractFailure {
+            filename: info.filename,
+            error,
+        });
+    }
+}
+
+fn write_file(target: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(target, data)
+}
+
+/// Like [`write_file`], but fails with [`io::ErrorKind::AlreadyExists`]
+/// instead of replacing an existing file.
+fn write_new_file(target: &Path, data: &[u8]) -> std::io::Result<()> {
+    create_new_file(target)?.write_all(data)
+}
+
+/// Creates `target` and any missing parent directories, failing with
+/// [`io::ErrorKind::AlreadyExists`] if it exists.
+fn create_new_file(target: &Path) -> std::io::Result<fs::File> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)
+}
+
+/// `target`, then `target` numbered from 1 as in `name (1).ext`.
+fn candidates(target: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    (0..).map(|n| match n {
+        0 => target.to_path_buf(),
+        n => {
+            let mut name = OsString::from(target.file_stem().unwrap_or_default());
+            name.push(format!(" ({})", n));
+            if let Some(extension) = target.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+            target.with_file_name(name)
+        }
+    })
+}
+
+/// Creates the first free name from [`candidates`] with `create`, which must
+/// fail with [`io::ErrorKind::AlreadyExists`] rather than replace a file, and
+/// returns the path used with what `create` returned.
+fn create_renamed<T>(
+    target: &Path,
+    mut create: impl FnMut(&Path) -> io::Result<T>,
+) -> io::Result<(PathBuf, T)> {
+    for path in candidates(target) {
+        match create(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|created| (path, created)),
+        }
+    }
+    unreachable!("candidate names never run out")
+}
+
+/// Applies the modification time and permissions of `info` to the file or
+/// directory at `path`, as far as `options` asks for them.
+fn set_metadata(path: &Path, info: &EntryInfo, options: &ExtractOptions) -> std::io::Result<()> {
+    if options.preserve_mtime
+        && let Some(modified) = info.modified_time()
+    {
+        open_for_times(path, info.is_dir)?.set_modified(modified)?;
+    }
+
+    // Permissions last, as they may make the file unreadable.
+    #[cfg(unix)]
+    if options.preserve_permissions
+        && let Some(mode) = info.unix_mode
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let permissions = fs::Permissions::from_mode(mode & 0o7777 & !options.umask);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// Opens `path` with enough access to change its timestamps.
+#[cfg(not(windows))]
+fn open_for_times(path: &Path, _is_dir: bool) -> std::io::Result<fs::File> {
+    // Setting times only needs ownership, so read-only access is enough,
+    // including for directories.
+    fs::File::open(path)
+}
+
+/// Opens `path` with enough access to change its timestamps.
+#[cfg(windows)]
+fn open_for_times(path: &Path, is_dir: bool) -> std::io::Result<fs::File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    let mut options = fs::OpenOptions::new();
+    options.access_mode(FILE_WRITE_ATTRIBUTES);
+    if is_dir {
+        options.custom_flags(FILE_FLAG_BACKUP_SEMANTICS);
+    }
+    options.open(path)
+}
+
+/// Whether the symbolic link at `link`, relative to the output directory,
+/// pointing to `target` stays inside the output directory.
+fn link_stays_inside(link: &Path, target: &[u8]) -> bool {
+    let Ok(target) = std::str::from_utf8(target) else {
+        return false;
+    };
+    let mut depth = link.components().count().saturating_sub(1);
+
+    for part in target.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." if depth == 0 => return false,
+            ".." => depth -= 1,
+            _ if part.contains(':') => return false,
+            _ => depth += 1,
+        }
+    }
+    !target.starts_with(['/', '\\'])
+}
+
+/// Whether the symbolic link at `link` pointing to `target` resolves inside
+/// `root`, following any links already on disk.
+fn link_resolves_inside(root: &Path, link: &Path, target: &[u8]) -> bool {
+    let (Some(parent), Ok(target)) = (link.parent(), std::str::from_utf8(target)) else {
+        return false;
+    };
+    resolves_inside(root, &parent.join(target))
+}
+
+/// Whether `path` stays inside `root`, a canonical path, once the links
+/// already on disk along it are followed. Parts that do not exist yet are
+/// taken as they are; a link whose target does not exist counts as outside,
+/// since writing through it could create anything.
+fn resolves_inside(root: &Path, path: &Path) -> bool {
+    let Ok(path) = std::path::absolute(path) else {
+        return false;
+    };
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(part) => {
+                resolved.push(part);
+                match resolved.canonicalize() {
+                    Ok(real) => resolved = real,
+                    Err(_) if resolved.symlink_metadata().is_ok() => return false,
+                    Err(_) => {}
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => resolved.push(component),
+        }
+    }
+    resolved.starts_with(root)
+}
+
+/// Creates the link, replacing a file already at `link` if `replace` is set
+/// and otherwise failing with [`io::ErrorKind::AlreadyExists`].
+#[cfg(unix)]
+fn write_symlink(link: &Path, target: &[u8], replace: bool) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if replace && link.symlink_metadata().is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(link)?;
+    }
+    std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(target), link)
+}
+
+/// Links are written as regular files holding the target where creating
+/// them needs extra privileges.
+#[cfg(not(unix))]
+fn write_symlink(link: &Path, target: &[u8], replace: bool) -> std::io::Result<()> {
+    match replace {
+        true => write_file(link, target),
+        false => write_new_file(link, target),
+    }
+}
+
+/// Converts an entry name into a relative path that stays inside the output
+/// directory, or `None` if the name is unsafe or empty.
+pub fn sanitize_path(name: &str) -> Option<PathBuf> {
+    let normalized = name.replace('\\', "/");
+    let mut path = PathBuf::new();
+
+    for part in normalized.split('/') {
+        match Path::new(part).components().next() {
+            None | Some(Component::CurDir) => continue,
+            Some(Component::Normal(_)) if !part.contains(':') => path.push(part),
+            _ => return None,
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_stays_inside() {
+        let inside =
+            |link: &str, target: &str| link_stays_inside(Path::new(link), target.as_bytes());
+        assert!(inside("latest", "v2/data.csv"));
+        assert!(inside("a/b/link", "../c"));
+        assert!(inside("a/link", "./../x"));
+        assert!(!inside("link", "../outside"));
+        assert!(!inside("a/link", "../../outside"));
+        assert!(!inside("link", "/etc/passwd"));
+        assert!(!inside("link", "C:/windows"));
+    }
+
+    #[test]
+    fn test_candidates() {
+        let names = |target: &str| {
+            candidates(Path::new(target))
+                .take(3)
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names("docs/readme.txt"),
+            [
+                "docs/readme.txt",
+                "docs/readme (1).txt",
+                "docs/readme (2).txt"
+            ]
+        );
+        assert_eq!(names("LICENSE")[1], "LICENSE (1)");
+        assert_eq!(names(".env")[1], ".env (1)");
+        assert_eq!(names("data.tar.gz")[1], "data.tar (1).gz");
+    }
+
+    #[test]
+    fn test_sanitize_path() {
+        assert_eq!(sanitize_path("a/b.txt"), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(sanitize_path("./a//b.txt"), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(sanitize_path("dir/"), Some(PathBuf::from("dir")));
+        assert_eq!(sanitize_path("a\\b.txt"), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(sanitize_path("../evil.txt"), None);
+        assert_eq!(sanitize_path("a/../../evil.txt"), None);
+        assert_eq!(
+            sanitize_path("/etc/passwd"),
+            Some(PathBuf::from("etc/passwd"))
+        );
+        assert_eq!(sanitize_path("C:/windows.txt"), None);
+        assert_eq!(sanitize_path(""), None);
+    }
+}
@@ -0,0 +1,156 @@
+//! The index kept by [`ExtractOptions::with_cache_dir`](crate::extract::ExtractOptions::with_cache_dir),
+//! recording what was last written for each entry so unchanged entries are
+//! not written again.
+
+use crate::EntryInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const INDEX_FILE: &str = "extract-index.json";
+/// Indexes written with another version are ignored.
+const VERSION: u32 = 1;
+
+/// What identifies the data an entry was extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Identity {
+    crc32: u32,
+    size: u64,
+    /// Seconds since the Unix epoch
+    mtime: Option<u64>,
+}
+
+impl Identity {
+    fn of(info: &EntryInfo) -> Self {
+        Self {
+            crc32: info.crc32,
+            size: info.uncompressed_size,
+            mtime: info
+                .modified_time()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|age| age.as_secs()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    /// The output directory the entries were written to
+    dir: PathBuf,
+    entries: HashMap<String, Identity>,
+}
+
+pub(crate) struct ExtractIndex {
+    path: PathBuf,
+    file: IndexFile,
+}
+
+impl ExtractIndex {
+    /// Loads the index in `cache_dir` for extracting into `dir`. One that is
+    /// missing, unreadable, from another version or for another directory
+    /// is replaced by an empty one.
+    pub(crate) fn load(cache_dir: &Path, dir: &Path) -> Self {
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let path = cache_dir.join(INDEX_FILE);
+        let file = fs::read(&path)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<IndexFile>(&json).ok())
+            .filter(|file| file.version == VERSION && file.dir == dir)
+            .unwrap_or_else(|| IndexFile {
+                version: VERSION,
+                dir,
+                entries: HashMap::new(),
+            });
+        Self { path, file }
+    }
+
+    /// Whether `target` still holds the data last written there for an
+    /// entry with the same name as `info`.
+    pub(crate) fn is_unchanged(&self, info: &EntryInfo, target: &Path) -> bool {
+        self.file.entries.get(&info.filename) == Some(&Identity::of(info))
+            && fs::symlink_metadata(target)
+                .is_ok_and(|meta| meta.is_file() && meta.len() == info.uncompressed_size)
+    }
+
+    pub(crate) fn record(&mut self, info: &EntryInfo) {
+        self.file
+            .entries
+            .insert(info.filename.clone(), Identity::of(info));
+    }
+
+    pub(crate) fn forget(&mut self, filename: &str) {
+        self.file.entries.remove(filename);
+    }
+
+    /// Writes the index through a temporary file, so an interrupted save
+    /// leaves the previous one in place.
+    pub(crate) fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = self.path.with_extension("json.partial");
+        let json = serde_json::to_vec(&self.file).map_err(io::Error::from)?;
+        fs::write(&temp, json)?;
+        fs::rename(&temp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(filename: &str, crc32: u32, size: u64) -> EntryInfo {
+        EntryInfo {
+            filename: filename.to_string(),
+            compressed_size: size,
+            uncompressed_size: size,
+            compression_method: crate::CompressionMethod::Stored,
+            crc32,
+            modified: None,
+            is_dir: false,
+            version_needed: 20,
+            filename_raw: None,
+            unix_mode: None,
+            unix_mtime: Some(1_700_000_000),
+            header_offset: 0,
+            data_offset: 0,
+            data_len_on_wire: size,
+        }
+    }
+
+    #[test]
+    fn test_damaged_or_foreign_index_is_cold() {
+        let cache = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let target = out.path().join("a.txt");
+        fs::write(&target, b"hello").unwrap();
+
+        let mut index = ExtractIndex::load(cache.path(), out.path());
+        index.record(&info("a.txt", 1, 5));
+        index.save().unwrap();
+
+        let index = ExtractIndex::load(cache.path(), out.path());
+        assert!(index.is_unchanged(&info("a.txt", 1, 5), &target));
+        assert!(!index.is_unchanged(&info("a.txt", 2, 5), &target));
+
+        // Another output directory does not share the index.
+        let other = tempfile::tempdir().unwrap();
+        let index = ExtractIndex::load(cache.path(), other.path());
+        assert!(!index.is_unchanged(&info("a.txt", 1, 5), &target));
+
+        let path = cache.path().join(INDEX_FILE);
+        let json = fs::read_to_string(&path).unwrap();
+        for damaged in [
+            json.replace("\"version\":1", "\"version\":99"),
+            json[..json.len() / 2].to_string(),
+        ] {
+            fs::write(&path, damaged).unwrap();
+            let index = ExtractIndex::load(cache.path(), out.path());
+            assert!(!index.is_unchanged(&info("a.txt", 1, 5), &target));
+        }
+    }
+}
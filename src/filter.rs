@@ -0,0 +1,202 @@
+//! Reusable entry filters for [`MuyZipido::with_entry_filter`](crate::MuyZipido::with_entry_filter).
+//!
+//! Filters see each entry's local header before its data is read, so
+//! rejected entries are skipped without being buffered or decompressed.
+//! They combine with [`ZipEntryFilter::and`], [`ZipEntryFilter::or`] and
+//! [`ZipEntryFilter::not`]:
+//!
+//! ```
+//! use muy_zipido::filter::{DirectoryFilter, GlobFilter, SizeFilter, ZipEntryFilter};
+//!
+//! let csv_under_1mb = GlobFilter::new("**/*.csv")
+//!     .and(SizeFilter { min: 0, max: 1024 * 1024 })
+//!     .and(DirectoryFilter.not());
+//! ```
+
+use crate::entry::{CompressionMethod, EntryInfo};
+use crate::glob::Pattern;
+
+/// Decides whether an entry is read.
+pub trait ZipEntryFilter: Send + Sync {
+    fn accepts(&self, info: &EntryInfo) -> bool;
+
+    /// Accepts entries accepted by both filters.
+    fn and<F: ZipEntryFilter>(self, other: F) -> And<Self, F>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Accepts entries accepted by either filter.
+    fn or<F: ZipEntryFilter>(self, other: F) -> Or<Self, F>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// Accepts the entries this filter rejects.
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F> ZipEntryFilter for F
+where
+    F: Fn(&EntryInfo) -> bool + Send + Sync,
+{
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        self(info)
+    }
+}
+
+/// See [`ZipEntryFilter::and`].
+#[derive(Debug, Clone)]
+pub struct And<A, B>(A, B);
+
+impl<A: ZipEntryFilter, B: ZipEntryFilter> ZipEntryFilter for And<A, B> {
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        self.0.accepts(info) && self.1.accepts(info)
+    }
+}
+
+/// See [`ZipEntryFilter::or`].
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(A, B);
+
+impl<A: ZipEntryFilter, B: ZipEntryFilter> ZipEntryFilter for Or<A, B> {
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        self.0.accepts(info) || self.1.accepts(info)
+    }
+}
+
+/// See [`ZipEntryFilter::not`].
+#[derive(Debug, Clone)]
+pub struct Not<A>(A);
+
+impl<A: ZipEntryFilter> ZipEntryFilter for Not<A> {
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        !self.0.accepts(info)
+    }
+}
+
+/// Accepts entries whose full path matches a [glob pattern](crate::glob::Pattern).
+#[derive(Debug, Clone)]
+pub struct GlobFilter(Pattern);
+
+impl GlobFilter {
+    pub fn new(pattern: &str) -> Self {
+        Self(Pattern::new(pattern))
+    }
+}
+
+impl From<Pattern> for GlobFilter {
+    fn from(pattern: Pattern) -> Self {
+        Self(pattern)
+    }
+}
+
+impl ZipEntryFilter for GlobFilter {
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        self.0.matches(&info.filename)
+    }
+}
+
+/// Accepts entries whose uncompressed size is between `min` and `max`
+/// bytes, inclusive.
+///
+/// Entries streamed with a data descriptor only record their size after
+/// the data, so they are seen as 0 bytes long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeFilter {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl ZipEntryFilter for SizeFilter {
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        (self.min..=self.max).contains(&info.uncompressed_size)
+    }
+}
+
+/// Accepts entries compressed with the given method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionFilter(pub CompressionMethod);
+
+impl ZipEntryFilter for CompressionFilter {
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        info.compression_method == self.0
+    }
+}
+
+/// Accepts directory entries. Use `DirectoryFilter.not()` to leave them out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirectoryFilter;
+
+impl ZipEntryFilter for DirectoryFilter {
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        info.is_dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(filename: &str, size: u64, method: CompressionMethod) -> EntryInfo {
+        EntryInfo {
+            filename: filename.to_string(),
+            compressed_size: size,
+            uncompressed_size: size,
+            compression_method: method,
+            crc32: 0,
+            modified: None,
+            is_dir: filename.ends_with('/'),
+            version_needed: 20,
+            filename_raw: None,
+            unix_mode: None,
+            unix_mtime: None,
+            header_offset: 0,
+            data_offset: 0,
+            data_len_on_wire: size,
+        }
+    }
+
+    #[test]
+    fn test_builtin_filters() {
+        let csv = info("data/a.csv", 100, CompressionMethod::Deflate);
+        let dir = info("data/", 0, CompressionMethod::Stored);
+
+        assert!(GlobFilter::new("**/*.csv").accepts(&csv));
+        assert!(!GlobFilter::new("*.csv").accepts(&csv));
+        assert!(SizeFilter { min: 100, max: 100 }.accepts(&csv));
+        assert!(!SizeFilter { min: 101, max: 200 }.accepts(&csv));
+        assert!(CompressionFilter(CompressionMethod::Deflate).accepts(&csv));
+        assert!(!CompressionFilter(CompressionMethod::Deflate).accepts(&dir));
+        assert!(DirectoryFilter.accepts(&dir));
+        assert!(!DirectoryFilter.accepts(&csv));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let csv = info("a.csv", 100, CompressionMethod::Deflate);
+        let txt = info("a.txt", 5, CompressionMethod::Stored);
+        let big = |info: &EntryInfo| info.uncompressed_size > 50;
+
+        let filter = GlobFilter::new("*.csv").and(big);
+        assert!(filter.accepts(&csv));
+        assert!(!filter.accepts(&txt));
+
+        let filter = GlobFilter::new("*.txt").or(big);
+        assert!(filter.accepts(&csv));
+        assert!(filter.accepts(&txt));
+
+        let filter = CompressionFilter(CompressionMethod::Stored).not();
+        assert!(filter.accepts(&csv));
+        assert!(!filter.accepts(&txt));
+    }
+}
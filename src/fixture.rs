@@ -0,0 +1,308 @@
+//! Archive fixtures for tests, enabled by the `test-util` feature.
+
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use std::io::Write;
+
+/// Builds small, valid ZIP archives in memory: local headers, data
+/// descriptors, central directory and end of central directory record.
+///
+/// ```
+/// use muy_zipido::MuyZipido;
+/// use muy_zipido::fixture::ArchiveBuilder;
+///
+/// let archive = ArchiveBuilder::new()
+///     .stored("a.txt", b"hello")
+///     .deflated("b.csv", b"x,y\n1,2\n")
+///     .with_data_descriptor("c.bin", &[0u8; 64])
+///     .build();
+///
+/// let names: Vec<String> = MuyZipido::from_bytes(archive, 1024)
+///     .map(|entry| entry.unwrap().info.filename)
+///     .collect();
+/// assert_eq!(names, ["a.txt", "b.csv", "c.bin"]);
+/// ```
+#[derive(Default)]
+pub struct ArchiveBuilder {
+    local: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+    /// Local extra field and external attributes for the next entry
+    extra: Vec<u8>,
+    external_attrs: u32,
+    /// Uncompressed size for the next entry's local header, if not its own
+    declared_size: Option<u32>,
+    /// Flags and Zip64 sizes for the next entry
+    encrypted: bool,
+    zip64_sizes: bool,
+    comment: Vec<u8>,
+    zip64_end: bool,
+    unsigned_descriptors: bool,
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an uncompressed entry.
+    pub fn stored(self, name: &str, data: &[u8]) -> Self {
+        self.entry(name, data, 0, false)
+    }
+
+    /// Adds a deflated entry with its sizes in the local header.
+    pub fn deflated(self, name: &str, data: &[u8]) -> Self {
+        self.entry(name, data, 8, false)
+    }
+
+    /// Adds an uncompressed entry whose sizes and CRC-32 follow the data in
+    /// a data descriptor.
+    pub fn stored_with_descriptor(self, name: &str, data: &[u8]) -> Self {
+        self.entry(name, data, 0, true)
+    }
+
+    /// Adds a deflated entry whose sizes and CRC-32 follow the data in a data
+    /// descriptor, as written by streaming ZIP writers.
+    pub fn with_data_descriptor(self, name: &str, data: &[u8]) -> Self {
+        self.entry(name, data, 8, true)
+    }
+
+    /// Adds an entry whose data is `payload` exactly as given, labelled with
+    /// `method`, for building corrupt or unsupported entries.
+    pub fn raw(self, name: &str, method: u16, payload: &[u8], descriptor: bool) -> Self {
+        self.push(name, payload.to_vec(), 0, payload.len(), method, descriptor)
+    }
+
+    /// Adds a symbolic link to `target`, as written by Info-ZIP's `zip -y`:
+    /// the target is the entry's data, and the Unix mode is recorded in the
+    /// external attributes and an ASi Unix extra field.
+    pub fn symlink(self, name: &str, target: &str) -> Self {
+        self.unix_extra(0o120777, target.as_bytes())
+            .entry(name, target.as_bytes(), 0, false)
+    }
+
+    /// Records `mode`, e.g. `0o100755`, for the next entry in its external
+    /// attributes and an ASi Unix extra field.
+    pub fn with_unix_mode(self, mode: u16) -> Self {
+        self.unix_extra(mode, &[])
+    }
+
+    /// Records `seconds` since the Unix epoch as the next entry's
+    /// modification time in an extended timestamp extra field.
+    pub fn with_mtime(mut self, seconds: u32) -> Self {
+        self.extra.extend_from_slice(&0x5455u16.to_le_bytes());
+        self.extra.extend_from_slice(&5u16.to_le_bytes());
+        self.extra.push(1);
+        self.extra.extend_from_slice(&seconds.to_le_bytes());
+        self
+    }
+
+    /// Records `size` as the uncompressed size in the next entry's local
+    /// header in place of the real one, for building inconsistent headers.
+    /// Ignored for entries with a data descriptor.
+    pub fn with_declared_size(mut self, size: u32) -> Self {
+        self.declared_size = Some(size);
+        self
+    }
+
+    /// Sets the encrypted flag on the next entry, without encrypting its data.
+    pub fn with_encrypted_flag(mut self) -> Self {
+        self.encrypted = true;
+        self
+    }
+
+    /// Moves the sizes in the next entry's local header into a Zip64 extra
+    /// field, leaving `0xFFFFFFFF` in their place, and marks it as needing
+    /// version 4.5. For an entry with a data descriptor the field holds
+    /// zeros and the descriptor has 64-bit sizes.
+    pub fn with_zip64_sizes(mut self) -> Self {
+        self.zip64_sizes = true;
+        self
+    }
+
+    /// Writes data descriptors without their optional signature.
+    pub fn with_unsigned_descriptors(mut self) -> Self {
+        self.unsigned_descriptors = true;
+        self
+    }
+
+    /// Sets the archive comment in the end of central directory record.
+    pub fn with_comment(mut self, comment: &[u8]) -> Self {
+        self.comment = comment.to_vec();
+        self
+    }
+
+    /// Ends the archive with Zip64 end of central directory records, and
+    /// the classic record's counts, size and offset saturated as Zip64
+    /// writers leave them.
+    pub fn with_zip64_end_records(mut self) -> Self {
+        self.zip64_end = true;
+        self
+    }
+
+    fn unix_extra(mut self, mode: u16, link_target: &[u8]) -> Self {
+        let mut unix = Vec::new();
+        unix.extend_from_slice(&mode.to_le_bytes());
+        unix.extend_from_slice(&0u32.to_le_bytes());
+        unix.extend_from_slice(&[0u8; 4]);
+        unix.extend_from_slice(link_target);
+        let mut crc = flate2::Crc::new();
+        crc.update(&unix);
+
+        self.extra.extend_from_slice(&0x756eu16.to_le_bytes());
+        self.extra
+            .extend_from_slice(&(unix.len() as u16 + 4).to_le_bytes());
+        self.extra.extend_from_slice(&crc.sum().to_le_bytes());
+        self.extra.extend_from_slice(&unix);
+        self.external_attrs = (mode as u32) << 16;
+        self
+    }
+
+    fn entry(self, name: &str, data: &[u8], method: u16, descriptor: bool) -> Self {
+        let payload = if method == 8 {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        } else {
+            data.to_vec()
+        };
+
+        let mut crc = flate2::Crc::new();
+        crc.update(data);
+
+        self.push(name, payload, crc.sum(), data.len(), method, descriptor)
+    }
+
+    fn push(
+        mut self,
+        name: &str,
+        payload: Vec<u8>,
+        crc: u32,
+        uncompressed_len: usize,
+        method: u16,
+        descriptor: bool,
+    ) -> Self {
+        let data_len = uncompressed_len as u32;
+
+        let mut flags: u16 = if descriptor { 0x08 } else { 0 };
+        if std::mem::take(&mut self.encrypted) {
+            flags |= 0x01;
+        }
+        let declared_size = self.declared_size.take();
+        let (header_crc, mut header_compressed, mut header_uncompressed) = if descriptor {
+            (0, 0, 0)
+        } else {
+            (crc, payload.len() as u32, declared_size.unwrap_or(data_len))
+        };
+        let header_offset = self.local.len() as u32;
+        let mut extra = std::mem::take(&mut self.extra);
+        let external_attrs = std::mem::take(&mut self.external_attrs);
+
+        let zip64 = std::mem::take(&mut self.zip64_sizes);
+        if zip64 {
+            let mut field = Vec::new();
+            field.extend_from_slice(&0x0001u16.to_le_bytes());
+            field.extend_from_slice(&16u16.to_le_bytes());
+            field.extend_from_slice(&(header_uncompressed as u64).to_le_bytes());
+            field.extend_from_slice(&(header_compressed as u64).to_le_bytes());
+            extra.splice(0..0, field);
+            (header_compressed, header_uncompressed) = (u32::MAX, u32::MAX);
+        }
+        let version_needed: u16 = if zip64 { 45 } else { 20 };
+
+        self.local.extend_from_slice(b"PK\x03\x04");
+        self.local.extend_from_slice(&version_needed.to_le_bytes());
+        self.local.extend_from_slice(&flags.to_le_bytes());
+        self.local.extend_from_slice(&method.to_le_bytes());
+        self.local.extend_from_slice(&0u16.to_le_bytes());
+        self.local.extend_from_slice(&0x21u16.to_le_bytes());
+        self.local.extend_from_slice(&header_crc.to_le_bytes());
+        self.local
+            .extend_from_slice(&header_compressed.to_le_bytes());
+        self.local
+            .extend_from_slice(&header_uncompressed.to_le_bytes());
+        self.local
+            .extend_from_slice(&(name.len() as u16).to_le_bytes());
+        self.local
+            .extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        self.local.extend_from_slice(name.as_bytes());
+        self.local.extend_from_slice(&extra);
+        self.local.extend_from_slice(&payload);
+
+        if descriptor {
+            if !self.unsigned_descriptors {
+                self.local.extend_from_slice(b"PK\x07\x08");
+            }
+            self.local.extend_from_slice(&crc.to_le_bytes());
+            if zip64 {
+                self.local
+                    .extend_from_slice(&(payload.len() as u64).to_le_bytes());
+                self.local
+                    .extend_from_slice(&(uncompressed_len as u64).to_le_bytes());
+            } else {
+                self.local
+                    .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+                self.local.extend_from_slice(&data_len.to_le_bytes());
+            }
+        }
+
+        self.central.extend_from_slice(b"PK\x01\x02");
+        self.central.extend_from_slice(&20u16.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes());
+        self.central.extend_from_slice(&flags.to_le_bytes());
+        self.central.extend_from_slice(&method.to_le_bytes());
+        self.central.extend_from_slice(&0u16.to_le_bytes());
+        self.central.extend_from_slice(&0x21u16.to_le_bytes());
+        self.central.extend_from_slice(&crc.to_le_bytes());
+        self.central
+            .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.central.extend_from_slice(&data_len.to_le_bytes());
+        self.central
+            .extend_from_slice(&(name.len() as u16).to_le_bytes());
+        self.central.extend_from_slice(&[0u8; 8]);
+        self.central
+            .extend_from_slice(&external_attrs.to_le_bytes());
+        self.central.extend_from_slice(&header_offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+        self
+    }
+
+    /// Returns the finished archive.
+    pub fn build(self) -> Vec<u8> {
+        let mut archive = self.local;
+        let mut central_offset = archive.len() as u32;
+        archive.extend_from_slice(&self.central);
+
+        let (mut entries, mut central_len) = (self.entries, self.central.len() as u32);
+        if self.zip64_end {
+            let record_offset = archive.len() as u64;
+            archive.extend_from_slice(b"PK\x06\x06");
+            archive.extend_from_slice(&44u64.to_le_bytes());
+            archive.extend_from_slice(&45u16.to_le_bytes());
+            archive.extend_from_slice(&45u16.to_le_bytes());
+            archive.extend_from_slice(&[0u8; 8]);
+            archive.extend_from_slice(&(entries as u64).to_le_bytes());
+            archive.extend_from_slice(&(entries as u64).to_le_bytes());
+            archive.extend_from_slice(&(central_len as u64).to_le_bytes());
+            archive.extend_from_slice(&(central_offset as u64).to_le_bytes());
+
+            archive.extend_from_slice(b"PK\x06\x07");
+            archive.extend_from_slice(&0u32.to_le_bytes());
+            archive.extend_from_slice(&record_offset.to_le_bytes());
+            archive.extend_from_slice(&1u32.to_le_bytes());
+            (entries, central_len, central_offset) = (u16::MAX, u32::MAX, u32::MAX);
+        }
+
+        archive.extend_from_slice(b"PK\x05\x06");
+        archive.extend_from_slice(&[0u8; 4]);
+        archive.extend_from_slice(&entries.to_le_bytes());
+        archive.extend_from_slice(&entries.to_le_bytes());
+        archive.extend_from_slice(&central_len.to_le_bytes());
+        archive.extend_from_slice(&central_offset.to_le_bytes());
+        archive.extend_from_slice(&(self.comment.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&self.comment);
+        archive
+    }
+}
@@ -0,0 +1,294 @@
+/// A compiled glob pattern matched against full entry paths using `/` separators.
+///
+/// Supported syntax:
+/// - `*` matches any run of characters except `/`
+/// - `**` matches any run of characters including `/` (`**/` also matches zero directories)
+/// - `?` matches a single character except `/`
+/// - `[abc]`, `[a-z]` and `[!abc]` match character classes
+/// - `\` escapes the next character
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    tokens: Vec<Token>,
+    case_insensitive: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(char),
+    Any,
+    Star,
+    DoubleStar,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Self {
+        Self::with_case(pattern, false)
+    }
+
+    pub fn case_insensitive(pattern: &str) -> Self {
+        Self::with_case(pattern, true)
+    }
+
+    fn with_case(pattern: &str, case_insensitive: bool) -> Self {
+        let normalized: String = if case_insensitive {
+            pattern.to_lowercase()
+        } else {
+            pattern.to_string()
+        };
+
+        Self {
+            source: pattern.to_string(),
+            tokens: tokenize(&normalized),
+            case_insensitive,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns true if the pattern matches the whole path.
+    pub fn matches(&self, path: &str) -> bool {
+        let chars: Vec<char> = if self.case_insensitive {
+            path.to_lowercase().chars().collect()
+        } else {
+            path.chars().collect()
+        };
+        match_tokens(&self.tokens, &chars)
+    }
+
+    /// Returns true if the pattern matches the whole path or any of its parent
+    /// directories, so `Doc` and `Doc/*` both select `Doc/sub/readme.txt`.
+    pub fn matches_path_or_parent(&self, path: &str) -> bool {
+        let trimmed = path.trim_end_matches('/');
+        if self.matches(path) || self.matches(trimmed) {
+            return true;
+        }
+
+        trimmed
+            .match_indices('/')
+            .any(|(idx, _)| self.matches(&trimmed[..idx]))
+    }
+}
+
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' => {
+                if chars.get(i + 1) == Some(&'*') {
+                    tokens.push(Token::DoubleStar);
+                    i += 2;
+                    // `**/` also matches zero directories
+                    if chars.get(i) == Some(&'/') {
+                        i += 1;
+                    }
+                } else {
+                    tokens.push(Token::Star);
+                    i += 1;
+                }
+            }
+            '?' => {
+                tokens.push(Token::Any);
+                i += 1;
+            }
+            '[' => match parse_class(&chars, i) {
+                Some((token, next)) => {
+                    tokens.push(token);
+                    i = next;
+                }
+                None => {
+                    tokens.push(Token::Literal('['));
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < chars.len() => {
+                tokens.push(Token::Literal(chars[i + 1]));
+                i += 2;
+            }
+            c => {
+                tokens.push(Token::Literal(c));
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+fn parse_class(chars: &[char], start: usize) -> Option<(Token, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(chars.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    let mut first = true;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == ']' && !first {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            ranges.push((c, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+        first = false;
+    }
+
+    None
+}
+
+fn match_tokens(tokens: &[Token], path: &[char]) -> bool {
+    match tokens.first() {
+        None => path.is_empty(),
+        Some(Token::Literal(c)) => {
+            path.first() == Some(c) && match_tokens(&tokens[1..], &path[1..])
+        }
+        Some(Token::Any) => {
+            path.first().is_some_and(|&c| c != '/') && match_tokens(&tokens[1..], &path[1..])
+        }
+        Some(Token::Class { negated, ranges }) => {
+            path.first().is_some_and(|&c| {
+                c != '/' && ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }) && match_tokens(&tokens[1..], &path[1..])
+        }
+        Some(Token::Star) => {
+            for i in 0..=path.len() {
+                if match_tokens(&tokens[1..], &path[i..]) {
+                    return true;
+                }
+                if i < path.len() && path[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some(Token::DoubleStar) => (0..=path.len()).any(|i| match_tokens(&tokens[1..], &path[i..])),
+    }
+}
+
+/// Include/exclude selection over entry paths.
+///
+/// Excludes always win over includes, and with no includes every entry is a
+/// candidate. Patterns also select everything below a matching directory.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+}
+
+impl PathFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include(mut self, pattern: Pattern) -> Self {
+        self.includes.push(pattern);
+        self
+    }
+
+    pub fn exclude(mut self, pattern: Pattern) -> Self {
+        self.excludes.push(pattern);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.includes.is_empty() && self.excludes.is_empty()
+    }
+
+    pub fn accepts(&self, path: &str) -> bool {
+        if self
+            .excludes
+            .iter()
+            .any(|pattern| pattern.matches_path_or_parent(path))
+        {
+            return false;
+        }
+
+        self.includes.is_empty()
+            || self
+                .includes
+                .iter()
+                .any(|pattern| pattern.matches_path_or_parent(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_star_does_not_cross_separator() {
+        let pattern = Pattern::new("*.csv");
+        assert!(pattern.matches("points.csv"));
+        assert!(!pattern.matches("data/points.csv"));
+        assert!(!pattern.matches("points.csv.gz"));
+    }
+
+    #[test]
+    fn test_double_star_and_classes() {
+        let pattern = Pattern::new("**/*.csv");
+        assert!(pattern.matches("points.csv"));
+        assert!(pattern.matches("a/b/points.csv"));
+
+        let pattern = Pattern::new("data/file[0-9]?.t[!x]t");
+        assert!(pattern.matches("data/file1a.tmt"));
+        assert!(!pattern.matches("data/fileXa.tmt"));
+        assert!(!pattern.matches("data/file1a.txt"));
+    }
+
+    #[test]
+    fn test_directory_prefix_matching() {
+        let pattern = Pattern::new("Doc");
+        assert!(pattern.matches_path_or_parent("Doc/"));
+        assert!(pattern.matches_path_or_parent("Doc/readme.txt"));
+        assert!(pattern.matches_path_or_parent("Doc/sub/readme.txt"));
+        assert!(!pattern.matches_path_or_parent("Docs/readme.txt"));
+
+        let pattern = Pattern::new("Doc/*");
+        assert!(pattern.matches_path_or_parent("Doc/sub/readme.txt"));
+        assert!(!pattern.matches_path_or_parent("Doc"));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert!(!Pattern::new("*.CSV").matches("points.csv"));
+        assert!(Pattern::case_insensitive("*.CSV").matches("Points.csv"));
+    }
+
+    #[test]
+    fn test_filter_precedence() {
+        let filter = PathFilter::new();
+        assert!(filter.accepts("anything/at/all.bin"));
+
+        let filter = PathFilter::new()
+            .include(Pattern::new("**/*.csv"))
+            .include(Pattern::new("Doc"))
+            .exclude(Pattern::new("Doc/*"));
+
+        assert!(filter.accepts("data/points.csv"));
+        assert!(!filter.accepts("data/points.gpkg"));
+        // Overlapping include and exclude: exclude wins
+        assert!(!filter.accepts("Doc/points.csv"));
+        assert!(!filter.accepts("Doc/readme.txt"));
+
+        let filter = PathFilter::new().exclude(Pattern::new("*.txt"));
+        assert!(!filter.accepts("readme.txt"));
+        assert!(filter.accepts("sub/readme.txt"));
+    }
+}
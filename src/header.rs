@@ -0,0 +1,769 @@
+//! Parsing the records of a ZIP file from byte slices, without any I/O.
+//!
+//! The extractor reads records off the stream and parses them here, but the
+//! parsers work on any slice, e.g. to examine headers found inside another
+//! file. Each `parse` expects the record to start at the beginning of the
+//! slice, signature included, and returns it with the number of bytes it
+//! takes up. A slice too short for the record fails with
+//! [`ZipError::UnexpectedEof`], and one starting with the wrong signature
+//! with [`ZipError::InvalidSignature`] at offset 0.
+
+use crate::entry::{CompressionMethod, ZipDateTime};
+use crate::error::ZipError;
+use crate::parser::{read_le_u16, read_le_u32, read_le_u64};
+
+pub const LOCAL_FILE_HEADER_SIG: [u8; 4] = *b"PK\x03\x04";
+pub const DATA_DESCRIPTOR_SIG: [u8; 4] = *b"PK\x07\x08";
+pub const CENTRAL_DIRECTORY_HEADER_SIG: [u8; 4] = *b"PK\x01\x02";
+pub const END_OF_CENTRAL_DIRECTORY_SIG: [u8; 4] = *b"PK\x05\x06";
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_SIG: [u8; 4] = *b"PK\x06\x06";
+pub const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIG: [u8; 4] = *b"PK\x06\x07";
+
+/// Size fields holding this value are stored in a Zip64 extra field instead.
+const ZIP64_SENTINEL: u32 = u32::MAX;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// The general purpose bit flags of a local or central directory header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Flags(pub u16);
+
+impl Flags {
+    /// The data is encrypted.
+    pub const ENCRYPTED: Self = Self(0x0001);
+    /// The sizes and CRC-32 follow the data in a data descriptor.
+    pub const DATA_DESCRIPTOR: Self = Self(0x0008);
+    /// The name and comment are UTF-8.
+    pub const UTF8: Self = Self(0x0800);
+
+    /// Whether every bit set in `other` is set here.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_encrypted(self) -> bool {
+        self.contains(Self::ENCRYPTED)
+    }
+
+    pub fn has_data_descriptor(self) -> bool {
+        self.contains(Self::DATA_DESCRIPTOR)
+    }
+
+    pub fn is_utf8(self) -> bool {
+        self.contains(Self::UTF8)
+    }
+}
+
+impl std::ops::BitOr for Flags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// The header in front of each entry's data.
+///
+/// Sizes recorded in a Zip64 extra field replace the 32-bit ones they stand
+/// in for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalFileHeader {
+    pub version_needed: u16,
+    pub flags: Flags,
+    pub compression_method: CompressionMethod,
+    pub mod_time: u16,
+    pub mod_date: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    /// The entry name, as stored.
+    pub filename: Vec<u8>,
+    pub extra_field: Vec<u8>,
+}
+
+impl LocalFileHeader {
+    /// Length of the header without the name and extra field.
+    pub const FIXED_LEN: usize = 30;
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), ZipError> {
+        let (filename_len, extra_len) = Self::variable_lengths(bytes)?;
+        let name_start = Self::FIXED_LEN;
+        let extra_start = name_start + filename_len;
+        let len = extra_start + extra_len;
+        let bytes = bytes.get(..len).ok_or(ZipError::UnexpectedEof)?;
+
+        let extra_field = bytes[extra_start..].to_vec();
+        let (uncompressed_size, compressed_size) =
+            zip64_sizes(&extra_field, read_le_u32(bytes, 22), read_le_u32(bytes, 18));
+        let header = Self {
+            version_needed: read_le_u16(bytes, 4),
+            flags: Flags(read_le_u16(bytes, 6)),
+            compression_method: CompressionMethod::from(read_le_u16(bytes, 8)),
+            mod_time: read_le_u16(bytes, 10),
+            mod_date: read_le_u16(bytes, 12),
+            crc32: read_le_u32(bytes, 14),
+            compressed_size,
+            uncompressed_size,
+            filename: bytes[name_start..extra_start].to_vec(),
+            extra_field,
+        };
+        Ok((header, len))
+    }
+
+    /// The lengths of the name and extra field of the header whose fixed
+    /// part starts `bytes`, for reading the rest of it.
+    pub fn variable_lengths(bytes: &[u8]) -> Result<(usize, usize), ZipError> {
+        let fixed = bytes
+            .get(..Self::FIXED_LEN)
+            .ok_or(ZipError::UnexpectedEof)?;
+        check_signature(fixed, LOCAL_FILE_HEADER_SIG)?;
+        Ok((
+            read_le_u16(fixed, 26) as usize,
+            read_le_u16(fixed, 28) as usize,
+        ))
+    }
+
+    /// The DOS modification time, if it holds a date.
+    pub fn modified(&self) -> Option<ZipDateTime> {
+        ZipDateTime::from_dos(self.mod_date, self.mod_time)
+    }
+
+    /// The Unix mode from an ASi Unix extra field, if any.
+    pub fn unix_mode(&self) -> Option<u32> {
+        unix_mode(&self.extra_field)
+    }
+
+    /// The modification time from an extended timestamp extra field, if any.
+    pub fn unix_mtime(&self) -> Option<u32> {
+        unix_mtime(&self.extra_field)
+    }
+
+    /// Whether the header has a Zip64 extra field, in which case a data
+    /// descriptor after the entry holds 64-bit sizes.
+    pub fn has_zip64_field(&self) -> bool {
+        extra_field(&self.extra_field, ZIP64_EXTRA_ID).is_some()
+    }
+}
+
+/// The CRC-32 and sizes that follow an entry's data when its header has
+/// [`Flags::DATA_DESCRIPTOR`] set. The signature in front is optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataDescriptor {
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+impl DataDescriptor {
+    /// Length of the fields after the signature, with 32-bit sizes.
+    pub const LEN: usize = 12;
+    /// Length of the fields after the signature, with 64-bit sizes.
+    pub const ZIP64_LEN: usize = 20;
+
+    /// Parses a descriptor with 32-bit sizes.
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), ZipError> {
+        Self::parse_sized(bytes, false)
+    }
+
+    /// Parses a descriptor with 64-bit sizes, as written after entries
+    /// whose local header has a Zip64 extra field.
+    pub fn parse_zip64(bytes: &[u8]) -> Result<(Self, usize), ZipError> {
+        Self::parse_sized(bytes, true)
+    }
+
+    fn parse_sized(bytes: &[u8], zip64: bool) -> Result<(Self, usize), ZipError> {
+        let start = match bytes.starts_with(&DATA_DESCRIPTOR_SIG) {
+            true => 4,
+            false => 0,
+        };
+        let len = if zip64 { Self::ZIP64_LEN } else { Self::LEN };
+        let fields = bytes
+            .get(start..start + len)
+            .ok_or(ZipError::UnexpectedEof)?;
+        let (compressed_size, uncompressed_size) = match zip64 {
+            true => (read_le_u64(fields, 4), read_le_u64(fields, 12)),
+            false => (read_le_u32(fields, 4) as u64, read_le_u32(fields, 8) as u64),
+        };
+        let descriptor = Self {
+            crc32: read_le_u32(fields, 0),
+            compressed_size,
+            uncompressed_size,
+        };
+        Ok((descriptor, start + len))
+    }
+}
+
+/// One file header from the central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CentralDirectoryHeader {
+    pub version_made_by: u16,
+    pub version_needed: u16,
+    pub flags: Flags,
+    pub compression_method: CompressionMethod,
+    pub mod_time: u16,
+    pub mod_date: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    pub disk_start: u16,
+    pub internal_attrs: u16,
+    /// Host-dependent attributes; the upper 16 bits hold the Unix mode for
+    /// archives written on Unix.
+    pub external_attrs: u32,
+    /// Offset of the entry's local header from the start of the archive.
+    pub local_header_offset: u64,
+    pub filename: Vec<u8>,
+    pub extra_field: Vec<u8>,
+    pub comment: Vec<u8>,
+}
+
+impl CentralDirectoryHeader {
+    /// Length of the header without the name, extra field and comment.
+    pub const FIXED_LEN: usize = 46;
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), ZipError> {
+        let fixed = bytes
+            .get(..Self::FIXED_LEN)
+            .ok_or(ZipError::UnexpectedEof)?;
+        check_signature(fixed, CENTRAL_DIRECTORY_HEADER_SIG)?;
+        let name_start = Self::FIXED_LEN;
+        let extra_start = name_start + read_le_u16(fixed, 28) as usize;
+        let comment_start = extra_start + read_le_u16(fixed, 30) as usize;
+        let len = comment_start + read_le_u16(fixed, 32) as usize;
+        let bytes = bytes.get(..len).ok_or(ZipError::UnexpectedEof)?;
+
+        let extra_field = bytes[extra_start..comment_start].to_vec();
+        let (uncompressed_size, compressed_size) =
+            zip64_sizes(&extra_field, read_le_u32(bytes, 24), read_le_u32(bytes, 20));
+        // The offset comes after the sizes in the Zip64 field.
+        let local_header_offset = match read_le_u32(bytes, 42) {
+            ZIP64_SENTINEL => {
+                let skip = [read_le_u32(bytes, 24), read_le_u32(bytes, 20)]
+                    .iter()
+                    .filter(|&&size| size == ZIP64_SENTINEL)
+                    .count();
+                extra_field_u64(&extra_field, ZIP64_EXTRA_ID, skip).unwrap_or(ZIP64_SENTINEL as u64)
+            }
+            offset => offset as u64,
+        };
+
+        let header = Self {
+            version_made_by: read_le_u16(bytes, 4),
+            version_needed: read_le_u16(bytes, 6),
+            flags: Flags(read_le_u16(bytes, 8)),
+            compression_method: CompressionMethod::from(read_le_u16(bytes, 10)),
+            mod_time: read_le_u16(bytes, 12),
+            mod_date: read_le_u16(bytes, 14),
+            crc32: read_le_u32(bytes, 16),
+            compressed_size,
+            uncompressed_size,
+            disk_start: read_le_u16(bytes, 34),
+            internal_attrs: read_le_u16(bytes, 36),
+            external_attrs: read_le_u32(bytes, 38),
+            local_header_offset,
+            filename: bytes[name_start..extra_start].to_vec(),
+            extra_field,
+            comment: bytes[comment_start..].to_vec(),
+        };
+        Ok((header, len))
+    }
+
+    /// The DOS modification time, if it holds a date.
+    pub fn modified(&self) -> Option<ZipDateTime> {
+        ZipDateTime::from_dos(self.mod_date, self.mod_time)
+    }
+}
+
+/// The record that ends an archive and locates its central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndOfCentralDirectory {
+    pub disk_number: u16,
+    pub start_disk: u16,
+    pub entries_on_disk: u16,
+    pub total_entries: u16,
+    pub central_dir_size: u32,
+    pub central_dir_offset: u32,
+    pub comment: Vec<u8>,
+}
+
+impl EndOfCentralDirectory {
+    /// Length of the record without the comment.
+    pub const FIXED_LEN: usize = 22;
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), ZipError> {
+        let fixed = bytes
+            .get(..Self::FIXED_LEN)
+            .ok_or(ZipError::UnexpectedEof)?;
+        check_signature(fixed, END_OF_CENTRAL_DIRECTORY_SIG)?;
+        let len = Self::FIXED_LEN + read_le_u16(fixed, 20) as usize;
+        let bytes = bytes.get(..len).ok_or(ZipError::UnexpectedEof)?;
+
+        let record = Self {
+            disk_number: read_le_u16(bytes, 4),
+            start_disk: read_le_u16(bytes, 6),
+            entries_on_disk: read_le_u16(bytes, 8),
+            total_entries: read_le_u16(bytes, 10),
+            central_dir_size: read_le_u32(bytes, 12),
+            central_dir_offset: read_le_u32(bytes, 16),
+            comment: bytes[Self::FIXED_LEN..].to_vec(),
+        };
+        Ok((record, len))
+    }
+
+    /// Position of the record in `archive`, a complete ZIP file, searching
+    /// back from the end over a comment of up to 64 KiB.
+    pub fn find(archive: &[u8]) -> Option<usize> {
+        let last = archive.len().checked_sub(Self::FIXED_LEN)?;
+        let first = last.saturating_sub(u16::MAX as usize);
+        (first..=last)
+            .rev()
+            .find(|&pos| archive[pos..].starts_with(&END_OF_CENTRAL_DIRECTORY_SIG))
+    }
+
+    /// Whether a count, size or offset is saturated, meaning the real value
+    /// is in the [`Zip64EndOfCentralDirectory`].
+    pub fn is_zip64(&self) -> bool {
+        self.entries_on_disk == u16::MAX
+            || self.total_entries == u16::MAX
+            || self.central_dir_size == ZIP64_SENTINEL
+            || self.central_dir_offset == ZIP64_SENTINEL
+    }
+}
+
+/// The Zip64 version of [`EndOfCentralDirectory`], written before it when
+/// the archive has too many entries or is too large for its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zip64EndOfCentralDirectory {
+    pub version_made_by: u16,
+    pub version_needed: u16,
+    pub disk_number: u32,
+    pub start_disk: u32,
+    pub entries_on_disk: u64,
+    pub total_entries: u64,
+    pub central_dir_size: u64,
+    pub central_dir_offset: u64,
+    pub extensible_data: Vec<u8>,
+}
+
+impl Zip64EndOfCentralDirectory {
+    /// Length of the record without the extensible data.
+    pub const FIXED_LEN: usize = 56;
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), ZipError> {
+        let fixed = bytes
+            .get(..Self::FIXED_LEN)
+            .ok_or(ZipError::UnexpectedEof)?;
+        check_signature(fixed, ZIP64_END_OF_CENTRAL_DIRECTORY_SIG)?;
+        let len = Self::record_len(fixed);
+        let bytes = usize::try_from(len)
+            .ok()
+            .and_then(|len| bytes.get(..len))
+            .ok_or(ZipError::UnexpectedEof)?;
+
+        let record = Self {
+            version_made_by: read_le_u16(bytes, 12),
+            version_needed: read_le_u16(bytes, 14),
+            disk_number: read_le_u32(bytes, 16),
+            start_disk: read_le_u32(bytes, 20),
+            entries_on_disk: read_le_u64(bytes, 24),
+            total_entries: read_le_u64(bytes, 32),
+            central_dir_size: read_le_u64(bytes, 40),
+            central_dir_offset: read_le_u64(bytes, 48),
+            extensible_data: bytes[Self::FIXED_LEN..].to_vec(),
+        };
+        Ok((record, bytes.len()))
+    }
+
+    /// The length of the whole record from the size field in its first 12
+    /// bytes, which leaves out the signature and the field itself. A size
+    /// too small for the fixed fields is taken as no extensible data.
+    pub fn record_len(bytes: &[u8]) -> u64 {
+        read_le_u64(bytes, 4)
+            .saturating_add(12)
+            .max(Self::FIXED_LEN as u64)
+    }
+}
+
+/// Points to the [`Zip64EndOfCentralDirectory`], just before the
+/// [`EndOfCentralDirectory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zip64EndOfCentralDirectoryLocator {
+    /// The disk holding the Zip64 record
+    pub eocd_disk: u32,
+    /// Offset of the Zip64 record from the start of the archive
+    pub eocd_offset: u64,
+    pub total_disks: u32,
+}
+
+impl Zip64EndOfCentralDirectoryLocator {
+    pub const LEN: usize = 20;
+
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), ZipError> {
+        let bytes = bytes.get(..Self::LEN).ok_or(ZipError::UnexpectedEof)?;
+        check_signature(bytes, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIG)?;
+        let locator = Self {
+            eocd_disk: read_le_u32(bytes, 4),
+            eocd_offset: read_le_u64(bytes, 8),
+            total_disks: read_le_u32(bytes, 16),
+        };
+        Ok((locator, Self::LEN))
+    }
+}
+
+/// The data of the first extra field with `id` in `extra`, if any.
+pub fn extra_field(extra: &[u8], id: u16) -> Option<&[u8]> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let field_id = read_le_u16(rest, 0);
+        let size = read_le_u16(rest, 2) as usize;
+        let data = rest.get(4..4 + size)?;
+        if field_id == id {
+            return Some(data);
+        }
+        rest = &rest[4 + size..];
+    }
+    None
+}
+
+/// Number of trailing bytes in `extra` too short for a field header, which
+/// are usually padding, or `None` if a field does not fit within it.
+pub(crate) fn extra_fields_slack(extra: &[u8]) -> Option<usize> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let size = read_le_u16(rest, 2) as usize;
+        rest = rest.get(4 + size..)?;
+    }
+    Some(rest.len())
+}
+
+/// The Unix mode from an ASi Unix extra field (0x756e) in `extra`, if any.
+fn unix_mode(extra: &[u8]) -> Option<u32> {
+    // CRC-32 of the rest of the field, then the mode.
+    let data = extra_field(extra, 0x756e).filter(|data| data.len() >= 6)?;
+    Some(read_le_u16(data, 4) as u32)
+}
+
+/// The modification time from an extended timestamp extra field (0x5455) in
+/// `extra`, if any.
+fn unix_mtime(extra: &[u8]) -> Option<u32> {
+    // A flags byte, bit 0 set when the modification time follows.
+    let data = extra_field(extra, 0x5455).filter(|data| data.len() >= 5 && data[0] & 1 != 0)?;
+    Some(read_le_u32(data, 1))
+}
+
+/// The uncompressed and compressed sizes, taking those set to the sentinel
+/// from the Zip64 extra field, which holds them in that order. Sizes the
+/// field lacks keep the sentinel.
+fn zip64_sizes(extra: &[u8], uncompressed: u32, compressed: u32) -> (u64, u64) {
+    let mut index = 0;
+    let mut resolve = |size: u32| match size {
+        ZIP64_SENTINEL => {
+            let value = extra_field_u64(extra, ZIP64_EXTRA_ID, index);
+            index += 1;
+            value.unwrap_or(size as u64)
+        }
+        size => size as u64,
+    };
+    (resolve(uncompressed), resolve(compressed))
+}
+
+/// The `index`th 64-bit value in the extra field with `id`.
+fn extra_field_u64(extra: &[u8], id: u16, index: usize) -> Option<u64> {
+    let data = extra_field(extra, id)?;
+    let value = data.get(index * 8..index * 8 + 8)?;
+    Some(read_le_u64(value, 0))
+}
+
+fn check_signature(bytes: &[u8], expected: [u8; 4]) -> Result<(), ZipError> {
+    if bytes[..4] == expected {
+        return Ok(());
+    }
+    Err(ZipError::InvalidSignature {
+        found: signature_hex(&bytes[..4]),
+        offset: 0,
+    })
+}
+
+/// `signature` as lowercase hex, for [`ZipError::InvalidSignature`].
+pub(crate) fn signature_hex(signature: &[u8]) -> String {
+    signature.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::ArchiveBuilder;
+
+    /// A local header for `name` with the given fields and extra field.
+    fn local_header(flags: Flags, sizes: (u32, u32), name: &[u8], extra: &[u8]) -> Vec<u8> {
+        let mut header = LOCAL_FILE_HEADER_SIG.to_vec();
+        header.extend_from_slice(&45u16.to_le_bytes());
+        header.extend_from_slice(&flags.0.to_le_bytes());
+        header.extend_from_slice(&8u16.to_le_bytes());
+        header.extend_from_slice(&0x6000u16.to_le_bytes());
+        header.extend_from_slice(&0x5821u16.to_le_bytes());
+        header.extend_from_slice(&0xdeadbeefu32.to_le_bytes());
+        header.extend_from_slice(&sizes.0.to_le_bytes());
+        header.extend_from_slice(&sizes.1.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        header.extend_from_slice(name);
+        header.extend_from_slice(extra);
+        header
+    }
+
+    fn zip64_extra(values: &[u64]) -> Vec<u8> {
+        let mut extra = ZIP64_EXTRA_ID.to_le_bytes().to_vec();
+        extra.extend_from_slice(&(values.len() as u16 * 8).to_le_bytes());
+        for value in values {
+            extra.extend_from_slice(&value.to_le_bytes());
+        }
+        extra
+    }
+
+    #[test]
+    fn test_local_file_header() {
+        let bytes = local_header(Flags::UTF8, (5, 11), "café.txt".as_bytes(), b"");
+        let mut trailing = bytes.clone();
+        trailing.extend_from_slice(b"data follows");
+
+        let (header, len) = LocalFileHeader::parse(&trailing).unwrap();
+        assert_eq!(len, bytes.len());
+        assert_eq!(header.version_needed, 45);
+        assert_eq!(header.compression_method, CompressionMethod::Deflate);
+        assert_eq!(header.crc32, 0xdeadbeef);
+        assert_eq!((header.compressed_size, header.uncompressed_size), (5, 11));
+        assert_eq!(header.filename, "café.txt".as_bytes());
+        assert_eq!(header.flags, Flags::UTF8);
+        assert_eq!(
+            header.modified().unwrap().to_string(),
+            "2024-01-01T12:00:00"
+        );
+
+        // Every shorter slice is too short.
+        for len in 0..bytes.len() {
+            assert!(
+                matches!(
+                    LocalFileHeader::parse(&bytes[..len]),
+                    Err(ZipError::UnexpectedEof)
+                ),
+                "{}",
+                len
+            );
+        }
+
+        let mut wrong = bytes.clone();
+        wrong[2] = 1;
+        assert!(matches!(
+            LocalFileHeader::parse(&wrong),
+            Err(ZipError::InvalidSignature { found, offset: 0 }) if found == "504b0104"
+        ));
+    }
+
+    #[test]
+    fn test_flags() {
+        for (flags, descriptor, encrypted, utf8) in [
+            (Flags(0), false, false, false),
+            (Flags::DATA_DESCRIPTOR, true, false, false),
+            (Flags::ENCRYPTED | Flags::UTF8, false, true, true),
+            (Flags(0xffff), true, true, true),
+            (Flags(0x0006), false, false, false),
+        ] {
+            let (header, _) =
+                LocalFileHeader::parse(&local_header(flags, (0, 0), b"a", b"")).unwrap();
+            assert_eq!(header.flags, flags);
+            assert_eq!(flags.has_data_descriptor(), descriptor, "{:?}", flags);
+            assert_eq!(flags.is_encrypted(), encrypted, "{:?}", flags);
+            assert_eq!(flags.is_utf8(), utf8, "{:?}", flags);
+        }
+        assert!(!Flags::UTF8.contains(Flags::UTF8 | Flags::ENCRYPTED));
+    }
+
+    #[test]
+    fn test_zip64_sizes() {
+        let big = 5 << 30;
+        let sentinel = ZIP64_SENTINEL;
+
+        // Both sizes in the Zip64 field: uncompressed first.
+        let extra = zip64_extra(&[big, big / 2]);
+        let bytes = local_header(Flags(0), (sentinel, sentinel), b"big.bin", &extra);
+        let (header, _) = LocalFileHeader::parse(&bytes).unwrap();
+        assert_eq!(header.uncompressed_size, big);
+        assert_eq!(header.compressed_size, big / 2);
+
+        // Only the one set to the sentinel.
+        let bytes = local_header(Flags(0), (100, sentinel), b"big.bin", &zip64_extra(&[big]));
+        let (header, _) = LocalFileHeader::parse(&bytes).unwrap();
+        assert_eq!(
+            (header.compressed_size, header.uncompressed_size),
+            (100, big)
+        );
+
+        // A sentinel without the field, or with a short one, stays as it is.
+        for extra in [Vec::new(), zip64_extra(&[])] {
+            let bytes = local_header(Flags(0), (sentinel, 7), b"a", &extra);
+            let (header, _) = LocalFileHeader::parse(&bytes).unwrap();
+            assert_eq!(header.compressed_size, sentinel as u64);
+            assert_eq!(header.uncompressed_size, 7);
+        }
+    }
+
+    #[test]
+    fn test_data_descriptor() {
+        let mut bytes = DATA_DESCRIPTOR_SIG.to_vec();
+        for field in [0xdeadbeefu32, 5, 11] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        let expected = DataDescriptor {
+            crc32: 0xdeadbeef,
+            compressed_size: 5,
+            uncompressed_size: 11,
+        };
+
+        assert_eq!(DataDescriptor::parse(&bytes).unwrap(), (expected, 16));
+        assert_eq!(DataDescriptor::parse(&bytes[4..]).unwrap(), (expected, 12));
+        assert!(matches!(
+            DataDescriptor::parse(&bytes[..15]),
+            Err(ZipError::UnexpectedEof)
+        ));
+
+        let mut bytes = DATA_DESCRIPTOR_SIG.to_vec();
+        bytes.extend_from_slice(&0xdeadbeefu32.to_le_bytes());
+        for field in [5u64 << 32, 11 << 32] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        let expected = DataDescriptor {
+            crc32: 0xdeadbeef,
+            compressed_size: 5 << 32,
+            uncompressed_size: 11 << 32,
+        };
+        assert_eq!(DataDescriptor::parse_zip64(&bytes).unwrap(), (expected, 24));
+        assert_eq!(
+            DataDescriptor::parse_zip64(&bytes[4..]).unwrap(),
+            (expected, 20)
+        );
+    }
+
+    #[test]
+    fn test_central_directory() {
+        let archive = ArchiveBuilder::new()
+            .stored("a.txt", b"hello")
+            .with_unix_mode(0o100644)
+            .deflated("dir/b.csv", b"x,y\n1,2\n")
+            .build();
+
+        let end = EndOfCentralDirectory::find(&archive).unwrap();
+        let (eocd, len) = EndOfCentralDirectory::parse(&archive[end..]).unwrap();
+        assert_eq!(end + len, archive.len());
+        assert_eq!((eocd.entries_on_disk, eocd.total_entries), (2, 2));
+        assert!(eocd.comment.is_empty());
+        assert_eq!(
+            (eocd.central_dir_offset + eocd.central_dir_size) as usize,
+            end
+        );
+
+        let mut pos = eocd.central_dir_offset as usize;
+        let mut headers = Vec::new();
+        while pos < end {
+            let (header, len) = CentralDirectoryHeader::parse(&archive[pos..]).unwrap();
+            headers.push(header);
+            pos += len;
+        }
+        assert_eq!(pos, end);
+        assert_eq!(headers[0].filename, b"a.txt");
+        assert_eq!(headers[0].local_header_offset, 0);
+        assert_eq!(headers[1].filename, b"dir/b.csv");
+        assert_eq!(headers[1].external_attrs >> 16, 0o100644);
+        assert_eq!(headers[1].compression_method, CompressionMethod::Deflate);
+        assert_eq!(headers[1].uncompressed_size, 8);
+
+        let (local, _) = LocalFileHeader::parse(&archive).unwrap();
+        assert_eq!(local.crc32, headers[0].crc32);
+
+        assert!(matches!(
+            CentralDirectoryHeader::parse(&archive[eocd.central_dir_offset as usize..][..45]),
+            Err(ZipError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            CentralDirectoryHeader::parse(&archive),
+            Err(ZipError::InvalidSignature { found, offset: 0 }) if found == "504b0304"
+        ));
+        assert!(matches!(
+            EndOfCentralDirectory::parse(&archive),
+            Err(ZipError::InvalidSignature { .. })
+        ));
+        assert_eq!(EndOfCentralDirectory::find(b"PK\x05\x06 too short"), None);
+    }
+
+    #[test]
+    fn test_zip64_end_of_central_directory() {
+        let archive = ArchiveBuilder::new()
+            .stored("a.txt", b"hello")
+            .with_zip64_end_records()
+            .build();
+        let end = EndOfCentralDirectory::find(&archive).unwrap();
+        let (eocd, _) = EndOfCentralDirectory::parse(&archive[end..]).unwrap();
+        assert!(eocd.is_zip64());
+
+        let locator_start = end - Zip64EndOfCentralDirectoryLocator::LEN;
+        let (locator, len) =
+            Zip64EndOfCentralDirectoryLocator::parse(&archive[locator_start..]).unwrap();
+        assert_eq!(locator_start + len, end);
+        assert_eq!(locator.total_disks, 1);
+
+        let start = locator.eocd_offset as usize;
+        let (record, len) = Zip64EndOfCentralDirectory::parse(&archive[start..]).unwrap();
+        assert_eq!(start + len, locator_start);
+        assert_eq!((record.entries_on_disk, record.total_entries), (1, 1));
+        assert_eq!(
+            record.central_dir_offset + record.central_dir_size,
+            start as u64
+        );
+        assert!(record.extensible_data.is_empty());
+
+        assert!(matches!(
+            Zip64EndOfCentralDirectory::parse(&archive[start..][..55]),
+            Err(ZipError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            Zip64EndOfCentralDirectoryLocator::parse(&archive[start..]),
+            Err(ZipError::InvalidSignature { found, offset: 0 }) if found == "504b0606"
+        ));
+    }
+
+    #[test]
+    fn test_end_of_central_directory_comment() {
+        let comment = b"made by hand";
+        let archive = ArchiveBuilder::new()
+            .stored("a.txt", b"hello")
+            .with_comment(comment)
+            .build();
+
+        let end = EndOfCentralDirectory::find(&archive).unwrap();
+        let (eocd, len) = EndOfCentralDirectory::parse(&archive[end..]).unwrap();
+        assert_eq!(eocd.comment, comment);
+        assert_eq!(end + len, archive.len());
+        assert!(matches!(
+            EndOfCentralDirectory::parse(&archive[end..archive.len() - 1]),
+            Err(ZipError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn test_extra_fields() {
+        let mut extra = zip64_extra(&[1, 2]);
+        extra.extend_from_slice(&0x5455u16.to_le_bytes());
+        extra.extend_from_slice(&5u16.to_le_bytes());
+        extra.push(1);
+        extra.extend_from_slice(&1_700_000_000u32.to_le_bytes());
+
+        assert_eq!(extra_field(&extra, 0x5455).unwrap().len(), 5);
+        assert_eq!(unix_mtime(&extra), Some(1_700_000_000));
+        assert_eq!(unix_mode(&extra), None);
+        assert_eq!(extra_fields_slack(&extra), Some(0));
+        extra.push(0);
+        assert_eq!(extra_fields_slack(&extra), Some(1));
+        // A field running past the end.
+        assert_eq!(extra_fields_slack(&extra[..extra.len() - 3]), None);
+        assert_eq!(extra_field(&extra[..extra.len() - 3], 0x5455), None);
+    }
+}
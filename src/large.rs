@@ -0,0 +1,355 @@
+//! Streaming entries too large to hold in memory, for
+//! [`MuyZipido::next_entry_kind`].
+
+use crate::{CompressionMethod, EntryInfo, MuyZipido, Transform, ZipEntry, ZipError};
+use flate2::read::MultiGzDecoder;
+use std::io::{self, Read};
+use std::ops::Deref;
+
+/// An entry from [`MuyZipido::next_entry_kind`].
+#[derive(Debug)]
+pub enum ZipEntryKind<'a> {
+    /// An entry decoded into memory, as the iterator returns it.
+    Small(ZipEntry),
+    /// An entry over the
+    /// [large entry threshold](MuyZipido::with_large_entry_threshold), to be
+    /// read from the stream.
+    Large(LargeZipEntry<'a>),
+}
+
+impl ZipEntryKind<'_> {
+    pub fn info(&self) -> &EntryInfo {
+        match self {
+            ZipEntryKind::Small(entry) => &entry.info,
+            ZipEntryKind::Large(entry) => &entry.info,
+        }
+    }
+}
+
+/// An entry whose decompressed data is read on demand, straight from the
+/// archive stream.
+///
+/// It borrows the extractor, so it must be finished with before the next
+/// entry is asked for. Dropping it part way through skips the rest of its
+/// data. Reading fails with [`io::ErrorKind::InvalidData`] if the data does
+/// not match its CRC-32, and with the [`ZipError`] as the inner error if the
+/// stream fails; see [`io::Error::into_inner`].
+///
+/// [`Transform::GunzipByExtension`] is applied as the data is read. The
+/// entry is then named without its `.gz` suffix, but `info.uncompressed_size`
+/// is still the size of the gzip member, since the size of its contents is
+/// not known until they are read.
+pub struct LargeZipEntry<'a> {
+    pub info: EntryInfo,
+    /// The metadata as stored in the archive, when a transform changed the
+    /// entry, as in [`ZipEntry::original`].
+    pub original: Option<EntryInfo>,
+    reader: Decoded<'a>,
+    /// Bytes read so far
+    read: u64,
+    max_size: u64,
+}
+
+/// The entry's data with any transforms applied.
+enum Decoded<'a> {
+    Stored(Box<EntryReader<'a>>),
+    Gunzip(Box<MultiGzDecoder<Decoded<'a>>>),
+}
+
+impl Read for Decoded<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decoded::Stored(reader) => reader.read(buf),
+            Decoded::Gunzip(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Decompresses an entry's data as it is read from the stream and checks
+/// its CRC-32 at the end.
+struct EntryReader<'a> {
+    info: EntryInfo,
+    extractor: &'a mut MuyZipido,
+    /// Compressed bytes still on the stream
+    remaining: u64,
+    inflater: Option<flate2::Decompress>,
+    /// Compressed bytes read from the stream but not yet decoded
+    input: Vec<u8>,
+    consumed: usize,
+    crc: flate2::Crc,
+    decoded: u64,
+    done: bool,
+    /// The stream failed, so the extractor's position is unknown
+    broken: bool,
+}
+
+impl<'a> LargeZipEntry<'a> {
+    fn new(extractor: &'a mut MuyZipido, mut info: EntryInfo) -> Self {
+        let stored = info.clone();
+        let mut gunzips = 0;
+        if !extractor.raw_entries && !info.is_dir {
+            for transform in &extractor.transforms {
+                if let (Transform::GunzipByExtension, Some(name)) =
+                    (transform, info.filename.strip_suffix(".gz"))
+                {
+                    info.filename = name.to_string();
+                    gunzips += 1;
+                }
+            }
+        }
+
+        let max_size = extractor.limits.max_entry_size;
+        let mut reader = Decoded::Stored(Box::new(EntryReader::new(extractor, stored.clone())));
+        for _ in 0..gunzips {
+            reader = Decoded::Gunzip(Box::new(MultiGzDecoder::new(reader)));
+        }
+        Self {
+            info,
+            original: (gunzips > 0).then_some(stored),
+            reader,
+            read: 0,
+            max_size,
+        }
+    }
+}
+
+impl<'a> EntryReader<'a> {
+    fn new(extractor: &'a mut MuyZipido, info: EntryInfo) -> Self {
+        let inflater = (!extractor.raw_entries
+            && info.compression_method == CompressionMethod::Deflate)
+            .then(|| flate2::Decompress::new(false));
+        Self {
+            remaining: info.compressed_size,
+            info,
+            extractor,
+            inflater,
+            input: Vec::new(),
+            consumed: 0,
+            crc: flate2::Crc::new(),
+            decoded: 0,
+            done: false,
+            broken: false,
+        }
+    }
+
+    /// Decodes into `buf` from the compressed bytes at hand, returning the
+    /// bytes produced and whether the end of the data was reached.
+    fn decode(&mut self, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        let input = &self.input[self.consumed..];
+        let Some(inflater) = &mut self.inflater else {
+            let len = input.len().min(buf.len());
+            buf[..len].copy_from_slice(&input[..len]);
+            self.consumed += len;
+            return Ok((len, len == 0 && self.remaining == 0));
+        };
+
+        let (total_in, total_out) = (inflater.total_in(), inflater.total_out());
+        let status = inflater
+            .decompress(input, buf, flate2::FlushDecompress::None)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let consumed = (inflater.total_in() - total_in) as usize;
+        let produced = (inflater.total_out() - total_out) as usize;
+        self.consumed += consumed;
+
+        let stalled = consumed == 0 && produced == 0;
+        if stalled && !input.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "deflate stream stalled",
+            ));
+        }
+        let end = status == flate2::Status::StreamEnd || (stalled && self.remaining == 0);
+        Ok((produced, end && produced == 0))
+    }
+
+    /// Checks the decoded data once it is all read.
+    fn finish(&mut self) -> io::Result<()> {
+        self.done = true;
+        self.skip_rest()?;
+        if let Some(observer) = &self.extractor.observer {
+            observer.entry_progress(&self.info, self.decoded);
+        }
+        if self.extractor.raw_entries {
+            return Ok(());
+        }
+        self.extractor
+            .verify_decoded(&mut self.info, self.crc.sum(), self.decoded)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Consumes any of the entry's data left on the stream, e.g. padding
+    /// after the end of a deflate stream.
+    fn skip_rest(&mut self) -> io::Result<()> {
+        let remaining = std::mem::take(&mut self.remaining);
+        let skipped = self.extractor.skip_bytes(remaining);
+        self.extractor.entry_start = self.extractor.offset;
+        skipped.map_err(|e| self.stream_error(e))
+    }
+
+    fn stream_error(&mut self, error: ZipError) -> io::Error {
+        self.broken = true;
+        io::Error::other(self.extractor.in_entry(&self.info.filename, error))
+    }
+}
+
+impl Read for LargeZipEntry<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.read += read as u64;
+        // The stored data is checked against the limit as it is decoded,
+        // but gunzipped output can be far larger.
+        if self.read > self.max_size {
+            return Err(io::Error::other(crate::limit_exceeded(
+                self.max_size,
+                &self.info.filename,
+            )));
+        }
+        Ok(read)
+    }
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.consumed == self.input.len() && self.remaining > 0 {
+                self.input = match self.extractor.read_some(self.remaining) {
+                    Ok(input) => input,
+                    Err(e) => return Err(self.stream_error(e)),
+                };
+                self.consumed = 0;
+                self.remaining -= self.input.len() as u64;
+            }
+
+            let (produced, end) = self.decode(buf)?;
+            if produced > 0 {
+                self.crc.update(&buf[..produced]);
+                self.decoded += produced as u64;
+                self.extractor.count_decompressed(produced as u64);
+                let max_size = self.extractor.limits.max_entry_size;
+                if self.decoded > max_size {
+                    return Err(io::Error::other(crate::limit_exceeded(
+                        max_size,
+                        &self.info.filename,
+                    )));
+                }
+                return Ok(produced);
+            }
+            if end {
+                self.finish()?;
+                return Ok(0);
+            }
+        }
+    }
+}
+
+impl Deref for LargeZipEntry<'_> {
+    type Target = EntryInfo;
+
+    fn deref(&self) -> &EntryInfo {
+        &self.info
+    }
+}
+
+impl std::fmt::Debug for LargeZipEntry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("LargeZipEntry")
+            .field("info", &self.info)
+            .field("original", &self.original)
+            .field("read", &self.read)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for EntryReader<'_> {
+    /// Skips the unread rest of the entry so the extractor is positioned at
+    /// the next one. If that fails, or the stream failed earlier, the
+    /// extractor stops.
+    fn drop(&mut self) {
+        if !self.broken && !self.done {
+            let _ = self.skip_rest();
+        }
+        if self.broken {
+            self.extractor.finished = true;
+        }
+    }
+}
+
+impl MuyZipido {
+    /// Returns the next entry like the iterator does, except that entries
+    /// over the [large entry threshold](MuyZipido::with_large_entry_threshold)
+    /// are returned as a [`LargeZipEntry`] to be read from the stream.
+    ///
+    /// Entries streamed with a data descriptor have no size in their header
+    /// and are always decoded into memory, as are all entries when a
+    /// [custom decompressor](MuyZipido::with_decompressor), signature check
+    /// or [`Transform::Custom`] is set.
+    ///
+    /// ```no_run
+    /// # use muy_zipido::{MuyZipido, ZipEntryKind};
+    /// let file = std::fs::File::open("data.zip")?;
+    /// let mut extractor = MuyZipido::from_reader(file, 8192)
+    ///     .with_large_entry_threshold(50 * 1024 * 1024);
+    /// while let Some(entry) = extractor.next_entry_kind() {
+    ///     match entry? {
+    ///         ZipEntryKind::Small(entry) => println!("{}: {} bytes", entry.filename, entry.data.len()),
+    ///         ZipEntryKind::Large(mut entry) => {
+    ///             let mut file = std::fs::File::create("large.bin")?;
+    ///             std::io::copy(&mut entry, &mut file)?;
+    ///         }
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn next_entry_kind(&mut self) -> Option<Result<ZipEntryKind<'_>, ZipError>> {
+        self.next_entry_kind_inner().transpose()
+    }
+
+    fn next_entry_kind_inner(&mut self) -> Result<Option<ZipEntryKind<'_>>, ZipError> {
+        let next = self
+            .next_accepted()
+            .map_err(|e| self.stop_unless_recoverable(e));
+        let Some((mut info, has_data_descriptor)) = next? else {
+            return Ok(None);
+        };
+
+        #[cfg(feature = "signatures")]
+        let streamable =
+            !has_data_descriptor && self.decompressor.is_none() && self.signature_check.is_none();
+        #[cfg(not(feature = "signatures"))]
+        let streamable = !has_data_descriptor && self.decompressor.is_none();
+        let streamable = streamable
+            && !self
+                .transforms
+                .iter()
+                .any(|transform| matches!(transform, Transform::Custom(_)));
+
+        if streamable
+            && self
+                .large_entry_threshold
+                .is_some_and(|threshold| info.uncompressed_size > threshold)
+        {
+            if let Err(e) = self.check_decodable(&mut info, false) {
+                let e = self.in_entry(&info.filename, e);
+                return Err(self.stop_unless_recoverable(e));
+            }
+            return Ok(Some(ZipEntryKind::Large(LargeZipEntry::new(self, info))));
+        }
+
+        self.read_entry(info, has_data_descriptor)
+            .map(|entry| Some(ZipEntryKind::Small(entry)))
+            .map_err(|e| self.stop_unless_recoverable(e))
+    }
+
+    /// Ends iteration after `error` unless it is recoverable, as
+    /// `Iterator::next` does.
+    fn stop_unless_recoverable(&mut self, error: ZipError) -> ZipError {
+        if !error.is_recoverable() {
+            self.finished = true;
+        }
+        error
+    }
+}
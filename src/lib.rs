@@ -1,79 +1,348 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
+mod cache;
+pub mod central;
+pub mod channel;
+#[cfg(feature = "sha256")]
+pub mod checksum;
 pub mod circular_buffer;
+pub mod decompress;
+pub mod entry;
+pub mod error;
+pub mod extract;
+#[cfg(feature = "serde")]
+mod extract_cache;
+pub mod filter;
+#[cfg(feature = "test-util")]
+pub mod fixture;
+pub mod glob;
+pub mod header;
+mod large;
+pub mod limits;
+pub mod parser;
 pub mod progress_bar;
+pub mod scanner;
+#[cfg(feature = "http")]
+pub mod session;
+#[cfg(feature = "signatures")]
+mod signature;
+pub mod transform;
 
-use circular_buffer::CircularBuffer;
+pub use entry::{CompressionMethod, DownloadStats, EntryInfo, ZipDateTime, ZipEntry};
+pub use error::{ZipError, ZipWarning};
+pub use large::{LargeZipEntry, ZipEntryKind};
+pub use limits::Limits;
+#[cfg(feature = "http")]
+pub use session::ZipSession;
+pub use transform::Transform;
+
+use decompress::Decompressor;
+use extract::DuplicatePolicy;
+use filter::ZipEntryFilter;
 use flate2::read::DeflateDecoder;
-use progress_bar::ProgressBar;
-use std::error::Error;
+use header::{
+    CENTRAL_DIRECTORY_HEADER_SIG, CentralDirectoryHeader, DATA_DESCRIPTOR_SIG, DataDescriptor,
+    END_OF_CENTRAL_DIRECTORY_SIG, EndOfCentralDirectory, Flags, LOCAL_FILE_HEADER_SIG,
+    LocalFileHeader, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIG,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIG, Zip64EndOfCentralDirectory,
+    Zip64EndOfCentralDirectoryLocator,
+};
+use progress_bar::{ProgressBar, ProgressBasis, ProgressObserver};
+use scanner::SignatureScanner;
+use std::collections::HashSet;
 use std::fmt;
-use std::io::Read;
-
-#[derive(Debug)]
-pub enum ZipError {
-    Http(reqwest::Error),
-    UnexpectedEof,
-    InvalidSignature(String),
-    Io(std::io::Error),
-    Decompression(String),
-}
-
-impl fmt::Display for ZipError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            ZipError::Http(e) => write!(f, "HTTP error: {}", e),
-            ZipError::UnexpectedEof => write!(f, "Unexpected end of stream"),
-            ZipError::InvalidSignature(sig) => write!(f, "Invalid signature: {}", sig),
-            ZipError::Io(e) => write!(f, "IO error: {}", e),
-            ZipError::Decompression(e) => write!(f, "Decompression error: {}", e),
-        }
-    }
-}
-
-impl Error for ZipError {}
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-impl From<reqwest::Error> for ZipError {
-    fn from(e: reqwest::Error) -> Self {
-        ZipError::Http(e)
-    }
-}
-
-impl From<std::io::Error> for ZipError {
-    fn from(e: std::io::Error) -> Self {
-        ZipError::Io(e)
-    }
-}
-
-pub struct ZipEntry {
-    pub filename: String,
-    pub uncompressed_size: u32,
-    pub data: Vec<u8>,
-}
+type EntryFilter = Arc<dyn ZipEntryFilter>;
+type WarningHandler = Arc<dyn Fn(&ZipWarning) + Send + Sync>;
 
+/// Streams the entries of a ZIP archive from an HTTP response or any reader.
+///
+/// Dropping the extractor, for example by breaking out of a `for` loop,
+/// stops the download at once: the response is closed without reading the
+/// rest of it, a writer given to [`MuyZipido::with_tee`] is flushed but not
+/// completed, and a progress bar is cleared rather than left at a stale
+/// percentage. Nothing runs in the background once it is dropped.
+/// [`MuyZipido::abort`] does the same explicitly.
+///
+/// Reading is driven by the caller. The stream is only read while an entry
+/// is being returned, and no further than the end of that entry plus one
+/// chunk, so a caller slow to ask for the next entry holds the download
+/// back through TCP flow control rather than letting data pile up. Each
+/// entry is held in memory whole, up to [`Limits::max_entry_size`]; use
+/// [`MuyZipido::next_entry_kind`] to stream large ones instead, or
+/// [`MuyZipido::into_channel`] to queue a bounded number of entries for
+/// other threads.
 pub struct MuyZipido {
-    response: Option<reqwest::blocking::Response>,
+    url: Option<String>,
+    suggested_filename: Option<String>,
+    etag: Option<String>,
+    /// URLs requested to reach the response, ending with the one that answered
+    redirect_chain: Vec<String>,
+    /// The session that made the request, reused by `clone_config_with_url`
+    #[cfg(feature = "http")]
+    session: Option<ZipSession>,
+    /// The place among the session's requests under way that the response
+    /// holds, shared with a retry by `clone_config_with_url`
+    #[cfg(feature = "http")]
+    slot: std::sync::Weak<session::Slot>,
+    reader: Option<Box<dyn Read + Send>>,
+    content_length: Option<usize>,
+    /// Bytes read from `reader` so far
+    received: u64,
+    tee: Option<Box<dyn Write + Send>>,
+    cache: Option<cache::CacheFile>,
     chunk_size: usize,
+    /// Bounds `chunk_size` is kept within as it follows the download speed
+    adaptive_chunk_size: Option<(usize, usize)>,
     buffer: Vec<u8>,
     offset: usize,
+    /// Offset of the local header of the entry being read (or the next one)
+    entry_start: usize,
+    /// Number of local headers read so far
+    entries_seen: usize,
     finished: bool,
+    /// Set by [`MuyZipido::abort`]: the rest of the stream is not read and
+    /// the progress bar is cleared
+    aborted: bool,
+    cancel: Option<Arc<AtomicBool>>,
     progress_bar: Option<ProgressBar>,
+    progress_config: Option<(progress_bar::Style, progress_bar::Colour)>,
+    progress_basis: ProgressBasis,
+    /// Total for the progress bar given with `with_total_size`
+    total_size: Option<u64>,
+    observer: Option<Arc<dyn ProgressObserver>>,
+    progress_interval: u64,
+    filter: Option<EntryFilter>,
+    limits: Limits,
+    raw_entries: bool,
+    #[cfg(feature = "signatures")]
+    signature_check: Option<signature::SignatureCheck>,
+    /// Replaces the built-in inflate when set
+    decompressor: Option<Arc<dyn Decompressor>>,
+    /// Entries larger than this are streamed by `next_entry_kind`
+    large_entry_threshold: Option<u64>,
+    /// Size of the buffer deflate output is decoded into
+    decompression_chunk_size: usize,
+    transforms: Vec<Transform>,
+    duplicates: DuplicatePolicy,
+    ignore_version_needed: bool,
+    allow_missing_central_directory: bool,
+    require_non_empty: bool,
+    strict: bool,
+    /// Under `strict`, the first inconsistency in the current entry's
+    /// header, raised once its data has been consumed
+    violation: Option<ZipWarning>,
+    /// The current entry's local header has a Zip64 extra field, so its
+    /// data descriptor holds 64-bit sizes
+    zip64_descriptor: bool,
+    raw_filenames: bool,
+    #[cfg(feature = "encoding")]
+    filename_encoding: Option<&'static encoding_rs::Encoding>,
+    /// Names of the entries read so far, kept unless `duplicates` is `KeepAll`
+    seen_names: HashSet<String>,
+    warnings: Vec<ZipWarning>,
+    warning_handler: Option<WarningHandler>,
+    /// The end of central directory records, once read
+    eocd: Option<EndOfCentralDirectory>,
+    zip64_eocd: Option<Zip64EndOfCentralDirectory>,
+    quiet: bool,
+    timing: bool,
+    stats: DownloadStats,
+    /// Time spent inflating the entry being read
+    decompression_time: Duration,
+    /// Download and decompression times of the entry just read
+    entry_timing: Option<(Duration, Duration)>,
 }
 
 impl MuyZipido {
+    /// Requests `url` and prepares to stream the archive in the response.
+    ///
+    /// `url` is tidied up first: surrounding whitespace is trimmed and
+    /// characters such as spaces are percent-encoded; see
+    /// [`normalize_url`](session::normalize_url). Use a [`ZipSession`] to
+    /// share one HTTP client across several archives, or to change how the
+    /// request is made, e.g. to limit redirects with
+    /// [`ZipSession::with_redirect_policy`].
+    #[cfg(feature = "http")]
     pub fn new(url: &str, chunk_size: usize) -> Result<Self, ZipError> {
-        let response = reqwest::blocking::get(url)?;
+        ZipSession::new().open(url, chunk_size)
+    }
 
-        if !response.status().is_success() {
-            return Err(ZipError::Http(response.error_for_status().unwrap_err()));
-        }
+    /// Requests `url` exactly as given, without the clean-up
+    /// [`MuyZipido::new`] does.
+    #[cfg(feature = "http")]
+    pub fn from_url(url: reqwest::Url, chunk_size: usize) -> Result<Self, ZipError> {
+        ZipSession::new().open_url(url, chunk_size)
+    }
+
+    /// Streams the archive in a response from an existing `reqwest` client,
+    /// e.g. one set up with cookies or authentication.
+    ///
+    /// Fails with [`ZipError::HttpStatus`] unless the response succeeded.
+    /// [`MuyZipido::source_url`] is the response's final URL, after redirects.
+    #[cfg(feature = "http")]
+    pub fn from_response(
+        response: reqwest::blocking::Response,
+        chunk_size: usize,
+    ) -> Result<Self, ZipError> {
+        let url = response.url().to_string();
+        let response = session::check_status(response, &url)?;
+        Ok(Self::from_http_response(&url, response, chunk_size))
+    }
+
+    /// Streams `url` from the copy cached at `cache_path`, downloading it
+    /// again if the cache is missing or older than `max_age`.
+    ///
+    /// See [`ZipSession::open_cached`].
+    #[cfg(feature = "http")]
+    pub fn from_cache_or_url(
+        url: &str,
+        cache_path: &Path,
+        max_age: Duration,
+        chunk_size: usize,
+    ) -> Result<Self, ZipError> {
+        ZipSession::new().open_cached(url, cache_path, max_age, chunk_size)
+    }
+
+    #[cfg(feature = "http")]
+    fn from_http_response(
+        url: &str,
+        response: reqwest::blocking::Response,
+        chunk_size: usize,
+    ) -> Self {
+        let headers = response.headers();
+        let content_length = headers
+            .get("content-length")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|s| s.parse::<usize>().ok());
+        let suggested_filename = headers
+            .get("content-disposition")
+            .and_then(|value| value.to_str().ok())
+            .and_then(session::content_disposition_filename);
+        let etag = headers
+            .get("etag")
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
 
-        Ok(Self {
-            response: Some(response),
+        let mut extractor = Self::from_reader(response, chunk_size);
+        extractor.url = Some(url.to_string());
+        extractor.content_length = content_length;
+        extractor.suggested_filename = suggested_filename;
+        extractor.etag = etag;
+        extractor.redirect_chain = vec![url.to_string()];
+        extractor
+    }
+
+    /// Streams an archive already held in memory.
+    pub fn from_bytes(bytes: Vec<u8>, chunk_size: usize) -> Self {
+        Self::from_reader(std::io::Cursor::new(bytes), chunk_size)
+    }
+
+    /// Streams the archive from any reader instead of an HTTP response.
+    pub fn from_reader(reader: impl Read + Send + 'static, chunk_size: usize) -> Self {
+        Self {
+            url: None,
+            suggested_filename: None,
+            etag: None,
+            redirect_chain: Vec::new(),
+            #[cfg(feature = "http")]
+            session: None,
+            #[cfg(feature = "http")]
+            slot: std::sync::Weak::new(),
+            reader: Some(Box::new(reader)),
+            content_length: None,
+            received: 0,
+            tee: None,
+            cache: None,
             chunk_size,
+            adaptive_chunk_size: None,
             buffer: Vec::new(),
             offset: 0,
+            entry_start: 0,
+            entries_seen: 0,
             finished: false,
+            aborted: false,
+            cancel: None,
             progress_bar: None,
-        })
+            progress_config: None,
+            progress_basis: ProgressBasis::Downloaded,
+            total_size: None,
+            observer: None,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            filter: None,
+            limits: Limits::default(),
+            raw_entries: false,
+            #[cfg(feature = "signatures")]
+            signature_check: None,
+            decompressor: None,
+            large_entry_threshold: None,
+            decompression_chunk_size: DEFAULT_DECOMPRESSION_CHUNK_SIZE,
+            transforms: Vec::new(),
+            duplicates: DuplicatePolicy::KeepAll,
+            ignore_version_needed: false,
+            allow_missing_central_directory: false,
+            require_non_empty: false,
+            strict: false,
+            violation: None,
+            zip64_descriptor: false,
+            raw_filenames: false,
+            #[cfg(feature = "encoding")]
+            filename_encoding: None,
+            seen_names: HashSet::new(),
+            warnings: Vec::new(),
+            warning_handler: None,
+            eocd: None,
+            zip64_eocd: None,
+            quiet: false,
+            timing: false,
+            stats: DownloadStats::default(),
+            decompression_time: Duration::ZERO,
+            entry_timing: None,
+        }
+    }
+
+    /// The URL passed to [`MuyZipido::new`], or `None` for extractors built
+    /// from a reader.
+    pub fn source_url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// The URL the archive was actually served from, after any redirects, or
+    /// `None` for extractors built from a reader.
+    pub fn final_url(&self) -> Option<&str> {
+        self.redirect_chain.last().map(String::as_str)
+    }
+
+    /// Every URL requested to reach the archive, starting with
+    /// [`MuyZipido::source_url`] and ending with [`MuyZipido::final_url`].
+    pub fn redirect_chain(&self) -> &[String] {
+        &self.redirect_chain
+    }
+
+    /// The file name from the response's `Content-Disposition` header, if the
+    /// server sent one.
+    pub fn suggested_filename(&self) -> Option<&str> {
+        self.suggested_filename.as_deref()
+    }
+
+    /// The response's `ETag` header, used to check that a resumed download
+    /// still refers to the same archive.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    /// Offset in the archive of the local header of the entry currently being
+    /// read, or of the next entry when between entries.
+    ///
+    /// Every entry before this offset has been fully read, so if the stream
+    /// fails this is where to pick up again with [`ZipSession::open_at`].
+    pub fn resume_offset(&self) -> u64 {
+        self.entry_start as u64
     }
 
     pub fn with_progress(
@@ -81,227 +350,1947 @@ impl MuyZipido {
         style: progress_bar::Style,
         color: progress_bar::Colour,
     ) -> Self {
-        let content_length = if let Some(response) = &self.response {
-            response
-                .headers()
-                .get("content-length")
-                .and_then(|value| value.to_str().ok())
-                .and_then(|s| s.parse::<usize>().ok())
-        } else {
-            None
-        };
+        self.progress_config = Some((style, color));
+        self.reset_progress_bar();
+        self
+    }
+
+    /// Counts downloaded or decompressed bytes on the progress bar. Defaults
+    /// to [`ProgressBasis::Downloaded`].
+    ///
+    /// ```no_run
+    /// # use muy_zipido::MuyZipido;
+    /// # use muy_zipido::progress_bar::{Colour, ProgressBasis, Style};
+    /// let file = std::fs::File::open("data.zip")?;
+    /// let extractor = MuyZipido::from_reader(file, 8192)
+    ///     .with_progress_basis(ProgressBasis::Decompressed)
+    ///     // The uncompressed size from the publisher's manifest.
+    ///     .with_total_size(9_400_000_000)
+    ///     .with_progress(Style::Classic, Colour::Green);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_progress_basis(mut self, basis: ProgressBasis) -> Self {
+        self.progress_basis = basis;
+        self.reset_progress_bar();
+        self
+    }
+
+    /// The total the progress bar counts towards, in place of the
+    /// Content-Length: the size of the archive, or under
+    /// [`ProgressBasis::Decompressed`] the size of its entries once
+    /// decompressed.
+    pub fn with_total_size(mut self, bytes: u64) -> Self {
+        self.total_size = Some(bytes);
+        self.reset_progress_bar();
+        self
+    }
 
-        let progress_bar = ProgressBar::new(content_length)
-            .with_description("Downloading ZIP".to_string())
+    /// Sets up the bar for [`MuyZipido::with_progress`] afresh, once its
+    /// total or description may have changed.
+    fn reset_progress_bar(&mut self) {
+        let Some((style, color)) = self.progress_config else {
+            return;
+        };
+        let (total, description) = match self.progress_basis {
+            ProgressBasis::Downloaded => (
+                self.total_size
+                    .or(self.content_length.map(|len| len as u64)),
+                "Downloading ZIP",
+            ),
+            ProgressBasis::Decompressed => (self.total_size, "Extracting ZIP"),
+        };
+        let progress_bar = ProgressBar::new(total.map(|total| total as usize))
+            .with_description(description.to_string())
             .with_style(style)
             .with_color(color);
         self.progress_bar = Some(progress_bar);
+    }
+
+    /// Counts `bytes` of decompressed entry data on the progress bar, under
+    /// [`ProgressBasis::Decompressed`].
+    fn count_decompressed(&mut self, bytes: u64) {
+        if self.progress_basis == ProgressBasis::Decompressed
+            && let Some(progress_bar) = &mut self.progress_bar
+        {
+            progress_bar.update(bytes as usize);
+        }
+    }
+
+    /// Reports download progress on an existing bar, e.g. one carried over
+    /// from a previous archive with [`MuyZipido::take_progress_bar`] to show
+    /// progress across several downloads.
+    pub fn with_progress_bar(mut self, progress_bar: ProgressBar) -> Self {
+        self.progress_bar = Some(progress_bar);
         self
     }
 
-    fn read_exact(&mut self, size: usize) -> Result<Vec<u8>, ZipError> {
+    /// Removes the progress bar without finishing it, so it can be handed to
+    /// another extractor.
+    pub fn take_progress_bar(&mut self) -> Option<ProgressBar> {
+        self.progress_bar.take()
+    }
+
+    /// The current state of the progress bar, if one is attached.
+    pub fn progress_snapshot(&self) -> Option<progress_bar::ProgressSnapshot> {
+        self.progress_bar.as_ref().map(ProgressBar::snapshot)
+    }
+
+    /// Issues a fresh request for `url` and returns a new extractor with the
+    /// same configuration as this one, e.g. to retry a download or process an
+    /// archive twice.
+    ///
+    /// The request goes through the [`ZipSession`] that opened this archive,
+    /// with its headers, User-Agent, certificates, redirect policy, rate
+    /// limiting and response validator; extractors built some other way use
+    /// a default session. Every builder setting is carried over, including
+    /// the cancel flag and signature check, which starts afresh. A writer set
+    /// with [`MuyZipido::with_tee`] and a cache set with
+    /// [`MuyZipido::with_cache`] are not. This instance is left untouched
+    /// and remains usable. Under
+    /// [`ZipSession::with_max_concurrent`] the new request shares this
+    /// response's place, so retrying while it is open does not wait for it.
+    #[cfg(feature = "http")]
+    pub fn clone_config_with_url(&self, url: &str) -> Result<MuyZipido, ZipError> {
+        let session = self.session.clone().unwrap_or_default();
+        let mut clone = session.reopen(url, self.chunk_size, self.slot.upgrade())?;
+        clone.cancel = self.cancel.clone();
+        clone.filter = self.filter.clone();
+        clone.limits = self.limits;
+        clone.raw_entries = self.raw_entries;
+        #[cfg(feature = "signatures")]
+        {
+            clone.signature_check = self.signature_check.as_ref().map(|check| check.fresh());
+        }
+        clone.decompressor = self.decompressor.clone();
+        clone.large_entry_threshold = self.large_entry_threshold;
+        clone.decompression_chunk_size = self.decompression_chunk_size;
+        clone.transforms = self.transforms.clone();
+        clone.duplicates = self.duplicates;
+        clone.ignore_version_needed = self.ignore_version_needed;
+        clone.allow_missing_central_directory = self.allow_missing_central_directory;
+        clone.require_non_empty = self.require_non_empty;
+        clone.strict = self.strict;
+        clone.raw_filenames = self.raw_filenames;
+        #[cfg(feature = "encoding")]
+        {
+            clone.filename_encoding = self.filename_encoding;
+        }
+        clone.warning_handler = self.warning_handler.clone();
+        clone.quiet = self.quiet;
+        clone.timing = self.timing;
+        clone.adaptive_chunk_size = self.adaptive_chunk_size;
+        clone.observer = self.observer.clone();
+        clone.progress_interval = self.progress_interval;
+        clone.progress_basis = self.progress_basis;
+        clone.total_size = self.total_size;
+
+        if let Some((style, color)) = self.progress_config {
+            clone = clone.with_progress(style, color);
+        }
+
+        Ok(clone)
+    }
+
+    /// Reports per-entry decompression progress to `observer`.
+    pub fn with_progress_observer(mut self, observer: impl ProgressObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// How many decompressed bytes to produce between calls to
+    /// [`ProgressObserver::entry_progress`]. Defaults to 1 MiB.
+    pub fn with_progress_interval(mut self, bytes: u64) -> Self {
+        self.progress_interval = bytes.max(1);
+        self
+    }
+
+    /// Only yield entries whose filename is accepted by `filter`.
+    ///
+    /// Rejected entries are skipped in the stream without being buffered or
+    /// decompressed. Replaces any earlier filter, including one set with
+    /// [`MuyZipido::with_entry_filter`].
+    pub fn with_filter<F>(self, filter: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.with_entry_filter(move |info: &EntryInfo| filter(&info.filename))
+    }
+
+    /// Only yield entries accepted by `filter`, which sees the whole
+    /// [`EntryInfo`] from the local header. See the [`filter`] module for
+    /// built-in filters and how to combine them.
+    ///
+    /// Replaces any earlier filter, including one set with
+    /// [`MuyZipido::with_filter`].
+    pub fn with_entry_filter<F: ZipEntryFilter + 'static>(mut self, filter: F) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Replaces the default [`Limits`] on header lengths and entry sizes.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Yields entries with their data exactly as stored in the archive,
+    /// skipping decompression, e.g. to copy members into another archive
+    /// without recompressing them.
+    ///
+    /// [`EntryInfo`] still describes the stored data (method, CRC-32 and
+    /// sizes, from the data descriptor for streamed entries), but the CRC-32
+    /// cannot be checked without decompressing, so corrupt data is not
+    /// detected. Entries with compression methods this crate cannot decode
+    /// are returned too.
+    pub fn with_raw_entries(mut self, raw: bool) -> Self {
+        self.raw_entries = raw;
+        self
+    }
+
+    /// Checks the archive against a detached Ed25519 signature for
+    /// `public_key`, given as 32 raw bytes or a PEM `PUBLIC KEY` block.
+    ///
+    /// The signature is the data of the entry whose name ends in `.sig`: 64
+    /// bytes signing a `sha256sum`-style listing of every other file in the
+    /// archive, in archive order, as formatted by
+    /// [`Manifest`](checksum::Manifest). A publisher can produce the listing
+    /// with `sha256sum ./file1 ./file2 ...`, naming the files in the order
+    /// they are added to the archive.
+    ///
+    /// The check applies however the archive is read: iterating,
+    /// [`next_entry_kind`](MuyZipido::next_entry_kind),
+    /// [`find_entry`](MuyZipido::find_entry),
+    /// [`list_entries`](MuyZipido::list_entries),
+    /// [`preview`](MuyZipido::preview),
+    /// [`count_entries_fast`](MuyZipido::count_entries_fast),
+    /// [`extract_to_dir`](MuyZipido::extract_to_dir),
+    /// [`process_with`](MuyZipido::process_with) and
+    /// [`validate`](MuyZipido::validate). Entries that are skipped or not
+    /// wanted are still decoded so they can be hashed. Once the archive
+    /// ends, iteration and listing yield a last error, and the other methods
+    /// fail with it: [`ZipError::UnsupportedFeature`] if there is no
+    /// signature entry, or [`ZipError::SignatureRejected`] if the signature
+    /// does not match. Entries are yielded and files written as they are read,
+    /// so treat them as untrusted until then.
+    #[cfg(feature = "signatures")]
+    pub fn with_verify_signatures(mut self, public_key: &[u8]) -> Result<Self, ZipError> {
+        self.signature_check = Some(signature::SignatureCheck::new(public_key)?);
+        Ok(self)
+    }
+
+    /// Decodes entries with `decompressor` instead of the built-in `flate2`
+    /// inflate, e.g. to use another deflate implementation or to support
+    /// more [compression methods](Decompressor::supports).
+    ///
+    /// A decompressor is given an entry's whole compressed data at once, so
+    /// entries are buffered even when extracting to disk, and
+    /// [`Limits::max_entry_size`] is checked against the output afterwards.
+    /// Progress is reported once the entry has been decoded.
+    pub fn with_decompressor<D: Decompressor + 'static>(mut self, decompressor: D) -> Self {
+        self.decompressor = Some(Arc::new(decompressor));
+        self
+    }
+
+    /// Decodes deflated entries `bytes` at a time. When entries are
+    /// streamed, by [`extract_to_dir`](MuyZipido::extract_to_dir),
+    /// [`process_with`](MuyZipido::process_with) and
+    /// [`validate`](MuyZipido::validate), this buffer is all of the decoded
+    /// data held at once, so smaller saves memory and larger means fewer,
+    /// bigger writes. Entries decoded into memory grow by `bytes` at a time,
+    /// and a [`LargeZipEntry`] decodes straight into the caller's buffer.
+    /// Defaults to 64 KiB.
+    pub fn with_decompression_chunk_size(mut self, bytes: usize) -> Self {
+        self.decompression_chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Has [`MuyZipido::next_entry_kind`] stream entries whose header gives
+    /// an uncompressed size over `bytes`, as a [`LargeZipEntry`], instead of
+    /// decoding them into memory.
+    ///
+    /// Iterating the extractor is unaffected and still returns every entry
+    /// whole, since an `Iterator` cannot hand out items that borrow it.
+    pub fn with_large_entry_threshold(mut self, bytes: u64) -> Self {
+        self.large_entry_threshold = Some(bytes);
+        self
+    }
+
+    /// Applies `transform` to each entry after decompression, e.g.
+    /// [`Transform::GunzipByExtension`] for archives of `.gz` files.
+    /// Transforms run in the order they were added and are skipped for
+    /// directories and [raw entries](MuyZipido::with_raw_entries).
+    ///
+    /// The filter still sees the names as stored in the archive. A
+    /// [`LargeZipEntry`] is gunzipped as it is read; any [`Transform::Custom`]
+    /// needs the whole entry, so entries are then decoded into memory.
+    pub fn with_entry_transform(mut self, transform: Transform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Decides what happens to entries whose name repeats an earlier
+    /// entry's. Defaults to [`DuplicatePolicy::KeepAll`].
+    pub fn with_duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Reads entries even if their "version needed to extract" asks for
+    /// features this crate lacks, instead of failing with
+    /// [`ZipError::UnsupportedFeature`], for writers that set the field
+    /// higher than the entry needs.
+    pub fn with_ignore_version_needed(mut self, ignore: bool) -> Self {
+        self.ignore_version_needed = ignore;
+        self
+    }
+
+    /// Ends the archive without an error if the stream ends exactly where
+    /// the next local header would start, as for archives from streaming
+    /// writers that leave out the central directory. Otherwise that fails
+    /// with [`ZipError::UnexpectedEofInHeader`].
+    ///
+    /// A stream that ends inside a header or an entry's data, even part way
+    /// through the next signature, still fails. Ending early is recorded as
+    /// [`ZipWarning::MissingCentralDirectory`].
+    pub fn with_allow_missing_central_directory(mut self, allow: bool) -> Self {
+        self.allow_missing_central_directory = allow;
+        self
+    }
+
+    /// Fails with [`ZipError::EmptyArchive`] if the archive ends without any
+    /// entries, instead of simply ending. Entries left out by a filter still
+    /// count.
+    pub fn with_require_non_empty(mut self, require: bool) -> Self {
+        self.require_non_empty = require;
+        self
+    }
+
+    /// Fails entries whose headers are inconsistent with
+    /// [`ZipError::Inconsistent`] instead of only raising a warning: sizes
+    /// that disagree with the decoded data or the data descriptor, sizes in
+    /// the local header of an entry flagged as having a data descriptor,
+    /// sizes given for entries with no data, and extra fields that do not
+    /// exactly fill the extra data.
+    ///
+    /// The error is raised once the entry has been consumed, so reading
+    /// continues with the next entry. CRC-32 mismatches are errors either way.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Decodes entry names without the UTF-8 flag (bit 11) with `encoding`,
+    /// e.g. `encoding_rs::SHIFT_JIS`, instead of trying UTF-8 and then
+    /// CP437.
+    #[cfg(feature = "encoding")]
+    pub fn with_filename_encoding(mut self, encoding: &'static encoding_rs::Encoding) -> Self {
+        self.filename_encoding = Some(encoding);
+        self
+    }
+
+    /// Keeps each entry name's bytes as stored in
+    /// [`EntryInfo::filename_raw`], for callers that decode names
+    /// themselves. `filename` is then a lossy UTF-8 decoding.
+    pub fn with_raw_filenames(mut self, raw: bool) -> Self {
+        self.raw_filenames = raw;
+        self
+    }
+
+    /// Calls `handler` with each [`ZipWarning`] as it is raised, in addition
+    /// to collecting it for [`MuyZipido::warnings`].
+    pub fn with_warning_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ZipWarning) + Send + Sync + 'static,
+    {
+        self.warning_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// The anomalies noticed so far that did not stop the archive being
+    /// read, in the order they were found.
+    pub fn warnings(&self) -> &[ZipWarning] {
+        &self.warnings
+    }
+
+    /// The end of central directory record, with the entry count and
+    /// archive comment, once iteration has passed the central directory to
+    /// reach it. `None` before then, or if the stream ended without one or
+    /// it could not be read.
+    pub fn eocd(&self) -> Option<&EndOfCentralDirectory> {
+        self.eocd.as_ref()
+    }
+
+    /// The Zip64 end of central directory record, read along with
+    /// [`MuyZipido::eocd`] in archives that have one. Its counts, size and
+    /// offset are the real ones where the classic record's are saturated.
+    pub fn zip64_eocd(&self) -> Option<&Zip64EndOfCentralDirectory> {
+        self.zip64_eocd.as_ref()
+    }
+
+    fn warn(&mut self, warning: ZipWarning) {
+        if let Some(handler) = &self.warning_handler {
+            handler(&warning);
+        }
+        self.warnings.push(warning);
+    }
+
+    /// Records an inconsistency in the header just read: a warning, or under
+    /// [`MuyZipido::with_strict`] an error for the entry.
+    fn inconsistent(&mut self, warning: ZipWarning) {
+        if self.strict {
+            self.violation.get_or_insert(warning);
+        } else {
+            self.warn(warning);
+        }
+    }
+
+    /// Stops reading with [`ZipError::Cancelled`] once `flag` is set, e.g.
+    /// from a Ctrl-C handler. The flag is checked before every read from the
+    /// underlying stream.
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
+    /// Suppresses the per-entry diagnostics otherwise printed to stderr
+    /// while processing. Errors are still returned as usual.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Measures how long each entry takes to read from the stream and to
+    /// decompress, filling in [`ZipEntry::download_duration`] and
+    /// [`ZipEntry::decompression_duration`] and the totals in
+    /// [`MuyZipido::download_stats`].
+    pub fn with_timing(mut self, timing: bool) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// The most bytes asked of the source in one read.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Changes the most bytes asked of the source in one read from the next
+    /// read on, e.g. between entries once the connection's speed is known.
+    pub fn set_chunk_size(&mut self, size: usize) {
+        self.chunk_size = size.max(1);
+    }
+
+    /// Adjusts the chunk size to the download speed, keeping it between
+    /// `min` and `max` bytes. It doubles after a read fast enough to fill
+    /// the current size in under 10 ms, cutting the number of reads on a
+    /// fast connection, and halves after one slow enough to take over
+    /// 100 ms, so a slow connection is not asked for more than it delivers.
+    ///
+    /// ```no_run
+    /// # use muy_zipido::MuyZipido;
+    /// let file = std::fs::File::open("data.zip")?;
+    /// let extractor = MuyZipido::from_reader(file, 16 * 1024)
+    ///     .with_adaptive_chunk_size(4 * 1024, 4 * 1024 * 1024);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_adaptive_chunk_size(mut self, min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+        self.chunk_size = self.chunk_size.clamp(min, max);
+        self.adaptive_chunk_size = Some((min, max));
+        self
+    }
+
+    /// Doubles or halves the chunk size after reading `bytes` in `elapsed`,
+    /// as set up by [`MuyZipido::with_adaptive_chunk_size`].
+    fn adapt_chunk_size(&mut self, bytes: usize, elapsed: Duration) {
+        let Some((min, max)) = self.adaptive_chunk_size else {
+            return;
+        };
+        // Bytes the connection delivers in the time a chunk should take.
+        let per_second = bytes as f64 / elapsed.as_secs_f64().max(1e-9);
+        if per_second * ADAPTIVE_FAST_READ.as_secs_f64() >= self.chunk_size as f64 {
+            self.chunk_size = self.chunk_size.saturating_mul(2).min(max);
+        } else if per_second * ADAPTIVE_SLOW_READ.as_secs_f64() < self.chunk_size as f64 {
+            self.chunk_size = (self.chunk_size / 2).max(min);
+        }
+    }
+
+    /// Totals for the entries read so far, or `None` unless
+    /// [`MuyZipido::with_timing`] is enabled.
+    pub fn download_stats(&self) -> Option<DownloadStats> {
+        self.timing.then_some(self.stats)
+    }
+
+    /// Copies the raw archive bytes to `writer` as they are read from the
+    /// stream, like the Unix `tee` command.
+    ///
+    /// Parsing is unaffected. Once iteration reaches the end of the local
+    /// entries the rest of the stream (central directory and trailer) is also
+    /// copied so `writer` receives the complete archive, and `writer` is
+    /// flushed.
+    pub fn with_tee(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.tee = Some(Box::new(writer));
+        self
+    }
+
+    /// Saves the raw archive to `path` as it is read, for
+    /// [`ZipSession::open_cached`] to reuse instead of downloading it again.
+    ///
+    /// The file only appears at `path` once the whole archive has been read;
+    /// until then the data goes to `path` with a `.partial` suffix, which is
+    /// removed if the extractor is dropped early. The response's ETag, if
+    /// any, is saved next to it with an `.etag` suffix.
+    pub fn with_cache(mut self, path: impl AsRef<Path>) -> Result<Self, ZipError> {
+        self.cache = Some(cache::CacheFile::create(path.as_ref())?);
+        Ok(self)
+    }
+
+    /// Stops the download immediately, closing the connection without
+    /// reading the rest of the response and clearing any progress bar.
+    pub fn abort(mut self) {
+        self.reader = None;
+        self.aborted = true;
+    }
+
+    fn log(&self, message: fmt::Arguments) {
+        if !self.quiet {
+            eprintln!("{}", message);
+        }
+    }
+
+    fn read_chunk(&mut self, chunk: &mut [u8]) -> Result<usize, ZipError> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+        {
+            return Err(ZipError::Cancelled);
+        }
+
+        let Some(reader) = &mut self.reader else {
+            return Ok(0);
+        };
+
+        let mut chunk = chunk;
+        if let Some(max) = self.limits.max_download_bytes {
+            let remaining = max.saturating_sub(self.received);
+            if remaining == 0 {
+                // Only an error if the source actually has more to send.
+                return match reader.read(&mut [0u8]) {
+                    Ok(0) => Ok(0),
+                    Ok(_) => Err(ZipError::ArchiveLimitExceeded {
+                        limit: "max_download_bytes",
+                        max,
+                    }),
+                    Err(e) if self.url.is_some() => Err(self.interrupted(e.to_string())),
+                    Err(e) => Err(e.into()),
+                };
+            }
+            let len = remaining.min(chunk.len() as u64) as usize;
+            chunk = &mut chunk[..len];
+        }
+
+        let started = self.adaptive_chunk_size.is_some().then(Instant::now);
+        let bytes_read = match reader.read(chunk) {
+            Ok(bytes_read) => bytes_read,
+            // Errors reading an HTTP response come from the connection.
+            Err(e) if self.url.is_some() => return Err(self.interrupted(e.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+        self.received += bytes_read as u64;
+        if let Some(started) = started
+            && bytes_read > 0
+        {
+            self.adapt_chunk_size(bytes_read, started.elapsed());
+        }
+
+        if bytes_read == 0
+            && let Some(expected) = self.content_length
+            && self.received < expected as u64
+        {
+            return Err(self.interrupted(format!(
+                "connection closed after {} of {} bytes",
+                self.received, expected
+            )));
+        }
+
+        if let Some(tee) = &mut self.tee {
+            tee.write_all(&chunk[..bytes_read])?;
+        }
+        if let Some(cache) = &mut self.cache {
+            cache.write(&chunk[..bytes_read])?;
+        }
+
+        if self.progress_basis == ProgressBasis::Downloaded
+            && let Some(progress_bar) = &mut self.progress_bar
+        {
+            progress_bar.update(bytes_read);
+        }
+
+        Ok(bytes_read)
+    }
+
+    fn interrupted(&self, reason: String) -> ZipError {
+        ZipError::NetworkInterrupted {
+            offset: (self.offset + self.buffer.len()) as u64,
+            reason,
+        }
+    }
+
+    /// Copies the rest of the stream to the tee and cache, if any, once the
+    /// local entries have been read.
+    fn finish_tee(&mut self) -> Result<(), ZipError> {
+        if self.aborted || (self.tee.is_none() && self.cache.is_none()) {
+            return Ok(());
+        }
+
+        let mut chunk = vec![0u8; self.chunk_size];
+        while self.read_chunk(&mut chunk)? > 0 {}
+        if let Some(expected) = self.content_length
+            && self.received != expected as u64
+        {
+            self.warn(ZipWarning::ContentLengthMismatch {
+                expected: expected as u64,
+                received: self.received,
+            });
+        }
+
+        if let Some(tee) = &mut self.tee {
+            tee.flush()?;
+        }
+        if let Some(cache) = self.cache.take() {
+            cache.commit(self.etag.as_deref())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads until at least `size` bytes are buffered, without consuming
+    /// them.
+    fn fill_buffer(&mut self, size: usize) -> Result<(), ZipError> {
         while self.buffer.len() < size {
-            if let Some(response) = &mut self.response {
-                let mut chunk = vec![0u8; self.chunk_size];
-                let bytes_read = response.read(&mut chunk)?;
+            let mut chunk = vec![0u8; self.chunk_size];
+            let bytes_read = self.read_chunk(&mut chunk)?;
+
+            if bytes_read == 0 {
+                return Err(truncated((size - self.buffer.len()) as u64));
+            }
+
+            self.buffer.extend_from_slice(&chunk[..bytes_read]);
+        }
+
+        Ok(())
+    }
+
+    fn read_exact(&mut self, size: usize) -> Result<Vec<u8>, ZipError> {
+        if size < self.chunk_size || size <= self.buffer.len() {
+            self.fill_buffer(size)?;
+
+            let data = self.buffer[..size].to_vec();
+            self.buffer.drain(..size);
+            self.offset += size;
+            return Ok(data);
+        }
+
+        // Reads of a chunk or more, such as entry data, are allocated once
+        // and read straight into, after whatever was already buffered.
+        let mut data = vec![0u8; size];
+        let mut filled = self.buffer.len();
+        data[..filled].copy_from_slice(&self.buffer);
+        self.buffer.clear();
+        self.offset += filled;
+
+        while filled < size {
+            let end = size.min(filled + self.chunk_size);
+            let bytes_read = self.read_chunk(&mut data[filled..end])?;
+            if bytes_read == 0 {
+                return Err(truncated((size - filled) as u64));
+            }
+            filled += bytes_read;
+            self.offset += bytes_read;
+        }
+
+        Ok(data)
+    }
+
+    fn skip_bytes(&mut self, size: u64) -> Result<(), ZipError> {
+        let buffered = size.min(self.buffer.len() as u64) as usize;
+        self.buffer.drain(..buffered);
+        self.offset += buffered;
+
+        let mut remaining = size - buffered as u64;
+        let mut chunk = vec![0u8; self.chunk_size];
 
-                if bytes_read == 0 {
-                    return Err(ZipError::UnexpectedEof);
+        while remaining > 0 {
+            let to_read = remaining.min(chunk.len() as u64) as usize;
+            let bytes_read = self.read_chunk(&mut chunk[..to_read])?;
+
+            if bytes_read == 0 {
+                return Err(truncated(remaining));
+            }
+
+            remaining -= bytes_read as u64;
+            self.offset += bytes_read;
+        }
+
+        Ok(())
+    }
+
+    /// Scans for the data descriptor of `info`'s entry and applies it,
+    /// keeping the raw (still compressed) bytes if there are no more than
+    /// `keep` of them.
+    ///
+    /// Larger entries are scanned without being buffered and return `None`
+    /// in place of the data, so either way the stream ends up positioned at
+    /// the next entry.
+    fn scan_to_descriptor(
+        &mut self,
+        info: &mut EntryInfo,
+        keep: u64,
+    ) -> Result<Option<Vec<u8>>, ZipError> {
+        let not_found = || ZipError::DescriptorNotFound {
+            filename: info.filename.clone(),
+        };
+        let mut raw = Some(Vec::new());
+        let mut scanner = SignatureScanner::new(DATA_DESCRIPTOR_SIG);
+
+        loop {
+            let budget = self.limits.max_descriptor_scan - scanner.position();
+            if budget == 0 {
+                return Err(not_found());
+            }
+            match self.fill_buffer(1) {
+                Ok(()) => {}
+                Err(ZipError::UnexpectedEofInEntry { .. }) => return Err(not_found()),
+                Err(e) => return Err(e),
+            }
+
+            let len = (self.buffer.len() as u64).min(budget) as usize;
+            let found = scanner.feed(&self.buffer[..len]);
+            let consumed = found.map_or(len, |m| m.consumed);
+
+            if let Some(data) = &mut raw {
+                // Allow for the signature bytes, which are removed below.
+                if (data.len() + consumed) as u64 > keep.saturating_add(4) {
+                    raw = None;
+                } else {
+                    data.extend_from_slice(&self.buffer[..consumed]);
                 }
+            }
+            self.buffer.drain(..consumed);
+            self.offset += consumed;
+
+            if let Some(found) = found {
+                // The scan started at the entry data, so the signature's
+                // offset is the data length.
+                let (descriptor, _) = match self.zip64_descriptor {
+                    true => {
+                        DataDescriptor::parse_zip64(&self.read_exact(DataDescriptor::ZIP64_LEN)?)?
+                    }
+                    false => DataDescriptor::parse(&self.read_exact(DataDescriptor::LEN)?)?,
+                };
+                info.crc32 = descriptor.crc32;
+                info.compressed_size = descriptor.compressed_size;
+                info.uncompressed_size = descriptor.uncompressed_size;
+                info.data_len_on_wire = found.offset;
+                return Ok(raw.map(|mut data| {
+                    data.truncate(data.len() - 4);
+                    data
+                }));
+            }
+        }
+    }
+
+    fn process_next_entry(&mut self) -> Result<Option<ZipEntry>, ZipError> {
+        match self.next_accepted()? {
+            Some((info, has_data_descriptor)) => {
+                self.read_entry(info, has_data_descriptor).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reads up to the data of the next entry to be returned, skipping the
+    /// entries the filter or duplicate policy leave out.
+    fn next_accepted(&mut self) -> Result<Option<(EntryInfo, bool)>, ZipError> {
+        while !self.finished {
+            let Some((mut info, has_data_descriptor)) = self.read_local_header()? else {
+                self.finish_signature_check()?;
+                break;
+            };
+
+            if !self.accepts(&info)
+                || (self.is_duplicate(&info)? && self.duplicates == DuplicatePolicy::KeepFirst)
+            {
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+                continue;
+            }
+
+            return Ok(Some((info, has_data_descriptor)));
+        }
+
+        Ok(None)
+    }
 
-                chunk.truncate(bytes_read);
-                self.buffer.extend_from_slice(&chunk);
+    fn next_info(&mut self) -> Result<Option<EntryInfo>, ZipError> {
+        while !self.finished {
+            let Some((mut info, has_data_descriptor)) = self.read_local_header()? else {
+                self.finish_signature_check()?;
+                break;
+            };
 
-                if let Some(ref mut progress_bar) = self.progress_bar {
-                    progress_bar.update(bytes_read);
+            self.pass_over_entry(&mut info, has_data_descriptor)?;
+
+            if self.accepts(&info)
+                && !(self.is_duplicate(&info)? && self.duplicates == DuplicatePolicy::KeepFirst)
+            {
+                if let Some(violation) = self.violation.take() {
+                    return Err(self.in_entry(&info.filename, ZipError::Inconsistent(violation)));
                 }
-            } else {
-                return Err(ZipError::UnexpectedEof);
+                return Ok(Some(info));
             }
         }
 
-        let data = self.buffer[..size].to_vec();
-        self.buffer.drain(..size);
-        self.offset += size;
+        Ok(None)
+    }
 
-        Ok(data)
+    fn accepts(&self, info: &EntryInfo) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.accepts(info))
+    }
+
+    /// Whether an earlier entry had the same name as `info`. Fails with
+    /// [`ZipError::DuplicateEntry`] under [`DuplicatePolicy::Error`].
+    /// Directories are never duplicates.
+    fn is_duplicate(&mut self, info: &EntryInfo) -> Result<bool, ZipError> {
+        if self.duplicates == DuplicatePolicy::KeepAll || info.is_dir {
+            return Ok(false);
+        }
+        if self.seen_names.insert(info.filename.clone()) {
+            return Ok(false);
+        }
+        match self.duplicates {
+            DuplicatePolicy::Error => {
+                return Err(ZipError::DuplicateEntry {
+                    filename: info.filename.clone(),
+                });
+            }
+            DuplicatePolicy::Warn => self.warn(ZipWarning::DuplicateEntry {
+                filename: info.filename.clone(),
+            }),
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Reads the next local file header, returning `Ok(None)` once the central
+    /// directory or end record is reached.
+    fn read_local_header(&mut self) -> Result<Option<(EntryInfo, bool)>, ZipError> {
+        let header = match self.read_local_header_inner() {
+            Err(ZipError::UnexpectedEofInEntry { .. })
+                if self.allow_missing_central_directory
+                    && self.offset == self.entry_start
+                    && self.buffer.is_empty() =>
+            {
+                self.warn(ZipWarning::MissingCentralDirectory {
+                    offset: self.entry_start as u64,
+                });
+                self.finished = true;
+                self.finish_tee()?;
+                Ok(None)
+            }
+            Err(ZipError::UnexpectedEofInEntry { .. }) => Err(ZipError::UnexpectedEofInHeader {
+                offset: self.entry_start as u64,
+            }),
+            result => result,
+        };
+        match header {
+            Ok(None) if self.require_non_empty && self.entries_seen == 0 => {
+                Err(ZipError::EmptyArchive)
+            }
+            header => header,
+        }
     }
 
-    fn process_with_descriptor(&mut self, compression: u16) -> Result<Vec<u8>, ZipError> {
-        const DATA_DESC_SIG: [u8; 4] = [0x50, 0x4b, 0x07, 0x08];
+    /// Skips over the central directory headers starting with `signature`
+    /// and keeps the end of central directory records after them.
+    fn read_end_records(&mut self, mut signature: Vec<u8>) -> Result<(), ZipError> {
+        while signature == CENTRAL_DIRECTORY_HEADER_SIG {
+            let fixed = self.read_exact(CentralDirectoryHeader::FIXED_LEN - 4)?;
+            // The name, extra field and comment lengths, counted from after
+            // the signature.
+            let variable: u64 = [24, 26, 28]
+                .into_iter()
+                .map(|at| parser::read_le_u16(&fixed, at) as u64)
+                .sum();
+            self.skip_bytes(variable)?;
+            signature = self.read_exact(4)?;
+        }
 
-        let mut data = Vec::new();
-        let mut sig_buffer: CircularBuffer<u8> = CircularBuffer::new(4);
+        if signature == ZIP64_END_OF_CENTRAL_DIRECTORY_SIG {
+            let mut record = signature;
+            record.extend_from_slice(&self.read_exact(8)?);
+            let len = Zip64EndOfCentralDirectory::record_len(&record);
+            if len > MAX_ZIP64_END_RECORD {
+                return Err(ZipError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Zip64 end of central directory record of {} bytes", len),
+                )));
+            }
+            record.extend_from_slice(&self.read_exact(len as usize - record.len())?);
+            let (zip64, _) = Zip64EndOfCentralDirectory::parse(&record)?;
+            self.zip64_eocd = Some(zip64);
+            signature = self.read_exact(4)?;
+        }
+        if signature == ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIG {
+            let mut locator = signature;
+            locator.extend_from_slice(
+                &self.read_exact(Zip64EndOfCentralDirectoryLocator::LEN - locator.len())?,
+            );
+            Zip64EndOfCentralDirectoryLocator::parse(&locator)?;
+            signature = self.read_exact(4)?;
+        }
 
-        if compression == 8 {
-            let mut compressed_data = Vec::new();
+        if signature != END_OF_CENTRAL_DIRECTORY_SIG {
+            return Err(ZipError::InvalidSignature {
+                found: header::signature_hex(&signature),
+                offset: (self.offset - signature.len()) as u64,
+            });
+        }
 
-            loop {
-                let byte = self.read_exact(1)?[0];
-                compressed_data.push(byte);
-                sig_buffer.write(byte);
+        let mut record = signature;
+        record.extend_from_slice(&self.read_exact(EndOfCentralDirectory::FIXED_LEN - 4)?);
+        let comment_len = parser::read_le_u16(&record, 20) as usize;
+        record.extend_from_slice(&self.read_exact(comment_len)?);
+        let (eocd, _) = EndOfCentralDirectory::parse(&record)?;
+        self.eocd = Some(eocd);
+        Ok(())
+    }
 
-                if sig_buffer.len() >= 4 {
-                    let last_4 = sig_buffer.get_last_n(4);
-                    if last_4.as_slice() == DATA_DESC_SIG {
-                        compressed_data.truncate(compressed_data.len() - 4);
+    fn read_local_header_inner(&mut self) -> Result<Option<(EntryInfo, bool)>, ZipError> {
+        self.entry_start = self.offset;
+        self.violation = None;
+        let mut bytes = self.read_exact(4)?;
 
-                        let mut decoder = DeflateDecoder::new(&compressed_data[..]);
-                        decoder.read_to_end(&mut data)?;
+        if [
+            CENTRAL_DIRECTORY_HEADER_SIG,
+            ZIP64_END_OF_CENTRAL_DIRECTORY_SIG,
+            ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIG,
+            END_OF_CENTRAL_DIRECTORY_SIG,
+        ]
+        .iter()
+        .any(|signature| bytes == *signature)
+        {
+            self.log(format_args!("Reached end of local file entries"));
+            self.finished = true;
+            // Every entry was read, so a damaged tail is not worth failing
+            // over.
+            if let Err(e) = self.read_end_records(bytes) {
+                self.log(format_args!("End of central directory not read: {}", e));
+            }
+            self.finish_tee()?;
+            return Ok(None);
+        }
 
-                        let _crc = self.read_exact(4)?;
-                        let _compressed_size = self.read_exact(4)?;
-                        let _uncompressed_size = self.read_exact(4)?;
+        if bytes != LOCAL_FILE_HEADER_SIG {
+            return Err(ZipError::InvalidSignature {
+                found: header::signature_hex(&bytes),
+                offset: (self.offset - bytes.len()) as u64,
+            });
+        }
+        self.entries_seen += 1;
+        if let Some(max) = self.limits.max_entries
+            && self.entries_seen > max
+        {
+            return Err(ZipError::ArchiveLimitExceeded {
+                limit: "max_entries",
+                max: max as u64,
+            });
+        }
 
-                        break;
-                    }
+        bytes.extend_from_slice(&self.read_exact(LocalFileHeader::FIXED_LEN - 4)?);
+        let (filename_len, extra_len) = LocalFileHeader::variable_lengths(&bytes)?;
+        for (field, len, limit) in [
+            ("file name", filename_len, self.limits.max_filename_len),
+            ("extra field", extra_len, self.limits.max_extra_len),
+        ] {
+            if len > limit {
+                return Err(ZipError::InvalidHeader {
+                    offset: self.entry_start as u64,
+                    reason: format!("{} length {} exceeds the limit of {}", field, len, limit),
+                });
+            }
+        }
+        bytes.extend_from_slice(&self.read_exact(filename_len + extra_len)?);
+        let (header, _) = LocalFileHeader::parse(&bytes)?;
+
+        let filename = self.decode_filename(&header.filename, header.flags);
+        // Padding is only worth flagging in strict mode.
+        let slack = header::extra_fields_slack(&header.extra_field);
+        if slack.is_none() || (self.strict && slack != Some(0)) {
+            self.inconsistent(ZipWarning::TruncatedExtraField {
+                filename: filename.clone(),
+            });
+        }
+
+        let has_data_descriptor = header.flags.has_data_descriptor();
+        self.zip64_descriptor = has_data_descriptor && header.has_zip64_field();
+        if has_data_descriptor && (header.compressed_size != 0 || header.uncompressed_size != 0) {
+            self.inconsistent(ZipWarning::DescriptorWithSizes {
+                filename: filename.clone(),
+            });
+        }
+
+        // Nothing to decode, whatever the header says.
+        let mut uncompressed_size = header.uncompressed_size;
+        if !has_data_descriptor && header.compressed_size == 0 && uncompressed_size != 0 {
+            self.inconsistent(ZipWarning::EmptyDataWithSize {
+                filename: filename.clone(),
+                uncompressed_size,
+            });
+            uncompressed_size = 0;
+        }
+
+        let info = EntryInfo {
+            is_dir: filename.ends_with('/'),
+            filename,
+            compressed_size: header.compressed_size,
+            uncompressed_size,
+            compression_method: header.compression_method,
+            crc32: header.crc32,
+            modified: header.modified(),
+            version_needed: header.version_needed,
+            unix_mode: header.unix_mode(),
+            unix_mtime: header.unix_mtime(),
+            header_offset: self.entry_start as u64,
+            data_offset: self.offset as u64,
+            data_len_on_wire: if has_data_descriptor {
+                0
+            } else {
+                header.compressed_size
+            },
+            filename_raw: self.raw_filenames.then_some(header.filename),
+        };
+
+        Ok(Some((info, has_data_descriptor)))
+    }
+
+    /// Decodes an entry name: as UTF-8 if `flags` says so, else with the
+    /// encoding given to [`MuyZipido::with_filename_encoding`], else as UTF-8
+    /// if valid and CP437 if not.
+    fn decode_filename(&mut self, bytes: &[u8], flags: Flags) -> String {
+        // The flag promises UTF-8, so an invalid name is damaged rather than
+        // in another encoding.
+        if flags.is_utf8() || self.raw_filenames {
+            return String::from_utf8_lossy(bytes).to_string();
+        }
+        #[cfg(feature = "encoding")]
+        if let Some(encoding) = self.filename_encoding {
+            return encoding.decode_without_bom_handling(bytes).0.into_owned();
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(name) => name.to_string(),
+            Err(_) => {
+                let name = decode_cp437(bytes);
+                self.warn(ZipWarning::Cp437Filename {
+                    filename: name.clone(),
+                    offset: self.entry_start as u64,
+                });
+                name
+            }
+        }
+    }
+
+    /// Wraps an error raised while reading the data of `filename`, the most
+    /// recent entry, with its position in the archive.
+    fn in_entry(&self, filename: &str, error: ZipError) -> ZipError {
+        let error = match error {
+            ZipError::UnexpectedEofInEntry { bytes_missing, .. } => {
+                ZipError::UnexpectedEofInEntry {
+                    filename: filename.to_string(),
+                    bytes_missing,
                 }
+            }
+            error => error,
+        };
+        match error {
+            ZipError::Cancelled => error,
+            _ => ZipError::InEntry {
+                filename: filename.to_string(),
+                index: self.entries_seen.saturating_sub(1),
+                offset: self.offset as u64,
+                source: Box::new(error),
+            },
+        }
+    }
+
+    fn skip_entry_data(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+    ) -> Result<(), ZipError> {
+        self.skip_entry_data_inner(info, has_data_descriptor)
+            .map_err(|e| self.in_entry(&info.filename, e))
+    }
+
+    fn read_entry(
+        &mut self,
+        mut info: EntryInfo,
+        has_data_descriptor: bool,
+    ) -> Result<ZipEntry, ZipError> {
+        let mut data = self.read_entry_data(&mut info, has_data_descriptor)?;
+        let (download_duration, decompression_duration) = self.entry_timing.take().unzip();
 
-                if compressed_data.len() > 100_000_000 {
-                    return Err(ZipError::Decompression(
-                        "Data descriptor not found within reasonable limit".to_string(),
-                    ));
+        let mut original = None;
+        if !self.raw_entries && !info.is_dir {
+            for transform in &self.transforms {
+                let applied = transform
+                    .apply(&info.filename, &data, self.limits.max_entry_size)
+                    .map_err(|e| self.in_entry(&info.filename, e))?;
+                if let Some((filename, transformed)) = applied {
+                    original.get_or_insert_with(|| info.clone());
+                    info.filename = filename;
+                    info.uncompressed_size = transformed.len() as u64;
+                    data = transformed;
                 }
             }
-        } else if compression == 0 {
-            loop {
-                let byte = self.read_exact(1)?[0];
-                data.push(byte);
-                sig_buffer.write(byte);
+        }
 
-                if sig_buffer.len() >= 4 {
-                    let last_4 = sig_buffer.get_last_n(4);
-                    if last_4.as_slice() == DATA_DESC_SIG {
-                        data.truncate(data.len() - 4);
+        Ok(ZipEntry {
+            info,
+            data,
+            original,
+            download_duration,
+            decompression_duration,
+        })
+    }
 
-                        let _crc = self.read_exact(4)?;
-                        let _compressed_size = self.read_exact(4)?;
-                        let _uncompressed_size = self.read_exact(4)?;
+    fn read_entry_data(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+    ) -> Result<Vec<u8>, ZipError> {
+        let start = self.timing.then(Instant::now);
+        self.decompression_time = Duration::ZERO;
 
-                        break;
-                    }
+        let data = self
+            .read_entry_data_inner(info, has_data_descriptor)
+            .map_err(|e| self.in_entry(&info.filename, e))?;
+        #[cfg(feature = "signatures")]
+        if let Some(check) = &mut self.signature_check {
+            check.observe(info, &data);
+        }
+
+        self.record_timing(start, info);
+        Ok(data)
+    }
+
+    /// Splits the time since `start` into download and decompression time
+    /// for the entry just read and adds it to the totals, when timing is
+    /// enabled.
+    fn record_timing(&mut self, start: Option<Instant>, info: &EntryInfo) {
+        if let Some(start) = start {
+            let decompression = self.decompression_time;
+            let download = start.elapsed().saturating_sub(decompression);
+            self.entry_timing = Some((download, decompression));
+            self.stats.entries += 1;
+            self.stats.compressed_bytes += info.compressed_size;
+            self.stats.download_duration += download;
+            self.stats.decompression_duration += decompression;
+        }
+    }
+
+    /// Decompresses `raw`, the entry's data as stored, reporting progress to
+    /// the observer and adding the time taken to the entry's decompression
+    /// time when timing is enabled.
+    fn decompress(&mut self, raw: Vec<u8>, info: &EntryInfo) -> Result<Vec<u8>, ZipError> {
+        let observer = self.observer.clone();
+        let interval = self.progress_interval;
+        let report = |decompressed: u64| {
+            if let Some(observer) = &observer {
+                observer.entry_progress(info, decompressed);
+            }
+        };
+
+        let data = if let Some(decompressor) = &self.decompressor
+            && info.compression_method != CompressionMethod::Stored
+        {
+            let start = self.timing.then(Instant::now);
+            let result = decompressor.decompress(info.compression_method, &raw);
+            if let Some(start) = start {
+                self.decompression_time += start.elapsed();
+            }
+            let data = result.map_err(|e| decompress::attribute(e, &info.filename))?;
+            let max_size = self.max_entry_size();
+            if data.len() as u64 > max_size {
+                return Err(limit_exceeded(max_size, &info.filename));
+            }
+            data
+        } else if info.compression_method == CompressionMethod::Deflate && !raw.is_empty() {
+            let start = self.timing.then(Instant::now);
+            let mut reported = 0;
+            let max_size = self.max_entry_size();
+            let block_size = self.decompression_chunk_size;
+            let result = inflate(&raw, &info.filename, max_size, block_size, |decompressed| {
+                if decompressed - reported >= interval {
+                    reported = decompressed;
+                    report(decompressed);
                 }
+            });
+            if let Some(start) = start {
+                self.decompression_time += start.elapsed();
+            }
+            result?
+        } else {
+            raw
+        };
+
+        report(data.len() as u64);
+        Ok(data)
+    }
+
+    /// [`Limits::max_entry_size`], capped so that entry sizes within it
+    /// always fit in a `usize`, even on 32-bit targets.
+    fn max_entry_size(&self) -> u64 {
+        self.limits.max_entry_size.min(usize::MAX as u64)
+    }
+
+    /// Skips the data of an entry that is not wanted. With a signature check,
+    /// which needs every entry, it is decoded and hashed instead.
+    pub(crate) fn pass_over_entry(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+    ) -> Result<(), ZipError> {
+        #[cfg(feature = "signatures")]
+        if self.signature_check.is_some() {
+            let mut discard = std::io::sink();
+            let mut sink = EntrySink::new(&mut discard);
+            return self.stream_entry_data(info, has_data_descriptor, &mut sink);
+        }
+        self.skip_entry_data(info, has_data_descriptor)
+    }
+
+    /// Checks the signature once the last entry has been read, failing with
+    /// [`ZipError::UnsupportedFeature`] if it is missing or
+    /// [`ZipError::SignatureRejected`] if it does not match.
+    pub(crate) fn finish_signature_check(&mut self) -> Result<(), ZipError> {
+        #[cfg(feature = "signatures")]
+        if let Some(check) = self.signature_check.take() {
+            check.finish()?;
+        }
+        Ok(())
+    }
+
+    fn skip_entry_data_inner(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+    ) -> Result<(), ZipError> {
+        if has_data_descriptor {
+            self.scan_to_descriptor(info, 0)?;
+        } else {
+            self.skip_bytes(info.compressed_size)?;
+        }
+        self.entry_start = self.offset;
+        Ok(())
+    }
 
-                if data.len() > 100_000_000 {
-                    return Err(ZipError::Decompression(
-                        "Data descriptor not found within reasonable limit".to_string(),
-                    ));
+    /// Checks that the entry whose header was just read can be decoded,
+    /// skipping its data if it cannot.
+    fn check_decodable(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+    ) -> Result<(), ZipError> {
+        if let Some(violation) = self.violation.take() {
+            self.skip_entry_data_inner(info, has_data_descriptor)?;
+            return Err(ZipError::Inconsistent(violation));
+        }
+        if let Err(e) = check_version_needed(info) {
+            if !self.ignore_version_needed {
+                return Err(e);
+            }
+            self.warn(ZipWarning::VersionNeededIgnored {
+                filename: info.filename.clone(),
+                needed_version: info.version_needed,
+            });
+        }
+        let compression = info.compression_method;
+
+        self.log(format_args!("\nProcessing: {}", info.filename));
+        self.log(format_args!("  Compression: {}", compression));
+
+        // Consume the whole entry before decoding it, so that errors below
+        // leave the stream positioned at the next entry.
+        let supported = match &self.decompressor {
+            Some(decompressor) => decompressor.supports(compression),
+            None => matches!(
+                compression,
+                CompressionMethod::Stored | CompressionMethod::Deflate
+            ),
+        };
+        if !self.raw_entries && !supported {
+            self.skip_entry_data_inner(info, has_data_descriptor)?;
+            return Err(ZipError::UnsupportedCompression {
+                method: compression.code(),
+                filename: info.filename.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks decoded data of `len` bytes with CRC-32 `crc` against the
+    /// values recorded for the entry.
+    fn verify_decoded(&mut self, info: &mut EntryInfo, crc: u32, len: u64) -> Result<(), ZipError> {
+        if crc != info.crc32 {
+            return Err(ZipError::CrcMismatch {
+                filename: info.filename.clone(),
+                expected: info.crc32,
+                actual: crc,
+            });
+        }
+        if len != info.uncompressed_size {
+            let filename = info.filename.clone();
+            let warning = match info.uncompressed_size {
+                0 => ZipWarning::DataWithZeroSize {
+                    filename,
+                    actual: len,
+                },
+                header => ZipWarning::SizeMismatch {
+                    filename,
+                    header,
+                    actual: len,
+                },
+            };
+            if self.strict {
+                return Err(ZipError::Inconsistent(warning));
+            }
+            self.warn(warning);
+            info.uncompressed_size = len;
+        }
+        Ok(())
+    }
+
+    fn read_entry_data_inner(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+    ) -> Result<Vec<u8>, ZipError> {
+        self.check_decodable(info, has_data_descriptor)?;
+
+        let max_size = self.max_entry_size();
+        let raw = if has_data_descriptor {
+            self.log(format_args!("  Streaming with data descriptor..."));
+            let header_size = info.uncompressed_size;
+            let raw = self.scan_to_descriptor(info, max_size)?;
+            self.entry_start = self.offset;
+            if header_size != 0 && header_size != info.uncompressed_size {
+                let warning = ZipWarning::SizeMismatch {
+                    filename: info.filename.clone(),
+                    header: header_size,
+                    actual: info.uncompressed_size,
+                };
+                if self.strict {
+                    return Err(ZipError::Inconsistent(warning));
                 }
+                self.warn(warning);
             }
+            raw.ok_or_else(|| limit_exceeded(max_size, &info.filename))?
+        } else if info.compressed_size > max_size {
+            self.skip_bytes(info.compressed_size)?;
+            self.entry_start = self.offset;
+            return Err(limit_exceeded(max_size, &info.filename));
         } else {
-            return Err(ZipError::Decompression(format!(
-                "Unsupported compression method: {}",
-                compression
-            )));
+            let raw = self.read_exact(info.compressed_size as usize)?;
+            self.entry_start = self.offset;
+            raw
+        };
+
+        if self.raw_entries {
+            self.count_decompressed(raw.len() as u64);
+            return Ok(raw);
         }
 
+        let data = self.decompress(raw, info)?;
+        self.count_decompressed(data.len() as u64);
+        self.log(format_args!("  Processed {} bytes", data.len()));
+
+        let mut crc = flate2::Crc::new();
+        crc.update(&data);
+        self.verify_decoded(info, crc.sum(), data.len() as u64)?;
+
         Ok(data)
     }
 
-    fn process_next_entry(&mut self) -> Result<Option<ZipEntry>, ZipError> {
-        const LOCAL_FILE_HEADER_SIG: &[u8] = b"PK\x03\x04";
-        const CENTRAL_DIR_SIG: &[u8] = b"PK\x01\x02";
-        const END_CENTRAL_DIR_SIG: &[u8] = b"PK\x05\x06";
+    /// Writes the data of the entry whose header was just read to `out`,
+    /// decompressing it a chunk at a time rather than buffering the entry.
+    ///
+    /// Entries with a data descriptor, whose size is not known up front, and
+    /// entries decoded by a [custom decompressor](MuyZipido::with_decompressor)
+    /// are buffered as usual and then written.
+    fn stream_entry_data(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+        out: &mut EntrySink,
+    ) -> Result<(), ZipError> {
+        let start = self.timing.then(Instant::now);
+        self.decompression_time = Duration::ZERO;
 
-        if self.finished {
-            return Ok(None);
+        self.stream_and_observe(info, has_data_descriptor, out)
+            .map_err(|e| self.in_entry(&info.filename, e))?;
+
+        self.record_timing(start, info);
+        Ok(())
+    }
+
+    /// Streams the entry, feeding it to the signature check if one is set.
+    /// The signature entry itself is small and needed whole, so it is read
+    /// into memory.
+    #[cfg(feature = "signatures")]
+    fn stream_and_observe(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+        out: &mut EntrySink,
+    ) -> Result<(), ZipError> {
+        if self.signature_check.is_none() {
+            return self.stream_entry_data_inner(info, has_data_descriptor, out);
+        }
+        if signature::SignatureCheck::is_signature(info) {
+            let data = self.read_entry_data_inner(info, has_data_descriptor)?;
+            out.write(&data);
+            if let Some(check) = &mut self.signature_check {
+                check.observe(info, &data);
+            }
+            return Ok(());
         }
 
-        let sig = self.read_exact(4)?;
+        // Share the caller's digest if it asked for one.
+        let hashed_here = out.sha256.is_none();
+        if hashed_here {
+            out.sha256 = Some(checksum::Sha256Hasher::default());
+        }
+        let result = self.stream_entry_data_inner(info, has_data_descriptor, out);
+        let hasher = match hashed_here {
+            true => out.sha256.take(),
+            false => out.sha256.clone(),
+        };
+        result?;
+        if let (Some(check), Some(hasher)) = (&mut self.signature_check, hasher) {
+            check.record(info, hasher.finish());
+        }
+        Ok(())
+    }
 
-        if sig == CENTRAL_DIR_SIG || sig == END_CENTRAL_DIR_SIG {
-            println!("Reached end of local file entries");
+    #[cfg(not(feature = "signatures"))]
+    fn stream_and_observe(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+        out: &mut EntrySink,
+    ) -> Result<(), ZipError> {
+        self.stream_entry_data_inner(info, has_data_descriptor, out)
+    }
+
+    fn stream_entry_data_inner(
+        &mut self,
+        info: &mut EntryInfo,
+        has_data_descriptor: bool,
+        out: &mut EntrySink,
+    ) -> Result<(), ZipError> {
+        if has_data_descriptor || self.decompressor.is_some() {
+            let data = self.read_entry_data_inner(info, has_data_descriptor)?;
+            out.write(&data);
+            return Ok(());
+        }
+        self.check_decodable(info, false)?;
+
+        let max_size = self.max_entry_size();
+        let mut inflater = (!self.raw_entries
+            && info.compression_method == CompressionMethod::Deflate)
+            .then(|| {
+                let buffer = vec![0u8; self.decompression_chunk_size];
+                (flate2::Decompress::new(false), buffer)
+            });
+        let mut remaining = info.compressed_size;
+        let mut failure = None;
+
+        // Errors stop the decoding, but the rest of the entry is still
+        // consumed so the stream ends up at the next entry.
+        while remaining > 0 {
+            let chunk = self.read_some(remaining)?;
+            remaining -= chunk.len() as u64;
+            if failure.is_some() {
+                continue;
+            }
+
+            let before = out.decoded;
+            let decoded = match &mut inflater {
+                Some((inflater, buffer)) => {
+                    let started = self.timing.then(Instant::now);
+                    let decoded = inflate_chunk(inflater, &chunk, buffer, out);
+                    if let Some(started) = started {
+                        self.decompression_time += started.elapsed();
+                    }
+                    decoded
+                }
+                None => {
+                    out.write(&chunk);
+                    Ok(())
+                }
+            };
+            self.count_decompressed(out.decoded - before);
+            failure = match decoded {
+                Err(e) => Some(ZipError::CorruptData {
+                    filename: info.filename.clone(),
+                    source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+                }),
+                Ok(()) if out.decoded > max_size => Some(limit_exceeded(max_size, &info.filename)),
+                Ok(()) => None,
+            };
+        }
+        self.entry_start = self.offset;
+
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        if let Some(observer) = &self.observer {
+            observer.entry_progress(info, out.decoded);
+        }
+        if self.raw_entries {
+            return Ok(());
+        }
+        self.verify_decoded(info, out.crc.sum(), out.decoded)
+    }
+
+    /// Consumes between one and `max` bytes of the stream: whatever is
+    /// buffered, or else the next chunk read.
+    fn read_some(&mut self, max: u64) -> Result<Vec<u8>, ZipError> {
+        self.fill_buffer(1)?;
+        let len = (self.buffer.len() as u64).min(max) as usize;
+        self.read_exact(len)
+    }
+
+    /// Skips ahead to the first entry matching `predicate` and returns it,
+    /// leaving the stream positioned just after that entry.
+    ///
+    /// Entries before the match are skipped without being decompressed or
+    /// buffered. Returns `Ok(None)` if no remaining entry matches.
+    pub fn find_entry<P>(&mut self, mut predicate: P) -> Result<Option<ZipEntry>, ZipError>
+    where
+        P: FnMut(&EntryInfo) -> bool,
+    {
+        let result = (|| {
+            while !self.finished {
+                let Some((mut info, has_data_descriptor)) = self.read_local_header()? else {
+                    self.finish_signature_check()?;
+                    break;
+                };
+
+                if self.accepts(&info) && predicate(&info) {
+                    return self.read_entry(info, has_data_descriptor).map(Some);
+                }
+
+                self.pass_over_entry(&mut info, has_data_descriptor)?;
+            }
+
+            Ok(None)
+        })();
+
+        if result
+            .as_ref()
+            .is_err_and(|e: &ZipError| !e.is_recoverable())
+        {
             self.finished = true;
-            return Ok(None);
         }
+        result
+    }
+
+    /// Counts the remaining entries by reading only their local headers and
+    /// skipping over their data, consuming the stream.
+    ///
+    /// Every entry is counted, whatever the filter. Entries with a data
+    /// descriptor have to be scanned for their end, so this is only fast
+    /// for archives that record sizes in their local headers. For an
+    /// archive already in memory,
+    /// [`CentralDirectory::parse`](central::CentralDirectory::parse) avoids
+    /// reading the entries at all.
+    pub fn count_entries_fast(mut self) -> Result<usize, ZipError> {
+        let mut count = 0;
+        while let Some((mut info, has_data_descriptor)) = self.read_local_header()? {
+            self.pass_over_entry(&mut info, has_data_descriptor)?;
+            count += 1;
+        }
+        self.finish_signature_check()?;
+        Ok(count)
+    }
+
+    /// Returns the metadata of the first `n` entries, skipping their data,
+    /// then closes the connection without reading the rest of the archive.
+    ///
+    /// Useful for a quick look inside a large remote archive: only the
+    /// headers and data of those entries are downloaded, and only up to the
+    /// next chunk. Repeated names are all listed whatever the
+    /// [duplicate policy](MuyZipido::with_duplicates), except that
+    /// [`DuplicatePolicy::KeepFirst`] leaves out later copies.
+    ///
+    /// With a signature check set, the rest of the archive is read after
+    /// all, since the signature covers every entry.
+    pub fn preview(mut self, n: usize) -> Result<Vec<EntryInfo>, ZipError> {
+        let mut entries = Vec::new();
+        let result = (|| {
+            while entries.len() < n {
+                match self.next_info()? {
+                    Some(info) => entries.push(info),
+                    None => break,
+                }
+            }
+            #[cfg(feature = "signatures")]
+            while self.signature_check.is_some() && self.next_info()?.is_some() {}
+            Ok(())
+        })();
+        self.abort();
+        result.map(|()| entries)
+    }
+
+    /// Iterates over entry metadata only, skipping entry data without
+    /// decompressing or buffering it.
+    pub fn list_entries(self) -> ListEntries {
+        ListEntries {
+            inner: self,
+            held: None,
+        }
+    }
+}
+
+/// Iterator returned by [`MuyZipido::list_entries`].
+pub struct ListEntries {
+    inner: MuyZipido,
+    /// Under [`DuplicatePolicy::KeepLast`], the whole listing, read on the
+    /// first call to `next`
+    held: Option<std::vec::IntoIter<Result<EntryInfo, ZipError>>>,
+}
+
+impl ListEntries {
+    /// See [`MuyZipido::warnings`].
+    pub fn warnings(&self) -> &[ZipWarning] {
+        self.inner.warnings()
+    }
+
+    /// See [`MuyZipido::eocd`].
+    pub fn eocd(&self) -> Option<&EndOfCentralDirectory> {
+        self.inner.eocd()
+    }
+
+    /// See [`MuyZipido::zip64_eocd`].
+    pub fn zip64_eocd(&self) -> Option<&Zip64EndOfCentralDirectory> {
+        self.inner.zip64_eocd()
+    }
+
+    /// Reads every entry, keeping only the last of each name in the position
+    /// it appeared. An error ends the listing.
+    fn read_keeping_last(&mut self) -> Vec<Result<EntryInfo, ZipError>> {
+        let mut listing: Vec<Result<EntryInfo, ZipError>> = Vec::new();
+        while let Some(item) = self.next_in_stream() {
+            if let Ok(info) = &item {
+                listing.retain(|earlier| {
+                    earlier
+                        .as_ref()
+                        .map_or(true, |e| e.is_dir || e.filename != info.filename)
+                });
+            }
+            listing.push(item);
+        }
+        listing
+    }
+
+    fn next_in_stream(&mut self) -> Option<Result<EntryInfo, ZipError>> {
+        match self.inner.next_info() {
+            Ok(Some(info)) => Some(Ok(info)),
+            Ok(None) => None,
+            Err(e) => {
+                self.inner.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Iterator for ListEntries {
+    type Item = Result<EntryInfo, ZipError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.inner.duplicates != DuplicatePolicy::KeepLast {
+            return self.next_in_stream();
+        }
+        if self.held.is_none() {
+            self.held = Some(self.read_keeping_last().into_iter());
+        }
+        self.held.as_mut()?.next()
+    }
+}
+
+/// Characters for bytes 0x80 to 0xff in code page 437, the encoding of ZIP
+/// file names without the UTF-8 flag. The lower half matches ASCII.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+    ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+    αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+fn decode_cp437(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0..0x80 => b as char,
+            _ => CP437_HIGH.chars().nth((b - 0x80) as usize).unwrap_or('?'),
+        })
+        .collect()
+}
+
+/// Fails with [`ZipError::UnsupportedFeature`] if `info` needs a version of
+/// the ZIP specification whose features this crate cannot handle.
+///
+/// Versions that only introduce compression methods are left to the
+/// compression method check, which skips the entry instead.
+fn check_version_needed(info: &EntryInfo) -> Result<(), ZipError> {
+    // The upper byte is reserved here but some writers copy the host system
+    // from "version made by" into it.
+    let version = info.version_needed & 0xff;
+    let hint = match version {
+        0..=21 | 25 | 27 | 45 | 46 => return Ok(()),
+        50..=62 => "strong and AES encryption are not supported",
+        _ => "this version of the ZIP specification is not supported",
+    };
+    Err(ZipError::UnsupportedFeature {
+        needed_version: info.version_needed,
+        filename: info.filename.clone(),
+        hint: hint.to_string(),
+    })
+}
+
+fn limit_exceeded(limit: u64, filename: &str) -> ZipError {
+    ZipError::LimitExceeded {
+        limit,
+        filename: filename.to_string(),
+    }
+}
+
+/// The stream ended `bytes_missing` bytes short. The entry's name is filled
+/// in by `in_entry`, and `read_local_header` reports it as
+/// [`ZipError::UnexpectedEofInHeader`] instead.
+fn truncated(bytes_missing: u64) -> ZipError {
+    ZipError::UnexpectedEofInEntry {
+        filename: String::new(),
+        bytes_missing,
+    }
+}
+
+/// Default for [`MuyZipido::with_progress_interval`].
+const DEFAULT_PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// A read at a speed that would fill the chunk size within this doubles it,
+/// under [`MuyZipido::with_adaptive_chunk_size`].
+const ADAPTIVE_FAST_READ: Duration = Duration::from_millis(10);
+
+/// A read at a speed that would take longer than this to fill the chunk
+/// size halves it.
+const ADAPTIVE_SLOW_READ: Duration = Duration::from_millis(100);
+
+/// Default for [`MuyZipido::with_decompression_chunk_size`].
+const DEFAULT_DECOMPRESSION_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The longest Zip64 end of central directory record read. Writers leave
+/// its extensible data empty, so anything near this is damaged.
+const MAX_ZIP64_END_RECORD: u64 = 64 * 1024;
+
+/// Inflates `compressed` a block of `block_size` bytes at a time, calling
+/// `progress` with the running output size after every block. Fails once
+/// the output would exceed `max_size`.
+fn inflate(
+    compressed: &[u8],
+    filename: &str,
+    max_size: u64,
+    block_size: usize,
+    mut progress: impl FnMut(u64),
+) -> Result<Vec<u8>, ZipError> {
+    let mut decoder = DeflateDecoder::new(compressed);
+    let mut data = Vec::new();
+    let mut block = vec![0u8; block_size];
+
+    loop {
+        let read = decoder
+            .read(&mut block)
+            .map_err(|source| ZipError::CorruptData {
+                filename: filename.to_string(),
+                source,
+            })?;
+        if read == 0 {
+            return Ok(data);
+        }
+        if (data.len() + read) as u64 > max_size {
+            return Err(limit_exceeded(max_size, filename));
+        }
+        data.extend_from_slice(&block[..read]);
+        progress(data.len() as u64);
+    }
+}
+
+/// Decompresses one chunk of a deflate stream from `input` into `out`,
+/// using `buffer` for the output.
+fn inflate_chunk(
+    inflater: &mut flate2::Decompress,
+    mut input: &[u8],
+    buffer: &mut [u8],
+    out: &mut EntrySink,
+) -> Result<(), flate2::DecompressError> {
+    loop {
+        let (total_in, total_out) = (inflater.total_in(), inflater.total_out());
+        let status = inflater.decompress(input, buffer, flate2::FlushDecompress::None)?;
+        let consumed = (inflater.total_in() - total_in) as usize;
+        let produced = (inflater.total_out() - total_out) as usize;
+        out.write(&buffer[..produced]);
+        input = &input[consumed..];
+
+        let stalled = consumed == 0 && produced == 0;
+        if status == flate2::Status::StreamEnd
+            || stalled
+            || (input.is_empty() && produced < buffer.len())
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Passes an entry's decoded data on to a writer, keeping its CRC-32 and
+/// size. Once the writer fails it is not written to again, but the data is
+/// still counted so the entry can be checked.
+pub(crate) struct EntrySink<'a> {
+    writer: &'a mut dyn Write,
+    crc: flate2::Crc,
+    /// Digest of the decoded data, if asked for with `with_sha256`
+    #[cfg(feature = "sha256")]
+    pub(crate) sha256: Option<checksum::Sha256Hasher>,
+    /// Bytes of decoded data passed in
+    pub(crate) decoded: u64,
+    /// Bytes accepted by the writer
+    pub(crate) written: u64,
+    pub(crate) error: Option<std::io::Error>,
+}
 
-        if sig != LOCAL_FILE_HEADER_SIG {
-            let mut hex_string = String::with_capacity(sig.len() * 2);
-            for b in &sig {
-                hex_string.push_str(&format!("{:02x}", b));
-            }
-            return Err(ZipError::InvalidSignature(hex_string));
-        }
-
-        let header_data = self.read_exact(26)?;
-        let _version = u16::from_le_bytes([header_data[0], header_data[1]]);
-        let flags = u16::from_le_bytes([header_data[2], header_data[3]]);
-        let compression = u16::from_le_bytes([header_data[4], header_data[5]]);
-        let _mod_time = u16::from_le_bytes([header_data[6], header_data[7]]);
-        let _mod_date = u16::from_le_bytes([header_data[8], header_data[9]]);
-        let _crc32 = u32::from_le_bytes([
-            header_data[10],
-            header_data[11],
-            header_data[12],
-            header_data[13],
-        ]);
-        let compressed_size = u32::from_le_bytes([
-            header_data[14],
-            header_data[15],
-            header_data[16],
-            header_data[17],
-        ]);
-        let uncompressed_size = u32::from_le_bytes([
-            header_data[18],
-            header_data[19],
-            header_data[20],
-            header_data[21],
-        ]);
-        let filename_len = u16::from_le_bytes([header_data[22], header_data[23]]);
-        let extra_len = u16::from_le_bytes([header_data[24], header_data[25]]);
-
-        let filename_bytes = self.read_exact(filename_len as usize)?;
-        let filename = String::from_utf8_lossy(&filename_bytes).to_string();
-        let _extra_field = self.read_exact(extra_len as usize)?;
-
-        let has_data_descriptor = (flags & 0x08) != 0;
-
-        println!("\nProcessing: {}", filename);
-        println!("  Compression: {} (0=none, 8=deflate)", compression);
-
-        let data = if !has_data_descriptor && compressed_size > 0 {
-            let compressed_data = self.read_exact(compressed_size as usize)?;
-
-            match compression {
-                0 => compressed_data,
-                8 => {
-                    let mut decoder = DeflateDecoder::new(&compressed_data[..]);
-                    let mut decompressed = Vec::new();
-                    decoder.read_to_end(&mut decompressed)?;
-                    decompressed
+impl<'a> EntrySink<'a> {
+    pub(crate) fn new(writer: &'a mut dyn Write) -> Self {
+        Self {
+            writer,
+            crc: flate2::Crc::new(),
+            #[cfg(feature = "sha256")]
+            sha256: None,
+            decoded: 0,
+            written: 0,
+            error: None,
+        }
+    }
+
+    /// Also computes the SHA-256 digest of the decoded data.
+    #[cfg(feature = "sha256")]
+    pub(crate) fn with_sha256(mut self) -> Self {
+        self.sha256 = Some(checksum::Sha256Hasher::default());
+        self
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        self.crc.update(data);
+        #[cfg(feature = "sha256")]
+        if let Some(sha256) = &mut self.sha256 {
+            sha256.update(data);
+        }
+        self.decoded += data.len() as u64;
+        if self.error.is_some() {
+            return;
+        }
+
+        // As `write_all`, but counting what a failing writer did accept.
+        let mut rest = data;
+        while !rest.is_empty() {
+            match self.writer.write(rest) {
+                Ok(0) => {
+                    self.error = Some(std::io::ErrorKind::WriteZero.into());
+                    return;
+                }
+                Ok(len) => {
+                    self.written += len as u64;
+                    rest = &rest[len..];
                 }
-                _ => {
-                    return Err(ZipError::Decompression(format!(
-                        "Unsupported compression method: {}",
-                        compression
-                    )));
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
                 }
             }
-        } else if has_data_descriptor {
-            println!("  Streaming with data descriptor...");
-            self.process_with_descriptor(compression)?
-        } else {
-            Vec::new()
-        };
+        }
+    }
 
-        println!("  Processed {} bytes", data.len());
+    /// Flushes the writer, returning the error that stopped it, if any.
+    pub(crate) fn finish(mut self) -> Option<std::io::Error> {
+        if self.error.is_none()
+            && let Err(e) = self.writer.flush()
+        {
+            self.error = Some(e);
+        }
+        self.error
+    }
+}
 
-        Ok(Some(ZipEntry {
-            filename,
-            uncompressed_size,
-            data,
-        }))
+impl fmt::Debug for MuyZipido {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        /// Stands in for fields whose types are not `Debug`.
+        struct Opaque(&'static str);
+
+        impl fmt::Debug for Opaque {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.0)
+            }
+        }
+
+        f.debug_struct("MuyZipido")
+            .field("url", &self.url)
+            .field("offset", &self.offset)
+            .field("entry_start", &self.entry_start)
+            .field("entries_seen", &self.entries_seen)
+            .field("finished", &self.finished)
+            .field("chunk_size", &self.chunk_size)
+            .field("buffered", &self.buffer.len())
+            .field("content_length", &self.content_length)
+            .field("reader", &self.reader.as_ref().map(|_| Opaque("<reader>")))
+            .field("tee", &self.tee.as_ref().map(|_| Opaque("<writer>")))
+            .field("filter", &self.filter.as_ref().map(|_| Opaque("<filter>")))
+            .field("progress_bar", &self.progress_bar.is_some())
+            .field("timing", &self.timing)
+            .finish_non_exhaustive()
     }
 }
 
 impl Drop for MuyZipido {
     fn drop(&mut self) {
+        if let Some(tee) = &mut self.tee {
+            let _ = tee.flush();
+        }
+
         if let Some(ref mut progress_bar) = self.progress_bar {
-            progress_bar.finish();
+            if self.finished && !self.aborted {
+                progress_bar.finish();
+            } else {
+                progress_bar.finish_and_clear();
+            }
         }
     }
 }
 
+/// Yields the entries in archive order. After an error that
+/// [`is_recoverable`](ZipError::is_recoverable) iteration continues with the
+/// next entry; after any other error it ends.
 impl Iterator for MuyZipido {
     type Item = Result<ZipEntry, ZipError>;
 
@@ -310,7 +2299,9 @@ impl Iterator for MuyZipido {
             Ok(Some(entry)) => Some(Ok(entry)),
             Ok(None) => None,
             Err(e) => {
-                self.finished = true;
+                if !e.is_recoverable() {
+                    self.finished = true;
+                }
                 Some(Err(e))
             }
         }
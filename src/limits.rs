@@ -0,0 +1,77 @@
+/// Bounds on the sizes an archive can make the extractor read or allocate.
+///
+/// Header lengths and entry sizes come straight from the archive, so a
+/// malicious or corrupt one could otherwise ask for arbitrarily large
+/// buffers. Set with [`MuyZipido::with_limits`](crate::MuyZipido::with_limits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Longest file name accepted in a local header. Defaults to 4096 bytes.
+    pub max_filename_len: usize,
+    /// Longest extra field accepted in a local header. Defaults to 16 KiB.
+    pub max_extra_len: usize,
+    /// Largest entry, compressed or decompressed, held in memory. Larger
+    /// entries are skipped with [`ZipError::LimitExceeded`](crate::ZipError::LimitExceeded).
+    /// Defaults to 4 GiB.
+    pub max_entry_size: u64,
+    /// Most bytes scanned for the data descriptor of a streamed entry before
+    /// giving up with
+    /// [`ZipError::DescriptorNotFound`](crate::ZipError::DescriptorNotFound),
+    /// so a corrupt archive cannot keep the scan going indefinitely.
+    /// Defaults to 100 MiB.
+    pub max_descriptor_scan: u64,
+    /// Most bytes read from the source for the whole archive, for sources
+    /// that could send an endless stream. Unbounded by default.
+    pub max_download_bytes: Option<u64>,
+    /// Most entries read, including those skipped by a filter. Unbounded by
+    /// default.
+    pub max_entries: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_filename_len: 4096,
+            max_extra_len: 16 * 1024,
+            max_entry_size: 4 * 1024 * 1024 * 1024,
+            max_descriptor_scan: 100 * 1024 * 1024,
+            max_download_bytes: None,
+            max_entries: None,
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_filename_len(mut self, len: usize) -> Self {
+        self.max_filename_len = len;
+        self
+    }
+
+    pub fn with_max_extra_len(mut self, len: usize) -> Self {
+        self.max_extra_len = len;
+        self
+    }
+
+    pub fn with_max_entry_size(mut self, size: u64) -> Self {
+        self.max_entry_size = size;
+        self
+    }
+
+    pub fn with_max_descriptor_scan(mut self, bytes: u64) -> Self {
+        self.max_descriptor_scan = bytes;
+        self
+    }
+
+    pub fn with_max_download_bytes(mut self, bytes: u64) -> Self {
+        self.max_download_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_entries(mut self, entries: usize) -> Self {
+        self.max_entries = Some(entries);
+        self
+    }
+}
@@ -1,40 +1,1368 @@
+use clap::{ArgAction, Parser, ValueEnum};
 use muy_zipido::{
-    MuyZipido,
-    progress_bar::{Colour, Style},
+    EntryInfo, MuyZipido, ZipEntryKind, ZipError, ZipSession,
+    checksum::{Manifest, sha256_hex},
+    extract::{
+        DuplicatePolicy, ExtractOptions, ExtractReport, OverwritePolicy, SkipReason, sanitize_path,
+    },
+    glob::{PathFilter, Pattern},
+    progress_bar::{Colour, ProgressBar, Style},
 };
+use serde::{Deserialize, Serialize};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let url = "https://api.os.uk/downloads/v1/products/BuiltUpAreas/downloads?area=GB&format=GeoPackage&redirect";
-    println!("Fetching and processing ZIP from: {}", url);
+/// Stream remote ZIP archives and extract them on the fly.
+#[derive(Parser, Debug)]
+#[command(name = "muyzipido", version, about)]
+struct Cli {
+    /// URLs of the ZIP archives
+    #[arg(required_unless_present = "urls_from")]
+    urls: Vec<String>,
 
-    let extractor = MuyZipido::new(url, 10240)?.with_progress(Style::Blocks, Colour::Magenta);
+    /// Read further URLs from FILE, one per line (blank lines and # comments
+    /// are ignored)
+    #[arg(long, value_name = "FILE")]
+    urls_from: Option<PathBuf>,
 
-    let mut total_entries = 0;
-    let mut total_bytes = 0;
+    /// Directory to extract entries into
+    #[arg(short, long, default_value = ".")]
+    out: PathBuf,
 
-    for entry_result in extractor {
-        match entry_result {
-            Ok(entry) => {
-                total_entries += 1;
-                total_bytes += entry.data.len();
+    /// Name of each archive's subdirectory in batch mode; {index} is the
+    /// 1-based position and {filename} the archive name without .zip
+    #[arg(long, value_name = "TEMPLATE")]
+    name_template: Option<String>,
 
-                println!(
-                    "Entry {}: {} ({} bytes)",
-                    total_entries,
-                    entry.filename,
-                    entry.data.len()
-                );
+    /// Show one progress bar across all archives instead of one per archive
+    #[arg(long)]
+    aggregate_progress: bool,
+
+    /// Stop at the first archive that fails
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Wait at least SECS between archive requests, to go easy on the server
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    request_delay: Option<Duration>,
+
+    /// Identify as AGENT instead of muy-zipido/<version>
+    #[arg(long, value_name = "AGENT")]
+    user_agent: Option<String>,
+
+    /// Save progress to STATEFILE when interrupted and continue from it on
+    /// the next run
+    #[arg(long, value_name = "STATEFILE", conflicts_with_all = ["list", "cat", "cat_first_match", "dry_run"])]
+    resume: Option<PathBuf>,
+
+    /// Only extract entries whose path matches GLOB (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip entries whose path matches GLOB (repeatable, overrides --include)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Match --include/--exclude globs case-insensitively
+    #[arg(long)]
+    iglob: bool,
+
+    /// List entries instead of extracting them
+    #[arg(short, long)]
+    list: bool,
+
+    /// Print machine-readable JSON on stdout (NDJSON when listing)
+    #[arg(long)]
+    json: bool,
+
+    /// Print listings as a single JSON array instead of NDJSON
+    #[arg(long)]
+    json_array: bool,
+
+    /// Write the decompressed bytes of the entry at PATH to stdout
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["list", "cat_first_match"])]
+    cat: Option<String>,
+
+    /// Like --cat, for the first entry whose path matches GLOB
+    #[arg(long, value_name = "GLOB", conflicts_with = "list")]
+    cat_first_match: Option<String>,
+
+    /// Replace files that already exist in the output directory
+    #[arg(long, group = "existing")]
+    overwrite: bool,
+
+    /// Leave files that already exist untouched (default)
+    #[arg(long, group = "existing")]
+    skip_existing: bool,
+
+    /// Treat files that already exist as failures
+    #[arg(long, group = "existing")]
+    error_on_existing: bool,
+
+    /// Keep files that already exist and write the new one as
+    /// `name (1).ext`, `name (2).ext`, ...
+    #[arg(long, group = "existing")]
+    rename_existing: bool,
+
+    /// Create symbolic link entries as links (Unix only) instead of as files
+    /// holding the link target
+    #[arg(long)]
+    allow_symlinks: bool,
+
+    /// Restore modification times recorded in the archive
+    #[arg(long)]
+    preserve_mtime: bool,
+
+    /// Restore Unix permissions recorded in the archive, masked by --umask
+    #[arg(long)]
+    preserve_permissions: bool,
+
+    /// Permission bits cleared when restoring permissions, in octal
+    #[arg(long, value_name = "MODE", default_value = "022", value_parser = parse_octal)]
+    umask: u32,
+
+    /// What to do with entries whose path repeats an earlier entry's
+    #[arg(long, value_enum, value_name = "POLICY", default_value = "keep-all")]
+    duplicates: Duplicates,
+
+    /// Keep an index of extracted files in DIR and skip entries unchanged
+    /// since the last extraction into the same output directory
+    #[arg(long, value_name = "DIR", conflicts_with = "list")]
+    cache: Option<PathBuf>,
+
+    /// Show what would be extracted without writing anything
+    #[arg(long, conflicts_with = "list")]
+    dry_run: bool,
+
+    /// Read the whole archive and check that every entry would extract
+    /// cleanly, without writing anything
+    #[arg(long, conflicts_with_all = ["list", "cat", "cat_first_match", "dry_run", "verify", "resume"])]
+    validate: bool,
+
+    /// Write a checksum manifest (SHA256SUMS) into the output directory, or to
+    /// stdout with --list
+    #[arg(long, value_enum, value_name = "ALGORITHM", conflicts_with = "dry_run")]
+    checksums: Option<ChecksumAlgorithm>,
+
+    /// Check extracted files against a sha256sum-style MANIFEST
+    #[arg(long, value_name = "MANIFEST", conflicts_with_all = ["list", "dry_run"])]
+    verify: Option<PathBuf>,
+
+    /// Report entries that are corrupt or fail CRC-32 verification and continue
+    #[arg(long)]
+    keep_going: bool,
+
+    /// Read size in bytes for each network request
+    #[arg(long, default_value_t = 10240)]
+    chunk_size: usize,
+
+    /// Do not show the download progress bar
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Progress bar style: classic, ascii, dots, arrows, blocks or gradient [env: MUYZIPIDO_STYLE]
+    #[arg(long, value_name = "STYLE")]
+    progress_style: Option<Style>,
+
+    /// When to colour the progress bar (auto = only when stderr is a terminal) [env: NO_COLOR]
+    #[arg(long, value_enum, value_name = "WHEN")]
+    color: Option<ColorChoice>,
+
+    /// Suppress all output except errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print more detail: -v lists written files, -vv adds per-entry parser output
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Duplicates {
+    /// Extract or list every copy
+    KeepAll,
+    /// Keep the first copy
+    KeepFirst,
+    /// Keep the last copy
+    KeepLast,
+    /// Keep every copy, warning about repeated paths
+    Warn,
+    /// Fail on the first repeated path
+    Error,
+}
+
+impl From<Duplicates> for DuplicatePolicy {
+    fn from(duplicates: Duplicates) -> Self {
+        match duplicates {
+            Duplicates::KeepAll => DuplicatePolicy::KeepAll,
+            Duplicates::KeepFirst => DuplicatePolicy::KeepFirst,
+            Duplicates::KeepLast => DuplicatePolicy::KeepLast,
+            Duplicates::Warn => DuplicatePolicy::Warn,
+            Duplicates::Error => DuplicatePolicy::Error,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ChecksumAlgorithm {
+    Sha256,
+}
+
+impl Cli {
+    fn pattern(&self, glob: &str) -> Pattern {
+        if self.iglob {
+            Pattern::case_insensitive(glob)
+        } else {
+            Pattern::new(glob)
+        }
+    }
+
+    /// Resolves the progress bar settings from flags, then environment
+    /// variables, then defaults. Returns `None` when no bar should be shown.
+    fn progress(
+        &self,
+        env: impl Fn(&str) -> Option<String>,
+        stderr_is_tty: bool,
+    ) -> Option<(Style, Colour)> {
+        if self.no_progress || self.quiet {
+            return None;
+        }
+
+        let style = self
+            .progress_style
+            .or_else(|| env("MUYZIPIDO_STYLE").and_then(|s| s.parse().ok()))
+            .unwrap_or(Style::Blocks);
+
+        let no_color = env("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let color = match self.color {
+            Some(ColorChoice::Always) => true,
+            Some(ColorChoice::Never) => false,
+            Some(ColorChoice::Auto) | None => stderr_is_tty && !no_color,
+        };
+
+        Some((style, if color { Colour::Magenta } else { Colour::None }))
+    }
+
+    fn extract_options(&self) -> ExtractOptions {
+        let policy = if self.overwrite {
+            OverwritePolicy::Overwrite
+        } else if self.error_on_existing {
+            OverwritePolicy::Error
+        } else if self.rename_existing {
+            OverwritePolicy::Rename
+        } else {
+            OverwritePolicy::Skip
+        };
+
+        let options = ExtractOptions::new()
+            .with_overwrite(policy)
+            .with_dry_run(self.dry_run)
+            .with_keep_going(self.keep_going)
+            .with_allow_symlinks(self.allow_symlinks)
+            .with_preserve_mtime(self.preserve_mtime)
+            .with_preserve_permissions(self.preserve_permissions)
+            .with_umask(self.umask)
+            .with_checksums(self.checksums.is_some() || self.verify.is_some());
+        match &self.cache {
+            Some(dir) => options.with_cache_dir(dir),
+            None => options,
+        }
+    }
+
+    /// Collects the positional URLs followed by those in `--urls-from`.
+    fn all_urls(&self) -> io::Result<Vec<String>> {
+        let mut urls = self.urls.clone();
+
+        if let Some(path) = &self.urls_from {
+            let text = std::fs::read_to_string(path)?;
+            urls.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(String::from),
+            );
+        }
+
+        Ok(urls)
+    }
+
+    /// Batch mode extracts each archive into its own subdirectory of `--out`.
+    fn is_batch(&self, urls: &[String]) -> bool {
+        urls.len() > 1 || self.urls_from.is_some() || self.name_template.is_some()
+    }
+
+    /// Subdirectory name for the archive at 1-based `index`, named after the
+    /// Content-Disposition file name when the server sent one, otherwise
+    /// after the last segment of the URL path.
+    fn archive_dir_name(&self, index: usize, url: &str, suggested: Option<&str>) -> String {
+        let filename = suggested
+            .or_else(|| url_filename(url))
+            .map(|name| {
+                let lower = name.to_ascii_lowercase();
+                match lower.strip_suffix(".zip") {
+                    Some(stem) => name[..stem.len()].to_string(),
+                    None => name.to_string(),
+                }
+            })
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "archive".to_string());
+
+        let name = self
+            .name_template
+            .as_deref()
+            .unwrap_or("{filename}")
+            .replace("{index}", &index.to_string())
+            .replace("{filename}", &filename);
+
+        // Keep the name a single safe path component
+        match sanitize_path(&name.replace(['/', '\\'], "_")) {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => index.to_string(),
+        }
+    }
+
+    fn path_filter(&self) -> PathFilter {
+        let filter = self.include.iter().fold(PathFilter::new(), |filter, glob| {
+            filter.include(self.pattern(glob))
+        });
+
+        self.exclude
+            .iter()
+            .fold(filter, |filter, glob| filter.exclude(self.pattern(glob)))
+    }
+}
+
+/// Final report printed by `--json` in extraction mode.
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dir: Option<&'a Path>,
+    #[serde(flatten)]
+    report: Option<&'a ExtractReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verification: Option<&'a Verification>,
+    duration_secs: f64,
+    error: Option<String>,
+}
+
+/// Result of checking extracted files against `--verify MANIFEST`.
+#[derive(Serialize, Default)]
+struct Verification {
+    verified: usize,
+    mismatched: Vec<String>,
+    /// Manifest paths that were not extracted
+    missing: Vec<String>,
+}
+
+impl Verification {
+    fn passed(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty()
+    }
+}
+
+enum Outcome {
+    Extracted(ExtractReport, Option<Verification>),
+    Done,
+    NotFound(String),
+    /// `--validate` found problems, already reported
+    Invalid,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let urls = match cli.all_urls() {
+        Ok(urls) if urls.is_empty() => {
+            eprintln!("Error: no URLs given");
+            return ExitCode::FAILURE;
+        }
+        Ok(urls) => urls,
+        Err(e) => {
+            eprintln!("Error: cannot read URL list: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // The first Ctrl-C stops reading so state can be saved; a second one
+    // exits immediately.
+    let cancel = Arc::new(AtomicBool::new(false));
+    let handler_flag = Arc::clone(&cancel);
+    let _ = ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+    });
+
+    let mut session = ZipSession::new();
+    if let Some(agent) = &cli.user_agent {
+        session = match session.with_user_agent(agent) {
+            Ok(session) => session,
+            Err(e) => {
+                eprintln!("Error: --user-agent: {}", e);
+                return ExitCode::FAILURE;
             }
+        };
+    }
+    if let Some(delay) = cli.request_delay {
+        session = session.with_per_request_delay(delay);
+    }
+    if !cli.quiet {
+        session = session.with_rate_limit_handler(|url, wait| {
+            eprintln!("{}: rate limited, retrying in {}s", url, wait.as_secs())
+        });
+    }
+
+    if !cli.is_batch(&urls) {
+        return run_single(&cli, &session, &urls[0], &cancel);
+    }
+
+    if cli.list
+        || cli.validate
+        || cli.cat.is_some()
+        || cli.cat_first_match.is_some()
+        || cli.verify.is_some()
+        || cli.resume.is_some()
+    {
+        eprintln!(
+            "Error: --list, --validate, --cat, --cat-first-match, --verify and --resume take a single URL"
+        );
+        return ExitCode::FAILURE;
+    }
+
+    run_batch(&cli, &session, &urls, &cancel)
+}
+
+/// Applies the settings shared by every mode. Entries named in `skip` are
+/// left out in addition to those rejected by the path filter.
+fn configure(
+    cli: &Cli,
+    mut extractor: MuyZipido,
+    cancel: &Arc<AtomicBool>,
+    skip: Vec<String>,
+) -> MuyZipido {
+    extractor = extractor
+        .with_quiet(cli.verbose < 2)
+        .with_duplicates(cli.duplicates.into())
+        .with_cancel_flag(Arc::clone(cancel));
+    if cli.verbose > 0 {
+        extractor = extractor.with_warning_handler(|warning| eprintln!("Warning: {}", warning));
+    }
+
+    let filter = cli.path_filter();
+    if !filter.is_empty() || !skip.is_empty() {
+        extractor = extractor
+            .with_filter(move |name| filter.accepts(name) && !skip.iter().any(|done| done == name));
+    }
+    extractor
+}
+
+/// Progress persisted by `--resume` between runs.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct ResumeState {
+    url: String,
+    etag: Option<String>,
+    /// Offset of the first local header not yet fully extracted
+    offset: u64,
+    /// Entries extracted by earlier runs
+    completed: Vec<String>,
+}
+
+/// `--resume` bookkeeping for one run.
+struct Resume {
+    path: PathBuf,
+    previous: Option<ResumeState>,
+    completed: Arc<Mutex<Vec<String>>>,
+}
+
+impl Resume {
+    /// Loads the state file, ignoring it (with a warning) if it is unreadable
+    /// or belongs to a different URL.
+    fn load(path: &Path, url: &str) -> Self {
+        let previous = match std::fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str::<ResumeState>(&text) {
+                Ok(state) if state.url == url => Some(state),
+                Ok(state) => {
+                    eprintln!(
+                        "Warning: {} is for {}, starting a new download",
+                        path.display(),
+                        state.url
+                    );
+                    None
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Warning: ignoring invalid state file {}: {}",
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
             Err(e) => {
-                eprintln!("Error processing entry: {}", e);
-                break;
+                eprintln!("Warning: cannot read {}: {}", path.display(), e);
+                None
+            }
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            previous,
+            completed: Arc::default(),
+        }
+    }
+
+    /// Opens `url` where the previous run stopped. Falls back to a fresh
+    /// download if there is no usable state, the archive changed or the
+    /// server ignores the range request.
+    fn open(&self, cli: &Cli, session: &ZipSession, url: &str) -> Result<MuyZipido, ZipError> {
+        let Some(state) = &self.previous else {
+            return session.open(url, cli.chunk_size);
+        };
+
+        let Some(etag) = state.etag.as_deref() else {
+            eprintln!(
+                "Warning: no ETag was recorded for {}; downloading from the start",
+                url
+            );
+            return session.open(url, cli.chunk_size);
+        };
+
+        let extractor = session.open_at(url, state.offset, Some(etag), cli.chunk_size)?;
+        if extractor.resume_offset() == state.offset {
+            if !cli.quiet {
+                eprintln!("Resuming at byte {}", state.offset);
+            }
+        } else if extractor.etag() != Some(etag) {
+            eprintln!(
+                "Warning: the archive has changed since the last run; downloading from the start"
+            );
+            return Ok(extractor);
+        } else {
+            eprintln!(
+                "Warning: the server does not support range requests; downloading from the start"
+            );
+        }
+
+        *self.completed.lock().unwrap() = state.completed.clone();
+        Ok(extractor)
+    }
+
+    /// Entries an earlier run already extracted, which are skipped if the
+    /// download had to start over.
+    fn completed(&self) -> Vec<String> {
+        self.completed.lock().unwrap().clone()
+    }
+
+    fn record(&self, options: ExtractOptions) -> ExtractOptions {
+        let completed = Arc::clone(&self.completed);
+        options.with_on_written(move |info| {
+            completed.lock().unwrap().push(info.filename.clone());
+        })
+    }
+
+    fn save(&self, extractor: &MuyZipido, url: &str) -> io::Result<()> {
+        let state = ResumeState {
+            url: url.to_string(),
+            etag: extractor.etag().map(String::from),
+            offset: extractor.resume_offset(),
+            completed: self.completed.lock().unwrap().clone(),
+        };
+        let json = serde_json::to_string_pretty(&state).map_err(io::Error::from)?;
+        std::fs::write(&self.path, json)
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn run_single(cli: &Cli, session: &ZipSession, url: &str, cancel: &Arc<AtomicBool>) -> ExitCode {
+    let json = cli.json || cli.json_array;
+    let started = Instant::now();
+
+    if !json && !cli.quiet {
+        eprintln!("Fetching and processing ZIP from: {}", url);
+    }
+
+    let expected = match cli.verify.as_deref().map(read_manifest).transpose() {
+        Ok(expected) => expected,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let progress = cli.progress(|name| std::env::var(name).ok(), io::stderr().is_terminal());
+
+    let resume = cli.resume.as_deref().map(|path| Resume::load(path, url));
+    let opened = match &resume {
+        Some(resume) => resume.open(cli, session, url),
+        None => session.open(url, cli.chunk_size),
+    };
+
+    let result = opened.and_then(|mut extractor| {
+        if let Some((style, colour)) = progress {
+            extractor = extractor.with_progress(style, colour);
+        }
+        let skip = resume.as_ref().map_or_else(Vec::new, Resume::completed);
+        let mut extractor = configure(cli, extractor, cancel, skip);
+
+        if cli.cat.is_some() || cli.cat_first_match.is_some() {
+            return cat(cli, extractor);
+        } else if cli.list {
+            return list(cli, extractor).map(|()| Outcome::Done);
+        } else if cli.validate {
+            return validate(cli, extractor);
+        }
+
+        let mut options = cli.extract_options();
+        if let Some(resume) = &resume {
+            options = resume.record(options);
+        }
+        let result = extract(cli, &mut extractor, &options, &cli.out, expected.as_ref());
+
+        if let Some(resume) = &resume {
+            let saved = match &result {
+                Ok(_) => resume.clear(),
+                Err(_) => resume.save(&extractor, url).inspect(|()| {
+                    eprintln!(
+                        "Progress saved to {}; run the same command again to continue",
+                        resume.path.display()
+                    )
+                }),
+            };
+            if let Err(e) = saved {
+                eprintln!("Warning: cannot update {}: {}", resume.path.display(), e);
             }
         }
+
+        let (report, verification) = result?;
+        Ok(Outcome::Extracted(report, verification))
+    });
+
+    match result {
+        Ok(Outcome::Extracted(report, verification)) => print_report(
+            cli,
+            url,
+            &report,
+            verification.as_ref(),
+            started.elapsed().as_secs_f64(),
+        ),
+        Ok(Outcome::Done) => ExitCode::SUCCESS,
+        Ok(Outcome::Invalid) => ExitCode::FAILURE,
+        Ok(Outcome::NotFound(wanted)) => {
+            eprintln!("Error: no entry matching '{}' in archive", wanted);
+            ExitCode::from(2)
+        }
+        Err(ZipError::Cancelled) => {
+            eprintln!("Interrupted");
+            ExitCode::from(130)
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if json && !cli.list {
+                print_json(&JsonReport {
+                    url,
+                    dir: None,
+                    report: None,
+                    verification: None,
+                    duration_secs: started.elapsed().as_secs_f64(),
+                    error: Some(e.to_string()),
+                });
+            }
+            ExitCode::FAILURE
+        }
     }
+}
 
-    println!("\n=== Summary ===");
-    println!("Total entries: {}", total_entries);
-    println!("Total bytes processed: {}", total_bytes);
+fn extract(
+    cli: &Cli,
+    extractor: &mut MuyZipido,
+    options: &ExtractOptions,
+    out: &Path,
+    expected: Option<&Manifest>,
+) -> Result<(ExtractReport, Option<Verification>), ZipError> {
+    let report = extractor.extract_to_dir_with(out, options)?;
+    if cli.checksums.is_some() {
+        write_manifest(out, &report)?;
+    }
+    let verification = expected.map(|expected| verify(out, &report, expected));
+    Ok((report, verification))
+}
 
+/// Outcome of one archive in batch mode.
+struct ArchiveRun {
+    url: String,
+    dir: Option<PathBuf>,
+    result: Result<ExtractReport, ZipError>,
+    duration_secs: f64,
+}
+
+impl ArchiveRun {
+    fn succeeded(&self) -> bool {
+        self.result
+            .as_ref()
+            .is_ok_and(|report| report.failed.is_empty())
+    }
+}
+
+/// Final report printed by `--json` in batch mode.
+#[derive(Serialize)]
+struct JsonBatchReport<'a> {
+    archives: Vec<JsonReport<'a>>,
+    totals: &'a BatchTotals,
+    duration_secs: f64,
+}
+
+#[derive(Serialize, Default)]
+struct BatchTotals {
+    archives: usize,
+    failed_archives: usize,
+    files_written: usize,
+    bytes_written: u64,
+}
+
+/// Extracts each archive into its own subdirectory of `--out`, one after the
+/// other through a shared session.
+fn run_batch(
+    cli: &Cli,
+    session: &ZipSession,
+    urls: &[String],
+    cancel: &Arc<AtomicBool>,
+) -> ExitCode {
+    let json = cli.json || cli.json_array;
+    let started = Instant::now();
+    let progress = cli.progress(|name| std::env::var(name).ok(), io::stderr().is_terminal());
+
+    // With --aggregate-progress a single bar is handed from one extractor to
+    // the next; the total size is unknown up front so it shows a spinner.
+    let mut shared_bar = progress
+        .filter(|_| cli.aggregate_progress)
+        .map(|(style, colour)| ProgressBar::new(None).with_style(style).with_color(colour));
+
+    let mut used_names: Vec<String> = Vec::new();
+    let mut runs = Vec::new();
+
+    for (index, url) in urls.iter().enumerate() {
+        let archive_started = Instant::now();
+        let mut dir = None;
+
+        if !json && !cli.quiet {
+            eprintln!("[{}/{}] Fetching {}", index + 1, urls.len(), url);
+        }
+
+        let result = session.open(url, cli.chunk_size).and_then(|mut extractor| {
+            let mut name = cli.archive_dir_name(index + 1, url, extractor.suggested_filename());
+            if used_names.contains(&name) {
+                name = format!("{}-{}", name, index + 1);
+            }
+            used_names.push(name.clone());
+            let out = cli.out.join(name);
+            dir = Some(out.clone());
+
+            if let Some(bar) = shared_bar.take() {
+                let description = format!("Downloading {}/{}", index + 1, urls.len());
+                extractor = extractor.with_progress_bar(bar.with_description(description));
+            } else if let Some((style, colour)) = progress {
+                extractor = extractor.with_progress(style, colour);
+            }
+            let mut extractor = configure(cli, extractor, cancel, Vec::new());
+
+            let result = extractor.extract_to_dir_with(&out, &cli.extract_options());
+            if cli.aggregate_progress {
+                shared_bar = extractor.take_progress_bar();
+            }
+            let report = result?;
+
+            if cli.checksums.is_some() {
+                write_manifest(&out, &report)?;
+            }
+            Ok(report)
+        });
+
+        if let Err(e) = &result {
+            eprintln!("Error: {}: {}", url, e);
+        }
+
+        runs.push(ArchiveRun {
+            url: url.clone(),
+            dir,
+            result,
+            duration_secs: archive_started.elapsed().as_secs_f64(),
+        });
+
+        let failed = !runs.last().is_some_and(ArchiveRun::succeeded);
+        if (cli.fail_fast && failed) || cancel.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+
+    if let Some(mut bar) = shared_bar {
+        bar.finish();
+    }
+
+    let code = print_batch_report(cli, &runs, started.elapsed().as_secs_f64());
+    if cancel.load(Ordering::SeqCst) {
+        ExitCode::from(130)
+    } else {
+        code
+    }
+}
+
+fn print_batch_report(cli: &Cli, runs: &[ArchiveRun], duration_secs: f64) -> ExitCode {
+    let mut totals = BatchTotals {
+        archives: runs.len(),
+        ..BatchTotals::default()
+    };
+
+    for run in runs {
+        if !run.succeeded() {
+            totals.failed_archives += 1;
+        }
+        if let Ok(report) = &run.result {
+            totals.files_written += report.written.len();
+            totals.bytes_written += report.bytes_written;
+        }
+    }
+
+    if cli.json || cli.json_array {
+        let archives = runs
+            .iter()
+            .map(|run| JsonReport {
+                url: &run.url,
+                dir: run.dir.as_deref(),
+                report: run.result.as_ref().ok(),
+                verification: None,
+                duration_secs: run.duration_secs,
+                error: run.result.as_ref().err().map(ZipError::to_string),
+            })
+            .collect();
+
+        print_json(&JsonBatchReport {
+            archives,
+            totals: &totals,
+            duration_secs,
+        });
+    } else {
+        for run in runs {
+            let Ok(report) = &run.result else { continue };
+            for failure in &report.failed {
+                eprintln!("Failed to write {}: {}", failure.filename, failure.error);
+            }
+        }
+
+        if !cli.quiet {
+            println!("\n=== Summary ===");
+            for run in runs {
+                let dir = run
+                    .dir
+                    .as_deref()
+                    .map(|dir| dir.display().to_string())
+                    .unwrap_or_else(|| "-".to_string());
+
+                match &run.result {
+                    Ok(report) => println!(
+                        "{:<8} {}: {} files, {} bytes",
+                        if run.succeeded() { "ok" } else { "partial" },
+                        dir,
+                        report.written.len(),
+                        report.bytes_written
+                    ),
+                    Err(e) => println!("{:<8} {}: {}", "failed", run.url, e),
+                }
+            }
+            println!(
+                "Archives: {} ({} failed), files written: {}, total bytes written: {}",
+                totals.archives, totals.failed_archives, totals.files_written, totals.bytes_written
+            );
+        }
+    }
+
+    if totals.failed_archives == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn cat(cli: &Cli, extractor: MuyZipido) -> Result<Outcome, ZipError> {
+    let path = cli.cat.clone();
+    let pattern = cli.cat_first_match.as_deref().map(|glob| cli.pattern(glob));
+    let filter = cli.path_filter();
+    // Everything before the member is skipped in the stream, and the member
+    // itself is copied out as it is decompressed.
+    let mut extractor = extractor
+        .with_entry_filter(move |info: &EntryInfo| {
+            filter.accepts(&info.filename)
+                && match (&path, &pattern) {
+                    (Some(path), _) => info.filename == *path,
+                    (None, Some(pattern)) => !info.is_dir && pattern.matches(&info.filename),
+                    (None, None) => false,
+                }
+        })
+        .with_large_entry_threshold(0);
+
+    let Some(entry) = extractor.next_entry_kind() else {
+        let wanted = cli.cat.as_ref().or(cli.cat_first_match.as_ref());
+        return Ok(Outcome::NotFound(wanted.cloned().unwrap_or_default()));
+    };
+
+    let mut stdout = io::stdout().lock();
+    match entry? {
+        ZipEntryKind::Small(entry) => stdout.write_all(&entry.data)?,
+        ZipEntryKind::Large(mut entry) => {
+            io::copy(&mut entry, &mut stdout)?;
+        }
+    }
+    stdout.flush()?;
+
+    Ok(Outcome::Done)
+}
+
+fn list(cli: &Cli, extractor: MuyZipido) -> Result<(), ZipError> {
+    if cli.checksums.is_some() {
+        return list_checksums(cli, extractor);
+    }
+
+    let mut stdout = io::stdout().lock();
+    let mut collected: Vec<EntryInfo> = Vec::new();
+
+    for info in extractor.list_entries() {
+        let info = info?;
+
+        if cli.json_array {
+            collected.push(info);
+        } else if cli.json {
+            serde_json::to_writer(&mut stdout, &info).map_err(io::Error::from)?;
+            writeln!(stdout)?;
+        } else {
+            writeln!(
+                stdout,
+                "{:>12} {:>12}  {}",
+                info.uncompressed_size, info.compressed_size, info.filename
+            )?;
+        }
+    }
+
+    if cli.json_array {
+        serde_json::to_writer(&mut stdout, &collected).map_err(io::Error::from)?;
+        writeln!(stdout)?;
+    }
+
+    Ok(())
+}
+
+/// Checks the whole archive, printing the entries with problems, or the
+/// report as JSON with `--json`.
+fn validate(cli: &Cli, extractor: MuyZipido) -> Result<Outcome, ZipError> {
+    let report = extractor.validate()?;
+
+    if cli.json || cli.json_array {
+        print_json(&report);
+    } else {
+        let mut stdout = io::stdout().lock();
+        for entry in report.findings() {
+            for problem in &entry.problems {
+                writeln!(stdout, "FAIL {}: {}", entry.filename, problem)?;
+            }
+        }
+        if cli.verbose > 0 {
+            for warning in &report.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+        }
+        if !cli.quiet {
+            let failed = report.findings().count();
+            writeln!(
+                stdout,
+                "{} entries checked, {} with problems",
+                report.entries.len(),
+                failed
+            )?;
+        }
+    }
+
+    Ok(match report.is_valid() {
+        true => Outcome::Done,
+        false => Outcome::Invalid,
+    })
+}
+
+/// Prints a manifest of every file entry to stdout. Entries have to be
+/// decompressed to be hashed, so this downloads the whole archive.
+fn list_checksums(cli: &Cli, extractor: MuyZipido) -> Result<(), ZipError> {
+    let mut manifest = Manifest::new();
+
+    for entry in extractor {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if cli.keep_going && e.is_recoverable() => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        if entry.is_dir {
+            continue;
+        }
+        let Some(path) = sanitize_path(&entry.filename) else {
+            continue;
+        };
+
+        manifest.insert(&manifest_path(&path), sha256_hex(&entry.data));
+    }
+
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{}", manifest)?;
     Ok(())
 }
+
+fn read_manifest(path: &Path) -> Result<Manifest, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    text.parse()
+}
+
+/// Writes `SHA256SUMS` into `out`, covering every file written by this run.
+fn write_manifest(out: &Path, report: &ExtractReport) -> io::Result<()> {
+    let mut manifest = Manifest::new();
+    for file in &report.written {
+        if let (Ok(relative), Some(digest)) = (file.path.strip_prefix(out), &file.sha256) {
+            manifest.insert(&manifest_path(relative), digest.clone());
+        }
+    }
+    std::fs::write(out.join("SHA256SUMS"), manifest.to_string())
+}
+
+fn verify(out: &Path, report: &ExtractReport, expected: &Manifest) -> Verification {
+    let written = report.written.iter().filter_map(|file| {
+        let relative = file.path.strip_prefix(out).ok()?;
+        Some((manifest_path(relative), file.sha256.clone()?))
+    });
+    // Files left in place because they were already there are checked as
+    // they stand on disk.
+    let kept = report
+        .skipped
+        .iter()
+        .filter(|skipped| {
+            matches!(
+                skipped.reason,
+                SkipReason::AlreadyExists | SkipReason::Unchanged
+            )
+        })
+        .filter_map(|skipped| {
+            let relative = sanitize_path(&skipped.filename)?;
+            let data = std::fs::read(out.join(&relative)).ok()?;
+            Some((manifest_path(&relative), sha256_hex(&data)))
+        });
+
+    let mut verification = Verification::default();
+    let mut seen = Vec::new();
+
+    for (path, digest) in written.chain(kept) {
+        match expected.get(&path) {
+            Some(wanted) if wanted == digest => verification.verified += 1,
+            Some(_) => verification.mismatched.push(path.clone()),
+            None => {}
+        }
+        seen.push(path);
+    }
+
+    verification.missing = expected
+        .iter()
+        .filter(|(path, _)| !seen.iter().any(|seen| seen == path))
+        .map(|(path, _)| path.to_string())
+        .collect();
+
+    verification
+}
+
+fn parse_octal(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value, 8).map_err(|_| format!("{} is not an octal mode", value))
+}
+
+fn parse_secs(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("{} is not a number of seconds", value))
+}
+
+/// The last non-empty segment of the URL path, ignoring any query string.
+fn url_filename(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    let (_, path) = path.split_once('/')?;
+    path.rsplit('/').find(|segment| !segment.is_empty())
+}
+
+/// Formats a relative path with `/` separators for a manifest line.
+fn manifest_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn print_report(
+    cli: &Cli,
+    url: &str,
+    report: &ExtractReport,
+    verification: Option<&Verification>,
+    duration_secs: f64,
+) -> ExitCode {
+    if cli.json || cli.json_array {
+        print_json(&JsonReport {
+            url,
+            dir: None,
+            report: Some(report),
+            verification,
+            duration_secs,
+            error: None,
+        });
+    } else {
+        for failure in &report.failed {
+            eprintln!("Failed to write {}: {}", failure.filename, failure.error);
+        }
+
+        if report.dry_run {
+            for file in &report.written {
+                println!("{:>12}  {}", file.size, file.path.display());
+            }
+        }
+
+        if !cli.quiet {
+            for skipped in &report.skipped {
+                match skipped.reason {
+                    SkipReason::UnsafePath => {
+                        eprintln!("Skipped unsafe entry name: {}", skipped.filename)
+                    }
+                    SkipReason::AlreadyExists if cli.verbose > 0 || report.dry_run => {
+                        eprintln!("Skipped existing file: {}", skipped.filename)
+                    }
+                    SkipReason::AlreadyExists => {}
+                    SkipReason::Duplicate => {
+                        eprintln!("Skipped duplicate entry: {}", skipped.filename)
+                    }
+                    SkipReason::Unchanged if cli.verbose > 0 => {
+                        eprintln!("Unchanged: {}", skipped.filename)
+                    }
+                    SkipReason::Unchanged => {}
+                }
+            }
+            if cli.verbose > 0 && !report.dry_run {
+                for file in &report.written {
+                    eprintln!("Wrote {}", file.path.display());
+                }
+            }
+
+            let verb = if report.dry_run {
+                "to write"
+            } else {
+                "written"
+            };
+            println!("\n=== Summary ===");
+            println!("Files {}: {}", verb, report.written.len());
+            println!("Files skipped: {}", report.skipped.len());
+            if cli.cache.is_some() {
+                println!("Files unchanged: {}", report.unchanged());
+            }
+            println!("Files failed: {}", report.failed.len());
+            println!("Total bytes {}: {}", verb, report.bytes_written);
+        }
+
+        if let Some(verification) = verification {
+            for path in &verification.mismatched {
+                eprintln!("Checksum mismatch: {}", path);
+            }
+            for path in &verification.missing {
+                eprintln!("Missing from archive: {}", path);
+            }
+            if !cli.quiet {
+                println!("Checksums verified: {}", verification.verified);
+            }
+        }
+    }
+
+    if report.failed.is_empty() && verification.is_none_or(Verification::passed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn print_json(value: &impl Serialize) {
+    let mut stdout = io::stdout().lock();
+    let _ = serde_json::to_writer(&mut stdout, value);
+    let _ = writeln!(stdout);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeatable_globs() {
+        let cli = Cli::parse_from([
+            "muyzipido",
+            "https://example.com/a.zip",
+            "--out",
+            "data",
+            "--include",
+            "*.csv",
+            "--include",
+            "Doc",
+            "--exclude",
+            "Doc/*",
+        ]);
+
+        let filter = cli.path_filter();
+        assert!(filter.accepts("points.csv"));
+        assert!(!filter.accepts("Doc/points.csv"));
+        assert!(!filter.accepts("points.gpkg"));
+    }
+
+    #[test]
+    fn test_progress_flags_override_environment() {
+        let env = |name: &str| match name {
+            "NO_COLOR" => Some("1".to_string()),
+            "MUYZIPIDO_STYLE" => Some("dots".to_string()),
+            _ => None,
+        };
+
+        let cli = Cli::parse_from(["muyzipido", "https://example.com/a.zip"]);
+        assert_eq!(cli.progress(env, true), Some((Style::Dots, Colour::None)));
+        assert_eq!(
+            cli.progress(|_| None, true),
+            Some((Style::Blocks, Colour::Magenta))
+        );
+        assert_eq!(
+            cli.progress(|_| None, false),
+            Some((Style::Blocks, Colour::None))
+        );
+
+        let cli = Cli::parse_from([
+            "muyzipido",
+            "https://example.com/a.zip",
+            "--progress-style",
+            "ascii",
+            "--color",
+            "always",
+        ]);
+        assert_eq!(
+            cli.progress(env, false),
+            Some((Style::Ascii, Colour::Magenta))
+        );
+
+        let cli = Cli::parse_from(["muyzipido", "https://example.com/a.zip", "--color", "never"]);
+        assert_eq!(
+            cli.progress(|_| None, true),
+            Some((Style::Blocks, Colour::None))
+        );
+
+        let cli = Cli::parse_from(["muyzipido", "https://example.com/a.zip", "--no-progress"]);
+        assert_eq!(cli.progress(|_| None, true), None);
+
+        let cli = Cli::parse_from(["muyzipido", "https://example.com/a.zip", "-q"]);
+        assert_eq!(cli.progress(|_| None, true), None);
+    }
+
+    #[test]
+    fn test_verbosity_and_style_parsing() {
+        let cli = Cli::parse_from(["muyzipido", "https://example.com/a.zip", "-vv"]);
+        assert_eq!(cli.verbose, 2);
+
+        assert!(Cli::try_parse_from(["muyzipido", "u", "--progress-style", "sparkles"]).is_err());
+        assert!(Cli::try_parse_from(["muyzipido", "u", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_overwrite_flags() {
+        let cli = Cli::parse_from(["muyzipido", "u"]);
+        let options = cli.extract_options();
+        assert_eq!(options.overwrite, OverwritePolicy::Skip);
+        assert!(!options.dry_run);
+
+        assert_eq!(options.cache_dir, None);
+
+        let cli = Cli::parse_from(["muyzipido", "u", "--overwrite", "--dry-run", "--cache", "c"]);
+        let options = cli.extract_options();
+        assert_eq!(options.overwrite, OverwritePolicy::Overwrite);
+        assert!(options.dry_run);
+        assert_eq!(options.cache_dir, Some(PathBuf::from("c")));
+
+        let cli = Cli::parse_from(["muyzipido", "u", "--error-on-existing"]);
+        assert_eq!(cli.extract_options().overwrite, OverwritePolicy::Error);
+
+        let cli = Cli::parse_from(["muyzipido", "u", "--rename-existing"]);
+        assert_eq!(cli.extract_options().overwrite, OverwritePolicy::Rename);
+
+        assert!(Cli::try_parse_from(["muyzipido", "u", "--overwrite", "--skip-existing"]).is_err());
+        assert!(Cli::try_parse_from(["muyzipido", "u", "--dry-run", "--list"]).is_err());
+    }
+
+    #[test]
+    fn test_archive_dir_names() {
+        let cli = Cli::parse_from(["muyzipido", "u1", "u2"]);
+        assert!(cli.is_batch(&cli.urls));
+        assert_eq!(
+            cli.archive_dir_name(1, "https://example.com/files/roads.zip?v=2", None),
+            "roads"
+        );
+        assert_eq!(
+            cli.archive_dir_name(2, "https://example.com/download", Some("Rail.ZIP")),
+            "Rail"
+        );
+        assert_eq!(
+            cli.archive_dir_name(3, "https://example.com/", None),
+            "archive"
+        );
+        assert_eq!(
+            cli.archive_dir_name(4, "https://example.com/a.zip", Some("../../evil.zip")),
+            ".._.._evil"
+        );
+
+        let cli = Cli::parse_from(["muyzipido", "u1", "--name-template", "{index}-{filename}"]);
+        assert!(cli.is_batch(&cli.urls));
+        assert_eq!(
+            cli.archive_dir_name(7, "https://example.com/roads.zip", None),
+            "7-roads"
+        );
+
+        let cli = Cli::parse_from(["muyzipido", "u1"]);
+        assert!(!cli.is_batch(&cli.urls));
+        assert!(Cli::try_parse_from(["muyzipido"]).is_err());
+    }
+
+    #[test]
+    fn test_iglob() {
+        let cli = Cli::parse_from([
+            "muyzipido",
+            "https://example.com/a.zip",
+            "--include",
+            "*.CSV",
+            "--iglob",
+        ]);
+
+        assert!(cli.path_filter().accepts("points.csv"));
+    }
+}
@@ -0,0 +1,58 @@
+//! Reading little-endian integers out of ZIP records.
+//!
+//! Callers check lengths before reading, e.g. with `slice::get`, so an
+//! offset past the end of `bytes` is a bug in the caller and panics with the
+//! offset and length involved.
+
+/// The `u16` at `offset` in `bytes`.
+pub fn read_le_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(read_array(bytes, offset))
+}
+
+/// The `u32` at `offset` in `bytes`.
+pub fn read_le_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(read_array(bytes, offset))
+}
+
+/// The `u64` at `offset` in `bytes`.
+pub fn read_le_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(read_array(bytes, offset))
+}
+
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    match offset.checked_add(N).and_then(|end| bytes.get(offset..end)) {
+        Some(field) => field.try_into().unwrap(),
+        None => panic!(
+            "reading {} bytes at offset {} from a slice of {} bytes",
+            N,
+            offset,
+            bytes.len()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_le() {
+        let bytes = [0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x08, 0x00, 0xff];
+        assert_eq!(read_le_u16(&bytes, 4), 20);
+        assert_eq!(read_le_u16(&bytes, 7), 0xff00);
+        assert_eq!(read_le_u32(&bytes, 0), 0x0403_4b50);
+        assert_eq!(read_le_u64(&bytes, 1), 0xff00_0800_1404_034b);
+    }
+
+    #[test]
+    #[should_panic(expected = "reading 4 bytes at offset 6 from a slice of 9 bytes")]
+    fn test_read_past_end() {
+        read_le_u32(&[0; 9], 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "reading 2 bytes at offset 18446744073709551615")]
+    fn test_offset_overflow() {
+        read_le_u16(&[0; 9], usize::MAX);
+    }
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The source of time for a [`ProgressBar`](super::ProgressBar), which
+/// measures speed, ETA and elapsed time and decides when to redraw by it.
+///
+/// Set with [`ProgressBar::with_clock`](super::ProgressBar::with_clock).
+/// The default, [`SystemClock`], reads [`Instant::now`]; tests can supply a
+/// clock they advance by hand to get the same frames on every run.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Real time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Lets the caller keep a handle on the clock, e.g. to advance it.
+impl<T: Clock + ?Sized> Clock for Arc<T> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+}
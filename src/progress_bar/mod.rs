@@ -1,5 +1,13 @@
+pub mod clock;
+pub mod observer;
+mod output;
 pub mod progress;
 pub mod style;
+pub use clock::Clock;
+pub use observer::ProgressObserver;
+pub use progress::BytesStyle;
 pub use progress::Colour;
 pub use progress::ProgressBar;
+pub use progress::ProgressBasis;
+pub use progress::ProgressSnapshot;
 pub use style::Style;
@@ -0,0 +1,26 @@
+use crate::EntryInfo;
+use std::sync::Arc;
+
+/// Receives progress events from an extractor, for driving a second
+/// progress bar or a caller's own UI.
+///
+/// Set with [`MuyZipido::with_progress_observer`](crate::MuyZipido::with_progress_observer).
+/// Methods are called on the thread doing the parsing and should return
+/// quickly.
+pub trait ProgressObserver: Send + Sync {
+    /// `decompressed` bytes of `entry` have been produced so far, out of
+    /// `entry.uncompressed_size`.
+    ///
+    /// Called every [`MuyZipido::with_progress_interval`](crate::MuyZipido::with_progress_interval)
+    /// output bytes while the entry is decompressed, and once more when it
+    /// is complete, so the last call for an entry reports its full size.
+    fn entry_progress(&self, entry: &EntryInfo, decompressed: u64);
+}
+
+/// Lets the caller keep a handle on the observer, e.g. to read what it
+/// recorded.
+impl<T: ProgressObserver + ?Sized> ProgressObserver for Arc<T> {
+    fn entry_progress(&self, entry: &EntryInfo, decompressed: u64) {
+        (**self).entry_progress(entry, decompressed);
+    }
+}
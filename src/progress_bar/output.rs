@@ -0,0 +1,178 @@
+//! Where a [`ProgressBar`](super::ProgressBar) writes, either directly or
+//! from a thread of its own so a blocked terminal never holds up the caller.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+pub(crate) struct Output {
+    writer: Option<Box<dyn Write + Send>>,
+    thread: Option<RenderThread>,
+    /// Write from a render thread, started on the first write
+    background: bool,
+}
+
+impl Output {
+    pub(crate) fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer: Some(writer),
+            thread: None,
+            background: true,
+        }
+    }
+
+    pub(crate) fn set_writer(&mut self, writer: Box<dyn Write + Send>) {
+        self.thread = None;
+        self.writer = Some(writer);
+    }
+
+    pub(crate) fn set_background(&mut self, background: bool) {
+        self.background = background;
+    }
+
+    /// Writes a frame, which a later frame may replace before it is shown if
+    /// the writer is busy.
+    pub(crate) fn frame(&mut self, frame: String) {
+        self.send(Message::Frame(frame));
+    }
+
+    /// Writes `text`, which is never skipped.
+    pub(crate) fn text(&mut self, text: &str) {
+        self.send(Message::Text(text.to_string()));
+    }
+
+    /// Waits until everything sent so far has been written.
+    pub(crate) fn flush(&mut self) {
+        match (&self.thread, &mut self.writer) {
+            (Some(thread), _) => thread.wait_idle(),
+            (None, Some(writer)) => {
+                let _ = writer.flush();
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        if self.background
+            && self.thread.is_none()
+            && let Some(writer) = self.writer.take()
+        {
+            self.thread = Some(RenderThread::spawn(writer));
+        }
+        match (&self.thread, &mut self.writer) {
+            (Some(thread), _) => thread.send(message),
+            (None, Some(writer)) => {
+                let _ = writer.write_all(message.as_str().as_bytes());
+                let _ = writer.flush();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+enum Message {
+    Frame(String),
+    Text(String),
+}
+
+impl Message {
+    fn as_str(&self) -> &str {
+        match self {
+            Message::Frame(text) | Message::Text(text) => text,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: VecDeque<Message>,
+    /// The thread is writing, without holding the lock
+    writing: bool,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Writes what is sent to it on a thread of its own. The lock is never held
+/// while writing, so sending never waits on the writer.
+struct RenderThread {
+    shared: Arc<Shared>,
+}
+
+impl RenderThread {
+    fn spawn(mut writer: Box<dyn Write + Send>) -> Self {
+        let shared = Arc::new(Shared::default());
+        let thread_shared = shared.clone();
+
+        thread::spawn(move || {
+            let shared = thread_shared;
+            let mut queue = shared.lock();
+            loop {
+                if queue.pending.is_empty() {
+                    if queue.closed {
+                        break;
+                    }
+                    queue = shared
+                        .changed
+                        .wait(queue)
+                        .unwrap_or_else(|e| e.into_inner());
+                    continue;
+                }
+
+                let text: String = queue
+                    .pending
+                    .drain(..)
+                    .map(|m| m.as_str().to_owned())
+                    .collect();
+                queue.writing = true;
+                drop(queue);
+                let _ = writer.write_all(text.as_bytes());
+                let _ = writer.flush();
+                queue = shared.lock();
+                queue.writing = false;
+                shared.changed.notify_all();
+            }
+        });
+
+        Self { shared }
+    }
+
+    fn send(&self, message: Message) {
+        let mut queue = self.shared.lock();
+        match (queue.pending.back_mut(), message) {
+            (Some(Message::Frame(last)), Message::Frame(frame)) => *last = frame,
+            (_, message) => queue.pending.push_back(message),
+        }
+        self.shared.changed.notify_all();
+    }
+
+    fn wait_idle(&self) {
+        let mut queue = self.shared.lock();
+        while !queue.pending.is_empty() || queue.writing {
+            queue = self
+                .shared
+                .changed
+                .wait(queue)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl Drop for RenderThread {
+    /// Lets the thread end once it has written what is queued.
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
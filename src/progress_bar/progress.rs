@@ -1,21 +1,74 @@
+use super::clock::{Clock, SystemClock};
+use super::output::Output;
 use super::style::Style;
+use std::collections::VecDeque;
 use std::io::{self, Write};
 use std::time::{Duration, Instant};
 
+/// The state of a [`ProgressBar`] at one moment, for rendering progress
+/// somewhere other than the terminal, e.g. in a GUI.
+///
+/// In [operations mode](ProgressBar::with_operations_mode) the byte counts
+/// count operations and `speed_mbps` is in operations per second.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProgressSnapshot {
+    pub bytes_downloaded: usize,
+    pub total_bytes: Option<usize>,
+    /// `None` when the total size is unknown.
+    pub percentage: Option<f64>,
+    /// Smoothed speed over the speed window in MiB/s, or MB/s with
+    /// [`BytesStyle::Decimal`].
+    pub speed_mbps: f64,
+    /// `None` when the total size is unknown or nothing has arrived within
+    /// the speed window.
+    pub eta_secs: Option<f64>,
+    pub elapsed: Duration,
+}
+
 pub struct ProgressBar {
     total_size: Option<usize>,
     current_chunk: usize,
     start_time: Instant,
     description: Option<String>,
+    max_description_width: Option<usize>,
     last_render_time: Instant,
     min_render_interval: Duration,
     smoothed_speed: Option<f64>,
     smoothing_factor: f64,
+    /// Times at which the byte count was updated, with the count at that
+    /// time, covering the speed window plus one older sample
+    samples: VecDeque<(Instant, usize)>,
+    speed_window: Duration,
+    /// Counting operations, e.g. entries, rather than bytes
+    operations: bool,
     style: Style,
     use_colour: Colour,
+    bytes_style: BytesStyle,
+    hide_cursor: bool,
+    cursor_hidden: bool,
+    clear_on_finish: bool,
+    /// Visible width of the last line drawn, so `clear` knows how much to erase
+    last_line_width: usize,
+    /// Terminal width set with `with_width`, instead of `COLUMNS`
+    width: Option<usize>,
+    output: Output,
+    clock: Box<dyn Clock>,
+    /// Replaces the built-in line when set
+    format_fn: Option<FormatFn>,
+    /// Callbacks not yet run, in order of percentage
+    milestones: Vec<(f64, Milestone)>,
 }
 
+type FormatFn = Box<dyn Fn(&ProgressSnapshot) -> String + Send>;
+type Milestone = Box<dyn FnOnce() + Send>;
+
 const RESET: &str = "\x1b[0m";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+/// Narrowest the description is cut to by default, even if that means
+/// wrapping on a narrow terminal, so it stays recognisable.
+const MIN_DESCRIPTION_WIDTH: usize = 16;
 
 impl ProgressBar {
     pub fn new(total_size: Option<usize>) -> Self {
@@ -25,20 +78,124 @@ impl ProgressBar {
             current_chunk: 0,
             start_time: now,
             description: None,
+            max_description_width: None,
             last_render_time: now,
             min_render_interval: Duration::from_millis(100),
             smoothed_speed: None,
             smoothing_factor: 0.3,
+            samples: VecDeque::from([(now, 0)]),
+            speed_window: Duration::from_secs(5),
+            operations: false,
             style: Style::default(),
             use_colour: Colour::default(),
+            bytes_style: BytesStyle::default(),
+            hide_cursor: false,
+            cursor_hidden: false,
+            clear_on_finish: false,
+            last_line_width: 0,
+            width: None,
+            output: Output::new(Box::new(io::stderr())),
+            clock: Box::new(SystemClock),
+            format_fn: None,
+            milestones: Vec::new(),
         }
     }
 
+    /// Draws each frame as the line `format` returns, in place of the
+    /// built-in bar and statistics. The description, style, colour and
+    /// width settings are then unused.
+    ///
+    /// ```
+    /// use muy_zipido::progress_bar::ProgressBar;
+    ///
+    /// let bar = ProgressBar::new(Some(1 << 20)).with_format_fn(|snapshot| {
+    ///     format!(
+    ///         "{:.0}% at {:.1} MiB/s",
+    ///         snapshot.percentage.unwrap_or(0.0),
+    ///         snapshot.speed_mbps
+    ///     )
+    /// });
+    /// ```
+    pub fn with_format_fn(
+        mut self,
+        format: impl Fn(&ProgressSnapshot) -> String + Send + 'static,
+    ) -> Self {
+        self.format_fn = Some(Box::new(format));
+        self
+    }
+
+    /// Runs `callback` once, from the update that takes progress to
+    /// `percentage` or beyond, e.g. to log or take a checkpoint. Milestones
+    /// passed in one update run in order of percentage. They never run while
+    /// the total is unknown.
+    pub fn with_milestone(
+        mut self,
+        percentage: f64,
+        callback: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        let index = self
+            .milestones
+            .partition_point(|(existing, _)| *existing <= percentage);
+        self.milestones
+            .insert(index, (percentage, Box::new(callback)));
+        self
+    }
+
+    /// Draws the bar on `writer` instead of standard error.
+    pub fn with_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.output.set_writer(Box::new(writer));
+        self
+    }
+
+    /// Whether to draw from a thread of the bar's own, on by default.
+    ///
+    /// With it, [`update`](ProgressBar::update) only hands the frame over
+    /// and never waits on the terminal: if the last frame is still being
+    /// written, e.g. because the terminal is paused with `Ctrl-S`, newer
+    /// frames replace each other until it is done. [`finish`](ProgressBar::finish)
+    /// waits until everything has been written.
+    ///
+    /// Without it, every frame is written as it is drawn, which tests that
+    /// check each frame rely on.
+    pub fn with_render_thread(mut self, enabled: bool) -> Self {
+        self.output.set_background(enabled);
+        self
+    }
+
+    /// Takes the time from `clock` instead of the system clock. The bar
+    /// restarts at the clock's current time.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        let now = clock.now();
+        self.start_time = now;
+        self.last_render_time = now;
+        self.samples = VecDeque::from([(now, self.current_chunk)]);
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// The width of the terminal in columns, which the description is cut
+    /// to fit. Defaults to `COLUMNS`, or 80 if that is unset.
+    pub fn with_width(mut self, columns: usize) -> Self {
+        self.width = Some(columns);
+        self
+    }
+
     pub fn with_description(mut self, desc: String) -> Self {
         self.description = Some(desc);
         self
     }
 
+    /// Truncates the description to `width` characters, ending in `…`, so
+    /// long entry paths don't wrap the line.
+    ///
+    /// By default the description gets whatever the bar and statistics
+    /// leave of the [terminal width](ProgressBar::with_width), but no fewer
+    /// than 16 characters.
+    pub fn with_max_description_width(mut self, width: usize) -> Self {
+        self.max_description_width = Some(width);
+        self
+    }
+
     pub fn with_style(mut self, style: Style) -> Self {
         self.style = style;
         self
@@ -49,89 +206,252 @@ impl ProgressBar {
         self
     }
 
-    pub fn update(&mut self, bytes_processed: usize) {
-        self.current_chunk += bytes_processed;
+    /// Tracks a count of operations, such as entries processed, instead of
+    /// bytes, out of `total_ops` if known. Advance it with
+    /// [`ProgressBar::update_ops`]; the bar shows `N/M entries` and
+    /// operations per second.
+    pub fn with_operations_mode(mut self, total_ops: Option<usize>) -> Self {
+        self.operations = true;
+        self.total_size = total_ops;
+        self
+    }
 
-        let elapsed = self.start_time.elapsed();
-        let instant_speed = if elapsed.as_secs_f64() > 0.0 {
-            self.current_chunk as f64 / elapsed.as_secs_f64()
+    /// How far back the current speed is measured. Shorter windows react
+    /// faster to stalls and bursts but are noisier. Defaults to 5 seconds.
+    pub fn with_speed_window(mut self, window: Duration) -> Self {
+        self.speed_window = window;
+        self
+    }
+
+    /// Weight of each new speed measurement in the displayed speed, from 0
+    /// (never changes) to 1 (no smoothing). Defaults to 0.3.
+    pub fn with_smoothing(mut self, factor: f64) -> Self {
+        self.smoothing_factor = factor.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Bytes per second over the speed window.
+    pub fn current_speed(&self) -> f64 {
+        self.speed_at(self.clock.now())
+    }
+
+    /// Bytes per second since the bar was created.
+    pub fn average_speed(&self) -> f64 {
+        let elapsed = self
+            .clock
+            .now()
+            .saturating_duration_since(self.start_time)
+            .as_secs_f64();
+        if elapsed > 0.0 {
+            self.current_chunk as f64 / elapsed
         } else {
             0.0
-        };
+        }
+    }
+
+    /// Speed over the window ending at `now`, measured from the last sample
+    /// taken before the window started, or the first sample if none was.
+    fn speed_at(&self, now: Instant) -> f64 {
+        let cutoff = now.checked_sub(self.speed_window);
+        let (since, bytes) = self
+            .samples
+            .iter()
+            .rev()
+            .find(|(time, _)| cutoff.is_some_and(|cutoff| *time <= cutoff))
+            .or(self.samples.front())
+            .copied()
+            .unwrap_or((self.start_time, 0));
 
+        let elapsed = now.saturating_duration_since(since).as_secs_f64();
+        if elapsed > 0.0 {
+            self.current_chunk.saturating_sub(bytes) as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Chooses between 1024-based (KiB, MiB) and 1000-based (KB, MB) units.
+    pub fn with_bytes_prefix(mut self, bytes_style: BytesStyle) -> Self {
+        self.bytes_style = bytes_style;
+        self
+    }
+
+    /// Hides the terminal cursor while the bar is being drawn. It is shown
+    /// again by [`ProgressBar::finish`], or when the bar is dropped.
+    pub fn with_hide_cursor(mut self, hide_cursor: bool) -> Self {
+        self.hide_cursor = hide_cursor;
+        self
+    }
+
+    /// Erases the bar in [`ProgressBar::finish`] instead of leaving the final
+    /// state on screen.
+    pub fn with_clear_on_finish(mut self, clear_on_finish: bool) -> Self {
+        self.clear_on_finish = clear_on_finish;
+        self
+    }
+
+    /// The current progress, without rendering anything.
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        self.snapshot_at(self.clock.now())
+    }
+
+    fn snapshot_at(&self, now: Instant) -> ProgressSnapshot {
+        let speed = self.smoothed_speed.unwrap_or(0.0);
+        let current_speed = self.speed_at(now);
+        let total = self.total_size.filter(|&total| total > 0);
+
+        ProgressSnapshot {
+            bytes_downloaded: self.current_chunk,
+            total_bytes: self.total_size,
+            percentage: total.map(|total| self.current_chunk as f64 / total as f64 * 100.0),
+            speed_mbps: self.scale_speed(speed),
+            eta_secs: total
+                .filter(|_| current_speed > 0.0)
+                .map(|total| total.saturating_sub(self.current_chunk) as f64 / current_speed),
+            elapsed: now.saturating_duration_since(self.start_time),
+        }
+    }
+
+    pub fn update(&mut self, bytes_processed: usize) {
+        self.update_at(bytes_processed, self.clock.now());
+    }
+
+    /// Records `n` more operations completed. The same as
+    /// [`ProgressBar::update`], for bars in operations mode.
+    pub fn update_ops(&mut self, n: usize) {
+        self.update(n);
+    }
+
+    /// `speed` in the unit shown: MiB/s or MB/s, or operations per second.
+    fn scale_speed(&self, speed: f64) -> f64 {
+        if self.operations {
+            speed
+        } else {
+            speed / (self.bytes_style.base() * self.bytes_style.base())
+        }
+    }
+
+    fn format_amount(&self, amount: usize) -> String {
+        if self.operations {
+            amount.to_string()
+        } else {
+            format_bytes(amount, self.bytes_style)
+        }
+    }
+
+    fn update_at(&mut self, bytes_processed: usize, now: Instant) {
+        self.current_chunk += bytes_processed;
+
+        if let Some(total) = self.total_size.filter(|&total| total > 0) {
+            let percentage = self.current_chunk as f64 / total as f64 * 100.0;
+            let reached = self
+                .milestones
+                .partition_point(|(milestone, _)| *milestone <= percentage);
+            for (_, callback) in self.milestones.drain(..reached) {
+                callback();
+            }
+        }
+
+        // Keep one sample from before the window as its starting point.
+        if let Some(cutoff) = now.checked_sub(self.speed_window) {
+            while self.samples.get(1).is_some_and(|(time, _)| *time <= cutoff) {
+                self.samples.pop_front();
+            }
+        }
+        self.samples.push_back((now, self.current_chunk));
+
+        let window_speed = self.speed_at(now);
         self.smoothed_speed = match self.smoothed_speed {
-            None => Some(instant_speed),
+            None => Some(window_speed),
             Some(prev_speed) => {
                 let beta = self.smoothing_factor;
-                Some(instant_speed * beta + prev_speed * (1.0 - beta))
+                Some(window_speed * beta + prev_speed * (1.0 - beta))
             }
         };
 
-        let now = Instant::now();
-        if now.duration_since(self.last_render_time) >= self.min_render_interval {
+        if now.saturating_duration_since(self.last_render_time) >= self.min_render_interval {
             self.render();
             self.last_render_time = now;
         }
     }
 
     pub fn finish(&mut self) {
-        self.render();
-        eprintln!();
+        if self.clear_on_finish {
+            self.clear();
+        } else {
+            self.render();
+            self.output.text("\n");
+        }
+        self.show_cursor();
+        self.output.flush();
+    }
+
+    /// Clears the bar and restores the cursor, for a download that stopped
+    /// before completing, where a final frozen percentage would mislead.
+    pub fn finish_and_clear(&mut self) {
+        self.clear();
+        self.show_cursor();
+        self.output.flush();
     }
 
-    fn render(&self) {
-        let elapsed = self.start_time.elapsed();
+    /// Overwrites the last rendered line with spaces and returns the cursor
+    /// to the start of the line.
+    pub fn clear(&mut self) {
+        self.output
+            .text(&format!("\r{}\r", " ".repeat(self.last_line_width)));
+        self.last_line_width = 0;
+    }
+
+    fn show_cursor(&mut self) {
+        if self.cursor_hidden {
+            self.output.text(SHOW_CURSOR);
+            self.cursor_hidden = false;
+        }
+    }
+
+    /// The bar and statistics, without the description.
+    fn status_line(&self, now: Instant) -> String {
+        let elapsed = now.saturating_duration_since(self.start_time);
         let speed = self.smoothed_speed.unwrap_or(0.0);
-        let speed_mb = speed / (1024.0 * 1024.0);
-        let desc = match &self.description {
-            Some(d) => format!("{}: ", d),
-            None => String::new(),
+        let speed_mb = self.scale_speed(speed);
+        let (mb, entries) = match self.operations {
+            true => ("ops", " entries"),
+            false => (self.bytes_style.units()[2], ""),
         };
+        let current_speed = self.speed_at(now);
 
-        let output = match self.total_size {
+        match self.total_size {
             Some(total) if total > 0 => {
                 let percentage = (self.current_chunk as f64 / total as f64) * 100.0;
-                let bar_width = 40;
-                let filled = ((percentage / 100.0) * bar_width as f64) as usize;
+                let (filled, empty) =
+                    self.style
+                        .render_bar(self.current_chunk as u64, total as u64, 40);
                 let bar = match self.use_colour {
-                    Colour::None => {
-                        // No color
-                        self.style.filled_char().to_string().repeat(filled)
-                            + &self
-                                .style
-                                .empty_char()
-                                .to_string()
-                                .repeat(bar_width - filled)
-                    }
-                    _ => {
-                        // With color
-                        format!(
-                            "{}{}{}{}",
-                            self.use_colour.ansi_code(),
-                            self.style.filled_char().to_string().repeat(filled),
-                            RESET,
-                            self.style
-                                .empty_char()
-                                .to_string()
-                                .repeat(bar_width - filled)
-                        )
-                    }
+                    Colour::None => filled + &empty,
+                    _ => format!(
+                        "{}{}{}{}",
+                        self.use_colour.ansi_code(),
+                        filled,
+                        RESET,
+                        empty
+                    ),
                 };
 
-                let eta_secs = if speed > 0.0 && total > self.current_chunk {
-                    (total - self.current_chunk) as f64 / speed
+                let eta_secs = if current_speed > 0.0 && total > self.current_chunk {
+                    (total - self.current_chunk) as f64 / current_speed
                 } else {
                     0.0
                 };
 
                 format!(
-                    "\r{}[{}] {:.1}% | {}/{} | {:.2} MB/s | ETA: {:.0}s",
-                    desc,
+                    "[{}] {:.1}% | {}/{}{} | {:.2} {}/s | ETA: {:.0}s",
                     bar,
                     percentage,
-                    format_bytes(self.current_chunk),
-                    format_bytes(total),
+                    self.format_amount(self.current_chunk),
+                    self.format_amount(total),
+                    entries,
                     speed_mb,
+                    mb,
                     eta_secs
                 )
             }
@@ -141,22 +461,110 @@ impl ProgressBar {
                 let spinner = spinner_chars[spinner_idx as usize];
 
                 format!(
-                    "\r{}{} {} | {:.2} MB/s | {}",
-                    desc,
+                    "{} {}{} | {:.2} {}/s | {}",
                     spinner,
-                    format_bytes(self.current_chunk),
+                    self.format_amount(self.current_chunk),
+                    entries,
                     speed_mb,
+                    mb,
                     format_elapsed(elapsed)
                 )
             }
+        }
+    }
+
+    fn render(&mut self) {
+        if self.hide_cursor && !self.cursor_hidden {
+            self.output.text(HIDE_CURSOR);
+            self.cursor_hidden = true;
+        }
+
+        if let Some(format) = &self.format_fn {
+            let output = format!("\r{}", format(&self.snapshot_at(self.clock.now())));
+            self.last_line_width = visible_width(&output);
+            self.output.frame(output);
+            return;
+        }
+
+        let line = self.status_line(self.clock.now());
+
+        let desc = match &self.description {
+            Some(d) => {
+                let width = self.max_description_width.unwrap_or_else(|| {
+                    // Leave room for the ": " separator.
+                    self.width
+                        .unwrap_or_else(terminal_width)
+                        .saturating_sub(visible_width(&line) + 2)
+                        .max(MIN_DESCRIPTION_WIDTH)
+                });
+                match truncate(d, width) {
+                    truncated if truncated.is_empty() => String::new(),
+                    truncated => format!("{}: ", truncated),
+                }
+            }
+            None => String::new(),
         };
+        let output = format!("\r{}{}", desc, line);
 
-        eprint!("{}", output);
-        let _ = io::stderr().flush();
+        self.last_line_width = visible_width(&output);
+        self.output.frame(output);
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Number of characters that take up space on screen, skipping ANSI escape
+/// sequences and carriage returns.
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                // CSI sequences end with a byte in the range @..~
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) && c != '[' {
+                        break;
+                    }
+                }
+            }
+            '\r' => {}
+            _ => width += 1,
+        }
+    }
+
+    width
+}
+
+/// Shortens `text` to at most `width` characters, marking the cut with `…`.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    match width {
+        0 => String::new(),
+        _ => text.chars().take(width - 1).chain(['…']).collect(),
+    }
+}
+
+/// Width of the terminal in columns, from `COLUMNS`, or 80 if unset.
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(80)
+}
+
+impl Drop for ProgressBar {
+    /// Restores the cursor if the bar is dropped without being finished, e.g.
+    /// while unwinding from a panic.
+    fn drop(&mut self) {
+        self.show_cursor();
+        self.output.flush();
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Colour {
     None,
     Red,
@@ -164,6 +572,7 @@ pub enum Colour {
     Yellow,
     Blue,
     Magenta,
+    #[default]
     Cyan,
     White,
 }
@@ -183,26 +592,61 @@ impl Colour {
     }
 }
 
-impl Default for Colour {
-    fn default() -> Self {
-        Colour::Cyan
+/// What a [`MuyZipido`](crate::MuyZipido) counts on its progress bar. Set
+/// with [`MuyZipido::with_progress_basis`](crate::MuyZipido::with_progress_basis).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressBasis {
+    /// Bytes of the archive read from the stream, against its
+    /// Content-Length when known.
+    #[default]
+    Downloaded,
+    /// Bytes of entry data after decompression, counted as each entry is
+    /// decoded, against a total given with
+    /// [`MuyZipido::with_total_size`](crate::MuyZipido::with_total_size).
+    /// Entries skipped without decoding, e.g. by a filter, do not count.
+    Decompressed,
+}
+
+/// Unit system used when displaying byte counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BytesStyle {
+    /// Powers of 1024: KiB, MiB, GiB
+    #[default]
+    Binary,
+    /// Powers of 1000: KB, MB, GB
+    Decimal,
+}
+
+impl BytesStyle {
+    fn base(&self) -> f64 {
+        match self {
+            BytesStyle::Binary => 1024.0,
+            BytesStyle::Decimal => 1000.0,
+        }
+    }
+
+    fn units(&self) -> &'static [&'static str] {
+        match self {
+            BytesStyle::Binary => &["B", "KiB", "MiB", "GiB"],
+            BytesStyle::Decimal => &["B", "KB", "MB", "GB"],
+        }
     }
 }
 
-fn format_bytes(bytes: usize) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+fn format_bytes(bytes: usize, style: BytesStyle) -> String {
+    let units = style.units();
     let mut size = bytes as f64;
     let mut unit_idx = 0;
 
-    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
-        size /= 1024.0;
+    while size >= style.base() && unit_idx < units.len() - 1 {
+        size /= style.base();
         unit_idx += 1;
     }
 
     if unit_idx == 0 {
-        format!("{:.0}{}", size, UNITS[unit_idx])
+        format!("{:.0}{}", size, units[unit_idx])
     } else {
-        format!("{:.1}{}", size, UNITS[unit_idx])
+        format!("{:.1}{}", size, units[unit_idx])
     }
 }
 
@@ -218,3 +662,148 @@ fn format_elapsed(elapsed: std::time::Duration) -> String {
         format!("{:02}:{:02}", minutes, seconds)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(visible_width("\rabc"), 3);
+        assert_eq!(visible_width("\r[\x1b[35m▰▰\x1b[0m▱] 50%"), 9);
+    }
+
+    /// A bar that never renders, so tests can update it freely.
+    fn quiet_bar(total: Option<usize>) -> ProgressBar {
+        let mut bar = ProgressBar::new(total);
+        bar.min_render_interval = Duration::MAX;
+        bar
+    }
+
+    /// The time `seconds` after `bar` was created.
+    fn at(bar: &ProgressBar, seconds: u64) -> Instant {
+        bar.start_time + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_milestones() {
+        use std::sync::{Arc, Mutex};
+
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let mut bar = quiet_bar(Some(200));
+        for percentage in [75.0, 25.0, 100.0, 50.0] {
+            let fired = fired.clone();
+            bar = bar.with_milestone(percentage, move || fired.lock().unwrap().push(percentage));
+        }
+
+        bar.update_at(40, at(&bar, 1));
+        assert!(fired.lock().unwrap().is_empty());
+        bar.update_at(10, at(&bar, 2));
+        assert_eq!(*fired.lock().unwrap(), [25.0]);
+        // One update past two milestones runs both, in order.
+        bar.update_at(110, at(&bar, 3));
+        assert_eq!(*fired.lock().unwrap(), [25.0, 50.0, 75.0]);
+        bar.update_at(0, at(&bar, 4));
+        bar.update_at(40, at(&bar, 5));
+        bar.update_at(40, at(&bar, 6));
+        assert_eq!(*fired.lock().unwrap(), [25.0, 50.0, 75.0, 100.0]);
+
+        let fired = Arc::new(Mutex::new(false));
+        let flag = fired.clone();
+        let mut bar = quiet_bar(None).with_milestone(0.0, move || *flag.lock().unwrap() = true);
+        bar.update_at(1 << 20, at(&bar, 1));
+        assert!(!*fired.lock().unwrap());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut bar = quiet_bar(Some(4096));
+        let snapshot = bar.snapshot();
+        assert_eq!(snapshot.bytes_downloaded, 0);
+        assert_eq!(snapshot.percentage, Some(0.0));
+        assert_eq!(snapshot.eta_secs, None);
+
+        bar.update_at(1024, at(&bar, 2));
+        bar.smoothed_speed = Some(512.0);
+        let snapshot = bar.snapshot_at(at(&bar, 2));
+        assert_eq!(snapshot.total_bytes, Some(4096));
+        assert_eq!(snapshot.percentage, Some(25.0));
+        assert_eq!(snapshot.eta_secs, Some(6.0));
+        assert_eq!(snapshot.speed_mbps, 512.0 / (1024.0 * 1024.0));
+
+        let unknown = ProgressBar::new(None).snapshot();
+        assert_eq!(unknown.percentage, None);
+        assert_eq!(unknown.eta_secs, None);
+    }
+
+    #[test]
+    fn test_windowed_speed_tracks_bursts_and_stalls() {
+        let mut bar = quiet_bar(Some(100_000)).with_speed_window(Duration::from_secs(2));
+
+        // A burst of 1000 B/s for 10 seconds...
+        for second in 1..=10 {
+            bar.update_at(1000, at(&bar, second));
+        }
+        assert_eq!(bar.speed_at(at(&bar, 10)), 1000.0);
+
+        // ...then a 20 second stall, which shows as zero once it outlasts the
+        // window, where the lifetime average would still be over 300 B/s...
+        assert_eq!(bar.speed_at(at(&bar, 13)), 0.0);
+        assert_eq!(bar.snapshot_at(at(&bar, 30)).eta_secs, None);
+
+        // ...and a second burst at 5000 B/s is at full speed within the window.
+        for second in 31..=34 {
+            bar.update_at(5000, at(&bar, second));
+        }
+        assert_eq!(bar.speed_at(at(&bar, 34)), 5000.0);
+        let eta = bar.snapshot_at(at(&bar, 34)).eta_secs.unwrap();
+        assert_eq!(eta, (100_000.0 - 30_000.0) / 5000.0);
+    }
+
+    #[test]
+    fn test_operations_mode() {
+        let mut bar = quiet_bar(None)
+            .with_operations_mode(Some(40))
+            .with_color(Colour::None)
+            .with_smoothing(1.0);
+        for second in 1..=4 {
+            bar.update_at(5, at(&bar, second));
+        }
+
+        let line = bar.status_line(at(&bar, 4));
+        assert!(
+            line.contains("| 20/40 entries | 5.00 ops/s | ETA: 4s"),
+            "{}",
+            line
+        );
+        let snapshot = bar.snapshot_at(at(&bar, 4));
+        assert_eq!(snapshot.speed_mbps, 5.0);
+        assert_eq!(snapshot.eta_secs, Some(4.0));
+
+        let unknown = quiet_bar(None).with_operations_mode(None);
+        assert!(
+            unknown
+                .status_line(at(&unknown, 1))
+                .contains(" 0 entries | 0.00 ops/s")
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("data/points.csv", 20), "data/points.csv");
+        assert_eq!(truncate("data/points.csv", 15), "data/points.csv");
+        assert_eq!(truncate("data/points.csv", 8), "data/po…");
+        assert_eq!(truncate("café.csv", 4), "caf…");
+        assert_eq!(truncate("data", 0), "");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512, BytesStyle::Binary), "512B");
+        assert_eq!(format_bytes(1536, BytesStyle::Binary), "1.5KiB");
+        assert_eq!(format_bytes(1536, BytesStyle::Decimal), "1.5KB");
+        assert_eq!(format_bytes(1_000_000, BytesStyle::Decimal), "1.0MB");
+        assert_eq!(format_bytes(1_000_000, BytesStyle::Binary), "976.6KiB");
+        assert_eq!(format_bytes(5 << 30, BytesStyle::Binary), "5.0GiB");
+    }
+}
@@ -1,6 +1,9 @@
-#[derive(Debug, Clone, Copy)]
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Style {
     /// Classic style: [████████░░░░░░░░]
+    #[default]
     Classic,
     /// ASCII style: [########--------]
     Ascii,
@@ -10,8 +13,13 @@ pub enum Style {
     Arrows,
     /// Blocks style: [▰▰▰▰▰▰▱▱▱▱▱▱]
     Blocks,
+    /// Gradient style, filling in eighths of a character: [██████▍     ]
+    Gradient,
 }
 
+/// Partly filled cells for [`Style::Gradient`], from one eighth to seven
+const EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
 impl Style {
     pub fn filled_char(&self) -> char {
         match self {
@@ -20,6 +28,7 @@ impl Style {
             Style::Dots => '●',
             Style::Arrows => '>',
             Style::Blocks => '▰',
+            Style::Gradient => '█',
         }
     }
 
@@ -30,12 +39,73 @@ impl Style {
             Style::Dots => '○',
             Style::Arrows => '-',
             Style::Blocks => '▱',
+            Style::Gradient => ' ',
+        }
+    }
+
+    /// Renders a bar `width` characters wide showing `done` out of `total`,
+    /// as the filled part and the empty part so they can be coloured
+    /// separately. [`Style::Gradient`] ends the filled part with a partly
+    /// filled character; other styles fill whole characters.
+    pub fn render_bar(&self, done: u64, total: u64, width: usize) -> (String, String) {
+        let eighths = match total {
+            0 => 0,
+            _ => (done.min(total) as u128 * width as u128 * 8 / total as u128) as usize,
+        };
+        let mut filled = self.filled_char().to_string().repeat(eighths / 8);
+        let mut cells = eighths / 8;
+
+        if *self == Style::Gradient && eighths % 8 > 0 {
+            filled.push(EIGHTHS[eighths % 8 - 1]);
+            cells += 1;
         }
+        let empty = self.empty_char().to_string().repeat(width - cells);
+        (filled, empty)
     }
 }
 
-impl Default for Style {
-    fn default() -> Self {
-        Style::Classic
+impl FromStr for Style {
+    type Err = String;
+
+    /// Parses a style name case-insensitively, e.g. `"blocks"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "classic" => Ok(Style::Classic),
+            "ascii" => Ok(Style::Ascii),
+            "dots" => Ok(Style::Dots),
+            "arrows" => Ok(Style::Arrows),
+            "blocks" => Ok(Style::Blocks),
+            "gradient" => Ok(Style::Gradient),
+            _ => Err(format!(
+                "unknown progress style '{}' (expected classic, ascii, dots, arrows, blocks or gradient)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(style: Style, done: u64, total: u64) -> String {
+        let (filled, empty) = style.render_bar(done, total, 4);
+        filled + &empty
+    }
+
+    #[test]
+    fn test_render_bar() {
+        assert_eq!(bar(Style::Ascii, 0, 100), "----");
+        assert_eq!(bar(Style::Ascii, 60, 100), "##--");
+        assert_eq!(bar(Style::Ascii, 100, 100), "####");
+        assert_eq!(bar(Style::Ascii, 200, 100), "####");
+        assert_eq!(bar(Style::Ascii, 5, 0), "----");
+
+        assert_eq!(bar(Style::Gradient, 0, 32), "    ");
+        assert_eq!(bar(Style::Gradient, 1, 32), "▏   ");
+        assert_eq!(bar(Style::Gradient, 12, 32), "█▌  ");
+        assert_eq!(bar(Style::Gradient, 31, 32), "███▉");
+        assert_eq!(bar(Style::Gradient, 32, 32), "████");
+        assert_eq!(Style::Gradient.render_bar(12, 32, 4).1, "  ");
     }
 }
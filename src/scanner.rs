@@ -0,0 +1,245 @@
+//! Searching a byte stream for 4-byte ZIP signatures.
+//!
+//! The extractor uses this to find the data descriptor that ends a streamed
+//! entry. It works just as well for carving ZIP members out of raw disk
+//! images or other data where the records are not where a header says.
+
+use std::io::{self, Read};
+
+const SIGNATURE_LEN: usize = 4;
+/// Bytes read at a time by [`find_next_signature`]
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Where [`SignatureScanner::feed`] found a signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureMatch {
+    /// Position of the signature's first byte, counted from the first byte
+    /// ever fed to the scanner. It may lie in an earlier slice.
+    pub offset: u64,
+    /// Bytes of the slice up to and including the signature's last byte.
+    pub consumed: usize,
+}
+
+/// Finds a signature in data that arrives in slices of any size, including
+/// signatures split across two or more slices.
+#[derive(Debug, Clone)]
+pub struct SignatureScanner {
+    signature: [u8; SIGNATURE_LEN],
+    /// The last bytes fed, which may be the start of a signature completed
+    /// by the next slice
+    tail: [u8; SIGNATURE_LEN - 1],
+    tail_len: usize,
+    position: u64,
+}
+
+impl SignatureScanner {
+    pub fn new(signature: [u8; SIGNATURE_LEN]) -> Self {
+        Self {
+            signature,
+            tail: [0; SIGNATURE_LEN - 1],
+            tail_len: 0,
+            position: 0,
+        }
+    }
+
+    /// Scans `bytes`, the next part of the stream, and returns the first
+    /// signature that ends in them.
+    ///
+    /// Only the bytes up to the end of a match count as fed. To look for
+    /// further matches, feed the rest of the slice, `&bytes[m.consumed..]`;
+    /// matches overlapping one already returned are not reported.
+    pub fn feed(&mut self, bytes: &[u8]) -> Option<SignatureMatch> {
+        let found = self
+            .match_from_tail(bytes)
+            .or_else(|| find(bytes, &self.signature).map(|at| at + SIGNATURE_LEN));
+
+        let Some(consumed) = found else {
+            self.keep_tail(bytes);
+            self.position += bytes.len() as u64;
+            return None;
+        };
+
+        self.tail_len = 0;
+        self.position += consumed as u64;
+        Some(SignatureMatch {
+            offset: self.position - SIGNATURE_LEN as u64,
+            consumed,
+        })
+    }
+
+    /// Total bytes fed so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Forgets any partial match, as if the stream restarted at the current
+    /// position.
+    pub fn reset(&mut self) {
+        self.tail_len = 0;
+    }
+
+    /// Length of the start of `bytes` that completes a signature begun in
+    /// the tail, if any.
+    fn match_from_tail(&self, bytes: &[u8]) -> Option<usize> {
+        (0..self.tail_len).find_map(|start| {
+            let held = self.tail_len - start;
+            let needed = SIGNATURE_LEN - held;
+            (self.tail[start..self.tail_len] == self.signature[..held]
+                && bytes.get(..needed)? == &self.signature[held..])
+                .then_some(needed)
+        })
+    }
+
+    /// Keeps the last bytes of the tail followed by `bytes`.
+    fn keep_tail(&mut self, bytes: &[u8]) {
+        let keep = (self.tail_len + bytes.len()).min(SIGNATURE_LEN - 1);
+        let from_bytes = keep.min(bytes.len());
+        let from_tail = keep - from_bytes;
+
+        let mut tail = [0; SIGNATURE_LEN - 1];
+        tail[..from_tail].copy_from_slice(&self.tail[self.tail_len - from_tail..self.tail_len]);
+        tail[from_tail..keep].copy_from_slice(&bytes[bytes.len() - from_bytes..]);
+        self.tail = tail;
+        self.tail_len = keep;
+    }
+}
+
+/// Reads from `reader` until `signature` turns up, looking at no more than
+/// `limit` bytes, and returns the offset of its first byte from where the
+/// reader started.
+///
+/// Returns `Ok(None)` if the reader ends or the limit is reached first. The
+/// reader is read in blocks, so it is left somewhere after the match; seek
+/// back to the returned offset to read from the signature.
+pub fn find_next_signature<R: Read>(
+    mut reader: R,
+    signature: [u8; SIGNATURE_LEN],
+    limit: u64,
+) -> Result<Option<u64>, io::Error> {
+    let mut scanner = SignatureScanner::new(signature);
+    let mut block = vec![0u8; BLOCK_SIZE];
+
+    while scanner.position() < limit {
+        let len = (limit - scanner.position()).min(BLOCK_SIZE as u64) as usize;
+        let bytes_read = match reader.read(&mut block[..len]) {
+            Ok(0) => return Ok(None),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        if let Some(found) = scanner.feed(&block[..bytes_read]) {
+            return Ok(Some(found.offset));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Index of the first whole `signature` in `haystack`.
+fn find(haystack: &[u8], signature: &[u8; SIGNATURE_LEN]) -> Option<usize> {
+    let mut from = 0;
+    while let Some(skip) = haystack[from..].iter().position(|&b| b == signature[0]) {
+        let at = from + skip;
+        if haystack.get(at..at + SIGNATURE_LEN)? == signature {
+            return Some(at);
+        }
+        from = at + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIG: [u8; 4] = *b"PK\x07\x08";
+
+    /// Offsets of the signatures found by feeding `data` in pieces cut at
+    /// `cuts`, feeding the rest of a piece again after each match.
+    fn scan(data: &[u8], cuts: &[usize], signature: [u8; 4]) -> Vec<u64> {
+        let mut scanner = SignatureScanner::new(signature);
+        let mut found = Vec::new();
+        let mut start = 0;
+        for &end in cuts.iter().chain([&data.len()]) {
+            let mut piece = &data[start..end];
+            while let Some(m) = scanner.feed(piece) {
+                found.push(m.offset);
+                piece = &piece[m.consumed..];
+            }
+            start = end;
+        }
+        assert_eq!(scanner.position(), data.len() as u64);
+        found
+    }
+
+    #[test]
+    fn test_signature_split_at_every_boundary() {
+        let mut data = b"xxPK\x07PKPK\x07\x07".to_vec();
+        let first = data.len() as u64;
+        data.extend_from_slice(&SIG);
+        data.extend_from_slice(b"zzPK");
+        let second = data.len() as u64;
+        data.extend_from_slice(&SIG);
+        data.extend_from_slice(b"P");
+
+        for a in 0..=data.len() {
+            assert_eq!(scan(&data, &[a], SIG), [first, second], "cut at {}", a);
+            for b in a..=data.len() {
+                assert_eq!(
+                    scan(&data, &[a, b], SIG),
+                    [first, second],
+                    "cuts at {} and {}",
+                    a,
+                    b
+                );
+            }
+        }
+        for size in 1..=data.len() {
+            let cuts: Vec<usize> = (size..data.len()).step_by(size).collect();
+            assert_eq!(scan(&data, &cuts, SIG), [first, second], "size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_self_overlapping_signature() {
+        let data = b"aaaaab";
+        for size in 1..=data.len() {
+            let cuts: Vec<usize> = (size..data.len()).step_by(size).collect();
+            assert_eq!(scan(data, &cuts, *b"aaab"), [2], "size {}", size);
+        }
+        // A match is not reported again as part of a later one.
+        assert_eq!(scan(b"aaaaaaaa", &[], *b"aaaa"), [0, 4]);
+    }
+
+    #[test]
+    fn test_no_false_matches() {
+        let data = b"PK\x07PK\x08P\x07\x08K\x07\x08";
+        for a in 0..=data.len() {
+            assert!(scan(data, &[a], SIG).is_empty());
+        }
+        assert!(scan(b"", &[], SIG).is_empty());
+    }
+
+    #[test]
+    fn test_find_next_signature() {
+        let mut data = vec![0u8; BLOCK_SIZE - 2];
+        data.extend_from_slice(&SIG);
+        data.extend_from_slice(&[0; 100]);
+        let at = (BLOCK_SIZE - 2) as u64;
+
+        assert_eq!(
+            find_next_signature(&data[..], SIG, u64::MAX).unwrap(),
+            Some(at)
+        );
+        assert_eq!(
+            find_next_signature(&data[..], SIG, at + 4).unwrap(),
+            Some(at)
+        );
+        assert_eq!(find_next_signature(&data[..], SIG, at + 3).unwrap(), None);
+        assert_eq!(
+            find_next_signature(&data[..at as usize], SIG, u64::MAX).unwrap(),
+            None
+        );
+    }
+}
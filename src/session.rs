@@ -0,0 +1,1050 @@
+use crate::ZipDateTime;
+use crate::{MuyZipido, ZipError, cache};
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{
+    AUTHORIZATION, COOKIE, HeaderMap, HeaderName, HeaderValue, IF_NONE_MATCH, IF_RANGE, LOCATION,
+    PROXY_AUTHORIZATION, RANGE, RETRY_AFTER, USER_AGENT,
+};
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, StatusCode, Url};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How much of an error response body is kept in [`ZipError::HttpStatus`].
+const BODY_SNIPPET_LIMIT: u64 = 4096;
+
+/// Redirects followed before giving up, the same as `reqwest`'s default.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Retries of a rate limited request before giving up.
+const DEFAULT_RATE_LIMIT_RETRIES: usize = 3;
+
+/// The longest `Retry-After` waited out by default.
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The wait after a 429 without a usable `Retry-After`.
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(1);
+
+/// The `User-Agent` sent unless another is set.
+const DEFAULT_USER_AGENT: &str = concat!("muy-zipido/", env!("CARGO_PKG_VERSION"));
+
+/// An HTTP client shared by several downloads, so connections and client
+/// setup are reused when processing a batch of archives.
+///
+/// ```no_run
+/// use muy_zipido::ZipSession;
+///
+/// let session = ZipSession::new();
+/// for url in ["https://example.com/a.zip", "https://example.com/b.zip"] {
+///     for entry in session.open(url, 10240)? {
+///         println!("{}", entry?.filename);
+///     }
+/// }
+/// # Ok::<(), muy_zipido::ZipError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ZipSession {
+    client: Client,
+    pool_size: Option<usize>,
+    headers: HeaderMap,
+    max_redirects: usize,
+    forward_auth_on_redirect: bool,
+    pinned_certificates: Vec<Certificate>,
+    rate_limit_retries: usize,
+    max_retry_after: Duration,
+    rate_limit_handler: Option<RateLimitHandler>,
+    response_validator: Option<ResponseValidator>,
+    user_agent: HeaderValue,
+    per_request_delay: Duration,
+    max_concurrent: Option<usize>,
+    /// Shared by clones, so they pace their requests together
+    pacing: Arc<Pacing>,
+}
+
+type RateLimitFn = dyn Fn(&str, Duration) + Send + Sync;
+
+/// A callback told about each wait for a rate limit.
+#[derive(Clone)]
+struct RateLimitHandler(Arc<RateLimitFn>);
+
+impl fmt::Debug for RateLimitHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("RateLimitHandler")
+    }
+}
+
+type ResponseValidatorFn = dyn Fn(&Response) -> Result<(), ZipError> + Send + Sync;
+
+/// A check run on each response before its body is read.
+#[derive(Clone)]
+struct ResponseValidator(Arc<ResponseValidatorFn>);
+
+impl fmt::Debug for ResponseValidator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ResponseValidator")
+    }
+}
+
+/// When the last request was sent and how many are under way, for
+/// [`ZipSession::with_per_request_delay`] and
+/// [`ZipSession::with_max_concurrent`].
+#[derive(Debug, Default)]
+struct Pacing {
+    last_request: Mutex<Option<Instant>>,
+    in_flight: Mutex<usize>,
+    slot_freed: Condvar,
+}
+
+/// A place among the requests under way, given up when dropped.
+pub(crate) struct Slot(Arc<Pacing>);
+
+/// A response body that keeps its request's [`Slot`] until it is dropped.
+struct SlotReader {
+    body: Box<dyn Read + Send>,
+    _slot: Arc<Slot>,
+}
+
+impl Read for SlotReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.body.read(buf)
+    }
+}
+
+/// The answer to a request, with how it was reached.
+struct Fetched {
+    response: Response,
+    /// Every URL requested, starting with the one asked for and ending with
+    /// the one that answered
+    chain: Vec<String>,
+    /// Times the request was rate limited and retried
+    rate_limited: usize,
+    /// Time spent waiting out rate limits
+    rate_limit_wait: Duration,
+    /// Held until the response body is dropped
+    slot: Option<Arc<Slot>>,
+}
+
+impl Default for ZipSession {
+    fn default() -> Self {
+        Self {
+            client: build_client(None, &[]),
+            pool_size: None,
+            headers: HeaderMap::new(),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            forward_auth_on_redirect: false,
+            pinned_certificates: Vec::new(),
+            rate_limit_retries: DEFAULT_RATE_LIMIT_RETRIES,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+            rate_limit_handler: None,
+            response_validator: None,
+            user_agent: HeaderValue::from_static(DEFAULT_USER_AGENT),
+            per_request_delay: Duration::ZERO,
+            max_concurrent: None,
+            pacing: Arc::default(),
+        }
+    }
+}
+
+impl ZipSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps up to `size` idle connections per host open for reuse, e.g. when
+    /// fetching many archives, or ranges of one, from the same server.
+    /// `reqwest` keeps an unbounded number by default.
+    pub fn with_connection_pool_size(mut self, size: usize) -> Self {
+        self.pool_size = Some(size);
+        self.client = build_client(self.pool_size, &self.pinned_certificates);
+        self
+    }
+
+    /// Retries a request answered with `429 Too Many Requests`, or `503
+    /// Service Unavailable` with a `Retry-After` header, up to `retries`
+    /// times, first waiting as long as `Retry-After` asks (1 second if it is
+    /// missing from a 429). Defaults to 3; 0 fails on the first one.
+    ///
+    /// A request still limited after that, or asked to wait longer than
+    /// [`with_max_retry_after`](Self::with_max_retry_after), fails with
+    /// [`ZipError::RateLimited`]. The waits are counted in
+    /// [`MuyZipido::download_stats`].
+    pub fn with_rate_limit_retries(mut self, retries: usize) -> Self {
+        self.rate_limit_retries = retries;
+        self
+    }
+
+    /// The longest `Retry-After` waited out before retrying. Defaults to 60
+    /// seconds.
+    pub fn with_max_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Calls `handler` with the URL and the wait before each retry of a rate
+    /// limited request, e.g. to show "rate limited, retrying in 30s".
+    pub fn with_rate_limit_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str, Duration) + Send + Sync + 'static,
+    {
+        self.rate_limit_handler = Some(RateLimitHandler(Arc::new(handler)));
+        self
+    }
+
+    /// Calls `validator` with each successful response before any of its
+    /// body is read, e.g. to check a custom header. An error it returns,
+    /// such as [`ZipError::ResponseRejected`], is returned in place of the
+    /// extractor. Setting another replaces it.
+    ///
+    /// ```no_run
+    /// use muy_zipido::{ZipError, ZipSession};
+    ///
+    /// let session = ZipSession::new().with_response_validator(|response| {
+    ///     match response.headers().get("X-Archive-Version") {
+    ///         Some(version) if version == "2" => Ok(()),
+    ///         _ => Err(ZipError::ResponseRejected {
+    ///             url: response.url().to_string(),
+    ///             reason: "expected archive version 2".to_string(),
+    ///         }),
+    ///     }
+    /// });
+    /// let extractor = session.open("https://example.com/data.zip", 8192)?;
+    /// # Ok::<(), ZipError>(())
+    /// ```
+    pub fn with_response_validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&Response) -> Result<(), ZipError> + Send + Sync + 'static,
+    {
+        self.response_validator = Some(ResponseValidator(Arc::new(validator)));
+        self
+    }
+
+    /// Identifies the client as `user_agent`, as many data portals ask
+    /// callers to do with the name of their project. Defaults to
+    /// `muy-zipido/<version>`.
+    ///
+    /// Fails if `user_agent` is not a valid header value, e.g. because it
+    /// contains a newline.
+    ///
+    /// ```no_run
+    /// use muy_zipido::ZipSession;
+    /// use std::time::Duration;
+    ///
+    /// let session = ZipSession::new()
+    ///     .with_user_agent("air-quality-mirror/2.1 (ops@example.org)")?
+    ///     .with_per_request_delay(Duration::from_secs(2));
+    /// for url in ["https://example.com/2023.zip", "https://example.com/2024.zip"] {
+    ///     session.open(url, 10240)?.extract_to_dir("data")?;
+    /// }
+    /// # Ok::<(), muy_zipido::ZipError>(())
+    /// ```
+    pub fn with_user_agent(mut self, user_agent: &str) -> Result<Self, ZipError> {
+        self.user_agent = header_value(user_agent)?;
+        Ok(self)
+    }
+
+    /// Waits until at least `delay` has passed since the session's previous
+    /// request before sending the next one, so a batch of archives from the
+    /// same server does not arrive all at once. The first request is sent
+    /// straight away, and redirects and rate limit retries of one request do
+    /// not wait again. Clones of the session share the spacing. Defaults to
+    /// no delay.
+    pub fn with_per_request_delay(mut self, delay: Duration) -> Self {
+        self.per_request_delay = delay;
+        self
+    }
+
+    /// Lets at most `max` requests through the session, and its clones, at
+    /// once; further ones wait for a place. A request holds its place until
+    /// its response body is dropped, so an open extractor counts as one
+    /// request under way until it is dropped or aborted. Redirects and
+    /// rate limit retries give the place up before the next request, and
+    /// [`MuyZipido::clone_config_with_url`] shares it. Defaults to no limit.
+    ///
+    /// Panics if `max` is 0.
+    pub fn with_max_concurrent(mut self, max: usize) -> Self {
+        assert!(max > 0, "max_concurrent must be at least 1");
+        self.max_concurrent = Some(max);
+        self
+    }
+
+    /// Trusts only servers whose certificate is `der_bytes`, a DER-encoded
+    /// certificate, or is issued by it, instead of the system's root
+    /// certificates. Calling it again trusts each certificate given.
+    ///
+    /// Pin the issuing CA, or a self-signed server certificate: a leaf
+    /// certificate issued by a CA cannot be pinned on its own. Servers
+    /// presenting another certificate fail with
+    /// [`ZipError::TlsCertificateRejected`]. Fails with
+    /// [`ZipError::InvalidCertificate`] if `der_bytes` is not a certificate.
+    ///
+    /// ```no_run
+    /// use muy_zipido::ZipSession;
+    ///
+    /// let ca = std::fs::read("internal-ca.der")?;
+    /// let extractor = ZipSession::new()
+    ///     .with_pinned_certificate(ca)?
+    ///     .open("https://updates.internal/data.zip", 8192)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_pinned_certificate(mut self, der_bytes: Vec<u8>) -> Result<Self, ZipError> {
+        let certificate = Certificate::from_der(&der_bytes)
+            .map_err(|e| ZipError::InvalidCertificate(e.to_string()))?;
+        self.pinned_certificates.push(certificate);
+        self.client = build_client(self.pool_size, &self.pinned_certificates);
+        Ok(self)
+    }
+
+    /// Sends `name: value` with every request, e.g. an `Authorization`
+    /// header for a portal that requires an API key.
+    ///
+    /// `Authorization`, `Proxy-Authorization` and `Cookie` are dropped when a
+    /// redirect leads to another origin, unless
+    /// [`with_forward_auth_on_redirect`](Self::with_forward_auth_on_redirect)
+    /// is set.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Follows at most `max_hops` redirects per request, failing with
+    /// [`ZipError::RedirectLoop`] after that. Defaults to 10; 0 fails on the
+    /// first redirect.
+    ///
+    /// ```no_run
+    /// use muy_zipido::ZipSession;
+    ///
+    /// let extractor = ZipSession::new()
+    ///     .with_redirect_policy(3)
+    ///     .open("https://example.com/data.zip", 8192)?;
+    /// # Ok::<(), muy_zipido::ZipError>(())
+    /// ```
+    pub fn with_redirect_policy(mut self, max_hops: usize) -> Self {
+        self.max_redirects = max_hops;
+        self
+    }
+
+    /// Keeps sending credentials set with [`with_header`](Self::with_header)
+    /// when a redirect leads to another scheme, host or port. Off by default,
+    /// so a redirect to a CDN or a compromised mirror does not see them.
+    pub fn with_forward_auth_on_redirect(mut self, forward: bool) -> Self {
+        self.forward_auth_on_redirect = forward;
+        self
+    }
+
+    /// Requests `url` and returns an extractor streaming the response body.
+    ///
+    /// `url` is tidied up first by [`normalize_url`].
+    pub fn open(&self, url: &str, chunk_size: usize) -> Result<MuyZipido, ZipError> {
+        self.open_url(normalize_url(url)?, chunk_size)
+    }
+
+    /// Requests `url` as given, without the clean-up [`ZipSession::open`]
+    /// does, and returns an extractor streaming the response body.
+    pub fn open_url(&self, url: Url, chunk_size: usize) -> Result<MuyZipido, ZipError> {
+        let fetched = self.send(url, HeaderMap::new(), None)?;
+        Ok(extractor(self, fetched, chunk_size))
+    }
+
+    /// Opens `url` again for an extractor that may still be streaming, in
+    /// the place among the requests under way that it holds, if any, so a
+    /// retry under [`with_max_concurrent`](Self::with_max_concurrent) does
+    /// not wait for itself.
+    pub(crate) fn reopen(
+        &self,
+        url: &str,
+        chunk_size: usize,
+        held: Option<Arc<Slot>>,
+    ) -> Result<MuyZipido, ZipError> {
+        let fetched = self.send(normalize_url(url)?, HeaderMap::new(), held)?;
+        Ok(extractor(self, fetched, chunk_size))
+    }
+
+    /// Requests `url` starting at byte `offset`, typically a previous
+    /// extractor's [`MuyZipido::resume_offset`], to continue an interrupted
+    /// download.
+    ///
+    /// With `etag`, the range is only honoured if the archive is unchanged
+    /// (`If-Range`). When the server answers with the whole archive instead,
+    /// because it does not support ranges or the archive changed, the
+    /// extractor starts from the beginning: check
+    /// [`MuyZipido::resume_offset`] to see where it starts.
+    pub fn open_at(
+        &self,
+        url: &str,
+        offset: u64,
+        etag: Option<&str>,
+        chunk_size: usize,
+    ) -> Result<MuyZipido, ZipError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, header_value(&format!("bytes={}-", offset))?);
+        if let Some(etag) = etag {
+            headers.insert(IF_RANGE, header_value(etag)?);
+        }
+
+        let fetched = self.send(normalize_url(url)?, headers, None)?;
+        let partial = fetched.response.status() == StatusCode::PARTIAL_CONTENT;
+
+        let mut extractor = extractor(self, fetched, chunk_size);
+        if partial {
+            extractor.offset = offset as usize;
+            extractor.entry_start = offset as usize;
+        }
+        Ok(extractor)
+    }
+
+    /// Streams the archive cached at `cache_path` if it was saved less than
+    /// `max_age` ago, otherwise downloads `url` and saves it there as it is
+    /// read (see [`MuyZipido::with_cache`]).
+    ///
+    /// A stale cache with a recorded ETag is revalidated with
+    /// `If-None-Match`; if the server answers `304 Not Modified` the cache is
+    /// reused and counts as fresh for another `max_age`.
+    pub fn open_cached(
+        &self,
+        url: &str,
+        cache_path: &Path,
+        max_age: Duration,
+        chunk_size: usize,
+    ) -> Result<MuyZipido, ZipError> {
+        let url = normalize_url(url)?;
+        let etag = fs::read_to_string(cache::etag_path(cache_path))
+            .ok()
+            .filter(|etag| !etag.is_empty());
+        let age = fs::metadata(cache_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .unwrap_or(Duration::ZERO)
+            });
+
+        let mut headers = HeaderMap::new();
+        match age {
+            Some(age) if age < max_age => return open_cache(self, cache_path, etag, chunk_size),
+            Some(_) => {
+                if let Some(etag) = &etag {
+                    headers.insert(IF_NONE_MATCH, header_value(etag)?);
+                }
+            }
+            None => {}
+        }
+
+        let fetched = self.get(url, headers, None)?;
+        if fetched.response.status() == StatusCode::NOT_MODIFIED && etag.is_some() {
+            File::options()
+                .write(true)
+                .open(cache_path)?
+                .set_modified(SystemTime::now())?;
+            return open_cache(self, cache_path, etag, chunk_size);
+        }
+
+        let fetched = self.validate(fetched.check_status()?)?;
+        extractor(self, fetched, chunk_size).with_cache(cache_path)
+    }
+
+    /// Requests `url` as [`get`](Self::get) does, failing on a non-success
+    /// status as [`check_status`] does or if the response validator rejects
+    /// the response.
+    fn send(
+        &self,
+        url: Url,
+        headers: HeaderMap,
+        held: Option<Arc<Slot>>,
+    ) -> Result<Fetched, ZipError> {
+        self.validate(self.get(url, headers, held)?.check_status()?)
+    }
+
+    fn validate(&self, fetched: Fetched) -> Result<Fetched, ZipError> {
+        if let Some(ResponseValidator(validator)) = &self.response_validator {
+            validator(&fetched.response)?;
+        }
+        Ok(fetched)
+    }
+
+    /// Sends a GET for `url` with the session's headers plus `headers`.
+    ///
+    /// Redirects are followed here rather than by `reqwest`, so the hop
+    /// limit and credential stripping are under the session's control.
+    /// Rate limited requests are retried here too.
+    ///
+    /// Each hop takes a place among the requests under way, or uses `held`,
+    /// and gives it up before a redirect is followed or a rate limit waited
+    /// out.
+    fn get(
+        &self,
+        url: Url,
+        headers: HeaderMap,
+        held: Option<Arc<Slot>>,
+    ) -> Result<Fetched, ZipError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ZipError::InvalidUrl {
+                url: url.to_string(),
+                reason: format!(
+                    "unsupported scheme `{}`, expected http or https",
+                    url.scheme()
+                ),
+            });
+        }
+        self.pacing.wait_turn(self.per_request_delay);
+
+        let origin = Some(url.origin());
+        let mut chain = vec![url.to_string()];
+        let mut rate_limited = 0;
+        let mut rate_limit_wait = Duration::ZERO;
+
+        loop {
+            let current = chain[chain.len() - 1].clone();
+            let mut request_headers = HeaderMap::new();
+            request_headers.insert(USER_AGENT, self.user_agent.clone());
+            request_headers.extend(self.headers.clone());
+            let same_origin = Url::parse(&current).ok().map(|url| url.origin()) == origin;
+            if !same_origin && !self.forward_auth_on_redirect {
+                for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+                    request_headers.remove(name);
+                }
+            }
+            request_headers.extend(headers.clone());
+
+            let slot = held.clone().or_else(|| {
+                self.max_concurrent
+                    .map(|max| Arc::new(Pacing::enter(&self.pacing, max)))
+            });
+            let response = self
+                .client
+                .get(&current)
+                .headers(request_headers)
+                .send()
+                .map_err(|error| request_error(&current, error))?;
+
+            if let Some(retry_after) = rate_limit(&response) {
+                let wait = retry_after.unwrap_or(DEFAULT_RATE_LIMIT_WAIT);
+                if rate_limited == self.rate_limit_retries || wait > self.max_retry_after {
+                    return Err(ZipError::RateLimited {
+                        url: current,
+                        retry_after,
+                    });
+                }
+                if let Some(RateLimitHandler(handler)) = &self.rate_limit_handler {
+                    handler(&current, wait);
+                }
+                drop((response, slot));
+                std::thread::sleep(wait);
+                rate_limited += 1;
+                rate_limit_wait += wait;
+                continue;
+            }
+
+            let Some(next) = redirect_target(&response) else {
+                return Ok(Fetched {
+                    response,
+                    chain,
+                    rate_limited,
+                    rate_limit_wait,
+                    slot,
+                });
+            };
+            drop((response, slot));
+            let revisited = chain.contains(&next);
+            chain.push(next);
+            if revisited || chain.len() > self.max_redirects + 1 {
+                return Err(ZipError::RedirectLoop { chain });
+            }
+        }
+    }
+}
+
+impl Pacing {
+    /// Sleeps until `delay` after the previous request, then records this
+    /// one. The lock is held while sleeping, so concurrent requests queue up
+    /// and are spaced out in turn.
+    fn wait_turn(&self, delay: Duration) {
+        let mut last_request = lock(&self.last_request);
+        if let Some(last) = *last_request {
+            std::thread::sleep(delay.saturating_sub(last.elapsed()));
+        }
+        *last_request = Some(Instant::now());
+    }
+
+    /// Waits until fewer than `max` requests are under way and takes a
+    /// place among them.
+    fn enter(pacing: &Arc<Self>, max: usize) -> Slot {
+        let mut in_flight = lock(&pacing.in_flight);
+        while *in_flight >= max {
+            in_flight = pacing
+                .slot_freed
+                .wait(in_flight)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *in_flight += 1;
+        Slot(Arc::clone(pacing))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *lock(&self.0.in_flight) -= 1;
+        self.0.slot_freed.notify_one();
+    }
+}
+
+/// Locks `mutex`, carrying on if a panicking thread poisoned it: the
+/// pacing state stays usable either way.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Fetched {
+    /// Fails on a non-success status as [`check_status`] does.
+    fn check_status(mut self) -> Result<Self, ZipError> {
+        let url = &self.chain[self.chain.len() - 1];
+        self.response = check_status(self.response, url)?;
+        Ok(self)
+    }
+}
+
+/// Builds a client with the given idle pool size, trusting only `pinned`
+/// if there are any. Redirects are left to [`ZipSession::get`].
+///
+/// Panics if the TLS backend cannot be initialised, as [`Client::new`] does.
+fn build_client(pool_size: Option<usize>, pinned: &[Certificate]) -> Client {
+    let mut builder = Client::builder().redirect(Policy::none());
+    if let Some(size) = pool_size {
+        builder = builder.pool_max_idle_per_host(size);
+    }
+    if !pinned.is_empty() {
+        builder = builder.tls_built_in_root_certs(false);
+        for certificate in pinned {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+    }
+    builder
+        .build()
+        .expect("failed to initialise the HTTP client")
+}
+
+/// Wraps a failed request for `url`, telling certificate verification
+/// failures apart from other connection errors.
+///
+/// `reqwest` does not classify TLS errors, so this walks the underlying
+/// errors down to the TLS library's own.
+fn request_error(url: &str, error: reqwest::Error) -> ZipError {
+    let mut source = std::error::Error::source(&error);
+    while let Some(inner) = source {
+        if let Some(tls) = inner.downcast_ref::<native_tls::Error>()
+            && certificate_rejected(tls)
+        {
+            return ZipError::TlsCertificateRejected(format!("{}: {}", url, tls));
+        }
+        source = inner.source();
+    }
+    ZipError::Http {
+        url: url.to_string(),
+        error,
+    }
+}
+
+/// Whether a TLS handshake failed because the server's certificate did
+/// not verify, which OpenSSL reports as `SSL_R_CERTIFICATE_VERIFY_FAILED`.
+#[cfg(not(any(target_os = "windows", target_vendor = "apple")))]
+fn certificate_rejected(error: &native_tls::Error) -> bool {
+    const ERR_LIB_SSL: std::ffi::c_int = 20;
+    const SSL_R_CERTIFICATE_VERIFY_FAILED: std::ffi::c_int = 134;
+    std::error::Error::source(error)
+        .and_then(|inner| inner.downcast_ref::<openssl::error::ErrorStack>())
+        .is_some_and(|stack| {
+            stack.errors().iter().any(|error| {
+                error.library_code() == ERR_LIB_SSL
+                    && error.reason_code() == SSL_R_CERTIFICATE_VERIFY_FAILED
+            })
+        })
+}
+
+/// Schannel and Secure Transport do not say why a handshake failed through
+/// `native-tls`, so any failed handshake counts.
+#[cfg(any(target_os = "windows", target_vendor = "apple"))]
+fn certificate_rejected(_error: &native_tls::Error) -> bool {
+    true
+}
+
+/// Whether `response` is a rate limit, a 429 or a 503 with `Retry-After`,
+/// and if so how long it asks to wait.
+fn rate_limit(response: &Response) -> Option<Option<Duration>> {
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, SystemTime::now()));
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS => Some(retry_after),
+        StatusCode::SERVICE_UNAVAILABLE if retry_after.is_some() => Some(retry_after),
+        _ => None,
+    }
+}
+
+/// Parses a `Retry-After` value, either seconds or an HTTP date, into the
+/// wait from `now`. A date in the past means no wait.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    // IMF-fixdate, the only form servers may send: `Sun, 06 Nov 1994 08:49:37 GMT`
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (_, date) = value.split_once(", ")?;
+    let fields: Vec<&str> = date.split(' ').collect();
+    let [day, month, year, time, "GMT"] = fields[..] else {
+        return None;
+    };
+    let time: Vec<&str> = time.split(':').collect();
+    let [hour, minute, second] = time[..] else {
+        return None;
+    };
+    let date = ZipDateTime {
+        year: year.parse().ok()?,
+        month: MONTHS.iter().position(|&name| name == month)? as u8 + 1,
+        day: day.parse().ok()?,
+        hour: hour.parse().ok()?,
+        minute: minute.parse().ok()?,
+        second: second.parse().ok()?,
+    };
+    let at = date.to_system_time()?;
+    Some(at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// The absolute URL `response` redirects to, if it is a redirect.
+fn redirect_target(response: &Response) -> Option<String> {
+    let redirect = matches!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    );
+    if !redirect {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok().map(String::from)
+}
+
+/// Tidies up a URL as typed or pasted, e.g. from a data portal, before it is
+/// requested.
+///
+/// Surrounding whitespace is trimmed, the scheme and host are lowercased,
+/// and characters not allowed in a path, query or fragment, such as spaces,
+/// brackets or non-ASCII letters, are percent-encoded. Existing `%XX`
+/// escapes are kept as they are; any other `%` is encoded.
+///
+/// Fails with [`ZipError::InvalidUrl`] for schemes other than `http` and
+/// `https`, control characters, and characters not allowed in the host,
+/// giving the position of the offending character, counted in characters
+/// from 0.
+///
+/// ```
+/// use muy_zipido::session::normalize_url;
+///
+/// let url = normalize_url(" https://example.com/My Data/file[1].zip ")?;
+/// assert_eq!(url.as_str(), "https://example.com/My%20Data/file%5B1%5D.zip");
+/// # Ok::<(), muy_zipido::ZipError>(())
+/// ```
+pub fn normalize_url(url: &str) -> Result<Url, ZipError> {
+    let invalid = |reason: String| ZipError::InvalidUrl {
+        url: url.to_string(),
+        reason,
+    };
+    let leading = url.chars().take_while(|c| c.is_whitespace()).count();
+    let trimmed = url.trim();
+
+    let Some((scheme, rest)) = trimmed.split_once("://") else {
+        return Err(invalid("missing http:// or https:// scheme".to_string()));
+    };
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return Err(invalid(format!(
+            "unsupported scheme `{}`, expected http or https",
+            scheme
+        )));
+    }
+
+    let mut normalized = format!("{}://", scheme.to_ascii_lowercase());
+    let first = leading + scheme.chars().count() + 3;
+    let authority_len = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    if authority_len == 0 {
+        return Err(invalid("missing host".to_string()));
+    }
+    for (i, c) in rest.char_indices() {
+        let position = first + rest[..i].chars().count();
+        if c.is_control() {
+            return Err(invalid(format!(
+                "control character U+{:04X} at position {}",
+                c as u32, position
+            )));
+        }
+
+        if i < authority_len {
+            if c.is_ascii() && !c.is_ascii_alphanumeric() && !"-._~!$&'()*+,;=:@[]%".contains(c) {
+                return Err(invalid(format!(
+                    "`{}` at position {} is not allowed in the host",
+                    c, position
+                )));
+            }
+            normalized.push(c);
+        } else if c == '%' {
+            let escape = rest[i + 1..].get(..2);
+            match escape.is_some_and(|hex| hex.chars().all(|c| c.is_ascii_hexdigit())) {
+                true => normalized.push('%'),
+                false => normalized.push_str("%25"),
+            }
+        } else if c.is_ascii_alphanumeric() || "-._~!$&'()*+,;=:@/?#".contains(c) {
+            normalized.push(c);
+        } else {
+            let mut utf8 = [0; 4];
+            for byte in c.encode_utf8(&mut utf8).bytes() {
+                normalized.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+
+    Url::parse(&normalized).map_err(|e| invalid(e.to_string()))
+}
+
+/// An extractor streaming the body of `fetched`, requested by `session`.
+fn extractor(session: &ZipSession, fetched: Fetched, chunk_size: usize) -> MuyZipido {
+    let mut extractor =
+        MuyZipido::from_http_response(&fetched.chain[0], fetched.response, chunk_size);
+    extractor.session = Some(session.clone());
+    if let Some(slot) = fetched.slot {
+        extractor.slot = Arc::downgrade(&slot);
+        extractor.reader = extractor
+            .reader
+            .take()
+            .map(|body| Box::new(SlotReader { body, _slot: slot }) as Box<dyn Read + Send>);
+    }
+    extractor.redirect_chain = fetched.chain;
+    extractor.stats.rate_limited = fetched.rate_limited;
+    extractor.stats.rate_limit_wait = fetched.rate_limit_wait;
+    extractor
+}
+
+/// A header value built from `value`, which came from the caller or a
+/// previous response.
+fn header_value(value: &str) -> Result<HeaderValue, ZipError> {
+    HeaderValue::from_str(value)
+        .map_err(|e| ZipError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))
+}
+
+/// Streams the archive cached at `path`.
+fn open_cache(
+    session: &ZipSession,
+    path: &Path,
+    etag: Option<String>,
+    chunk_size: usize,
+) -> Result<MuyZipido, ZipError> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut extractor = MuyZipido::from_reader(file, chunk_size);
+    extractor.session = Some(session.clone());
+    extractor.content_length = usize::try_from(len).ok();
+    extractor.etag = etag;
+    Ok(extractor)
+}
+
+/// Turns a non-success status into [`ZipError::HttpStatus`] with the start
+/// of the response body, which usually explains the failure.
+pub(crate) fn check_status(response: Response, url: &str) -> Result<Response, ZipError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let mut body = Vec::new();
+    // The status is the error; a body that cannot be read just leaves the
+    // snippet short.
+    let _ = response.take(BODY_SNIPPET_LIMIT).read_to_end(&mut body);
+    Err(ZipError::HttpStatus {
+        status: status.as_u16(),
+        url: url.to_string(),
+        body_snippet: String::from_utf8_lossy(&body).trim().to_string(),
+    })
+}
+
+/// Extracts the file name from a `Content-Disposition` header value,
+/// preferring the RFC 6266 `filename*` form over plain `filename`.
+pub(crate) fn content_disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+
+    for param in value.split(';').skip(1) {
+        let Some((name, raw)) = param.split_once('=') else {
+            continue;
+        };
+        let raw = raw.trim();
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                // charset'language'percent-encoded-name
+                let encoded = raw.splitn(3, '\'').nth(2)?;
+                return percent_decode(encoded).filter(|name| !name.is_empty());
+            }
+            "filename" => {
+                let unquoted = raw
+                    .strip_prefix('"')
+                    .and_then(|r| r.strip_suffix('"'))
+                    .unwrap_or(raw);
+                plain = Some(unquoted.replace("\\\"", "\""));
+            }
+            _ => {}
+        }
+    }
+
+    plain.filter(|name| !name.is_empty())
+}
+
+fn percent_decode(encoded: &str) -> Option<String> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = encoded.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        for (value, expected) in [
+            ("30", Some(30)),
+            (" 0 ", Some(0)),
+            ("Sun, 06 Nov 1994 08:50:07 GMT", Some(30)),
+            ("Sun, 06 Nov 1994 08:49:00 GMT", Some(0)),
+            ("Mon, 07 Nov 1994 08:49:37 GMT", Some(86_400)),
+            ("-5", None),
+            ("1.5", None),
+            ("Sun, 06 Nov 1994 08:50:07 UTC", None),
+            ("Sunday, 06-Nov-94 08:49:37 GMT", None),
+            ("Sun, 06 Foo 1994 08:50:07 GMT", None),
+            ("Sun, 06 Nov 1994 25:50:07 GMT", None),
+        ] {
+            assert_eq!(
+                parse_retry_after(value, now),
+                expected.map(Duration::from_secs),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        let cases = [
+            (
+                "  https://example.com/data.zip \n",
+                "https://example.com/data.zip",
+            ),
+            (
+                "HTTPS://Data.Example.COM/x.zip",
+                "https://data.example.com/x.zip",
+            ),
+            (
+                "https://example.com/My Data/file name.zip",
+                "https://example.com/My%20Data/file%20name.zip",
+            ),
+            (
+                "https://example.com/already%20encoded%2Fpath.zip",
+                "https://example.com/already%20encoded%2Fpath.zip",
+            ),
+            (
+                "https://example.com/100%.zip?p=50%",
+                "https://example.com/100%25.zip?p=50%25",
+            ),
+            (
+                "https://example.com/data[2024].zip?ids[]=1&q=a b",
+                "https://example.com/data%5B2024%5D.zip?ids%5B%5D=1&q=a%20b",
+            ),
+            (
+                "https://example.com/export?filter={\"year\":2024}",
+                "https://example.com/export?filter=%7B%22year%22:2024%7D",
+            ),
+            (
+                "https://example.com/données/çà.zip",
+                "https://example.com/donn%C3%A9es/%C3%A7%C3%A0.zip",
+            ),
+            (
+                "http://user:pw@[::1]:8080/a|b.zip#part 2",
+                "http://user:pw@[::1]:8080/a%7Cb.zip#part%202",
+            ),
+            ("https://example.com", "https://example.com/"),
+        ];
+        for (input, expected) in cases {
+            let url = normalize_url(input).unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            assert_eq!(url.as_str(), expected, "{:?}", input);
+            assert_eq!(normalize_url(url.as_str()).unwrap(), url, "{:?}", input);
+        }
+
+        let failures = [
+            ("ftp://example.com/x.zip", "unsupported scheme `ftp`"),
+            ("example.com/x.zip", "missing http:// or https:// scheme"),
+            (
+                " https://exa mple.com/x.zip",
+                "` ` at position 12 is not allowed in the host",
+            ),
+            (
+                "https://example.com/a\tb.zip",
+                "control character U+0009 at position 21",
+            ),
+            ("https:///x.zip", "missing host"),
+            ("https://exa%mple.com/", "invalid international domain name"),
+        ];
+        for (input, reason) in failures {
+            match normalize_url(input) {
+                Err(ZipError::InvalidUrl { url, reason: r }) => {
+                    assert_eq!(url, input);
+                    assert!(r.contains(reason), "{:?}: {}", input, r);
+                }
+                other => panic!("{:?}: {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_content_disposition_filename() {
+        let parse = content_disposition_filename;
+
+        assert_eq!(
+            parse("attachment; filename=\"data.zip\""),
+            Some("data.zip".to_string())
+        );
+        assert_eq!(
+            parse("attachment; filename=data.zip"),
+            Some("data.zip".to_string())
+        );
+        assert_eq!(
+            parse("attachment; filename=\"plain.zip\"; filename*=UTF-8''caf%C3%A9.zip"),
+            Some("café.zip".to_string())
+        );
+        assert_eq!(parse("inline"), None);
+        assert_eq!(parse("attachment; filename=\"\""), None);
+    }
+}
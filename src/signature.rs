@@ -0,0 +1,144 @@
+//! Checking a detached Ed25519 signature shipped inside the archive, for
+//! [`MuyZipido::with_verify_signatures`](crate::MuyZipido::with_verify_signatures).
+
+use crate::checksum::{Manifest, sha256_hex};
+use crate::entry::EntryInfo;
+use crate::error::ZipError;
+use ed25519_dalek::pkcs8::DecodePublicKey;
+use ed25519_dalek::{Signature, VerifyingKey};
+
+/// Collects the digests of the entries read and the signature entry, and
+/// checks them once the archive ends.
+pub(crate) struct SignatureCheck {
+    key: VerifyingKey,
+    manifest: Manifest,
+    signature: Option<Vec<u8>>,
+}
+
+impl SignatureCheck {
+    /// Accepts the public key as 32 raw bytes or a PEM `PUBLIC KEY` block.
+    pub(crate) fn new(public_key: &[u8]) -> Result<Self, ZipError> {
+        let key = match <&[u8; 32]>::try_from(public_key) {
+            Ok(raw) => VerifyingKey::from_bytes(raw).map_err(|e| e.to_string()),
+            Err(_) => std::str::from_utf8(public_key)
+                .map_err(|e| e.to_string())
+                .and_then(|pem| VerifyingKey::from_public_key_pem(pem).map_err(|e| e.to_string())),
+        }
+        .map_err(|e| rejected(format!("invalid public key: {}", e)))?;
+
+        Ok(Self {
+            key,
+            manifest: Manifest::new(),
+            signature: None,
+        })
+    }
+
+    /// A check with the same key that has seen nothing yet.
+    #[cfg(feature = "http")]
+    pub(crate) fn fresh(&self) -> Self {
+        Self {
+            key: self.key,
+            manifest: Manifest::new(),
+            signature: None,
+        }
+    }
+
+    /// Whether `info` is the entry holding the signature.
+    pub(crate) fn is_signature(info: &EntryInfo) -> bool {
+        info.filename.ends_with(".sig")
+    }
+
+    /// Records an entry's decoded data.
+    pub(crate) fn observe(&mut self, info: &EntryInfo, data: &[u8]) {
+        if Self::is_signature(info) {
+            self.signature = Some(data.to_vec());
+        } else {
+            self.record(info, sha256_hex(data));
+        }
+    }
+
+    /// Records the hex SHA-256 digest of an entry other than the signature,
+    /// hashed as it was streamed.
+    pub(crate) fn record(&mut self, info: &EntryInfo, digest: String) {
+        if !info.is_dir {
+            self.manifest.insert(&info.filename, digest);
+        }
+    }
+
+    /// Checks the signature against the entries seen, failing with
+    /// [`ZipError::UnsupportedFeature`] if there was none.
+    pub(crate) fn finish(&self) -> Result<(), ZipError> {
+        let signature = self
+            .signature
+            .as_deref()
+            .ok_or_else(|| ZipError::UnsupportedFeature {
+                needed_version: 0,
+                filename: String::new(),
+                hint: "no signature entry found".to_string(),
+            })?;
+        let signature = Signature::from_slice(signature)
+            .map_err(|_| rejected("the signature entry is not a 64-byte signature".to_string()))?;
+        self.key
+            .verify_strict(self.manifest.to_string().as_bytes(), &signature)
+            .map_err(|_| rejected("the signature does not match the archive".to_string()))
+    }
+}
+
+fn rejected(reason: String) -> ZipError {
+    ZipError::SignatureRejected { reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::EncodePublicKey;
+    use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn info(filename: &str) -> EntryInfo {
+        EntryInfo {
+            filename: filename.to_string(),
+            compressed_size: 0,
+            uncompressed_size: 0,
+            compression_method: crate::CompressionMethod::Stored,
+            crc32: 0,
+            modified: None,
+            is_dir: filename.ends_with('/'),
+            version_needed: 20,
+            filename_raw: None,
+            unix_mode: None,
+            unix_mtime: None,
+            header_offset: 0,
+            data_offset: 0,
+            data_len_on_wire: 0,
+        }
+    }
+
+    #[test]
+    fn test_signature_check() {
+        let signing = SigningKey::from_bytes(&[7; 32]);
+        let listing = format!("{}  ./a.txt\n", sha256_hex(b"hello"));
+        let signature = signing.sign(listing.as_bytes()).to_bytes();
+        let pem = signing
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+
+        for key in [&signing.verifying_key().to_bytes()[..], pem.as_bytes()] {
+            let mut check = SignatureCheck::new(key).unwrap();
+            check.observe(&info("dir/"), b"");
+            check.observe(&info("a.txt"), b"hello");
+            assert!(matches!(
+                check.finish(),
+                Err(ZipError::UnsupportedFeature { hint, .. }) if hint == "no signature entry found"
+            ));
+            check.observe(&info("release.sig"), &signature);
+            check.finish().unwrap();
+
+            check.observe(&info("a.txt"), b"tampered");
+            assert!(check.finish().is_err());
+        }
+
+        assert!(SignatureCheck::new(b"not a key").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Post-processing of entry data after decompression.
+
+use crate::ZipError;
+use flate2::read::MultiGzDecoder;
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+
+type TransformFn = dyn Fn(&str, &[u8]) -> Option<Vec<u8>> + Send + Sync;
+
+/// Rewrites an entry's data once it has been decompressed and its CRC-32
+/// checked, before the entry is yielded. Added with
+/// [`MuyZipido::with_entry_transform`](crate::MuyZipido::with_entry_transform).
+///
+/// When a transform changes an entry, [`ZipEntry::original`](crate::ZipEntry::original)
+/// keeps the metadata as stored in the archive.
+#[derive(Clone)]
+pub enum Transform {
+    /// Decompresses members whose name ends in `.gz` and drops the suffix,
+    /// so `points.csv.gz` is yielded as `points.csv`.
+    GunzipByExtension,
+    /// Calls the function with each entry's filename and data. `Some`
+    /// replaces the data, `None` leaves the entry as it is.
+    Custom(Arc<TransformFn>),
+}
+
+impl Transform {
+    /// A [`Transform::Custom`] calling `f`.
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&str, &[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        Transform::Custom(Arc::new(f))
+    }
+
+    /// The new filename and data for an entry, or `None` if the transform
+    /// does not apply to it. Output larger than `max_size` bytes fails with
+    /// [`ZipError::LimitExceeded`].
+    pub(crate) fn apply(
+        &self,
+        filename: &str,
+        data: &[u8],
+        max_size: u64,
+    ) -> Result<Option<(String, Vec<u8>)>, ZipError> {
+        let (name, output) = match self {
+            Transform::GunzipByExtension => {
+                let Some(name) = filename.strip_suffix(".gz") else {
+                    return Ok(None);
+                };
+                let mut output = Vec::new();
+                MultiGzDecoder::new(data)
+                    .take(max_size.saturating_add(1))
+                    .read_to_end(&mut output)
+                    .map_err(|source| ZipError::CorruptData {
+                        filename: filename.to_string(),
+                        source,
+                    })?;
+                (name, output)
+            }
+            Transform::Custom(f) => match f(filename, data) {
+                Some(output) => (filename, output),
+                None => return Ok(None),
+            },
+        };
+
+        if output.len() as u64 > max_size {
+            return Err(ZipError::LimitExceeded {
+                limit: max_size,
+                filename: filename.to_string(),
+            });
+        }
+        Ok(Some((name.to_string(), output)))
+    }
+}
+
+impl fmt::Debug for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transform::GunzipByExtension => f.write_str("GunzipByExtension"),
+            Transform::Custom(_) => f.write_str("Custom(<fn>)"),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use common::SharedBuffer;
+use muy_zipido::fixture::ArchiveBuilder;
+use muy_zipido::{Limits, MuyZipido, ZipError};
+
+/// A local header declaring the given lengths, followed by `rest`.
+fn local_header(compressed_size: u32, filename_len: u16, extra_len: u16, rest: &[u8]) -> Vec<u8> {
+    let mut header = b"PK\x03\x04".to_vec();
+    header.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0, 0, 0, 0, 0]);
+    header.extend_from_slice(&compressed_size.to_le_bytes());
+    header.extend_from_slice(&compressed_size.to_le_bytes());
+    header.extend_from_slice(&filename_len.to_le_bytes());
+    header.extend_from_slice(&extra_len.to_le_bytes());
+    header.extend_from_slice(rest);
+    header
+}
+
+/// A small deterministic generator, so failures are reproducible.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+/// Drains the extractor, which must finish within `max_items` items.
+fn drain(extractor: MuyZipido, max_items: usize) -> Vec<Result<String, ZipError>> {
+    let results: Vec<_> = extractor
+        .take(max_items + 1)
+        .map(|r| r.map(|e| e.info.filename))
+        .collect();
+    assert!(results.len() <= max_items, "iteration did not terminate");
+    results
+}
+
+#[test]
+fn test_header_lengths_over_limit_are_rejected() {
+    let archive = local_header(0, u16::MAX, 0, &[b'a'; 100]);
+    let results = drain(MuyZipido::from_bytes(archive, 64), 2);
+    assert!(matches!(
+        &results[..],
+        [Err(ZipError::InvalidHeader { offset: 0, reason })] if reason.contains("file name length 65535")
+    ));
+
+    let archive = local_header(0, 1, 40_000, b"a");
+    let results = drain(MuyZipido::from_bytes(archive, 64), 2);
+    assert!(matches!(
+        &results[..],
+        [Err(ZipError::InvalidHeader { reason, .. })] if reason.contains("extra field length 40000")
+    ));
+
+    let archive = ArchiveBuilder::new().stored("long-name.txt", b"x").build();
+    let limits = Limits::new().with_max_filename_len(8);
+    let extractor = MuyZipido::from_bytes(archive.clone(), 64).with_limits(limits);
+    assert!(matches!(
+        &drain(extractor, 2)[..],
+        [Err(ZipError::InvalidHeader { .. })]
+    ));
+    let extractor = MuyZipido::from_bytes(archive, 64).with_limits(Limits::new());
+    assert_eq!(drain(extractor, 2).len(), 1);
+}
+
+#[test]
+fn test_entries_over_size_limit_are_skipped() {
+    let big = vec![b'z'; 10_000];
+    let archive = ArchiveBuilder::new()
+        .stored("stored.bin", &big)
+        .stored_with_descriptor("streamed.bin", &big)
+        .deflated("bomb.bin", &big)
+        .stored("small.txt", b"ok")
+        .build();
+
+    let limits = Limits::new().with_max_entry_size(1000);
+    let results = drain(MuyZipido::from_bytes(archive, 256).with_limits(limits), 5);
+    assert_eq!(results.len(), 4);
+    for result in &results[..3] {
+        let error = result.as_ref().unwrap_err();
+        assert!(error.is_recoverable());
+        assert!(matches!(
+            error.root(),
+            ZipError::LimitExceeded { limit: 1000, .. }
+        ));
+    }
+    assert_eq!(results[3].as_ref().unwrap(), "small.txt");
+}
+
+#[test]
+fn test_descriptor_scan_is_bounded() {
+    // A streamed entry whose descriptor signature never appears.
+    let archive = ArchiveBuilder::new()
+        .stored_with_descriptor("runaway.bin", &[b'z'; 10_000])
+        .build();
+    let descriptor = archive.windows(4).position(|w| w == b"PK\x07\x08").unwrap();
+    let endless = Endless {
+        data: archive[..descriptor].to_vec(),
+        pos: 0,
+    };
+
+    let limits = Limits::new().with_max_descriptor_scan(4096);
+    let mut extractor = MuyZipido::from_reader(endless, 256).with_limits(limits);
+    let error = extractor.next().unwrap().unwrap_err();
+    assert!(matches!(
+        error.root(),
+        ZipError::DescriptorNotFound { filename } if filename == "runaway.bin"
+    ));
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_huge_declared_size_does_not_allocate() {
+    let mut archive = local_header(u32::MAX, 1, 0, b"a");
+    archive.extend_from_slice(&[0; 64]);
+    let results = drain(MuyZipido::from_bytes(archive.clone(), 64), 2);
+    assert!(matches!(
+        results[0].as_ref().unwrap_err().root(),
+        ZipError::UnexpectedEofInEntry { filename, .. } if filename == "a"
+    ));
+
+    let limits = Limits::new().with_max_entry_size(1024);
+    let results = drain(MuyZipido::from_bytes(archive, 64).with_limits(limits), 2);
+    assert!(matches!(
+        results[0].as_ref().unwrap_err().root(),
+        ZipError::UnexpectedEofInEntry { filename, .. } if filename == "a"
+    ));
+}
+
+#[test]
+fn test_random_and_mutated_input_never_panics() {
+    let mut rng = Lcg(0x5eed);
+    let limits = Limits::new().with_max_entry_size(64 * 1024);
+
+    for _ in 0..500 {
+        let len = (rng.next() % 512) as usize;
+        let mut bytes: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        if rng.next().is_multiple_of(2) {
+            bytes.splice(0..0, *b"PK\x03\x04");
+        }
+        drain(MuyZipido::from_bytes(bytes, 16).with_limits(limits), 1000);
+    }
+
+    let valid = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", &b"x,y\n1,2\n".repeat(40))
+        .with_data_descriptor("c.bin", &[7; 300])
+        .stored("d/", b"")
+        .build();
+    for _ in 0..2000 {
+        let mut bytes = valid.clone();
+        for _ in 0..=(rng.next() % 4) {
+            let at = (rng.next() as usize) % bytes.len();
+            bytes[at] = rng.next() as u8;
+        }
+        drain(MuyZipido::from_bytes(bytes, 16).with_limits(limits), 1000);
+    }
+}
+
+/// Repeats `data` forever, like a hostile server streaming without end.
+struct Endless {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl std::io::Read for Endless {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            buf[written] = self.data[self.pos % self.data.len()];
+            self.pos += 1;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+#[test]
+fn test_download_cap_stops_endless_stream() {
+    // A streamed entry whose data never ends.
+    let mut stream = local_header(0, 1, 0, b"a");
+    stream[6] = 0x08;
+    let tee = SharedBuffer::default();
+    let limits = Limits::new().with_max_download_bytes(100_000);
+    let mut extractor = MuyZipido::from_reader(
+        Endless {
+            data: stream,
+            pos: 0,
+        },
+        4096,
+    )
+    .with_limits(limits)
+    .with_tee(tee.clone());
+
+    let Some(Err(error)) = extractor.next() else {
+        panic!("expected an error");
+    };
+    assert!(
+        matches!(
+            error.root(),
+            ZipError::ArchiveLimitExceeded {
+                limit: "max_download_bytes",
+                max: 100_000
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert!(extractor.next().is_none());
+    assert_eq!(tee.contents().len(), 100_000);
+
+    // An archive of exactly the cap is read in full.
+    let archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    let limits = Limits::new().with_max_download_bytes(archive.len() as u64);
+    let entries: Vec<_> = MuyZipido::from_bytes(archive, 7)
+        .with_limits(limits)
+        .with_tee(SharedBuffer::default())
+        .collect();
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].is_ok());
+}
+
+#[test]
+fn test_entry_cap_stops_endless_archive() {
+    let archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    let local_end = archive.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+    let endless = Endless {
+        data: archive[..local_end].to_vec(),
+        pos: 0,
+    };
+
+    let results = drain(
+        MuyZipido::from_reader(endless, 64).with_limits(Limits::new().with_max_entries(50)),
+        100,
+    );
+    assert_eq!(results.len(), 51);
+    assert!(results[..50].iter().all(Result::is_ok));
+    assert!(matches!(
+        results[50],
+        Err(ZipError::ArchiveLimitExceeded {
+            limit: "max_entries",
+            max: 50
+        })
+    ));
+}
@@ -0,0 +1,56 @@
+use muy_zipido::MuyZipido;
+use muy_zipido::fixture::ArchiveBuilder;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn test_from_tcp_stream() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", b"x,y\n1,2\n")
+        .with_data_descriptor("c.bin", &[7u8; 4096])
+        .build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        for piece in archive.chunks(100) {
+            socket.write_all(piece).await.unwrap();
+        }
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let mut extractor = MuyZipido::from_tcp_stream(stream, 64);
+
+    let mut entries = Vec::new();
+    while let Some(entry) = extractor.next_entry().await {
+        entries.push(entry.unwrap());
+    }
+    server.await.unwrap();
+
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].data, b"hello");
+    assert_eq!(entries[1].data, b"x,y\n1,2\n");
+    assert_eq!(entries[2].data, vec![7u8; 4096]);
+}
+
+#[tokio::test]
+async fn test_from_tcp_stream_truncated() {
+    let archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        socket.write_all(&archive[..20]).await.unwrap();
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let mut extractor = MuyZipido::from_tcp_stream(stream, 64);
+
+    assert!(extractor.next_entry().await.unwrap().is_err());
+    assert!(extractor.next_entry().await.is_none());
+}
@@ -0,0 +1,105 @@
+use muy_zipido::fixture::ArchiveBuilder;
+use muy_zipido::{MuyZipido, ZipError};
+use std::io::{self, Cursor, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Counts the bytes read from the inner reader.
+struct Counting<R> {
+    inner: R,
+    read: Arc<AtomicUsize>,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.fetch_add(n, Ordering::SeqCst);
+        Ok(n)
+    }
+}
+
+/// Twenty stored entries of 1000 bytes each.
+fn archive() -> Vec<u8> {
+    (0..20)
+        .fold(ArchiveBuilder::new(), |builder, n| {
+            builder.stored(&format!("part-{:02}.bin", n), &[n as u8; 1000])
+        })
+        .build()
+}
+
+fn counted(archive: Vec<u8>) -> (MuyZipido, Arc<AtomicUsize>) {
+    let read = Arc::new(AtomicUsize::new(0));
+    let reader = Counting {
+        inner: Cursor::new(archive),
+        read: Arc::clone(&read),
+    };
+    (MuyZipido::from_reader(reader, 256), read)
+}
+
+#[test]
+fn test_into_channel_delivers_every_entry() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"alpha")
+        .deflated("b.txt", b"bravo bravo")
+        .build();
+    let (entries, handle) = MuyZipido::from_bytes(archive, 16).into_channel(1);
+
+    let names: Vec<String> = entries.iter().map(|e| e.unwrap().info.filename).collect();
+    assert_eq!(names, ["a.txt", "b.txt"]);
+    let stats = handle.join().unwrap().unwrap();
+    assert_eq!((stats.sent, stats.bytes, stats.completed), (2, 16, true));
+}
+
+#[test]
+fn test_into_channel_applies_backpressure() {
+    let (extractor, read) = counted(archive());
+    let (entries, handle) = extractor.into_channel(2);
+
+    // The consumer is stalled: the reader stops once two entries are queued
+    // and a third is waiting to be sent.
+    thread::sleep(Duration::from_millis(200));
+    let stalled_at = read.load(Ordering::SeqCst);
+    assert!(stalled_at < 5 * 1050, "read {} bytes", stalled_at);
+
+    // A slow consumer gets everything, and the reader never runs far ahead.
+    let mut received = 0;
+    for entry in &entries {
+        entry.unwrap();
+        received += 1;
+        thread::sleep(Duration::from_millis(5));
+        assert!(read.load(Ordering::SeqCst) < (received + 5) * 1050);
+    }
+    assert_eq!(received, 20);
+    assert!(handle.join().unwrap().unwrap().completed);
+}
+
+#[test]
+fn test_into_channel_stops_when_receiver_dropped() {
+    let archive = archive();
+    let len = archive.len();
+    let (extractor, read) = counted(archive);
+    let (entries, handle) = extractor.into_channel(1);
+
+    entries.recv().unwrap().unwrap();
+    drop(entries);
+
+    let stats = handle.join().unwrap().unwrap();
+    assert!(!stats.completed);
+    assert!(stats.sent < 4, "{:?}", stats);
+    assert!(read.load(Ordering::SeqCst) < len / 2);
+}
+
+#[test]
+fn test_into_channel_returns_fatal_error() {
+    let mut archive = archive();
+    archive.truncate(2500);
+    let (entries, handle) = MuyZipido::from_bytes(archive, 256).into_channel(4);
+
+    assert_eq!(entries.iter().filter(Result::is_ok).count(), 2);
+    assert!(matches!(
+        handle.join().unwrap(),
+        Err(e) if matches!(e.root(), ZipError::UnexpectedEofInEntry { .. })
+    ));
+}
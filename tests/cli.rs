@@ -0,0 +1,564 @@
+mod common;
+
+use common::{Response, TestServer, ranged_response};
+use muy_zipido::fixture::ArchiveBuilder;
+use serde_json::Value;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+fn fixture() -> Vec<u8> {
+    ArchiveBuilder::new()
+        .stored("Doc/", b"")
+        .stored("Doc/readme.txt", b"read me")
+        .deflated("data/points.csv", b"x,y\n1,2\n3,4\n")
+        .with_data_descriptor("data/lines.csv", b"a,b\n5,6\n")
+        .build()
+}
+
+fn muyzipido(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_muyzipido"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn assert_entry_schema(entry: &Value) {
+    assert!(entry["name"].is_string());
+    assert!(entry["compressed_size"].is_u64());
+    assert!(entry["uncompressed_size"].is_u64());
+    assert!(entry["method"].is_u64());
+    assert!(entry["crc"].is_u64());
+    assert!(entry["timestamp"].is_string());
+    assert!(entry["is_dir"].is_boolean());
+}
+
+#[test]
+fn test_list_json_is_ndjson() {
+    let server = TestServer::serve(fixture());
+    let output = muyzipido(&["--list", "--json", &server.url("/a.zip")]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let entries: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(entries.len(), 4);
+    entries.iter().for_each(assert_entry_schema);
+    assert_eq!(entries[0]["is_dir"], true);
+    assert_eq!(entries[2]["name"], "data/points.csv");
+    assert_eq!(entries[2]["method"], 8);
+    assert_eq!(entries[3]["name"], "data/lines.csv");
+    assert_eq!(entries[3]["uncompressed_size"], 8);
+}
+
+#[test]
+fn test_list_json_array() {
+    let server = TestServer::serve(fixture());
+    let output = muyzipido(&[
+        "--list",
+        "--json-array",
+        "--include",
+        "**/*.csv",
+        &server.url("/a.zip"),
+    ]);
+    assert!(output.status.success());
+
+    let entries: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    entries.iter().for_each(assert_entry_schema);
+}
+
+#[test]
+fn test_extract_json_report() {
+    let server = TestServer::serve(fixture());
+    let out = tempfile::tempdir().unwrap();
+    let output = muyzipido(&[
+        "--json",
+        "--out",
+        out.path().to_str().unwrap(),
+        &server.url("/a.zip"),
+    ]);
+    assert!(output.status.success());
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["written"].as_array().unwrap().len(), 3);
+    assert_eq!(report["bytes_written"], 7 + 12 + 8);
+    assert!(report["duration_secs"].is_f64());
+    assert!(report["failed"].as_array().unwrap().is_empty());
+    assert!(report["error"].is_null());
+
+    let csv = std::fs::read(out.path().join("data/points.csv")).unwrap();
+    assert_eq!(csv, b"x,y\n1,2\n3,4\n");
+}
+
+#[test]
+fn test_existing_files_are_categorised_in_json_report() {
+    let server = TestServer::serve(fixture());
+    let out = tempfile::tempdir().unwrap();
+    std::fs::create_dir(out.path().join("Doc")).unwrap();
+    std::fs::write(out.path().join("Doc/readme.txt"), b"mine").unwrap();
+
+    let dir = out.path().to_str().unwrap();
+    let url = server.url("/a.zip");
+
+    let output = muyzipido(&["--json", "--dry-run", "--out", dir, &url]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["written"].as_array().unwrap().len(), 2);
+    assert_eq!(report["skipped"][0]["reason"], "already_exists");
+    assert!(!out.path().join("data").exists());
+
+    let output = muyzipido(&["--json", "--error-on-existing", "--out", dir, &url]);
+    assert!(!output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["failed"][0]["filename"], "Doc/readme.txt");
+
+    let output = muyzipido(&["--json", "--overwrite", "--out", dir, &url]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["written"].as_array().unwrap().len(), 3);
+    let readme = std::fs::read(out.path().join("Doc/readme.txt")).unwrap();
+    assert_eq!(readme, b"read me");
+}
+
+#[test]
+fn test_checksum_manifest_round_trip() {
+    let server = TestServer::serve(fixture());
+    let url = server.url("/a.zip");
+    let first = tempfile::tempdir().unwrap();
+    let second = tempfile::tempdir().unwrap();
+
+    let output = muyzipido(&[
+        "-q",
+        "--checksums",
+        "sha256",
+        "--out",
+        first.path().to_str().unwrap(),
+        &url,
+    ]);
+    assert!(output.status.success());
+
+    let manifest_path = first.path().join("SHA256SUMS");
+    let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+    let lines: Vec<&str> = manifest.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].ends_with("  ./Doc/readme.txt"));
+    assert_eq!(lines[0].split("  ").next().unwrap().len(), 64);
+
+    let listed = muyzipido(&["--list", "--checksums", "sha256", &url]);
+    assert!(listed.status.success());
+    assert_eq!(String::from_utf8(listed.stdout).unwrap(), manifest);
+
+    let verify = |dir: &std::path::Path| {
+        muyzipido(&[
+            "--json",
+            "--verify",
+            manifest_path.to_str().unwrap(),
+            "--overwrite",
+            "--out",
+            dir.to_str().unwrap(),
+            &url,
+        ])
+    };
+
+    let output = verify(second.path());
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["verification"]["verified"], 3);
+
+    // Files already on disk are skipped and checked as they stand.
+    let output = muyzipido(&[
+        "--json",
+        "--verify",
+        manifest_path.to_str().unwrap(),
+        "--out",
+        first.path().to_str().unwrap(),
+        &url,
+    ]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["skipped"].as_array().unwrap().len(), 3);
+    assert_eq!(report["verification"]["verified"], 3);
+    assert_eq!(
+        report["verification"]["missing"].as_array().unwrap().len(),
+        0
+    );
+
+    let tampered = manifest.replacen(&lines[0][..4], "0000", 1);
+    std::fs::write(
+        &manifest_path,
+        tampered + "0000000000000000000000000000000000000000000000000000000000000000  ./gone.txt\n",
+    )
+    .unwrap();
+
+    let output = verify(second.path());
+    assert!(!output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["verification"]["mismatched"][0], "Doc/readme.txt");
+    assert_eq!(report["verification"]["missing"][0], "gone.txt");
+}
+
+#[test]
+fn test_cat_writes_entry_bytes_to_stdout() {
+    let server = TestServer::serve(fixture());
+
+    let output = muyzipido(&["--cat", "data/points.csv", &server.url("/a.zip")]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"x,y\n1,2\n3,4\n");
+
+    let output = muyzipido(&["--cat-first-match", "data/*.csv", &server.url("/a.zip")]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"x,y\n1,2\n3,4\n");
+}
+
+#[test]
+fn test_cat_missing_entry_exits_with_2() {
+    let server = TestServer::serve(fixture());
+
+    let output = muyzipido(&["--cat", "nope.csv", &server.url("/a.zip")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("nope.csv"));
+}
+
+#[test]
+fn test_validate() {
+    let server = TestServer::serve(fixture());
+    let output = muyzipido(&["--validate", "--no-progress", &server.url("/a.zip")]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "4 entries checked, 0 with problems\n"
+    );
+
+    let bad = ArchiveBuilder::new()
+        .stored("ok.txt", b"fine")
+        .stored("../escape.txt", b"sneaky")
+        .build();
+    let server = TestServer::serve(bad);
+    let output = muyzipido(&["--validate", "--no-progress", &server.url("/a.zip")]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("FAIL ../escape.txt: Unsafe path"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.ends_with("2 entries checked, 1 with problems\n"));
+
+    let output = muyzipido(&["--validate", "--json", &server.url("/a.zip")]);
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(report["entries"][0]["problems"], serde_json::json!([]));
+}
+
+#[test]
+fn test_color_never_strips_ansi_from_progress() {
+    let server = TestServer::serve(fixture());
+    let out = tempfile::tempdir().unwrap();
+    let dir = out.path().to_str().unwrap();
+
+    let output = muyzipido(&["--color", "always", "--out", dir, &server.url("/a.zip")]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Downloading ZIP"));
+    assert!(stderr.contains("\x1b["));
+
+    let output = muyzipido(&["--color", "never", "--out", dir, &server.url("/a.zip")]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Downloading ZIP"));
+    assert!(!stderr.contains("\x1b["));
+}
+
+#[test]
+fn test_no_progress() {
+    let server = TestServer::serve(fixture());
+    let out = tempfile::tempdir().unwrap();
+
+    let output = muyzipido(&[
+        "--no-progress",
+        "--out",
+        out.path().to_str().unwrap(),
+        &server.url("/a.zip"),
+    ]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Downloading ZIP"));
+}
+
+fn batch_server() -> TestServer {
+    TestServer::start(|request| match request.path.as_str() {
+        "/download?id=1" => Response::ok(fixture())
+            .with_header("Content-Disposition", "attachment; filename=\"roads.zip\""),
+        "/rail.zip" => Response::ok(ArchiveBuilder::new().stored("rail.txt", b"choo").build()),
+        _ => Response::status(404, b"not found".to_vec()),
+    })
+}
+
+#[test]
+fn test_batch_extracts_into_subdirectories() {
+    let server = batch_server();
+    let out = tempfile::tempdir().unwrap();
+    let list = out.path().join("urls.txt");
+    std::fs::write(&list, format!("# nightly\n\n{}\n", server.url("/rail.zip"))).unwrap();
+
+    let output = muyzipido(&[
+        "--json",
+        "--out",
+        out.path().to_str().unwrap(),
+        &server.url("/download?id=1"),
+        "--urls-from",
+        list.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    assert!(out.path().join("roads/data/points.csv").is_file());
+    assert_eq!(
+        std::fs::read(out.path().join("rail/rail.txt")).unwrap(),
+        b"choo"
+    );
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["archives"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        report["archives"][1]["written"].as_array().unwrap().len(),
+        1
+    );
+    assert_eq!(report["totals"]["files_written"], 4);
+    assert_eq!(report["totals"]["failed_archives"], 0);
+}
+
+#[test]
+fn test_batch_name_template() {
+    let server = batch_server();
+    let out = tempfile::tempdir().unwrap();
+
+    let output = muyzipido(&[
+        "-q",
+        "--name-template",
+        "{index}-{filename}",
+        "--out",
+        out.path().to_str().unwrap(),
+        &server.url("/download?id=1"),
+        &server.url("/rail.zip"),
+    ]);
+    assert!(output.status.success());
+    assert!(out.path().join("1-roads/Doc/readme.txt").is_file());
+    assert!(out.path().join("2-rail/rail.txt").is_file());
+}
+
+#[test]
+fn test_batch_failure_does_not_abort_unless_fail_fast() {
+    let server = batch_server();
+    let out = tempfile::tempdir().unwrap();
+    let dir = out.path().to_str().unwrap();
+    let missing = server.url("/missing.zip");
+    let rail = server.url("/rail.zip");
+
+    let output = muyzipido(&["--json", "--out", dir, &missing, &rail]);
+    assert!(!output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report["archives"][0]["error"].is_string());
+    assert_eq!(report["totals"]["failed_archives"], 1);
+    assert!(out.path().join("rail/rail.txt").is_file());
+
+    let before = server.requests().len();
+    let output = muyzipido(&["-q", "--fail-fast", "--out", dir, &missing, &rail]);
+    assert!(!output.status.success());
+    assert_eq!(server.requests().len(), before + 1);
+}
+
+#[test]
+fn test_parser_diagnostics_need_double_verbose() {
+    let server = TestServer::serve(fixture());
+    let out = tempfile::tempdir().unwrap();
+    let dir = out.path().to_str().unwrap();
+
+    let output = muyzipido(&["--no-progress", "--out", dir, &server.url("/a.zip")]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Processing:"));
+
+    let output = muyzipido(&[
+        "--no-progress",
+        "-vv",
+        "--overwrite",
+        "--out",
+        dir,
+        &server.url("/a.zip"),
+    ]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Processing: data/points.csv"));
+}
+
+#[test]
+fn test_resume_after_interrupt() {
+    let payload = |byte: u8| vec![byte; 40_000];
+    let archive = ArchiveBuilder::new()
+        .stored("a.bin", &payload(b'a'))
+        .stored("b.bin", &payload(b'b'))
+        .stored("c.bin", &payload(b'c'))
+        .build();
+    let server = TestServer::start(move |request| {
+        ranged_response(request, &archive, "\"v1\"", true)
+            .throttled(2048, Duration::from_millis(20))
+    });
+
+    let out = tempfile::tempdir().unwrap();
+    let state = out.path().join("state.json");
+    let extract_dir = out.path().join("data");
+    let url = server.url("/big.zip");
+    let args = [
+        "-q",
+        "--no-progress",
+        "--chunk-size",
+        "1024",
+        "--resume",
+        state.to_str().unwrap(),
+        "--out",
+        extract_dir.to_str().unwrap(),
+        &url,
+    ];
+
+    let child = Command::new(env!("CARGO_BIN_EXE_muyzipido"))
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !extract_dir.join("a.bin").exists() {
+        assert!(Instant::now() < deadline, "first entry was never written");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let status = Command::new("kill")
+        .args(["-s", "INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+
+    let saved: Value = serde_json::from_str(&std::fs::read_to_string(&state).unwrap()).unwrap();
+    let offset = saved["offset"].as_u64().unwrap();
+    assert!(offset > 0);
+    assert_eq!(saved["etag"], "\"v1\"");
+    assert_eq!(saved["completed"][0], "a.bin");
+    assert!(!extract_dir.join("c.bin").exists());
+
+    let output = muyzipido(&args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!state.exists());
+    for (name, byte) in [("a.bin", b'a'), ("b.bin", b'b'), ("c.bin", b'c')] {
+        assert_eq!(
+            std::fs::read(extract_dir.join(name)).unwrap(),
+            payload(byte)
+        );
+    }
+
+    let last = server.requests().pop().unwrap();
+    assert_eq!(
+        last.header("Range"),
+        Some(format!("bytes={}-", offset).as_str())
+    );
+    assert_eq!(last.header("If-Range"), Some("\"v1\""));
+}
+
+#[test]
+fn test_resume_without_range_support_starts_over() {
+    let archive = fixture();
+    let server =
+        TestServer::start(move |request| ranged_response(request, &archive, "\"v1\"", false));
+    let url = server.url("/a.zip");
+
+    let out = tempfile::tempdir().unwrap();
+    let state = out.path().join("state.json");
+    let state_json = serde_json::json!({
+        "url": url,
+        "etag": "\"v1\"",
+        "offset": 60,
+        "completed": ["Doc/readme.txt"],
+    });
+    std::fs::write(&state, state_json.to_string()).unwrap();
+
+    let dir = out.path().join("data");
+    let output = muyzipido(&[
+        "--no-progress",
+        "--resume",
+        state.to_str().unwrap(),
+        "--out",
+        dir.to_str().unwrap(),
+        &url,
+    ]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not support range requests"));
+
+    assert!(!dir.join("Doc/readme.txt").exists());
+    assert!(dir.join("data/points.csv").is_file());
+    assert!(!state.exists());
+}
+
+#[test]
+fn test_gen_fixture() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fixture.zip");
+    let generate = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_gen_fixture"))
+            .args(args)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        muy_zipido::MuyZipido::from_bytes(std::fs::read(&path).unwrap(), 64)
+    };
+
+    for args in [
+        &[][..],
+        &["--method", "stored", "--entries", "5"],
+        &["--data-descriptor", "--size", "5000"],
+        &["--zip64", "--comment", "generated"],
+    ] {
+        let mut extractor = generate(args);
+        let entries: Vec<_> = extractor.by_ref().map(|entry| entry.unwrap()).collect();
+        let expected = if args.contains(&"5") { 5 } else { 3 };
+        assert_eq!(entries.len(), expected, "{:?}", args);
+        assert_eq!(entries[0].filename, "entry-001.txt");
+        let size = if args.contains(&"5000") { 5000 } else { 1024 };
+        assert!(entries.iter().all(|entry| entry.data.len() == size));
+        assert_eq!(
+            extractor.zip64_eocd().is_some(),
+            args.contains(&"--zip64"),
+            "{:?}",
+            args
+        );
+        assert!(
+            extractor.warnings().is_empty(),
+            "{:?}",
+            extractor.warnings()
+        );
+    }
+
+    // Properties the reader does not handle yet, checked in the bytes.
+    generate(&["--data-descriptor", "--unsigned-descriptors"]);
+    let archive = std::fs::read(&path).unwrap();
+    assert!(!archive.windows(4).any(|window| window == b"PK\x07\x08"));
+    generate(&["--encrypted"]);
+    let archive = std::fs::read(&path).unwrap();
+    assert_eq!(archive[6] & 0x01, 0x01);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_gen_fixture"))
+        .args(["--unsigned-descriptors"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,254 @@
+#![allow(dead_code)]
+
+use std::io::Write;
+
+/// A cloneable in-memory writer so tests can inspect what was written after
+/// handing ownership of a writer to the library.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(pub std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A request as seen by [`TestServer`].
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A canned response for [`TestServer`].
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Write the body in pieces of this size, sleeping between them.
+    pub throttle: Option<(usize, std::time::Duration)>,
+    /// Hang up after this many body bytes, despite the full Content-Length.
+    pub cut_off: Option<usize>,
+}
+
+impl Response {
+    pub fn ok(body: Vec<u8>) -> Self {
+        Self::status(200, body)
+    }
+
+    pub fn status(status: u16, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body,
+            throttle: None,
+            cut_off: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn cut_off(mut self, bytes: usize) -> Self {
+        self.cut_off = Some(bytes);
+        self
+    }
+
+    pub fn throttled(mut self, piece: usize, delay: std::time::Duration) -> Self {
+        self.throttle = Some((piece, delay));
+        self
+    }
+}
+
+/// Answers with `archive` and an `ETag` of `etag`, honouring
+/// `Range: bytes=N-` (and any `If-Range`) with a 206 when `ranges` is true.
+pub fn ranged_response(request: &Request, archive: &[u8], etag: &str, ranges: bool) -> Response {
+    let start = request
+        .header("Range")
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+        .filter(|_| ranges && request.header("If-Range").is_none_or(|tag| tag == etag));
+
+    match start {
+        Some(start) => Response::status(206, archive[start..].to_vec()).with_header(
+            "Content-Range",
+            &format!("bytes {}-{}/{}", start, archive.len() - 1, archive.len()),
+        ),
+        None => Response::ok(archive.to_vec()),
+    }
+    .with_header("ETag", etag)
+}
+
+/// A minimal HTTP/1.1 server on localhost for exercising the network paths.
+pub struct TestServer {
+    addr: std::net::SocketAddr,
+    requests: std::sync::Arc<std::sync::Mutex<Vec<Request>>>,
+    active: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    sent: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl TestServer {
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::{Arc, Mutex};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let active = Arc::new(AtomicUsize::new(0));
+        let sent = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+
+        let seen = Arc::clone(&requests);
+        let connections = Arc::clone(&active);
+        let body_bytes = Arc::clone(&sent);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { break };
+                let handler = Arc::clone(&handler);
+                let seen = Arc::clone(&seen);
+                let connections = Arc::clone(&connections);
+                let body_bytes = Arc::clone(&body_bytes);
+                connections.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    handle_connection(stream, &*handler, &seen, &body_bytes);
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        Self {
+            addr,
+            requests,
+            active,
+            sent,
+        }
+    }
+
+    /// Serves `body` with status 200 for every request.
+    pub fn serve(body: Vec<u8>) -> Self {
+        Self::start(move |_| Response::ok(body.clone()))
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Connections still being served.
+    pub fn active_connections(&self) -> usize {
+        self.active.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Response body bytes handed to the socket so far, across all
+    /// connections. Throttled responses stop counting soon after the client
+    /// hangs up.
+    pub fn bytes_sent(&self) -> usize {
+        self.sent.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+fn handle_connection(
+    mut stream: std::net::TcpStream,
+    handler: &(dyn Fn(&Request) -> Response + Send + Sync),
+    seen: &std::sync::Mutex<Vec<Request>>,
+    sent: &std::sync::atomic::AtomicUsize,
+) {
+    use std::io::{BufRead, BufReader};
+    use std::sync::atomic::Ordering;
+
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+        return;
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let request = Request {
+        method,
+        path,
+        headers,
+    };
+    seen.lock().unwrap().push(request.clone());
+    let response = handler(&request);
+
+    let mut head = format!(
+        "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+
+    let body = &response.body[..response.cut_off.unwrap_or(response.body.len())];
+    match response.throttle {
+        Some((piece, delay)) => {
+            for chunk in body.chunks(piece) {
+                if stream.write_all(chunk).is_err() {
+                    return;
+                }
+                sent.fetch_add(chunk.len(), Ordering::SeqCst);
+                std::thread::sleep(delay);
+            }
+        }
+        None => {
+            if stream.write_all(body).is_ok() {
+                sent.fetch_add(body.len(), Ordering::SeqCst);
+            }
+        }
+    }
+    let _ = stream.flush();
+}
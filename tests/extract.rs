@@ -0,0 +1,751 @@
+mod common;
+
+use common::SharedBuffer;
+use muy_zipido::extract::{DuplicatePolicy, ExtractOptions, OverwritePolicy, SkipReason};
+use muy_zipido::fixture::ArchiveBuilder;
+use muy_zipido::{MuyZipido, ZipError, ZipWarning};
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::time::UNIX_EPOCH;
+
+fn fixture() -> MuyZipido {
+    let archive = ArchiveBuilder::new()
+        .stored("existing.txt", b"from archive")
+        .deflated("data/new.csv", b"x,y\n1,2\n")
+        .with_data_descriptor("data/streamed.csv", b"a,b\n3,4\n")
+        .build();
+    MuyZipido::from_reader(Cursor::new(archive), 16)
+}
+
+/// An output directory that already holds `existing.txt`.
+fn populated_dir() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("existing.txt"), b"on disk").unwrap();
+    dir
+}
+
+#[test]
+fn test_skip_existing_is_default() {
+    let dir = populated_dir();
+    let report = fixture().extract_to_dir(dir.path()).unwrap();
+
+    assert_eq!(report.written.len(), 2);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].filename, "existing.txt");
+    assert_eq!(report.skipped[0].reason, SkipReason::AlreadyExists);
+    assert!(report.failed.is_empty());
+    assert_eq!(
+        fs::read(dir.path().join("existing.txt")).unwrap(),
+        b"on disk"
+    );
+    assert_eq!(
+        fs::read(dir.path().join("data/streamed.csv")).unwrap(),
+        b"a,b\n3,4\n"
+    );
+}
+
+#[test]
+fn test_overwrite_replaces_existing() {
+    let dir = populated_dir();
+    let options = ExtractOptions::new().with_overwrite(OverwritePolicy::Overwrite);
+    let report = fixture().extract_to_dir_with(dir.path(), &options).unwrap();
+
+    assert_eq!(report.written.len(), 3);
+    assert!(report.skipped.is_empty());
+    assert_eq!(
+        fs::read(dir.path().join("existing.txt")).unwrap(),
+        b"from archive"
+    );
+}
+
+#[test]
+fn test_error_on_existing_records_failure_and_continues() {
+    let dir = populated_dir();
+    let options = ExtractOptions::new().with_overwrite(OverwritePolicy::Error);
+    let report = fixture().extract_to_dir_with(dir.path(), &options).unwrap();
+
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].filename, "existing.txt");
+    assert_eq!(report.written.len(), 2);
+    assert_eq!(
+        fs::read(dir.path().join("existing.txt")).unwrap(),
+        b"on disk"
+    );
+}
+
+#[test]
+fn test_rename_keeps_every_copy_across_archives() {
+    let archive = |n: u8| {
+        let archive = ArchiveBuilder::new()
+            .stored("readme.txt", &[b'0' + n])
+            .deflated("data/points.csv", &[b'0' + n])
+            .stored(&format!("only-in-{}.txt", n), b"")
+            .build();
+        MuyZipido::from_reader(Cursor::new(archive), 16)
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExtractOptions::new().with_overwrite(OverwritePolicy::Rename);
+
+    let mapping = |n| {
+        let report = archive(n)
+            .extract_to_dir_with(dir.path(), &options)
+            .unwrap();
+        assert!(report.skipped.is_empty() && report.failed.is_empty());
+        report
+            .written
+            .iter()
+            .map(|file| {
+                let path = file.path.strip_prefix(dir.path()).unwrap();
+                (file.filename.clone(), path.to_str().unwrap().to_string())
+            })
+            .collect::<Vec<_>>()
+    };
+    let pair = |filename: &str, path: &str| (filename.to_string(), path.to_string());
+
+    assert_eq!(
+        mapping(1),
+        [
+            pair("readme.txt", "readme.txt"),
+            pair("data/points.csv", "data/points.csv"),
+            pair("only-in-1.txt", "only-in-1.txt"),
+        ]
+    );
+    assert_eq!(
+        mapping(2),
+        [
+            pair("readme.txt", "readme (1).txt"),
+            pair("data/points.csv", "data/points (1).csv"),
+            pair("only-in-2.txt", "only-in-2.txt"),
+        ]
+    );
+    assert_eq!(
+        mapping(3)[..2],
+        [
+            pair("readme.txt", "readme (2).txt"),
+            pair("data/points.csv", "data/points (2).csv"),
+        ]
+    );
+
+    for (name, contents) in [
+        ("readme.txt", b"1"),
+        ("readme (1).txt", b"2"),
+        ("readme (2).txt", b"3"),
+        ("data/points (1).csv", b"2"),
+    ] {
+        assert_eq!(
+            fs::read(dir.path().join(name)).unwrap(),
+            contents,
+            "{}",
+            name
+        );
+    }
+
+    // A dry run picks the name a real run would.
+    let report = archive(4)
+        .extract_to_dir_with(dir.path(), &options.clone().with_dry_run(true))
+        .unwrap();
+    assert_eq!(report.written[0].path, dir.path().join("readme (3).txt"));
+    assert!(!dir.path().join("readme (3).txt").exists());
+}
+
+#[test]
+fn test_dry_run_writes_nothing() {
+    let dir = populated_dir();
+    let options = ExtractOptions::new().with_dry_run(true);
+    let report = fixture().extract_to_dir_with(dir.path(), &options).unwrap();
+
+    assert!(report.dry_run);
+    let planned: Vec<_> = report
+        .written
+        .iter()
+        .map(|file| (file.path.strip_prefix(dir.path()).unwrap(), file.size))
+        .collect();
+    assert_eq!(
+        planned,
+        vec![
+            (std::path::Path::new("data/new.csv"), 8),
+            (std::path::Path::new("data/streamed.csv"), 8),
+        ]
+    );
+    assert_eq!(report.bytes_written, 16);
+    assert_eq!(report.skipped[0].reason, SkipReason::AlreadyExists);
+    assert!(!dir.path().join("data").exists());
+
+    let missing = dir.path().join("not-created");
+    fixture().extract_to_dir_with(&missing, &options).unwrap();
+    assert!(!missing.exists());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_cache_dir_skips_unchanged_entries() {
+    let archive = |csv: &[u8]| {
+        let archive = ArchiveBuilder::new()
+            .stored("existing.txt", b"from archive")
+            .deflated("data/new.csv", csv)
+            .with_data_descriptor("data/streamed.csv", b"a,b\n3,4\n")
+            .build();
+        MuyZipido::from_reader(Cursor::new(archive), 16)
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    let options = ExtractOptions::new()
+        .with_overwrite(OverwritePolicy::Overwrite)
+        .with_cache_dir(cache.path());
+
+    let report = archive(b"x,y\n1,2\n")
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    assert_eq!(report.written.len(), 3);
+    assert_eq!(report.unchanged(), 0);
+
+    let report = archive(b"x,y\n1,2\n")
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    assert!(report.written.is_empty());
+    assert_eq!(report.bytes_written, 0);
+    assert_eq!(report.unchanged(), 3);
+
+    let report = archive(b"x,y\n5,6\n7,8\n")
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    let written: Vec<_> = report.written.iter().map(|f| f.filename.as_str()).collect();
+    assert_eq!(written, ["data/new.csv"]);
+    assert_eq!(report.unchanged(), 2);
+    assert_eq!(
+        fs::read(dir.path().join("data/new.csv")).unwrap(),
+        b"x,y\n5,6\n7,8\n"
+    );
+
+    // A file removed from the output directory is written again.
+    fs::remove_file(dir.path().join("existing.txt")).unwrap();
+    let report = archive(b"x,y\n5,6\n7,8\n")
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    let written: Vec<_> = report.written.iter().map(|f| f.filename.as_str()).collect();
+    assert_eq!(written, ["existing.txt"]);
+}
+
+#[test]
+fn test_keep_going_records_crc_mismatch() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("bad.txt", b"hello")
+        .stored("good.txt", b"world")
+        .build();
+    let at = archive.windows(5).position(|w| w == b"hello").unwrap();
+    archive[at] = b'j';
+
+    let dir = tempfile::tempdir().unwrap();
+    let result =
+        MuyZipido::from_reader(Cursor::new(archive.clone()), 16).extract_to_dir(dir.path());
+    assert!(matches!(
+        result.map_err(|e| e.root().to_string()),
+        Err(message) if message.starts_with("CRC-32 mismatch in bad.txt")
+    ));
+    assert!(!dir.path().join("good.txt").exists());
+
+    let options = ExtractOptions::new().with_keep_going(true);
+    let report = MuyZipido::from_reader(Cursor::new(archive.clone()), 16)
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    assert_eq!(report.failed[0].filename, "bad.txt");
+    assert!(!dir.path().join("bad.txt").exists());
+    assert_eq!(fs::read(dir.path().join("good.txt")).unwrap(), b"world");
+
+    // A corrupt entry leaves the file it would have replaced alone, and no
+    // partial file behind.
+    fs::write(dir.path().join("bad.txt"), b"on disk").unwrap();
+    let options = ExtractOptions::new()
+        .with_keep_going(true)
+        .with_overwrite(OverwritePolicy::Overwrite);
+    let report = MuyZipido::from_reader(Cursor::new(archive), 16)
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    assert_eq!(report.failed[0].filename, "bad.txt");
+    assert_eq!(fs::read(dir.path().join("bad.txt")).unwrap(), b"on disk");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_save_to_writes_atomically_with_mtime() {
+    let archive = ArchiveBuilder::new()
+        .stored("dir/", b"")
+        .deflated("dir/nested/b.csv", b"x,y\n1,2\n")
+        .build();
+    let out = tempfile::tempdir().unwrap();
+
+    for entry in MuyZipido::from_bytes(archive, 64) {
+        let entry = entry.unwrap();
+        entry.save_to(out.path().join(&entry.filename)).unwrap();
+    }
+
+    assert!(out.path().join("dir").is_dir());
+    let target = out.path().join("dir/nested/b.csv");
+    assert_eq!(fs::read(&target).unwrap(), b"x,y\n1,2\n");
+
+    // The fixture stamps entries 1980-01-01 00:00:00.
+    let modified = fs::metadata(&target).unwrap().modified().unwrap();
+    let secs = modified.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(secs, 315_532_800);
+
+    let leftovers: Vec<_> = fs::read_dir(out.path().join("dir/nested"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(leftovers, ["b.csv"]);
+}
+
+fn duplicates_fixture() -> MuyZipido {
+    let archive = ArchiveBuilder::new()
+        .stored("data.csv", b"old")
+        .deflated("readme.txt", b"hello")
+        .with_data_descriptor("data.csv", b"new")
+        .build();
+    MuyZipido::from_reader(Cursor::new(archive), 16)
+}
+
+#[test]
+fn test_duplicate_policies_when_extracting() {
+    let extract = |policy| {
+        let dir = tempfile::tempdir().unwrap();
+        let result = duplicates_fixture()
+            .with_duplicates(policy)
+            .extract_to_dir(dir.path());
+        let on_disk = fs::read(dir.path().join("data.csv")).ok();
+        (result, on_disk)
+    };
+
+    // Without a policy the default overwrite policy keeps the first copy.
+    let (report, on_disk) = extract(DuplicatePolicy::KeepAll);
+    assert_eq!(on_disk.unwrap(), b"old");
+    assert_eq!(report.unwrap().skipped[0].reason, SkipReason::AlreadyExists);
+
+    let (report, on_disk) = extract(DuplicatePolicy::KeepFirst);
+    assert_eq!(on_disk.unwrap(), b"old");
+    let report = report.unwrap();
+    assert_eq!(report.written.len(), 2);
+    assert_eq!(report.skipped[0].filename, "data.csv");
+    assert_eq!(report.skipped[0].reason, SkipReason::Duplicate);
+
+    let (report, on_disk) = extract(DuplicatePolicy::KeepLast);
+    assert_eq!(on_disk.unwrap(), b"new");
+    let report = report.unwrap();
+    assert_eq!(report.written.len(), 2);
+    assert_eq!(report.bytes_written, 8);
+    assert_eq!(report.skipped[0].reason, SkipReason::Duplicate);
+
+    let (result, on_disk) = extract(DuplicatePolicy::Error);
+    let Err(ZipError::DuplicateEntry { filename }) = result else {
+        panic!("expected a duplicate entry error");
+    };
+    assert_eq!(filename, "data.csv");
+    assert_eq!(on_disk.unwrap(), b"old");
+
+    let dir = tempfile::tempdir().unwrap();
+    let report = duplicates_fixture()
+        .with_duplicates(DuplicatePolicy::Warn)
+        .extract_to_dir(dir.path())
+        .unwrap();
+    assert_eq!(report.skipped[0].reason, SkipReason::AlreadyExists);
+    assert_eq!(
+        report.warnings,
+        [ZipWarning::DuplicateEntry {
+            filename: "data.csv".to_string()
+        }]
+    );
+}
+
+#[test]
+fn test_duplicate_policies_when_iterating() {
+    let names = |policy| -> Vec<(String, Vec<u8>)> {
+        duplicates_fixture()
+            .with_duplicates(policy)
+            .map(|entry| entry.map(|e| (e.info.filename, e.data)).unwrap())
+            .collect()
+    };
+    assert_eq!(names(DuplicatePolicy::KeepAll).len(), 3);
+    assert_eq!(names(DuplicatePolicy::KeepLast).len(), 3);
+    assert_eq!(names(DuplicatePolicy::Warn).len(), 3);
+    assert_eq!(
+        names(DuplicatePolicy::KeepFirst),
+        [
+            ("data.csv".to_string(), b"old".to_vec()),
+            ("readme.txt".to_string(), b"hello".to_vec())
+        ]
+    );
+
+    let mut extractor = duplicates_fixture().with_duplicates(DuplicatePolicy::Error);
+    assert!(extractor.next().unwrap().is_ok());
+    assert!(extractor.next().unwrap().is_ok());
+    assert!(matches!(
+        extractor.next(),
+        Some(Err(ZipError::DuplicateEntry { .. }))
+    ));
+    assert!(extractor.next().is_none());
+
+    let listing: Vec<(String, u64)> = duplicates_fixture()
+        .with_duplicates(DuplicatePolicy::KeepLast)
+        .list_entries()
+        .map(|info| info.map(|i| (i.filename, i.compressed_size)).unwrap())
+        .collect();
+    assert_eq!(listing[0].0, "readme.txt");
+    assert_eq!(listing[1].0, "data.csv");
+    assert_eq!(listing.len(), 2);
+}
+
+#[test]
+fn test_symlink_entries() {
+    let archive = ArchiveBuilder::new()
+        .stored("v2/data.csv", b"x,y\n")
+        .symlink("latest.csv", "v2/data.csv")
+        .symlink("escape", "../outside")
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_bytes(archive.clone(), 16)
+        .map(Result::unwrap)
+        .collect();
+    assert!(!entries[0].is_symlink());
+    assert_eq!(entries[0].symlink_target(), None);
+    assert!(entries[1].is_symlink());
+    assert_eq!(entries[1].unix_mode, Some(0o120777));
+    assert_eq!(entries[1].symlink_target(), Some("v2/data.csv"));
+
+    // By default links are written as files holding the target.
+    let dir = tempfile::tempdir().unwrap();
+    MuyZipido::from_bytes(archive.clone(), 16)
+        .extract_to_dir(dir.path())
+        .unwrap();
+    let link = dir.path().join("latest.csv");
+    assert!(!link.symlink_metadata().unwrap().is_symlink());
+    assert_eq!(fs::read(&link).unwrap(), b"v2/data.csv");
+
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExtractOptions::new().with_allow_symlinks(true);
+    let report = MuyZipido::from_bytes(archive, 16)
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].filename, "escape");
+    assert_eq!(report.skipped[0].reason, SkipReason::UnsafePath);
+    assert!(!dir.path().join("escape").exists());
+
+    #[cfg(unix)]
+    {
+        let link = dir.path().join("latest.csv");
+        assert!(link.symlink_metadata().unwrap().is_symlink());
+        assert_eq!(
+            fs::read_link(&link).unwrap(),
+            std::path::Path::new("v2/data.csv")
+        );
+        assert_eq!(fs::read(&link).unwrap(), b"x,y\n");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinks_through_earlier_links_stay_inside() {
+    let archive = ArchiveBuilder::new()
+        .symlink("a/b/l", "../..")
+        .symlink("a/b/l2", "l/..")
+        .stored("a/b/l2/escaped.txt", b"pwned")
+        .build();
+
+    let parent = tempfile::tempdir().unwrap();
+    let dir = parent.path().join("out");
+    let options = ExtractOptions::new().with_allow_symlinks(true);
+    let report = MuyZipido::from_bytes(archive, 16)
+        .extract_to_dir_with(&dir, &options)
+        .unwrap();
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].filename, "a/b/l2");
+    assert_eq!(report.skipped[0].reason, SkipReason::UnsafePath);
+    assert!(!parent.path().join("escaped.txt").exists());
+    assert_eq!(fs::read(dir.join("a/b/l2/escaped.txt")).unwrap(), b"pwned");
+
+    // Links already in the output directory are followed too.
+    std::os::unix::fs::symlink(parent.path(), dir.join("up")).unwrap();
+    let archive = ArchiveBuilder::new()
+        .stored("up/escaped.txt", b"pwned")
+        .build();
+    let report = MuyZipido::from_bytes(archive, 16)
+        .extract_to_dir(&dir)
+        .unwrap();
+    assert!(report.written.is_empty());
+    assert_eq!(report.skipped[0].reason, SkipReason::UnsafePath);
+    assert!(!parent.path().join("escaped.txt").exists());
+}
+
+#[test]
+fn test_preserve_mtime_and_permissions() {
+    let archive = ArchiveBuilder::new()
+        .with_unix_mode(0o40775)
+        .with_mtime(1_600_000_000)
+        .stored("bin/", b"")
+        .with_unix_mode(0o100777)
+        .with_mtime(1_700_000_000)
+        .stored("bin/run.sh", b"#!/bin/sh\n")
+        .with_unix_mode(0o100600)
+        .deflated("bin/secret.txt", b"hunter2")
+        .build();
+
+    let dir = tempfile::tempdir().unwrap();
+    let options = ExtractOptions::new()
+        .with_preserve_mtime(true)
+        .with_preserve_permissions(true);
+    let report = MuyZipido::from_bytes(archive, 16)
+        .extract_to_dir_with(dir.path(), &options)
+        .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+
+    let mtime = |path: &str| {
+        fs::metadata(dir.path().join(path))
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    };
+    assert_eq!(mtime("bin"), 1_600_000_000);
+    assert_eq!(mtime("bin/run.sh"), 1_700_000_000);
+    // Without an extended timestamp the DOS time (1980-01-01) is used.
+    assert_eq!(mtime("bin/secret.txt"), 315_532_800);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = |path: &str| {
+            fs::metadata(dir.path().join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+        assert_eq!(mode("bin"), 0o755);
+        assert_eq!(mode("bin/run.sh"), 0o755);
+        assert_eq!(mode("bin/secret.txt"), 0o600);
+    }
+}
+
+#[test]
+fn test_process_with_routes_entries_to_sinks() {
+    /// Accepts `limit` bytes, then fails.
+    struct Full {
+        limit: usize,
+    }
+
+    impl Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.limit == 0 {
+                return Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"));
+            }
+            let len = buf.len().min(self.limit);
+            self.limit -= len;
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let big: Vec<u8> = (0..200_000u32).flat_map(|i| i.to_le_bytes()).collect();
+    let archive = ArchiveBuilder::new()
+        .stored("meta.json", br#"{"rows": 2}"#)
+        .deflated("points.csv", &big)
+        .stored("skip.bin", &[0u8; 512])
+        .deflated("full.txt", &[b'x'; 4096])
+        .with_data_descriptor("streamed.json", b"[1, 2, 3]")
+        .build();
+
+    let dir = tempfile::tempdir().unwrap();
+    let memory = SharedBuffer::default();
+    let report = {
+        let memory = memory.clone();
+        let dir = dir.path().to_path_buf();
+        MuyZipido::from_bytes(archive, 1000)
+            .process_with(move |info| {
+                let sink: Box<dyn Write> = match info.filename.as_str() {
+                    "skip.bin" => return Ok(None),
+                    "points.csv" => Box::new(fs::File::create(dir.join("points.csv"))?),
+                    "full.txt" => Box::new(Full { limit: 100 }),
+                    _ => Box::new(memory.clone()),
+                };
+                Ok(Some(sink))
+            })
+            .unwrap()
+    };
+
+    assert_eq!(report.skipped, ["skip.bin"]);
+    let summary: Vec<(&str, u64, bool)> = report
+        .processed
+        .iter()
+        .map(|e| (e.filename.as_str(), e.bytes_written, e.error.is_some()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("meta.json", 11, false),
+            ("points.csv", big.len() as u64, false),
+            ("full.txt", 100, true),
+            ("streamed.json", 9, false),
+        ]
+    );
+    assert!(
+        report.processed[2]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("disk full")
+    );
+    assert_eq!(memory.contents(), br#"{"rows": 2}[1, 2, 3]"#);
+    assert_eq!(fs::read(dir.path().join("points.csv")).unwrap(), big);
+}
+
+#[test]
+fn test_process_with_reports_corrupt_entries() {
+    let mut archive = ArchiveBuilder::new()
+        .deflated("bad.txt", b"hello hello hello")
+        .stored("good.txt", b"fine")
+        .build();
+    // Corrupt the CRC-32 in the first local header.
+    archive[14] ^= 0xff;
+
+    let report = MuyZipido::from_bytes(archive, 16)
+        .process_with(|_| Ok(Some(Box::new(io::sink()) as Box<dyn Write>)))
+        .unwrap();
+    assert!(
+        report.processed[0]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("CRC-32")
+    );
+    assert_eq!(report.processed[1].error, None);
+    assert_eq!(report.bytes_written, 17 + 4);
+}
+
+#[test]
+fn test_validate_clean_archive() {
+    let report = fixture().validate().unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.findings().count(), 0);
+    let sizes: Vec<(&str, u64)> = report
+        .entries
+        .iter()
+        .map(|entry| (entry.filename.as_str(), entry.size))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            ("existing.txt", 12),
+            ("data/new.csv", 8),
+            ("data/streamed.csv", 8)
+        ]
+    );
+}
+
+#[test]
+fn test_validate_reports_each_bad_entry() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("bad.txt", b"hello")
+        .deflated("ok.csv", b"x,y\n1,2\n")
+        .stored("../escape.txt", b"sneaky")
+        .stored("fine.txt", b"fine")
+        .build();
+    archive[30 + 7] = b'j';
+
+    let report = MuyZipido::from_reader(Cursor::new(archive), 16)
+        .validate()
+        .unwrap();
+    assert!(!report.is_valid());
+    assert_eq!(report.entries.len(), 4);
+
+    let findings: Vec<_> = report.findings().collect();
+    assert_eq!(findings.len(), 2);
+    assert_eq!(findings[0].filename, "bad.txt");
+    assert_eq!(findings[0].problems.len(), 1);
+    assert!(findings[0].problems[0].contains("CRC"), "{:?}", findings[0]);
+    assert_eq!(findings[1].filename, "../escape.txt");
+    assert_eq!(findings[1].problems.len(), 1);
+    assert!(findings[1].problems[0].contains("Unsafe path"));
+}
+
+#[cfg(feature = "signatures")]
+fn signed_fixture(signed: bool) -> (Vec<u8>, [u8; 32]) {
+    use ed25519_dalek::{Signer, SigningKey};
+    use muy_zipido::checksum::sha256_hex;
+
+    let key = SigningKey::from_bytes(&[3; 32]);
+    let listing = format!(
+        "{}  ./a.txt\n{}  ./docs/b.txt\n",
+        sha256_hex(b"alpha"),
+        sha256_hex(b"bravo bravo bravo")
+    );
+    let builder = ArchiveBuilder::new()
+        .stored("a.txt", b"alpha")
+        .deflated("docs/b.txt", b"bravo bravo bravo");
+    let builder = match signed {
+        true => builder.stored("release.sig", &key.sign(listing.as_bytes()).to_bytes()),
+        false => builder,
+    };
+    (builder.build(), key.verifying_key().to_bytes())
+}
+
+#[cfg(feature = "signatures")]
+#[test]
+fn test_signatures_checked_when_extracting() {
+    for signed in [true, false] {
+        let (archive, public) = signed_fixture(signed);
+        let dir = tempfile::tempdir().unwrap();
+        let result = MuyZipido::from_bytes(archive, 16)
+            .with_verify_signatures(&public)
+            .unwrap()
+            .with_entry_filter(|info: &muy_zipido::EntryInfo| info.filename == "a.txt")
+            .extract_to_dir(dir.path());
+        match signed {
+            true => assert_eq!(result.unwrap().written.len(), 1),
+            false => assert!(matches!(result, Err(ZipError::UnsupportedFeature { .. }))),
+        }
+    }
+}
+
+#[cfg(feature = "signatures")]
+#[test]
+fn test_signatures_checked_when_processing() {
+    for signed in [true, false] {
+        let (archive, public) = signed_fixture(signed);
+        let result = MuyZipido::from_bytes(archive, 16)
+            .with_verify_signatures(&public)
+            .unwrap()
+            .process_with(|info| match info.filename.as_str() {
+                "a.txt" => Ok(Some(Box::new(io::sink()) as Box<dyn Write>)),
+                _ => Ok(None),
+            });
+        match signed {
+            true => assert_eq!(result.unwrap().processed.len(), 1),
+            false => assert!(matches!(result, Err(ZipError::UnsupportedFeature { .. }))),
+        }
+    }
+}
+
+#[cfg(feature = "signatures")]
+#[test]
+fn test_validate_checks_signatures() {
+    let (archive, public) = signed_fixture(true);
+    let report = MuyZipido::from_bytes(archive, 16)
+        .with_verify_signatures(&public)
+        .unwrap()
+        .validate()
+        .unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.entries.len(), 3);
+
+    let (archive, public) = signed_fixture(false);
+    let result = MuyZipido::from_bytes(archive, 16)
+        .with_verify_signatures(&public)
+        .unwrap()
+        .validate();
+    assert!(matches!(result, Err(ZipError::UnsupportedFeature { .. })));
+}
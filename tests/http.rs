@@ -0,0 +1,690 @@
+mod common;
+
+use common::{Response, TestServer, ranged_response};
+use muy_zipido::fixture::ArchiveBuilder;
+use muy_zipido::{MuyZipido, ZipError, ZipSession};
+use reqwest::header::{AUTHORIZATION, HeaderValue};
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+fn fixture() -> Vec<u8> {
+    ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", b"x,y\n1,2\n")
+        .stored("c.txt", b"world")
+        .build()
+}
+
+#[test]
+fn test_clone_config_with_url() {
+    let server = TestServer::serve(fixture());
+
+    let session =
+        ZipSession::new().with_header(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+    let original = session
+        .open(&server.url("/first.zip"), 4)
+        .unwrap()
+        .with_filter(|name| name.ends_with(".txt"));
+    let clone = original
+        .clone_config_with_url(&server.url("/second.zip"))
+        .unwrap();
+    assert_eq!(clone.source_url(), Some(server.url("/second.zip").as_str()));
+
+    let names = |extractor: MuyZipido| -> Vec<String> {
+        extractor
+            .map(|entry| entry.unwrap().info.filename)
+            .collect()
+    };
+
+    assert_eq!(names(clone), vec!["a.txt", "c.txt"]);
+    assert_eq!(names(original), vec!["a.txt", "c.txt"]);
+
+    // The re-request goes through the same session.
+    let requests = server.requests();
+    let paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(paths, vec!["/first.zip", "/second.zip"]);
+    assert!(
+        requests
+            .iter()
+            .all(|r| r.header("authorization") == Some("Bearer secret"))
+    );
+}
+
+#[test]
+fn test_messy_urls_are_normalised() {
+    let server = TestServer::serve(fixture());
+
+    let url = format!("  {}  ", server.url("/My Data/export[1].zip"));
+    let extractor = MuyZipido::new(&url, 4).unwrap();
+    assert_eq!(extractor.count(), 3);
+
+    // A parsed URL is requested as it is.
+    let url = reqwest::Url::parse(&server.url("/raw%20name.zip")).unwrap();
+    assert_eq!(MuyZipido::from_url(url, 4).unwrap().count(), 3);
+
+    let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths, ["/My%20Data/export%5B1%5D.zip", "/raw%20name.zip"]);
+
+    assert!(matches!(
+        MuyZipido::new("ftp://example.com/data.zip", 4),
+        Err(ZipError::InvalidUrl { .. })
+    ));
+    let url = reqwest::Url::parse("file:///tmp/data.zip").unwrap();
+    assert!(matches!(
+        MuyZipido::from_url(url, 4),
+        Err(ZipError::InvalidUrl { .. })
+    ));
+}
+
+#[test]
+fn test_http_error_names_url() {
+    let server = TestServer::start(|_| Response::status(404, b"not here".to_vec()));
+    let url = server.url("/missing.zip");
+
+    let Err(ZipError::HttpStatus {
+        status,
+        url: failed,
+        ..
+    }) = MuyZipido::new(&url, 1024)
+    else {
+        panic!("expected an HTTP status error");
+    };
+    assert_eq!(failed, url);
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn test_http_status_includes_body_snippet() {
+    let body = br#"{"error": "quota exceeded"}"#;
+    let server = TestServer::start(|_| {
+        Response::status(403, body.to_vec()).with_header("Content-Type", "application/json")
+    });
+    let url = server.url("/data.zip");
+
+    let Err(error) = MuyZipido::new(&url, 1024) else {
+        panic!("expected an HTTP status error");
+    };
+    let ZipError::HttpStatus {
+        status,
+        body_snippet,
+        ..
+    } = &error
+    else {
+        panic!("expected an HTTP status error, got {:?}", error);
+    };
+    assert_eq!(*status, 403);
+    assert_eq!(body_snippet, r#"{"error": "quota exceeded"}"#);
+    assert_eq!(
+        error.to_string(),
+        format!(
+            r#"HTTP status 403 for {}: {{"error": "quota exceeded"}}"#,
+            url
+        )
+    );
+
+    let large = TestServer::start(|_| Response::status(500, vec![b'x'; 64 * 1024]));
+    let Err(ZipError::HttpStatus { body_snippet, .. }) = MuyZipido::new(&large.url("/a.zip"), 1024)
+    else {
+        panic!("expected an HTTP status error");
+    };
+    assert_eq!(body_snippet.len(), 4096);
+}
+
+fn ranged_server(archive: Vec<u8>, ranges: bool) -> TestServer {
+    TestServer::start(move |request| ranged_response(request, &archive, "\"v1\"", ranges))
+}
+
+#[test]
+fn test_open_at_resumes_from_entry_boundary() {
+    let archive = fixture();
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive.clone()), 4);
+    assert_eq!(extractor.resume_offset(), 0);
+    extractor.next().unwrap().unwrap();
+    let offset = extractor.resume_offset();
+    assert_eq!(
+        &archive[offset as usize..offset as usize + 4],
+        b"PK\x03\x04"
+    );
+
+    let names = |extractor: MuyZipido| -> Vec<String> {
+        extractor
+            .map(|entry| entry.unwrap().info.filename)
+            .collect()
+    };
+
+    let server = ranged_server(archive.clone(), true);
+    let session = ZipSession::new();
+    let resumed = session
+        .open_at(&server.url("/a.zip"), offset, Some("\"v1\""), 4)
+        .unwrap();
+    assert_eq!(resumed.etag(), Some("\"v1\""));
+    assert_eq!(resumed.resume_offset(), offset);
+    assert_eq!(names(resumed), vec!["b.csv", "c.txt"]);
+    assert_eq!(
+        server.requests()[0].header("Range"),
+        Some(format!("bytes={}-", offset).as_str())
+    );
+
+    // A changed archive or a server without range support restarts
+    let restarted = session
+        .open_at(&server.url("/a.zip"), offset, Some("\"v2\""), 4)
+        .unwrap();
+    assert_eq!(restarted.resume_offset(), 0);
+    assert_eq!(names(restarted), vec!["a.txt", "b.csv", "c.txt"]);
+
+    let server = ranged_server(archive, false);
+    let restarted = session
+        .open_at(&server.url("/a.zip"), offset, None, 4)
+        .unwrap();
+    assert_eq!(restarted.resume_offset(), 0);
+}
+
+#[test]
+fn test_from_response() {
+    let server = TestServer::serve(fixture());
+    let client = reqwest::blocking::Client::new();
+    let response = client
+        .get(server.url("/a.zip"))
+        .header("Authorization", "Bearer token")
+        .send()
+        .unwrap();
+
+    let extractor = MuyZipido::from_response(response, 8).unwrap();
+    assert_eq!(extractor.source_url(), Some(server.url("/a.zip").as_str()));
+    let names: Vec<String> = extractor
+        .map(|entry| entry.unwrap().info.filename)
+        .collect();
+    assert_eq!(names, vec!["a.txt", "b.csv", "c.txt"]);
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Bearer token")
+    );
+
+    let denied = TestServer::start(|_| Response::status(403, b"denied".to_vec()));
+    let response = client.get(denied.url("/a.zip")).send().unwrap();
+    assert!(matches!(
+        MuyZipido::from_response(response, 8),
+        Err(ZipError::HttpStatus { status: 403, body_snippet, .. }) if body_snippet == "denied"
+    ));
+}
+
+#[test]
+fn test_network_interrupted_mid_stream() {
+    let archive = fixture();
+    let cut = archive.windows(5).position(|w| w == b"world").unwrap();
+    let server = TestServer::start(move |_| Response::ok(archive.clone()).cut_off(cut));
+
+    let mut extractor = MuyZipido::new(&server.url("/a.zip"), 4).unwrap();
+    assert_eq!(extractor.next().unwrap().unwrap().filename, "a.txt");
+    assert_eq!(extractor.next().unwrap().unwrap().filename, "b.csv");
+
+    let error = extractor.next().unwrap().unwrap_err();
+    let ZipError::InEntry { source, .. } = &error else {
+        panic!("expected entry context, got {:?}", error);
+    };
+    assert!(
+        matches!(**source, ZipError::NetworkInterrupted { offset, .. } if offset == cut as u64),
+        "{:?}",
+        source
+    );
+    assert!(!error.is_recoverable());
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_drop_mid_archive_closes_connection() {
+    let payload = vec![b'x'; 512 * 1024];
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .stored("b.bin", &payload)
+        .stored("c.bin", &payload)
+        .build();
+    let server = TestServer::start(move |_| {
+        Response::ok(archive.clone()).throttled(1024, Duration::from_millis(10))
+    });
+
+    let wait_for_close = |server: &TestServer| {
+        // Serving the whole archive would take about ten seconds.
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while server.active_connections() > 0 {
+            assert!(Instant::now() < deadline, "connection still open");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    };
+
+    let mut extractor = MuyZipido::new(&server.url("/a.zip"), 256).unwrap();
+    assert_eq!(extractor.next().unwrap().unwrap().filename, "a.txt");
+    drop(extractor);
+    wait_for_close(&server);
+
+    let mut extractor = MuyZipido::new(&server.url("/a.zip"), 256).unwrap();
+    assert_eq!(extractor.next().unwrap().unwrap().filename, "a.txt");
+    extractor.abort();
+    wait_for_close(&server);
+}
+
+#[test]
+fn test_preview_transfers_only_leading_entries() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", &b"x,y\n1,2\n".repeat(1000))
+        .with_data_descriptor("c.txt", b"streamed")
+        .stored("huge.bin", &vec![b'x'; 32 * 1024 * 1024])
+        .build();
+    let server = TestServer::start(move |_| {
+        Response::ok(archive.clone()).throttled(16 * 1024, Duration::from_millis(1))
+    });
+
+    let entries = MuyZipido::new(&server.url("/a.zip"), 8192)
+        .unwrap()
+        .preview(3)
+        .unwrap();
+    let names: Vec<&str> = entries.iter().map(|e| e.filename.as_str()).collect();
+    assert_eq!(names, ["a.txt", "b.csv", "c.txt"]);
+    assert_eq!(entries[2].uncompressed_size, 8);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while server.active_connections() > 0 {
+        assert!(Instant::now() < deadline, "connection still open");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(
+        server.bytes_sent() < 512 * 1024,
+        "sent {} bytes",
+        server.bytes_sent()
+    );
+}
+
+#[test]
+fn test_session_with_connection_pool_size() {
+    let server = TestServer::serve(fixture());
+    let session = ZipSession::new().with_connection_pool_size(1);
+
+    for path in ["/a.zip", "/b.zip", "/c.zip"] {
+        let extractor = session.open(&server.url(path), 64).unwrap();
+        assert_eq!(extractor.count(), 3);
+    }
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn test_rate_limited_requests_are_retried() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    let calls = AtomicUsize::new(0);
+    let server = TestServer::start(move |request| match request.path.as_str() {
+        "/busy.zip" if calls.fetch_add(1, Ordering::SeqCst) == 0 => {
+            Response::status(429, b"slow down".to_vec()).with_header("Retry-After", "1")
+        }
+        "/busy.zip" => Response::ok(fixture()),
+        "/quota.zip" => Response::status(429, Vec::new()).with_header("Retry-After", "3600"),
+        "/down.zip" => Response::status(503, Vec::new()),
+        _ => Response::status(429, Vec::new()).with_header("Retry-After", "0"),
+    });
+    let waits = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&waits);
+    let session = ZipSession::new().with_rate_limit_handler(move |url, wait| {
+        seen.lock().unwrap().push((url.to_string(), wait))
+    });
+
+    let started = Instant::now();
+    let extractor = session
+        .open(&server.url("/busy.zip"), 64)
+        .unwrap()
+        .with_timing(true);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    let stats = extractor.download_stats().unwrap();
+    assert_eq!(stats.rate_limited, 1);
+    assert_eq!(stats.rate_limit_wait, Duration::from_secs(1));
+    assert_eq!(extractor.count(), 3);
+    assert_eq!(
+        *waits.lock().unwrap(),
+        vec![(server.url("/busy.zip"), Duration::from_secs(1))]
+    );
+
+    // Longer than the cap: no wait at all.
+    let error = session.open(&server.url("/quota.zip"), 64).unwrap_err();
+    assert!(matches!(
+        error,
+        ZipError::RateLimited { retry_after: Some(wait), .. } if wait == Duration::from_secs(3600)
+    ));
+
+    // Out of retries.
+    let error = session
+        .clone()
+        .with_rate_limit_retries(2)
+        .open(&server.url("/always.zip"), 64)
+        .unwrap_err();
+    assert!(matches!(error, ZipError::RateLimited { .. }));
+    let always = server
+        .requests()
+        .iter()
+        .filter(|request| request.path == "/always.zip")
+        .count();
+    assert_eq!(always, 3);
+
+    // A 503 without Retry-After is an ordinary failure.
+    let error = session.open(&server.url("/down.zip"), 64).unwrap_err();
+    assert!(matches!(error, ZipError::HttpStatus { status: 503, .. }));
+}
+
+#[test]
+fn test_from_cache_or_url() {
+    let archive = fixture();
+    let served = archive.clone();
+    let server = TestServer::start(move |request| {
+        if request.header("If-None-Match") == Some("\"v1\"") {
+            Response::status(304, Vec::new())
+        } else {
+            Response::ok(served.clone()).with_header("ETag", "\"v1\"")
+        }
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("cache/a.zip");
+    let url = server.url("/a.zip");
+    let names = |extractor: MuyZipido| -> Vec<String> {
+        extractor
+            .map(|entry| entry.unwrap().info.filename)
+            .collect()
+    };
+
+    // Reading only part of the archive leaves no cache behind.
+    let mut partial =
+        MuyZipido::from_cache_or_url(&url, &cache, Duration::from_secs(60), 4).unwrap();
+    partial.next().unwrap().unwrap();
+    drop(partial);
+    assert!(!cache.exists());
+
+    let extractor = MuyZipido::from_cache_or_url(&url, &cache, Duration::from_secs(60), 4).unwrap();
+    assert_eq!(names(extractor), vec!["a.txt", "b.csv", "c.txt"]);
+    assert_eq!(std::fs::read(&cache).unwrap(), archive);
+    assert_eq!(server.requests().len(), 2);
+
+    // Fresh: served from disk without a request.
+    let cached = MuyZipido::from_cache_or_url(&url, &cache, Duration::from_secs(60), 4).unwrap();
+    assert_eq!(cached.source_url(), None);
+    assert_eq!(cached.etag(), Some("\"v1\""));
+    assert_eq!(names(cached), vec!["a.txt", "b.csv", "c.txt"]);
+    assert_eq!(server.requests().len(), 2);
+
+    // Stale: revalidated with the recorded ETag and reused on 304.
+    let revalidated = MuyZipido::from_cache_or_url(&url, &cache, Duration::ZERO, 4).unwrap();
+    assert_eq!(names(revalidated), vec!["a.txt", "b.csv", "c.txt"]);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[2].header("If-None-Match"), Some("\"v1\""));
+    assert_eq!(std::fs::read(&cache).unwrap(), archive);
+}
+
+#[test]
+fn test_redirects_strip_credentials_across_origins() {
+    let mirror = TestServer::serve(fixture());
+    let mirror_url = mirror.url("/mirror.zip");
+    let archive = fixture();
+    let server = TestServer::start(move |request| match request.path.as_str() {
+        "/same.zip" => Response::status(302, Vec::new()).with_header("Location", "/a.zip"),
+        "/away.zip" => Response::status(302, Vec::new()).with_header("Location", &mirror_url),
+        "/loop.zip" => Response::status(301, Vec::new()).with_header("Location", "/again.zip"),
+        "/again.zip" => Response::status(301, Vec::new()).with_header("Location", "/loop.zip"),
+        _ => Response::ok(archive.clone()),
+    });
+    let session =
+        ZipSession::new().with_header(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+
+    let extractor = session.open(&server.url("/same.zip"), 1024).unwrap();
+    assert_eq!(extractor.final_url(), Some(server.url("/a.zip").as_str()));
+    assert_eq!(extractor.count(), 3);
+    let requests = server.requests();
+    assert_eq!(requests[1].path, "/a.zip");
+    assert_eq!(requests[1].header("Authorization"), Some("Bearer secret"));
+
+    let extractor = session.open(&server.url("/away.zip"), 1024).unwrap();
+    assert_eq!(
+        extractor.source_url(),
+        Some(server.url("/away.zip").as_str())
+    );
+    assert_eq!(
+        extractor.redirect_chain(),
+        [server.url("/away.zip"), mirror.url("/mirror.zip")]
+    );
+    assert_eq!(
+        server.requests()[2].header("Authorization"),
+        Some("Bearer secret")
+    );
+    assert_eq!(mirror.requests()[0].header("Authorization"), None);
+
+    let forwarding = session.clone().with_forward_auth_on_redirect(true);
+    forwarding.open(&server.url("/away.zip"), 1024).unwrap();
+    assert_eq!(
+        mirror.requests()[1].header("Authorization"),
+        Some("Bearer secret")
+    );
+
+    let Err(ZipError::RedirectLoop { chain }) = session.open(&server.url("/loop.zip"), 1024) else {
+        panic!("expected a redirect loop");
+    };
+    assert_eq!(
+        chain,
+        [
+            server.url("/loop.zip"),
+            server.url("/again.zip"),
+            server.url("/loop.zip")
+        ]
+    );
+
+    let limited = ZipSession::new().with_redirect_policy(0);
+    let Err(ZipError::RedirectLoop { chain }) = limited.open(&server.url("/same.zip"), 1024) else {
+        panic!("expected too many redirects");
+    };
+    assert_eq!(chain, [server.url("/same.zip"), server.url("/a.zip")]);
+}
+
+/// Serves `body` over TLS with the certificate and key in `tests/common/tls`
+/// named `name`, for every connection until the test ends.
+fn serve_tls(name: &str, body: Vec<u8>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/tls/");
+    let cert = std::fs::read(format!("{}{}.pem", dir, name)).unwrap();
+    let key = std::fs::read(format!("{}{}.key", dir, name)).unwrap();
+    let identity = native_tls::Identity::from_pkcs8(&cert, &key).unwrap();
+    let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!(
+        "https://localhost:{}/data.zip",
+        listener.local_addr().unwrap().port()
+    );
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { break };
+            // The handshake fails when the client rejects the certificate.
+            let Ok(stream) = acceptor.accept(stream) else {
+                continue;
+            };
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                line.clear();
+            }
+            let stream = reader.get_mut();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+            let _ = stream.flush();
+        }
+    });
+    url
+}
+
+#[test]
+fn test_pinned_certificate() {
+    let pinned = include_bytes!("common/tls/pinned.der").to_vec();
+    let session = ZipSession::new().with_pinned_certificate(pinned).unwrap();
+
+    let url = serve_tls("pinned", fixture());
+    let names: Vec<String> = session
+        .open(&url, 64)
+        .unwrap()
+        .map(|entry| entry.unwrap().info.filename)
+        .collect();
+    assert_eq!(names, vec!["a.txt", "b.csv", "c.txt"]);
+
+    let url = serve_tls("other", fixture());
+    let error = session.open(&url, 64).unwrap_err();
+    assert!(
+        matches!(error, ZipError::TlsCertificateRejected(ref reason) if reason.contains(&url)),
+        "{:?}",
+        error
+    );
+
+    // A handshake failing for another reason is not a rejected certificate.
+    let server = TestServer::start(|_| Response::ok(fixture()));
+    let url = server.url("/a.zip").replacen("http://", "https://", 1);
+    let error = session.open(&url, 64).unwrap_err();
+    assert!(matches!(error, ZipError::Http { .. }), "{:?}", error);
+
+    assert!(matches!(
+        ZipSession::new().with_pinned_certificate(b"not a certificate".to_vec()),
+        Err(ZipError::InvalidCertificate(_))
+    ));
+}
+
+#[test]
+fn test_session_paces_requests() {
+    use std::sync::{Arc, Mutex};
+
+    let times = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&times);
+    let server = TestServer::start(move |_| {
+        seen.lock().unwrap().push(Instant::now());
+        Response::ok(fixture())
+    });
+    let delay = Duration::from_millis(300);
+
+    let started = Instant::now();
+    let session = ZipSession::new().with_per_request_delay(delay);
+    for path in ["/a.zip", "/b.zip", "/c.zip"] {
+        assert_eq!(session.open(&server.url(path), 64).unwrap().count(), 3);
+    }
+    let times = times.lock().unwrap().clone();
+    // The first request goes straight out.
+    assert!(times[0] - started < delay);
+    // Measured on arrival, so allow for the first connection taking longer
+    // to set up than the later ones.
+    for pair in times.windows(2) {
+        assert!(pair[1] - pair[0] >= delay - Duration::from_millis(50));
+    }
+
+    let agents: Vec<_> = server
+        .requests()
+        .iter()
+        .map(|request| request.header("User-Agent").unwrap().to_string())
+        .collect();
+    let default = format!("muy-zipido/{}", env!("CARGO_PKG_VERSION"));
+    assert_eq!(agents, vec![default; 3]);
+
+    let session = ZipSession::new()
+        .with_user_agent("transit-mirror/1.0")
+        .unwrap()
+        .with_max_concurrent(1);
+    session.open(&server.url("/d.zip"), 64).unwrap();
+    assert_eq!(
+        server.requests()[3].header("User-Agent"),
+        Some("transit-mirror/1.0")
+    );
+    assert!(ZipSession::new().with_user_agent("bad\nagent").is_err());
+}
+
+#[test]
+fn test_session_max_concurrent() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let active = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (now, most) = (Arc::clone(&active), Arc::clone(&peak));
+    let server = TestServer::start(move |request| {
+        if request.path == "/moved.zip" {
+            return Response::status(302, Vec::new()).with_header("Location", "/a.zip");
+        }
+        most.fetch_max(now.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(100));
+        now.fetch_sub(1, Ordering::SeqCst);
+        Response::ok(fixture())
+    });
+
+    let session = ZipSession::new().with_max_concurrent(2);
+    std::thread::scope(|scope| {
+        for _ in 0..6 {
+            scope.spawn(|| session.open(&server.url("/a.zip"), 64).unwrap().count());
+        }
+    });
+    assert_eq!(server.requests().len(), 6);
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+    // An open extractor keeps its place until it is dropped.
+    let session = ZipSession::new().with_max_concurrent(1);
+    let first = session.open(&server.url("/a.zip"), 64).unwrap();
+    std::thread::scope(|scope| {
+        let second = scope.spawn(|| session.open(&server.url("/b.zip"), 64).unwrap().count());
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(server.requests().len(), 7);
+        assert_eq!(first.count(), 3);
+        assert_eq!(second.join().unwrap(), 3);
+    });
+    assert_eq!(server.requests().len(), 8);
+
+    // Following a redirect, or retrying while the first response is still
+    // open, does not wait for the place already held.
+    let moved = session.open(&server.url("/moved.zip"), 64).unwrap();
+    let retry = moved
+        .clone_config_with_url(&server.url("/moved.zip"))
+        .unwrap();
+    assert_eq!(retry.count(), 3);
+    assert_eq!(moved.count(), 3);
+    assert_eq!(server.requests().len(), 12);
+}
+
+#[test]
+fn test_response_validator() {
+    let server = TestServer::start(|request| match request.path.as_str() {
+        "/old.zip" => Response::ok(fixture()).with_header("X-Archive-Version", "1"),
+        "/missing.zip" => Response::status(404, Vec::new()),
+        _ => Response::ok(fixture()).with_header("X-Archive-Version", "2"),
+    });
+    let session = ZipSession::new().with_response_validator(|response| {
+        match response.headers().get("X-Archive-Version") {
+            Some(version) if version == "2" => Ok(()),
+            _ => Err(ZipError::ResponseRejected {
+                url: response.url().to_string(),
+                reason: "expected archive version 2".to_string(),
+            }),
+        }
+    });
+
+    assert_eq!(
+        session.open(&server.url("/new.zip"), 64).unwrap().count(),
+        3
+    );
+    let error = session.open(&server.url("/old.zip"), 64).unwrap_err();
+    assert!(matches!(
+        &error,
+        ZipError::ResponseRejected { url, .. } if *url == server.url("/old.zip")
+    ));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Response from {} rejected: expected archive version 2",
+            server.url("/old.zip")
+        )
+    );
+
+    // Error statuses fail before the validator sees them.
+    let error = session.open(&server.url("/missing.zip"), 64).unwrap_err();
+    assert!(matches!(error, ZipError::HttpStatus { status: 404, .. }));
+}
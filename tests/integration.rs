@@ -0,0 +1,91 @@
+//! Downloads a real archive over the network, so it is only built with the
+//! `integration` feature and the test is ignored by default. Run it with
+//!
+//! ```text
+//! cargo test --features integration --test integration -- --include-ignored
+//! ```
+//!
+//! The archive is a GitHub source download of a pinned tag, so its contents
+//! never change. Rather than hard-coding every size and CRC-32, the test
+//! checks the streamed entries against the archive's own central directory,
+//! which the extractor never reads.
+
+mod common;
+
+use common::SharedBuffer;
+use muy_zipido::MuyZipido;
+
+const URL: &str = "https://github.com/rust-lang/mdBook/archive/refs/tags/v0.4.40.zip";
+const PREFIX: &str = "mdBook-0.4.40/";
+
+/// A central directory record: filename, compressed and uncompressed size,
+/// CRC-32.
+type Record = (String, u64, u64, u32);
+
+fn u16_at(bytes: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]]) as usize
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn central_directory(archive: &[u8]) -> Vec<Record> {
+    let eocd = archive
+        .windows(4)
+        .rposition(|w| w == b"PK\x05\x06")
+        .expect("no end of central directory record");
+    let count = u16_at(archive, eocd + 10);
+    let mut at = u32_at(archive, eocd + 16) as usize;
+
+    let mut records = Vec::with_capacity(count);
+    for _ in 0..count {
+        assert_eq!(&archive[at..at + 4], b"PK\x01\x02");
+        let name_len = u16_at(archive, at + 28);
+        let extra_len = u16_at(archive, at + 30);
+        let comment_len = u16_at(archive, at + 32);
+        let name = String::from_utf8_lossy(&archive[at + 46..at + 46 + name_len]).into_owned();
+        records.push((
+            name,
+            u32_at(archive, at + 20) as u64,
+            u32_at(archive, at + 24) as u64,
+            u32_at(archive, at + 16),
+        ));
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    records
+}
+
+#[test]
+#[ignore = "downloads from github.com; run with --include-ignored"]
+fn test_public_archive_matches_central_directory() {
+    let download = SharedBuffer::default();
+    let extractor = MuyZipido::new(URL, 64 * 1024)
+        .expect("failed to request the archive")
+        .with_tee(download.clone());
+
+    let mut streamed: Vec<Record> = Vec::new();
+    for entry in extractor {
+        // Each entry's CRC-32 is checked against the archive while streaming.
+        let entry = entry.expect("failed to read entry");
+        assert_eq!(entry.data.len() as u64, entry.uncompressed_size);
+        streamed.push((
+            entry.filename.clone(),
+            entry.compressed_size,
+            entry.uncompressed_size,
+            entry.crc32,
+        ));
+    }
+
+    let names: Vec<&str> = streamed.iter().map(|(name, ..)| name.as_str()).collect();
+    assert!(names.iter().all(|name| name.starts_with(PREFIX)));
+    for expected in ["Cargo.toml", "README.md", "src/main.rs"] {
+        assert!(
+            names.contains(&format!("{}{}", PREFIX, expected).as_str()),
+            "{} missing",
+            expected
+        );
+    }
+
+    assert_eq!(streamed, central_directory(&download.contents()));
+}
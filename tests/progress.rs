@@ -0,0 +1,321 @@
+//! Drives the progress bar through a whole extraction with a throttled
+//! reader and a hand-advanced clock, and checks every frame it draws.
+
+mod common;
+
+use common::SharedBuffer;
+use muy_zipido::MuyZipido;
+use muy_zipido::fixture::ArchiveBuilder;
+use muy_zipido::progress_bar::{Clock, Colour, ProgressBar, ProgressBasis, Style};
+use std::io::{self, Read};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+const WIDTH: usize = 110;
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// A clock that only moves when told to.
+struct ManualClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        })
+    }
+
+    fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+}
+
+/// Hands out at most `chunk` bytes per read, each read taking `delay` on
+/// the clock.
+struct ThrottledReader {
+    data: io::Cursor<Vec<u8>>,
+    chunk: usize,
+    delay: Duration,
+    clock: Arc<ManualClock>,
+}
+
+impl Read for ThrottledReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.clock.advance(self.delay);
+        let len = buf.len().min(self.chunk);
+        self.data.read(&mut buf[..len])
+    }
+}
+
+/// An archive of four 4000-byte stored entries, 16,446 bytes in all.
+fn archive() -> Vec<u8> {
+    let data: Vec<u8> = (0..4000u32).map(|i| (i * 7 % 251) as u8).collect();
+    (1..=4)
+        .fold(ArchiveBuilder::new(), |builder, n| {
+            builder.stored(&format!("data/part-{}.bin", n), &data)
+        })
+        .build()
+}
+
+/// Everything the bar writes while the whole of `archive` is read through
+/// it, 512 bytes every 40ms. The bar draws without a render thread, so no
+/// frame is skipped.
+fn capture(archive: Vec<u8>, bar: ProgressBar) -> String {
+    let clock = ManualClock::new();
+    let output = SharedBuffer::default();
+    let bar = bar
+        .with_render_thread(false)
+        .with_writer(output.clone())
+        .with_clock(clock.clone())
+        .with_width(WIDTH)
+        .with_description("Downloading a rather long archive name.zip".to_string());
+    let reader = ThrottledReader {
+        data: io::Cursor::new(archive),
+        chunk: 512,
+        delay: Duration::from_millis(40),
+        clock,
+    };
+
+    let entries = MuyZipido::from_reader(reader, 512)
+        .with_progress_bar(bar)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(entries.len(), 4);
+
+    String::from_utf8(output.contents()).unwrap()
+}
+
+/// The lines drawn, in order, after checking the output ends with the only
+/// newline.
+fn frames(output: &str) -> Vec<&str> {
+    let body = output.strip_suffix('\n').expect("ends with a newline");
+    assert!(!body.contains('\n'), "{:?}", output);
+    body.split('\r').filter(|frame| !frame.is_empty()).collect()
+}
+
+fn percentage(frame: &str) -> f64 {
+    let end = frame.find('%').expect("frame shows a percentage");
+    let start = frame[..end].rfind(' ').unwrap() + 1;
+    frame[start..end].parse().unwrap()
+}
+
+/// The frames drawn by [`test_progress_frames`]. The description shrinks as
+/// the statistics grow, to keep within [`WIDTH`], and the last frame is
+/// drawn twice: once for the last read and once by `finish`.
+const GOLDEN_FRAMES: [&str; 12] = [
+    "Downloading a rather…: [███░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░] 9.3% | 1.5KiB/16.1KiB | 0.01 MiB/s | ETA: 1s",
+    "Downloading a rathe…: [███████░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░░] 18.7% | 3.0KiB/16.1KiB | 0.01 MiB/s | ETA: 1s",
+    "Downloading a rathe…: [███████████░░░░░░░░░░░░░░░░░░░░░░░░░░░░░] 27.7% | 4.5KiB/16.1KiB | 0.01 MiB/s | ETA: 1s",
+    "Downloading a rathe…: [██████████████░░░░░░░░░░░░░░░░░░░░░░░░░░] 37.0% | 6.0KiB/16.1KiB | 0.01 MiB/s | ETA: 1s",
+    "Downloading a rathe…: [██████████████████░░░░░░░░░░░░░░░░░░░░░░] 46.4% | 7.5KiB/16.1KiB | 0.01 MiB/s | ETA: 1s",
+    "Downloading a rathe…: [██████████████████████░░░░░░░░░░░░░░░░░░] 55.4% | 8.9KiB/16.1KiB | 0.01 MiB/s | ETA: 1s",
+    "Downloading a rath…: [█████████████████████████░░░░░░░░░░░░░░░] 64.8% | 10.4KiB/16.1KiB | 0.01 MiB/s | ETA: 0s",
+    "Downloading a rath…: [█████████████████████████████░░░░░░░░░░░] 73.8% | 11.9KiB/16.1KiB | 0.01 MiB/s | ETA: 0s",
+    "Downloading a rath…: [█████████████████████████████████░░░░░░░] 83.1% | 13.4KiB/16.1KiB | 0.01 MiB/s | ETA: 0s",
+    "Downloading a rath…: [████████████████████████████████████░░░░] 92.5% | 14.9KiB/16.1KiB | 0.01 MiB/s | ETA: 0s",
+    "Downloading a rat…: [████████████████████████████████████████] 100.0% | 16.1KiB/16.1KiB | 0.01 MiB/s | ETA: 0s",
+    "Downloading a rat…: [████████████████████████████████████████] 100.0% | 16.1KiB/16.1KiB | 0.01 MiB/s | ETA: 0s",
+];
+
+#[test]
+fn test_progress_frames() {
+    let archive = archive();
+    let total = archive.len();
+    let output = capture(
+        archive,
+        ProgressBar::new(Some(total)).with_color(Colour::None),
+    );
+    let frames = frames(&output);
+
+    assert!(frames.len() >= 10, "{:#?}", frames);
+    assert!(!output.contains('\x1b'), "{:?}", output);
+    for frame in &frames {
+        assert!(frame.chars().count() <= WIDTH, "{:?}", frame);
+        assert!(frame.starts_with("Downloading a rat"), "{:?}", frame);
+    }
+
+    let percentages: Vec<f64> = frames.iter().map(|frame| percentage(frame)).collect();
+    assert!(
+        percentages.windows(2).all(|pair| pair[0] <= pair[1]),
+        "{:?}",
+        percentages
+    );
+    assert!(percentages[0] < 10.0, "{:?}", percentages);
+    assert_eq!(percentages.last(), Some(&100.0));
+
+    // The clock makes every frame the same on every run.
+    assert_eq!(frames, GOLDEN_FRAMES);
+}
+
+#[test]
+fn test_progress_frames_in_colour() {
+    let archive = archive();
+    let total = archive.len();
+    let output = capture(
+        archive,
+        ProgressBar::new(Some(total))
+            .with_color(Colour::Green)
+            .with_style(Style::Gradient),
+    );
+
+    assert!(output.contains("\x1b[32m"), "{:?}", output);
+    // Escape codes take no room on screen.
+    for frame in frames(&output) {
+        let visible = frame.replace("\x1b[32m", "").replace("\x1b[0m", "");
+        assert!(visible.chars().count() <= WIDTH, "{:?}", frame);
+    }
+}
+
+#[test]
+fn test_spinner_without_total() {
+    let output = capture(archive(), ProgressBar::new(None).with_color(Colour::None));
+    let frames = frames(&output);
+
+    assert!(frames.len() >= 10, "{:#?}", frames);
+    for frame in &frames {
+        assert!(frame.chars().count() <= WIDTH, "{:?}", frame);
+        assert!(!frame.contains('%'), "{:?}", frame);
+        let spinner = frame.split(": ").nth(1).unwrap().chars().next().unwrap();
+        assert!(SPINNER.contains(&spinner), "{:?}", frame);
+    }
+    assert!(
+        frames
+            .last()
+            .unwrap()
+            .ends_with("16.1KiB | 0.01 MiB/s | 00:01")
+    );
+}
+
+#[test]
+fn test_custom_format() {
+    let archive = archive();
+    let total = archive.len();
+    let bar = ProgressBar::new(Some(total)).with_format_fn(|snapshot| {
+        format!(
+            "{:.0}% ({} of {} bytes) after {}ms",
+            snapshot.percentage.unwrap(),
+            snapshot.bytes_downloaded,
+            snapshot.total_bytes.unwrap(),
+            snapshot.elapsed.as_millis()
+        )
+    });
+    let output = capture(archive, bar);
+    let frames = frames(&output);
+
+    assert_eq!(frames[0], "9% (1536 of 16446 bytes) after 120ms");
+    assert_eq!(
+        frames.last().unwrap(),
+        &"100% (16446 of 16446 bytes) after 1320ms"
+    );
+    assert!(frames.iter().all(|frame| !frame.contains("Downloading")));
+}
+
+/// A writer that blocks until it is opened, like a paused terminal.
+#[derive(Clone, Default)]
+struct GatedWriter {
+    open: Arc<(Mutex<bool>, Condvar)>,
+    output: SharedBuffer,
+}
+
+impl GatedWriter {
+    fn open(&self) {
+        *self.open.0.lock().unwrap() = true;
+        self.open.1.notify_all();
+    }
+}
+
+impl io::Write for GatedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (open, opened) = &*self.open;
+        let _open = opened
+            .wait_while(open.lock().unwrap(), |open| !*open)
+            .unwrap();
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_blocked_writer_does_not_block_updates() {
+    let clock = ManualClock::new();
+    let writer = GatedWriter::default();
+    let mut bar = ProgressBar::new(Some(100 * 1024))
+        .with_color(Colour::None)
+        .with_writer(writer.clone())
+        .with_clock(clock.clone())
+        .with_width(WIDTH);
+
+    let mut slowest = Duration::ZERO;
+    for _ in 0..100 {
+        // Past the render interval, so every update draws a frame.
+        clock.advance(Duration::from_millis(200));
+        let started = Instant::now();
+        bar.update(1024);
+        slowest = slowest.max(started.elapsed());
+    }
+    // Microseconds in practice; the bound allows for a loaded machine.
+    assert!(slowest < Duration::from_millis(50), "{:?}", slowest);
+
+    writer.open();
+    bar.finish();
+    let output = String::from_utf8(writer.output.contents()).unwrap();
+    let frames = frames(&output);
+
+    // At most the frame the writer blocked on, the last one drawn while it
+    // was blocked and the final one; the rest were skipped.
+    assert!(frames.len() <= 3, "{:#?}", frames);
+    assert!(frames.last().unwrap().contains("100.0%"), "{:?}", frames);
+}
+
+#[test]
+fn test_progress_tracks_decompressed_bytes() {
+    // Compresses to far less than it decompresses to, so the two bases
+    // disagree.
+    let sizes = [30_000, 10_000, 60_000];
+    let archive = sizes
+        .iter()
+        .enumerate()
+        .fold(ArchiveBuilder::new(), |builder, (n, &size)| {
+            builder.deflated(&format!("part-{}.txt", n), &vec![b'a' + n as u8; size])
+        })
+        .build();
+    let total: usize = sizes.iter().sum();
+
+    let mut extractor = MuyZipido::from_reader(io::Cursor::new(archive), 512)
+        .with_progress_basis(ProgressBasis::Decompressed)
+        .with_total_size(total as u64)
+        .with_progress(Style::Classic, Colour::None);
+    let snapshot = extractor.progress_snapshot().unwrap();
+    assert_eq!(snapshot.total_bytes, Some(total));
+    assert_eq!(snapshot.bytes_downloaded, 0);
+
+    let mut extracted = 0;
+    for size in sizes {
+        let entry = extractor.next().unwrap().unwrap();
+        assert_eq!(entry.data.len(), size);
+        extracted += size;
+
+        let snapshot = extractor.progress_snapshot().unwrap();
+        assert_eq!(snapshot.bytes_downloaded, extracted);
+        assert_eq!(
+            snapshot.percentage,
+            Some(extracted as f64 / total as f64 * 100.0)
+        );
+    }
+    assert_eq!(
+        extractor.progress_snapshot().unwrap().percentage,
+        Some(100.0)
+    );
+}
@@ -0,0 +1,1755 @@
+mod common;
+
+use common::SharedBuffer;
+use muy_zipido::central::CentralDirectory;
+use muy_zipido::decompress::{Decompressor, FlateDecompressor};
+use muy_zipido::filter::{
+    CompressionFilter, DirectoryFilter, GlobFilter, SizeFilter, ZipEntryFilter,
+};
+use muy_zipido::fixture::ArchiveBuilder;
+use muy_zipido::progress_bar::ProgressObserver;
+use muy_zipido::{CompressionMethod, EntryInfo, Limits, MuyZipido, ZipError, ZipWarning};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn test_tee_captures_complete_archive() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", b"x,y\n1,2\n1,2\n1,2\n")
+        .build();
+
+    let tee = SharedBuffer::default();
+    let extractor = MuyZipido::from_reader(Cursor::new(archive.clone()), 7).with_tee(tee.clone());
+
+    let names: Vec<String> = extractor
+        .map(|entry| entry.unwrap().info.filename)
+        .collect();
+
+    assert_eq!(names, vec!["a.txt", "b.csv"]);
+    assert_eq!(tee.contents(), archive);
+}
+
+#[test]
+fn test_filter_skips_rejected_entries() {
+    let archive = ArchiveBuilder::new()
+        .stored("keep/a.txt", b"hello")
+        .deflated("skip/b.csv", b"x,y\n1,2\n")
+        .with_data_descriptor("skip/c.csv", b"streamed")
+        .stored_with_descriptor("keep/d.txt", b"world")
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_reader(Cursor::new(archive), 5)
+        .with_filter(|name| name.starts_with("keep/"))
+        .map(|entry| entry.unwrap())
+        .collect();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].filename, "keep/a.txt");
+    assert_eq!(entries[1].filename, "keep/d.txt");
+    assert_eq!(entries[1].data, b"world");
+}
+
+#[test]
+fn test_entry_filter_combinators() {
+    let archive = ArchiveBuilder::new()
+        .stored("data/", b"")
+        .stored("data/small.csv", b"x\n")
+        .deflated("data/large.csv", &b"x,y\n1,2\n".repeat(100))
+        .deflated("readme.txt", b"hello")
+        .build();
+
+    fn names(archive: &[u8], filter: impl ZipEntryFilter + 'static) -> Vec<String> {
+        MuyZipido::from_bytes(archive.to_vec(), 16)
+            .with_entry_filter(filter)
+            .map(|entry| entry.unwrap().info.filename)
+            .collect()
+    }
+
+    let csv_or_dir = GlobFilter::new("**/*.csv").or(DirectoryFilter);
+    assert_eq!(
+        names(&archive, csv_or_dir.and(SizeFilter { min: 0, max: 100 })),
+        ["data/", "data/small.csv"]
+    );
+    assert_eq!(
+        names(
+            &archive,
+            CompressionFilter(CompressionMethod::Deflate).not()
+        ),
+        ["data/", "data/small.csv"]
+    );
+    assert_eq!(
+        names(&archive, |info: &EntryInfo| info.filename.ends_with(".txt")),
+        ["readme.txt"]
+    );
+}
+
+#[test]
+fn test_invalid_signature_reports_offset() {
+    let mut archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    let first_entry_len = 30 + "a.txt".len() + 5;
+    archive[first_entry_len..first_entry_len + 4].copy_from_slice(b"JUNK");
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive), 8);
+    assert!(extractor.next().unwrap().is_ok());
+
+    match extractor.next() {
+        Some(Err(ZipError::InvalidSignature { found, offset })) => {
+            assert_eq!(found, "4a554e4b");
+            assert_eq!(offset, first_entry_len as u64);
+        }
+        other => panic!(
+            "expected InvalidSignature, got {:?}",
+            other.map(|r| r.is_ok())
+        ),
+    }
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_recoverable_errors_continue_with_next_entry() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("1.txt", b"one")
+        .stored("2.txt", b"hello")
+        .raw("3.bin", 8, &[0xff; 16], true)
+        .raw("4.bz2", 12, b"BZh9", false)
+        .raw("5.bz2", 12, b"BZh9", true)
+        .with_data_descriptor("6.txt", b"six")
+        .build();
+    let at = archive.windows(5).position(|w| w == b"hello").unwrap();
+    archive[at] = b'j';
+
+    let results: Vec<_> = MuyZipido::from_reader(Cursor::new(archive), 8)
+        .map(|result| result.map(|entry| entry.info.filename))
+        .collect();
+    assert_eq!(results.len(), 6);
+    assert_eq!(results[0].as_ref().unwrap(), "1.txt");
+    for error in &results[1..5] {
+        assert!(error.as_ref().unwrap_err().is_recoverable());
+    }
+    assert!(matches!(
+        results[1].as_ref().unwrap_err().root(),
+        ZipError::CrcMismatch { .. }
+    ));
+    assert!(matches!(
+        results[2].as_ref().unwrap_err().root(),
+        ZipError::CorruptData { .. }
+    ));
+    assert_eq!(results[5].as_ref().unwrap(), "6.txt");
+}
+
+#[test]
+fn test_fatal_error_ends_iteration() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .stored("b.txt", b"world")
+        .build();
+    let at = archive.windows(5).position(|w| w == b"hello").unwrap();
+    archive[at + 5] = b'X';
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive), 8);
+    assert!(extractor.next().unwrap().is_ok());
+    let error = extractor.next().unwrap().unwrap_err();
+    assert!(!error.is_recoverable());
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_crc_mismatch_does_not_stop_iteration() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("bad.txt", b"hello")
+        .stored_with_descriptor("good.txt", b"world")
+        .build();
+    let at = archive.windows(5).position(|w| w == b"hello").unwrap();
+    archive[at] = b'j';
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive), 8);
+
+    match extractor.next() {
+        Some(Err(e)) => match e.root() {
+            ZipError::CrcMismatch { filename, .. } => assert_eq!(filename, "bad.txt"),
+            other => panic!("expected CRC mismatch, got {:?}", other),
+        },
+        other => panic!("expected CRC mismatch, got {:?}", other),
+    }
+    assert_eq!(extractor.next().unwrap().unwrap().data, b"world");
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_cancel_flag_stops_reading() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .stored("b.txt", b"world")
+        .build();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let mut extractor =
+        MuyZipido::from_reader(Cursor::new(archive), 8).with_cancel_flag(Arc::clone(&cancel));
+
+    assert_eq!(extractor.next().unwrap().unwrap().data, b"hello");
+    let boundary = extractor.resume_offset();
+    cancel.store(true, Ordering::Relaxed);
+    assert!(matches!(extractor.next(), Some(Err(ZipError::Cancelled))));
+    assert!(extractor.next().is_none());
+    assert_eq!(extractor.resume_offset(), boundary);
+}
+
+#[test]
+fn test_reads_only_as_entries_are_requested() {
+    struct Counting(Cursor<Vec<u8>>, Arc<AtomicUsize>);
+    impl std::io::Read for Counting {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.read(buf)?;
+            self.1.fetch_add(n, Ordering::SeqCst);
+            Ok(n)
+        }
+    }
+
+    let archive = (0..10)
+        .fold(ArchiveBuilder::new(), |builder, n| {
+            builder.stored(&format!("{}.bin", n), &[n; 1000])
+        })
+        .build();
+    let read = Arc::new(AtomicUsize::new(0));
+    let mut extractor =
+        MuyZipido::from_reader(Counting(Cursor::new(archive), Arc::clone(&read)), 64);
+
+    assert_eq!(read.load(Ordering::SeqCst), 0);
+    for n in 1..=3 {
+        extractor.next().unwrap().unwrap();
+        // Each entry is 1035 bytes, and reading never gets more than a
+        // chunk ahead of the entry returned.
+        assert!(read.load(Ordering::SeqCst) <= n * 1035 + 64);
+    }
+}
+
+#[test]
+fn test_error_variants_for_broken_entries() {
+    let next_error =
+        |archive: Vec<u8>| match MuyZipido::from_reader(Cursor::new(archive), 16).next() {
+            Some(Err(ZipError::InEntry { source, .. })) => *source,
+            other => panic!(
+                "expected an error, got {:?}",
+                other.map(|r| r.map(|e| e.info))
+            ),
+        };
+
+    let archive = ArchiveBuilder::new()
+        .raw("a.bz2", 12, b"BZh9", false)
+        .build();
+    assert!(matches!(
+        next_error(archive),
+        ZipError::UnsupportedCompression { method: 12, filename } if filename == "a.bz2"
+    ));
+
+    let archive = ArchiveBuilder::new()
+        .raw("a.bin", 8, &[0xff; 16], true)
+        .build();
+    let error = next_error(archive);
+    assert!(matches!(&error, ZipError::CorruptData { filename, .. } if filename == "a.bin"));
+    assert!(std::error::Error::source(&error).is_some());
+
+    // A streamed entry whose descriptor never arrives
+    let mut archive = ArchiveBuilder::new()
+        .stored_with_descriptor("a.txt", b"hello")
+        .build();
+    let at = archive.windows(4).position(|w| w == b"PK\x07\x08").unwrap();
+    archive.truncate(at);
+    assert!(matches!(
+        next_error(archive),
+        ZipError::DescriptorNotFound { filename } if filename == "a.txt"
+    ));
+}
+
+#[test]
+fn test_errors_carry_entry_context() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .raw("data/b.bin", 8, &[0xff; 16], false)
+        .stored("c.txt", b"world")
+        .build();
+    let b_end = archive.windows(16).position(|w| w == [0xff; 16]).unwrap() + 16;
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive), 8);
+    assert!(extractor.next().unwrap().is_ok());
+
+    let error = extractor.next().unwrap().unwrap_err();
+    let ZipError::InEntry {
+        filename,
+        index,
+        offset,
+        source,
+    } = &error
+    else {
+        panic!("expected entry context, got {:?}", error);
+    };
+    assert_eq!(filename, "data/b.bin");
+    assert_eq!(*index, 1);
+    assert_eq!(*offset, b_end as u64);
+    assert!(matches!(**source, ZipError::CorruptData { .. }));
+    assert!(matches!(error.root(), ZipError::CorruptData { .. }));
+    assert!(error.to_string().starts_with(&format!(
+        "while processing 'data/b.bin' (entry 1, offset {:#x}): Decompression error in data/b.bin",
+        b_end
+    )));
+}
+
+#[test]
+fn test_timing() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .with_data_descriptor("b.csv", &b"x,y\n1,2\n".repeat(100))
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_reader(Cursor::new(archive.clone()), 16)
+        .map(Result::unwrap)
+        .collect();
+    assert!(entries.iter().all(|e| e.download_duration.is_none()));
+    assert!(entries.iter().all(|e| e.decompression_duration.is_none()));
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive), 16).with_timing(true);
+    let entries: Vec<_> = extractor.by_ref().map(Result::unwrap).collect();
+    assert!(entries.iter().all(|e| e.download_duration.is_some()));
+    assert_eq!(entries[0].decompression_duration, Some(Duration::ZERO));
+    assert!(entries[1].decompression_duration.is_some());
+
+    let stats = extractor.download_stats().unwrap();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(
+        stats.compressed_bytes,
+        entries.iter().map(|e| e.compressed_size).sum::<u64>()
+    );
+    assert_eq!(
+        stats.decompression_duration,
+        entries
+            .iter()
+            .filter_map(|e| e.decompression_duration)
+            .sum()
+    );
+}
+
+#[test]
+fn test_debug_shows_stream_state() {
+    let archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive), 64);
+    assert!(format!("{:?}", extractor).starts_with("MuyZipido { url: None, offset: 0,"));
+
+    extractor.next().unwrap().unwrap();
+    let debug = format!("{:?}", extractor);
+    assert!(debug.contains("reader: Some(<reader>)"), "{}", debug);
+    assert!(debug.contains("chunk_size: 64"), "{}", debug);
+    assert!(debug.contains("progress_bar: false"), "{}", debug);
+}
+
+#[test]
+fn test_stored_and_deflated_entries() {
+    let csv = b"x,y\n1,2\n".repeat(50);
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", &csv)
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_bytes(archive, 7)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0].filename, "a.txt");
+    assert_eq!(entries[0].compression_method, CompressionMethod::Stored);
+    assert_eq!(entries[0].compressed_size, 5);
+    assert_eq!(entries[0].data, b"hello");
+
+    assert_eq!(entries[1].filename, "b.csv");
+    assert_eq!(entries[1].compression_method, CompressionMethod::Deflate);
+    assert_eq!(entries[1].uncompressed_size, csv.len() as u64);
+    assert!(entries[1].compressed_size < csv.len() as u64);
+    assert_eq!(entries[1].data, csv);
+}
+
+#[test]
+fn test_descriptor_entries_take_sizes_from_descriptor() {
+    let data = b"streamed ".repeat(20);
+    let archive = ArchiveBuilder::new()
+        .stored_with_descriptor("a.bin", &data)
+        .with_data_descriptor("b.bin", &data)
+        .stored("c.txt", b"after")
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_bytes(archive, 16)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(entries.len(), 3);
+    for entry in &entries[..2] {
+        assert_eq!(entry.data, data);
+        assert_eq!(entry.uncompressed_size, data.len() as u64);
+        assert_ne!(entry.crc32, 0);
+    }
+    assert_eq!(entries[0].compressed_size, data.len() as u64);
+    assert!(entries[1].compressed_size < data.len() as u64);
+    assert_eq!(entries[2].data, b"after");
+}
+
+#[test]
+fn test_empty_entries() {
+    let archive = ArchiveBuilder::new()
+        .stored("dir/", b"")
+        .stored("dir/empty.txt", b"")
+        .deflated("dir/empty.gz", b"")
+        .with_data_descriptor("dir/streamed", b"")
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_bytes(archive, 16)
+        .map(Result::unwrap)
+        .collect();
+    let names: Vec<&str> = entries.iter().map(|e| e.filename.as_str()).collect();
+    assert_eq!(
+        names,
+        ["dir/", "dir/empty.txt", "dir/empty.gz", "dir/streamed"]
+    );
+    assert!(entries[0].is_dir);
+    assert!(entries.iter().all(|e| e.data.is_empty()));
+}
+
+#[test]
+fn test_end_of_archive_detection() {
+    let empty = ArchiveBuilder::new().build();
+    assert!(MuyZipido::from_bytes(empty, 16).next().is_none());
+
+    // Reading stops at the central directory; whatever follows is never read.
+    let mut archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    archive.extend_from_slice(b"trailing garbage");
+    let mut extractor = MuyZipido::from_bytes(archive, 4);
+    assert_eq!(extractor.next().unwrap().unwrap().data, b"hello");
+    assert!(extractor.next().is_none());
+    assert!(extractor.next().is_none());
+
+    // Without a central directory the stream ends where a header should be.
+    let archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    let local_end = archive.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+    let mut extractor = MuyZipido::from_bytes(archive[..local_end].to_vec(), 4);
+    assert!(extractor.next().unwrap().is_ok());
+    assert!(matches!(
+        extractor.next(),
+        Some(Err(ZipError::UnexpectedEofInHeader { offset })) if offset == local_end as u64
+    ));
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_adaptive_chunk_size() {
+    /// Hands out at most 16 bytes per read, 20 ms apart.
+    struct Slow(Cursor<Vec<u8>>);
+
+    impl std::io::Read for Slow {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(20));
+            let len = buf.len().min(16);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    let large = ArchiveBuilder::new()
+        .stored("a.txt", &[b'a'; 50_000])
+        .deflated("b.txt", &[b'b'; 100])
+        .build();
+    let mut extractor = MuyZipido::from_bytes(large, 64).with_adaptive_chunk_size(64, 4096);
+    assert_eq!(extractor.by_ref().count(), 2);
+    assert_eq!(extractor.chunk_size(), 4096);
+
+    let small = ArchiveBuilder::new()
+        .stored("a.txt", &[b'a'; 100])
+        .deflated("b.txt", &[b'b'; 100])
+        .build();
+    let mut extractor =
+        MuyZipido::from_reader(Slow(Cursor::new(small)), 1024).with_adaptive_chunk_size(64, 4096);
+    assert_eq!(extractor.by_ref().count(), 2);
+    assert_eq!(extractor.chunk_size(), 64);
+
+    // Out of bounds sizes are brought within them.
+    let mut extractor =
+        MuyZipido::from_bytes(Vec::new(), 1 << 20).with_adaptive_chunk_size(64, 4096);
+    assert_eq!(extractor.chunk_size(), 4096);
+    extractor.set_chunk_size(0);
+    assert_eq!(extractor.chunk_size(), 1);
+}
+
+#[test]
+fn test_require_non_empty() {
+    let empty = ArchiveBuilder::new().build();
+    let mut extractor = MuyZipido::from_bytes(empty.clone(), 16).with_require_non_empty(true);
+    assert!(matches!(
+        extractor.next(),
+        Some(Err(ZipError::EmptyArchive))
+    ));
+    assert!(extractor.next().is_none());
+
+    // Without a central directory, an empty stream is an empty archive.
+    let mut extractor = MuyZipido::from_bytes(Vec::new(), 16)
+        .with_allow_missing_central_directory(true)
+        .with_require_non_empty(true);
+    assert!(matches!(
+        extractor.next(),
+        Some(Err(ZipError::EmptyArchive))
+    ));
+
+    let archive = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    let entries: Vec<_> = MuyZipido::from_bytes(archive.clone(), 16)
+        .with_require_non_empty(true)
+        .collect();
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].is_ok());
+
+    // Filtering everything out is not the same as having nothing.
+    let mut extractor = MuyZipido::from_bytes(archive, 16)
+        .with_filter(|name| name.ends_with(".csv"))
+        .with_require_non_empty(true);
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_end_of_central_directory_is_kept() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", b"x,y\n1,2\n")
+        .with_comment(b"exported 2024-05-01")
+        .build();
+
+    let mut extractor = MuyZipido::from_bytes(archive.clone(), 16);
+    assert!(extractor.eocd().is_none());
+    assert_eq!(extractor.by_ref().count(), 2);
+    let eocd = extractor.eocd().unwrap();
+    assert_eq!((eocd.entries_on_disk, eocd.total_entries), (2, 2));
+    assert_eq!(eocd.comment, b"exported 2024-05-01");
+
+    let mut listing = MuyZipido::from_bytes(ArchiveBuilder::new().build(), 16).list_entries();
+    assert!(listing.next().is_none());
+    assert_eq!(listing.eocd().unwrap().total_entries, 0);
+
+    // A damaged tail still ends the archive cleanly, without the record.
+    let mut damaged = archive.clone();
+    let end = damaged.len() - 22 - 19;
+    damaged[end] = b'X';
+    let mut extractor = MuyZipido::from_bytes(damaged, 16);
+    assert!(extractor.by_ref().all(|entry| entry.is_ok()));
+    assert!(extractor.eocd().is_none());
+    let mut extractor = MuyZipido::from_bytes(archive[..archive.len() - 3].to_vec(), 16);
+    assert_eq!(extractor.by_ref().count(), 2);
+    assert!(extractor.eocd().is_none());
+}
+
+#[test]
+fn test_zip64_end_of_central_directory() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.csv", b"x,y\n1,2\n")
+        .with_zip64_end_records()
+        .build();
+
+    let mut extractor = MuyZipido::from_bytes(archive.clone(), 16);
+    assert!(extractor.by_ref().all(|entry| entry.is_ok()));
+    assert_eq!(extractor.eocd().unwrap().total_entries, u16::MAX);
+    assert_eq!(extractor.zip64_eocd().unwrap().total_entries, 2);
+
+    // The central directory is found through the Zip64 record.
+    let directory = CentralDirectory::parse(&archive).unwrap();
+    let names: Vec<_> = directory.entries().iter().map(|e| &e.filename).collect();
+    assert_eq!(names, ["a.txt", "b.csv"]);
+
+    let plain = ArchiveBuilder::new().stored("a.txt", b"hello").build();
+    let mut extractor = MuyZipido::from_bytes(plain, 16);
+    assert_eq!(extractor.by_ref().count(), 1);
+    assert!(extractor.eocd().is_some());
+    assert!(extractor.zip64_eocd().is_none());
+}
+
+#[test]
+fn test_truncation_points() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .stored("b.txt", b"hello world")
+        .build();
+    // a.txt's header and data, then b.txt's.
+    let boundary = 30 + 5 + 5;
+    let b_data = boundary + 30 + 5;
+
+    let read = |len: usize, allow: bool| {
+        MuyZipido::from_bytes(archive[..len].to_vec(), 8)
+            .with_allow_missing_central_directory(allow)
+            .collect::<Vec<_>>()
+    };
+
+    // At an entry boundary: only the lenient mode accepts it.
+    let results = read(boundary, false);
+    assert_eq!(results.len(), 2);
+    assert!(matches!(
+        results[1],
+        Err(ZipError::UnexpectedEofInHeader { offset }) if offset == boundary as u64
+    ));
+    let mut extractor = MuyZipido::from_bytes(archive[..boundary].to_vec(), 8)
+        .with_allow_missing_central_directory(true);
+    let results: Vec<_> = extractor.by_ref().collect();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].as_ref().unwrap().data, b"hello");
+    assert_eq!(
+        extractor.warnings(),
+        [ZipWarning::MissingCentralDirectory {
+            offset: boundary as u64
+        }]
+    );
+
+    // Inside a header, even part way through the signature.
+    for len in [boundary + 2, boundary + 12, b_data - 1] {
+        for allow in [false, true] {
+            let results = read(len, allow);
+            assert_eq!(results.len(), 2, "{} {}", len, allow);
+            assert!(
+                matches!(
+                    results[1],
+                    Err(ZipError::UnexpectedEofInHeader { offset }) if offset == boundary as u64
+                ),
+                "{} {}: {:?}",
+                len,
+                allow,
+                results[1]
+            );
+        }
+    }
+
+    // Inside an entry's data.
+    for allow in [false, true] {
+        let results = read(b_data + 4, allow);
+        assert_eq!(results.len(), 2);
+        let error = results[1].as_ref().unwrap_err();
+        assert!(
+            matches!(
+                error.root(),
+                ZipError::UnexpectedEofInEntry { filename, bytes_missing: 7 } if filename == "b.txt"
+            ),
+            "{:?}",
+            error
+        );
+        assert!(!error.is_recoverable());
+    }
+}
+
+#[test]
+fn test_progress_observer_reports_decompression() {
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, u64, u64)>>);
+
+    impl ProgressObserver for Recorder {
+        fn entry_progress(&self, entry: &EntryInfo, decompressed: u64) {
+            let event = (
+                entry.filename.clone(),
+                decompressed,
+                entry.uncompressed_size,
+            );
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    let large: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+    let archive = ArchiveBuilder::new()
+        .deflated("large.bin", &large)
+        .with_data_descriptor("streamed.bin", &large[..500_000])
+        .stored("small.txt", b"hello")
+        .build();
+
+    let recorder = Arc::new(Recorder::default());
+    let interval = 256 * 1024;
+    let extractor = MuyZipido::from_bytes(archive, 64 * 1024)
+        .with_progress_observer(Arc::clone(&recorder))
+        .with_progress_interval(interval);
+    assert_eq!(extractor.count(), 3);
+
+    let events = recorder.0.lock().unwrap();
+    for (name, size) in [
+        ("large.bin", large.len() as u64),
+        ("streamed.bin", 500_000),
+        ("small.txt", 5),
+    ] {
+        let progress: Vec<u64> = events
+            .iter()
+            .filter(|(n, _, _)| n == name)
+            .map(|&(_, done, total)| {
+                assert_eq!(total, size);
+                done
+            })
+            .collect();
+        assert!(progress.windows(2).all(|w| w[0] < w[1]), "{:?}", progress);
+        assert_eq!(progress.last(), Some(&size));
+        assert!(progress.len() as u64 <= size / interval + 2);
+    }
+    assert!(events.iter().filter(|(n, _, _)| n == "large.bin").count() > 5);
+}
+
+#[test]
+fn test_decompression_chunk_size() {
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<u64>>);
+
+    impl ProgressObserver for Recorder {
+        fn entry_progress(&self, _: &EntryInfo, decompressed: u64) {
+            self.0.lock().unwrap().push(decompressed);
+        }
+    }
+
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+    let archive = ArchiveBuilder::new().deflated("data.bin", &data).build();
+
+    for chunk_size in [1, 1000, 1 << 20] {
+        let recorder = Arc::new(Recorder::default());
+        let entries: Vec<_> = MuyZipido::from_bytes(archive.clone(), 4096)
+            .with_decompression_chunk_size(chunk_size)
+            .with_progress_observer(Arc::clone(&recorder))
+            .with_progress_interval(1)
+            .collect();
+        assert_eq!(entries[0].as_ref().unwrap().data, data);
+
+        // Progress is reported once per block decoded.
+        let progress = recorder.0.lock().unwrap();
+        assert!(
+            progress
+                .windows(2)
+                .all(|w| w[1] - w[0] <= chunk_size as u64),
+            "{}: {:?}",
+            chunk_size,
+            progress
+        );
+        assert!(progress.len() >= data.len() / chunk_size);
+
+        let out = tempfile::tempdir().unwrap();
+        MuyZipido::from_bytes(archive.clone(), 4096)
+            .with_decompression_chunk_size(chunk_size)
+            .extract_to_dir(out.path())
+            .unwrap();
+        assert_eq!(std::fs::read(out.path().join("data.bin")).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_empty_streamed_entries() {
+    let archive = ArchiveBuilder::new()
+        .stored_with_descriptor("empty.txt", b"")
+        .stored_with_descriptor("full.txt", b"data")
+        .stored_with_descriptor("empty-too.txt", b"")
+        .build();
+
+    let mut extractor = MuyZipido::from_bytes(archive.clone(), 3);
+    let first = extractor.next().unwrap().unwrap();
+    assert!(first.data.is_empty());
+    assert_eq!(first.crc32, 0);
+    // Header, name and the 16-byte descriptor.
+    assert_eq!(extractor.resume_offset(), 30 + 9 + 16);
+
+    let rest: Vec<_> = extractor.map(|e| e.unwrap().data).collect();
+    assert_eq!(rest, [b"data".to_vec(), Vec::new()]);
+}
+
+#[test]
+fn test_raw_entries_are_returned_as_stored() {
+    let csv = b"x,y\n1,2\n".repeat(40);
+    let archive = ArchiveBuilder::new()
+        .deflated("b.csv", &csv)
+        .with_data_descriptor("c.csv", &csv)
+        .raw("d.bz2", 12, b"BZh9", false)
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_bytes(archive.clone(), 16)
+        .with_raw_entries(true)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(entries.len(), 3);
+
+    let data_start = 30 + "b.csv".len();
+    let stored = &archive[data_start..data_start + entries[0].compressed_size as usize];
+    assert_eq!(entries[0].data, stored);
+    assert_eq!(entries[0].compression_method, CompressionMethod::Deflate);
+    assert_eq!(entries[0].uncompressed_size, csv.len() as u64);
+
+    assert_eq!(entries[1].data, stored);
+    assert_eq!(entries[1].compressed_size, stored.len() as u64);
+    assert_eq!(entries[1].crc32, entries[0].crc32);
+
+    assert_eq!(entries[2].data, b"BZh9");
+    assert_eq!(entries[2].compression_method, CompressionMethod::Bzip2);
+}
+
+fn read_all(archive: Vec<u8>) -> Vec<muy_zipido::ZipEntry> {
+    MuyZipido::from_reader(Cursor::new(archive), 16)
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn test_stored_entry_metadata() {
+    let archive = ArchiveBuilder::new()
+        .stored("notes/a.txt", b"hello")
+        .build();
+
+    let entries = read_all(archive);
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.filename, "notes/a.txt");
+    assert_eq!(entry.compressed_size, 5);
+    assert_eq!(entry.uncompressed_size, 5);
+    assert_eq!(entry.crc32, 0x3610a686);
+    assert!(!entry.is_dir);
+    let modified = entry.modified.unwrap();
+    assert_eq!((modified.year, modified.month, modified.day), (1980, 1, 1));
+}
+
+#[test]
+fn test_deflated_entry_spanning_many_chunks() {
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 97) as u8).collect();
+    let archive = ArchiveBuilder::new().deflated("large.bin", &data).build();
+
+    let entries = read_all(archive);
+    assert_eq!(entries[0].data, data);
+    assert_eq!(entries[0].uncompressed_size, data.len() as u64);
+}
+
+#[test]
+fn test_stored_descriptor_entry() {
+    let archive = ArchiveBuilder::new()
+        .stored_with_descriptor("a.txt", b"streamed data")
+        .build();
+
+    let entries = read_all(archive);
+    assert_eq!(entries[0].data, b"streamed data");
+    assert_eq!(entries[0].compression_method, CompressionMethod::Stored);
+    assert_eq!(entries[0].compressed_size, 13);
+}
+
+#[test]
+fn test_deflated_descriptor_entry() {
+    let data = b"row,value\n".repeat(500);
+    let archive = ArchiveBuilder::new()
+        .with_data_descriptor("rows.csv", &data)
+        .build();
+
+    let entries = read_all(archive);
+    assert_eq!(entries[0].data, data);
+    assert_eq!(entries[0].compression_method, CompressionMethod::Deflate);
+    assert!(entries[0].compressed_size < data.len() as u64);
+}
+
+#[test]
+fn test_zero_byte_stored_entry() {
+    let archive = ArchiveBuilder::new()
+        .stored("empty.txt", b"")
+        .stored("b.txt", b"b")
+        .build();
+
+    let entries = read_all(archive);
+    assert!(entries[0].data.is_empty());
+    assert_eq!(entries[0].crc32, 0);
+    assert_eq!(entries[1].data, b"b");
+}
+
+#[test]
+fn test_many_entries_in_sequence() {
+    let mut builder = ArchiveBuilder::new();
+    for i in 0..40 {
+        let data = format!("entry {}", i).repeat(i + 1);
+        builder = if i % 3 == 0 {
+            builder.stored(&format!("{:02}.txt", i), data.as_bytes())
+        } else if i % 3 == 1 {
+            builder.deflated(&format!("{:02}.txt", i), data.as_bytes())
+        } else {
+            builder.with_data_descriptor(&format!("{:02}.txt", i), data.as_bytes())
+        };
+    }
+
+    let entries = read_all(builder.build());
+    assert_eq!(entries.len(), 40);
+    for (i, entry) in entries.iter().enumerate() {
+        assert_eq!(entry.filename, format!("{:02}.txt", i));
+        assert_eq!(entry.data, format!("entry {}", i).repeat(i + 1).as_bytes());
+    }
+}
+
+#[test]
+fn test_central_directory_ends_iteration() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"a")
+        .stored("b.txt", b"b")
+        .build();
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive), 16);
+    assert!(extractor.next().unwrap().is_ok());
+    assert!(extractor.next().unwrap().is_ok());
+    assert!(extractor.next().is_none());
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_entries_straddling_chunk_boundaries() {
+    let large: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+    let archive = ArchiveBuilder::new()
+        .stored("empty", b"")
+        .stored("one", b"1")
+        .stored("odd", &large[..37])
+        .deflated("deflated", &large)
+        .stored("large", &large)
+        .stored("tail", &large[..99])
+        .build();
+
+    let end_record = &archive[archive.len() - 22..];
+    let central_offset = u32::from_le_bytes(end_record[16..20].try_into().unwrap()) as u64;
+
+    // Chunk sizes that put entry boundaries at every position within a
+    // chunk, including entries larger and smaller than a chunk.
+    for chunk_size in (1..=64).chain([100, 999, 1000, 1001, 4096]) {
+        let tee = SharedBuffer::default();
+        let mut extractor =
+            MuyZipido::from_reader(Cursor::new(archive.clone()), chunk_size).with_tee(tee.clone());
+        let sizes: Vec<usize> = extractor
+            .by_ref()
+            .map(|entry| entry.unwrap().data.len())
+            .collect();
+
+        assert_eq!(
+            sizes,
+            [0, 1, 37, 1000, 1000, 99],
+            "chunk size {}",
+            chunk_size
+        );
+        assert_eq!(tee.contents(), archive, "chunk size {}", chunk_size);
+        assert_eq!(
+            extractor.resume_offset(),
+            central_offset,
+            "chunk size {}",
+            chunk_size
+        );
+    }
+
+    let large_entry = MuyZipido::from_reader(Cursor::new(archive), 7)
+        .find_entry(|info| info.filename == "large")
+        .unwrap()
+        .unwrap();
+    assert_eq!(large_entry.data, large);
+}
+
+#[test]
+fn test_count_entries_fast() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"a")
+        .raw("garbage.bin", 8, &[0xff; 64], false)
+        .with_data_descriptor("c.csv", b"x,y\n")
+        .raw("d.bz2", 12, b"BZh9", false)
+        .build();
+
+    // Nothing is decompressed, so undecodable entries are counted too.
+    let count = MuyZipido::from_bytes(archive.clone(), 16)
+        .with_filter(|name| name.ends_with(".txt"))
+        .count_entries_fast()
+        .unwrap();
+    assert_eq!(count, 4);
+
+    let mut extractor = MuyZipido::from_bytes(archive, 16);
+    extractor.next().unwrap().unwrap();
+    assert_eq!(extractor.count_entries_fast().unwrap(), 3);
+
+    let truncated = ArchiveBuilder::new().stored("a.txt", b"abc").build()[..32].to_vec();
+    assert!(
+        MuyZipido::from_bytes(truncated, 16)
+            .count_entries_fast()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_non_zip_input_is_invalid_signature() {
+    let mut extractor = MuyZipido::from_reader(Cursor::new(b"<html>oops</html>".to_vec()), 16);
+
+    let Some(Err(ZipError::InvalidSignature { found, offset })) = extractor.next() else {
+        panic!("expected an invalid signature");
+    };
+    assert_eq!(found, "3c68746d");
+    assert_eq!(offset, 0);
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_truncated_entry_data() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello world")
+        .build();
+    let truncated = archive[..30 + 5 + 4].to_vec();
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(truncated), 16);
+    let Some(Err(error)) = extractor.next() else {
+        panic!("expected an error");
+    };
+    assert!(
+        matches!(
+            error.root(),
+            ZipError::UnexpectedEofInEntry { filename, bytes_missing: 7 } if filename == "a.txt"
+        ),
+        "{:?}",
+        error
+    );
+    assert!(!error.is_recoverable());
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_empty_input() {
+    let mut extractor = MuyZipido::from_reader(Cursor::new(Vec::new()), 16);
+    assert!(matches!(
+        extractor.next(),
+        Some(Err(ZipError::UnexpectedEofInHeader { offset: 0 }))
+    ));
+    assert!(extractor.next().is_none());
+}
+
+#[test]
+fn test_gunzip_transform() {
+    use flate2::write::GzEncoder;
+    use muy_zipido::Transform;
+    use std::io::Write;
+
+    let csv = b"x,y\n1,2\n3,4\n".repeat(20);
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&csv).unwrap();
+    let gz = encoder.finish().unwrap();
+
+    let archive = ArchiveBuilder::new()
+        .stored("points.csv.gz", &gz)
+        .deflated("readme.txt", b"hello")
+        .stored("broken.csv.gz", b"not gzip")
+        .stored("last.csv.gz", &gz)
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_reader(Cursor::new(archive.clone()), 16)
+        .with_entry_transform(Transform::GunzipByExtension)
+        .with_entry_transform(Transform::custom(|name, data| {
+            name.ends_with(".txt").then(|| data.to_ascii_uppercase())
+        }))
+        .collect();
+    assert_eq!(entries.len(), 4);
+
+    let points = entries[0].as_ref().unwrap();
+    assert_eq!(points.filename, "points.csv");
+    assert_eq!(points.data, csv);
+    assert_eq!(points.uncompressed_size, csv.len() as u64);
+    let original = points.original.as_ref().unwrap();
+    assert_eq!(original.filename, "points.csv.gz");
+    assert_eq!(original.uncompressed_size, gz.len() as u64);
+
+    let readme = entries[1].as_ref().unwrap();
+    assert_eq!(readme.filename, "readme.txt");
+    assert_eq!(readme.data, b"HELLO");
+
+    let error = entries[2].as_ref().unwrap_err();
+    assert!(matches!(error.root(), ZipError::CorruptData { .. }));
+    assert!(error.is_recoverable());
+    assert_eq!(entries[3].as_ref().unwrap().data, csv);
+
+    // Raw entries are left as stored.
+    let raw = MuyZipido::from_bytes(archive, 16)
+        .with_raw_entries(true)
+        .with_entry_transform(Transform::GunzipByExtension)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(raw.filename, "points.csv.gz");
+    assert!(raw.original.is_none());
+}
+
+#[test]
+fn test_version_needed_check() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .stored("aes.bin", b"data")
+        .build();
+    let second = 30 + "a.txt".len() + 5;
+    archive[second + 4..second + 6].copy_from_slice(&51u16.to_le_bytes());
+
+    // Zip64 (4.5) is supported.
+    let mut zip64 = archive.clone();
+    zip64[second + 4..second + 6].copy_from_slice(&45u16.to_le_bytes());
+    let entries: Vec<_> = MuyZipido::from_bytes(zip64, 16)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(entries[1].version_needed, 45);
+
+    let mut extractor = MuyZipido::from_reader(Cursor::new(archive.clone()), 16);
+    let first = extractor.next().unwrap().unwrap();
+    assert_eq!(first.version_needed, 20);
+
+    let Some(Err(error)) = extractor.next() else {
+        panic!("expected an error");
+    };
+    let ZipError::UnsupportedFeature {
+        needed_version,
+        filename,
+        hint,
+    } = error.root()
+    else {
+        panic!("expected UnsupportedFeature, got {:?}", error);
+    };
+    assert_eq!(*needed_version, 51);
+    assert_eq!(filename, "aes.bin");
+    assert!(hint.contains("AES"), "{}", hint);
+    assert!(
+        error.to_string().contains("needs ZIP version 5.1"),
+        "{}",
+        error
+    );
+    assert!(!error.is_recoverable());
+    assert!(extractor.next().is_none());
+
+    // Listing does not decode anything, so it is not affected.
+    let listed: Vec<u16> = MuyZipido::from_reader(Cursor::new(archive.clone()), 16)
+        .list_entries()
+        .map(|info| info.unwrap().version_needed)
+        .collect();
+    assert_eq!(listed, [20, 51]);
+
+    let entries: Vec<_> = MuyZipido::from_reader(Cursor::new(archive), 16)
+        .with_ignore_version_needed(true)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(entries[1].data, b"data");
+}
+
+#[test]
+fn test_zip64_data_descriptor() {
+    let archive = ArchiveBuilder::new()
+        .with_zip64_sizes()
+        .with_data_descriptor("big.csv", b"x,y\n1,2\n")
+        .stored("z.txt", b"last")
+        .build();
+
+    let entries: Vec<_> = MuyZipido::from_bytes(archive.clone(), 16)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].version_needed, 45);
+    assert_eq!(entries[0].data, b"x,y\n1,2\n");
+    assert_eq!(entries[0].uncompressed_size, 8);
+    assert_eq!(entries[0].data_len_on_wire, entries[0].compressed_size);
+    assert_eq!(entries[1].data, b"last");
+
+    let listed: Vec<_> = MuyZipido::from_bytes(archive, 16)
+        .list_entries()
+        .map(|info| info.unwrap().filename)
+        .collect();
+    assert_eq!(listed, ["big.csv", "z.txt"]);
+}
+
+#[test]
+fn test_central_directory_matches_stream() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .with_data_descriptor("b.csv", b"x,y\n1,2\n")
+        .deflated("c/d.bin", &[7u8; 300])
+        .build();
+    // A trailing comment must not hide the end record.
+    let len = archive.len();
+    archive[len - 2..].copy_from_slice(&4u16.to_le_bytes());
+    archive.extend_from_slice(b"note");
+
+    let directory = CentralDirectory::parse(&archive).unwrap();
+    let streamed: Vec<EntryInfo> = MuyZipido::from_bytes(archive.clone(), 64)
+        .list_entries()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(directory.len(), 3);
+    for (central, info) in directory.entries_by_offset().zip(&streamed) {
+        assert_eq!(central.filename, info.filename);
+        assert_eq!(central.crc32, info.crc32);
+        assert_eq!(central.compressed_size, info.compressed_size);
+        assert_eq!(
+            &archive[central.local_header_offset as usize..][..4],
+            b"PK\x03\x04"
+        );
+        assert_eq!(central.local_header_offset, info.header_offset);
+    }
+}
+
+#[test]
+fn test_entry_byte_ranges() {
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .with_data_descriptor("b.csv", &b"x,y\n1,2\n".repeat(20))
+        .stored_with_descriptor("c.txt", b"streamed")
+        .deflated("d.bin", &[7u8; 300])
+        .build();
+
+    let check = |info: &EntryInfo| {
+        let header = info.header_offset as usize;
+        let data = info.data_offset as usize;
+        let end = data + info.data_len_on_wire as usize;
+        assert_eq!(&archive[header..header + 4], b"PK\x03\x04");
+        assert_eq!(data, header + 30 + info.filename.len());
+        assert_eq!(info.data_len_on_wire, info.compressed_size);
+        assert!(archive[end..].starts_with(b"PK"));
+    };
+
+    let entries: Vec<_> = MuyZipido::from_bytes(archive.clone(), 16)
+        .map(Result::unwrap)
+        .collect();
+    for entry in &entries {
+        check(entry);
+    }
+    assert_eq!(entries[0].header_offset, 0);
+    assert_eq!(entries[0].data_offset, 35);
+    assert_eq!(entries[0].data_len_on_wire, 5);
+    let c = &entries[2];
+    assert_eq!(
+        &archive[c.data_offset as usize..][..c.data_len_on_wire as usize],
+        b"streamed"
+    );
+    assert_eq!(
+        &archive[(c.data_offset + c.data_len_on_wire) as usize..][..4],
+        b"PK\x07\x08"
+    );
+
+    // Skipped entries are located too.
+    let listed: Vec<EntryInfo> = MuyZipido::from_bytes(archive.clone(), 16)
+        .list_entries()
+        .map(Result::unwrap)
+        .collect();
+    for (info, entry) in listed.iter().zip(&entries) {
+        check(info);
+        assert_eq!(info.data_offset, entry.data_offset);
+        assert_eq!(info.data_len_on_wire, entry.data_len_on_wire);
+    }
+}
+
+#[test]
+fn test_warnings_for_recoverable_anomalies() {
+    let mut archive = ArchiveBuilder::new()
+        .deflated("a.csv", b"x,y\n1,2\n1,2\n")
+        .stored("b.txt", b"hello")
+        .build();
+    // Understate the first entry's uncompressed size in its local header.
+    archive[22..26].copy_from_slice(&4u32.to_le_bytes());
+    // Replace the "b" in the second entry's name with a CP437 byte.
+    let second = archive
+        .windows(4)
+        .rposition(|w| w == b"PK\x03\x04")
+        .unwrap();
+    archive[second + 30] = 0x8e;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = Arc::clone(&seen);
+    let mut extractor = MuyZipido::from_bytes(archive, 16)
+        .with_warning_handler(move |w| handler_seen.lock().unwrap().push(w.clone()));
+
+    let entries: Vec<_> = extractor.by_ref().map(Result::unwrap).collect();
+    assert_eq!(entries[0].uncompressed_size, 12);
+    assert_eq!(entries[1].filename, "Ä.txt");
+
+    assert_eq!(
+        extractor.warnings(),
+        [
+            ZipWarning::SizeMismatch {
+                filename: "a.csv".to_string(),
+                header: 4,
+                actual: 12,
+            },
+            ZipWarning::Cp437Filename {
+                filename: "Ä.txt".to_string(),
+                offset: second as u64,
+            },
+        ]
+    );
+    assert_eq!(*seen.lock().unwrap(), extractor.warnings());
+}
+
+/// An archive with one entry named "データ.csv" in Shift-JIS, without the
+/// UTF-8 flag.
+fn shift_jis_archive() -> Vec<u8> {
+    const NAME: &[u8] = &[0x83, 0x66, 0x81, 0x5b, 0x83, 0x5e];
+
+    let mut archive = ArchiveBuilder::new().stored("xxxxxx.csv", b"x,y\n").build();
+    while let Some(pos) = archive.windows(6).position(|w| w == b"xxxxxx") {
+        archive[pos..pos + 6].copy_from_slice(NAME);
+    }
+    archive
+}
+
+#[test]
+fn test_raw_filenames() {
+    let entry = MuyZipido::from_bytes(shift_jis_archive(), 16)
+        .with_raw_filenames(true)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        entry.filename_raw.as_deref(),
+        Some(&b"\x83\x66\x81\x5b\x83\x5e.csv"[..])
+    );
+    assert_eq!(
+        entry.filename,
+        String::from_utf8_lossy(b"\x83\x66\x81\x5b\x83\x5e.csv")
+    );
+
+    // Without the override the name is taken as CP437.
+    let entry = MuyZipido::from_bytes(shift_jis_archive(), 16)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.filename_raw, None);
+    assert_eq!(entry.filename, "âfü[â^.csv");
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn test_filename_encoding_override() {
+    let mut extractor = MuyZipido::from_bytes(shift_jis_archive(), 16)
+        .with_filename_encoding(encoding_rs::SHIFT_JIS);
+    let entry = extractor.next().unwrap().unwrap();
+    assert_eq!(entry.filename, "データ.csv");
+    assert!(extractor.warnings().is_empty());
+
+    let dir = tempfile::tempdir().unwrap();
+    MuyZipido::from_bytes(shift_jis_archive(), 16)
+        .with_filename_encoding(encoding_rs::SHIFT_JIS)
+        .extract_to_dir(dir.path())
+        .unwrap();
+    assert_eq!(
+        std::fs::read(dir.path().join("データ.csv")).unwrap(),
+        b"x,y\n"
+    );
+}
+
+/// An archive whose first entry, "bad.txt", is damaged by `damage`, followed
+/// by a sound entry.
+fn damaged(descriptor: bool, damage: impl Fn(&mut Vec<u8>)) -> Vec<u8> {
+    let builder = ArchiveBuilder::new();
+    let builder = match descriptor {
+        true => builder.stored_with_descriptor("bad.txt", b"hello"),
+        false => builder.stored("bad.txt", b"hello"),
+    };
+    let mut archive = builder.stored("ok.txt", b"fine").build();
+    damage(&mut archive);
+    archive
+}
+
+/// Inserts `extra` as the first entry's extra field.
+fn with_extra(archive: &mut Vec<u8>, extra: &[u8]) {
+    archive[28..30].copy_from_slice(&(extra.len() as u16).to_le_bytes());
+    archive.splice(37..37, extra.iter().copied());
+}
+
+#[test]
+fn test_strict_mode_rejects_inconsistent_headers() {
+    let fixtures: [(&str, Vec<u8>, ZipWarning); 4] = [
+        (
+            "uncompressed size",
+            damaged(false, |a| a[22..26].copy_from_slice(&9u32.to_le_bytes())),
+            ZipWarning::SizeMismatch {
+                filename: "bad.txt".to_string(),
+                header: 9,
+                actual: 5,
+            },
+        ),
+        (
+            "sizes with descriptor",
+            damaged(true, |a| a[18..22].copy_from_slice(&5u32.to_le_bytes())),
+            ZipWarning::DescriptorWithSizes {
+                filename: "bad.txt".to_string(),
+            },
+        ),
+        (
+            "overlong extra field",
+            damaged(false, |a| with_extra(a, &[0x55, 0x54, 9, 0, 1])),
+            ZipWarning::TruncatedExtraField {
+                filename: "bad.txt".to_string(),
+            },
+        ),
+        (
+            "trailing extra bytes",
+            damaged(false, |a| with_extra(a, &[0x55, 0x54, 1, 0, 1, 0, 0])),
+            ZipWarning::TruncatedExtraField {
+                filename: "bad.txt".to_string(),
+            },
+        ),
+    ];
+
+    for (rule, archive, expected) in fixtures {
+        let mut lenient = MuyZipido::from_bytes(archive.clone(), 8);
+        let entries: Vec<_> = lenient.by_ref().collect();
+        assert!(entries.iter().all(Result::is_ok), "{}", rule);
+        // Padding is tolerated outside strict mode.
+        if rule != "trailing extra bytes" {
+            assert_eq!(
+                lenient.warnings(),
+                std::slice::from_ref(&expected),
+                "{}",
+                rule
+            );
+        }
+
+        let results: Vec<_> = MuyZipido::from_bytes(archive.clone(), 8)
+            .with_strict(true)
+            .collect();
+        assert_eq!(results.len(), 2, "{}", rule);
+        match results[0].as_ref().map_err(ZipError::root) {
+            Err(ZipError::Inconsistent(warning)) => assert_eq!(*warning, expected, "{}", rule),
+            other => panic!("{}: expected an inconsistency, got {:?}", rule, other),
+        }
+        assert!(results[0].as_ref().unwrap_err().is_recoverable());
+        assert_eq!(results[1].as_ref().unwrap().data, b"fine", "{}", rule);
+
+        // Listing reads headers but does not decode data.
+        if rule == "uncompressed size" {
+            continue;
+        }
+        let listing: Vec<_> = MuyZipido::from_bytes(archive, 8)
+            .with_strict(true)
+            .list_entries()
+            .collect();
+        assert!(
+            matches!(
+                listing[0].as_ref().map_err(ZipError::root),
+                Err(ZipError::Inconsistent(_))
+            ),
+            "{}",
+            rule
+        );
+    }
+
+    // A bad CRC-32 is an error either way.
+    let archive = damaged(false, |a| a[14] ^= 0xff);
+    for strict in [false, true] {
+        let first = MuyZipido::from_bytes(archive.clone(), 8)
+            .with_strict(strict)
+            .next()
+            .unwrap();
+        assert!(matches!(
+            first.unwrap_err().root(),
+            ZipError::CrcMismatch { .. }
+        ));
+    }
+}
+
+#[test]
+fn test_zero_sizes_disagreeing_with_the_data() {
+    let archive = ArchiveBuilder::new()
+        .with_declared_size(10)
+        .stored("declared.txt", b"")
+        .with_declared_size(0)
+        .deflated("undeclared.txt", b"hello")
+        .stored("empty.txt", b"")
+        .deflated("empty.csv", b"")
+        .build();
+
+    let mut lenient = MuyZipido::from_bytes(archive.clone(), 8);
+    let entries: Vec<_> = lenient.by_ref().map(Result::unwrap).collect();
+    let sizes: Vec<(&str, &[u8], u64)> = entries
+        .iter()
+        .map(|e| (e.filename.as_str(), e.data.as_slice(), e.uncompressed_size))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            ("declared.txt", &b""[..], 0),
+            ("undeclared.txt", b"hello", 5),
+            ("empty.txt", b"", 0),
+            ("empty.csv", b"", 0),
+        ]
+    );
+    let expected = [
+        ZipWarning::EmptyDataWithSize {
+            filename: "declared.txt".to_string(),
+            uncompressed_size: 10,
+        },
+        ZipWarning::DataWithZeroSize {
+            filename: "undeclared.txt".to_string(),
+            actual: 5,
+        },
+    ];
+    assert_eq!(lenient.warnings(), expected);
+
+    let results: Vec<_> = MuyZipido::from_bytes(archive, 8)
+        .with_strict(true)
+        .collect();
+    assert_eq!(results.len(), 4);
+    for (result, expected) in results.iter().zip(&expected) {
+        match result.as_ref().map_err(ZipError::root) {
+            Err(ZipError::Inconsistent(warning)) => assert_eq!(warning, expected),
+            other => panic!("expected an inconsistency, got {:?}", other),
+        }
+    }
+    assert!(results[2..].iter().all(Result::is_ok));
+}
+
+/// Decodes method 99, a byte-wise XOR with 0x5a, and counts its calls.
+#[derive(Default)]
+struct XorDecompressor {
+    calls: Arc<AtomicUsize>,
+}
+
+impl Decompressor for XorDecompressor {
+    fn decompress(&self, method: CompressionMethod, input: &[u8]) -> Result<Vec<u8>, ZipError> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match method {
+            CompressionMethod::Unknown(99) => Ok(input.iter().map(|b| b ^ 0x5a).collect()),
+            _ => FlateDecompressor.decompress(method, input),
+        }
+    }
+
+    fn supports(&self, method: CompressionMethod) -> bool {
+        method == CompressionMethod::Unknown(99) || FlateDecompressor.supports(method)
+    }
+}
+
+#[test]
+fn test_custom_decompressor() {
+    let mut archive = ArchiveBuilder::new()
+        .stored("a.txt", b"hello")
+        .deflated("b.txt", &[b'a'; 100])
+        .build();
+    // Re-encode the stored entry as method 99; its CRC-32 still matches.
+    archive[8..10].copy_from_slice(&99u16.to_le_bytes());
+    for byte in &mut archive[35..40] {
+        *byte ^= 0x5a;
+    }
+
+    let results: Vec<_> = MuyZipido::from_bytes(archive.clone(), 16).collect();
+    assert!(matches!(
+        results[0].as_ref().unwrap_err().root(),
+        ZipError::UnsupportedCompression { method: 99, .. }
+    ));
+
+    let decompressor = XorDecompressor::default();
+    let calls = Arc::clone(&decompressor.calls);
+    let entries: Vec<_> = MuyZipido::from_bytes(archive.clone(), 16)
+        .with_decompressor(decompressor)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(entries[0].data, b"hello");
+    assert_eq!(entries[1].data, [b'a'; 100]);
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+    // Output over the entry size limit is rejected after decoding.
+    let results: Vec<_> = MuyZipido::from_bytes(archive, 16)
+        .with_decompressor(XorDecompressor::default())
+        .with_limits(Limits::new().with_max_entry_size(50))
+        .collect();
+    assert!(results[0].is_ok());
+    assert!(matches!(
+        results[1].as_ref().unwrap_err().root(),
+        ZipError::LimitExceeded { limit: 50, .. }
+    ));
+}
+
+#[cfg(feature = "signatures")]
+#[test]
+fn test_verify_signatures() {
+    use ed25519_dalek::{Signer, SigningKey};
+    use muy_zipido::checksum::sha256_hex;
+
+    let key = SigningKey::from_bytes(&[3; 32]);
+    let public = key.verifying_key().to_bytes();
+    let listing = format!(
+        "{}  ./a.txt\n{}  ./docs/b.txt\n",
+        sha256_hex(b"alpha"),
+        sha256_hex(b"bravo bravo bravo")
+    );
+    let signature = key.sign(listing.as_bytes()).to_bytes();
+    let archive = |b_data: &[u8], signed: bool| {
+        let builder = ArchiveBuilder::new()
+            .stored("a.txt", b"alpha")
+            .stored("docs/", b"")
+            .deflated("docs/b.txt", b_data);
+        match signed {
+            true => builder.stored("release.sig", &signature),
+            false => builder,
+        }
+        .build()
+    };
+    let run = |archive: Vec<u8>, filter: bool| {
+        let extractor = MuyZipido::from_bytes(archive, 16)
+            .with_verify_signatures(&public)
+            .unwrap();
+        match filter {
+            true => extractor
+                .with_entry_filter(|info: &EntryInfo| info.filename == "a.txt")
+                .collect::<Vec<_>>(),
+            false => extractor.collect(),
+        }
+    };
+
+    let results = run(archive(b"bravo bravo bravo", true), false);
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(Result::is_ok));
+
+    // Entries left out by a filter still count towards the signature.
+    let results = run(archive(b"bravo bravo bravo", true), true);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_ok());
+
+    let results = run(archive(b"bravo bravo BRAVO", true), true);
+    assert_eq!(results.len(), 2);
+    assert!(matches!(
+        results.last().unwrap(),
+        Err(ZipError::SignatureRejected { reason })
+            if reason == "the signature does not match the archive"
+    ));
+
+    let results = run(archive(b"bravo bravo bravo", false), true);
+    assert_eq!(results.len(), 2);
+    let Some(Err(error)) = results.last() else {
+        panic!("expected an error");
+    };
+    assert!(matches!(
+        error,
+        ZipError::UnsupportedFeature { needed_version: 0, hint, .. }
+            if hint == "no signature entry found"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Unsupported archive: no signature entry found"
+    );
+
+    // Every way of reading the archive checks the signature.
+    let open = |archive: Vec<u8>| {
+        MuyZipido::from_bytes(archive, 16)
+            .with_verify_signatures(&public)
+            .unwrap()
+    };
+    let rejected = |result: Result<(), ZipError>| matches!(result, Err(ZipError::SignatureRejected { reason }) if reason == "the signature does not match the archive");
+    for tampered in [false, true] {
+        let b_data: &[u8] = match tampered {
+            true => b"bravo bravo BRAVO",
+            false => b"bravo bravo bravo",
+        };
+        let signed = archive(b_data, true);
+        let results = [
+            open(signed.clone())
+                .find_entry(|info| info.filename == "missing.txt")
+                .map(|found| assert!(found.is_none())),
+            open(signed.clone())
+                .count_entries_fast()
+                .map(|count| assert_eq!(count, 4)),
+            open(signed.clone())
+                .list_entries()
+                .collect::<Result<Vec<_>, _>>()
+                .map(|listed| assert_eq!(listed.len(), 4)),
+            open(signed)
+                .preview(1)
+                .map(|entries| assert_eq!(entries.len(), 1)),
+        ];
+        for result in results {
+            match tampered {
+                true => assert!(rejected(result)),
+                false => result.unwrap(),
+            }
+        }
+    }
+
+    assert!(matches!(
+        MuyZipido::from_bytes(archive(b"", true), 16).with_verify_signatures(b"short"),
+        Err(ZipError::SignatureRejected { .. })
+    ));
+}
+
+#[test]
+fn test_large_entries_are_streamed() {
+    use flate2::write::GzEncoder;
+    use muy_zipido::{Transform, ZipEntryKind};
+    use std::io::{Read, Write};
+
+    let csv: Vec<u8> = (0..5000)
+        .flat_map(|i| format!("{},{}\n", i, i * 2).into_bytes())
+        .collect();
+    let bin: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::none());
+    encoder.write_all(&csv).unwrap();
+    let gz = encoder.finish().unwrap();
+    let archive = ArchiveBuilder::new()
+        .stored("a.txt", b"small")
+        .deflated("big.csv", &csv)
+        .stored("big.bin", &bin)
+        .with_data_descriptor("streamed.bin", &bin)
+        .stored("points.csv.gz", &gz)
+        .stored("z.txt", b"last")
+        .build();
+
+    let mut extractor = MuyZipido::from_bytes(archive.clone(), 64)
+        .with_large_entry_threshold(1000)
+        .with_entry_transform(Transform::GunzipByExtension);
+    let mut seen = Vec::new();
+    while let Some(entry) = extractor.next_entry_kind() {
+        match entry.unwrap() {
+            ZipEntryKind::Small(entry) => seen.push((entry.info.filename, false)),
+            ZipEntryKind::Large(mut entry) if entry.filename.ends_with(".csv") => {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+                assert_eq!(data, csv);
+                let stored = entry.original.as_ref().map(|info| info.filename.as_str());
+                match entry.filename.as_str() {
+                    "points.csv" => assert_eq!(stored, Some("points.csv.gz")),
+                    _ => assert_eq!(stored, None),
+                }
+                seen.push((entry.info.filename.clone(), true));
+            }
+            // Dropped part way through, leaving the rest to be skipped.
+            ZipEntryKind::Large(mut entry) => {
+                let mut start = [0u8; 10];
+                entry.read_exact(&mut start).unwrap();
+                assert_eq!(start[..], bin[..10]);
+                seen.push((entry.info.filename.clone(), true));
+            }
+        }
+    }
+    let expected = [
+        ("a.txt", false),
+        ("big.csv", true),
+        ("big.bin", true),
+        ("streamed.bin", false),
+        ("points.csv", true),
+        ("z.txt", false),
+    ];
+    assert_eq!(
+        seen,
+        expected.map(|(name, large)| (name.to_string(), large))
+    );
+
+    // Iterating still returns every entry whole.
+    let entries: Vec<_> = MuyZipido::from_bytes(archive, 64)
+        .with_large_entry_threshold(1000)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(entries[1].data, csv);
+}
+
+#[test]
+fn test_large_entry_crc_mismatch() {
+    use muy_zipido::ZipEntryKind;
+    use std::io::{ErrorKind, Read};
+
+    let mut archive = ArchiveBuilder::new()
+        .stored("big.bin", &[7; 2000])
+        .stored("ok.txt", b"fine")
+        .build();
+    archive[14] ^= 0xff;
+
+    let mut extractor = MuyZipido::from_bytes(archive, 64).with_large_entry_threshold(1000);
+    let Some(Ok(ZipEntryKind::Large(mut entry))) = extractor.next_entry_kind() else {
+        panic!("expected a large entry");
+    };
+    let error = entry.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(matches!(
+        error.into_inner().unwrap().downcast_ref::<ZipError>(),
+        Some(ZipError::CrcMismatch { .. })
+    ));
+    drop(entry);
+
+    let Some(Ok(ZipEntryKind::Small(entry))) = extractor.next_entry_kind() else {
+        panic!("expected the next entry");
+    };
+    assert_eq!(entry.data, b"fine");
+    assert!(extractor.next_entry_kind().is_none());
+}